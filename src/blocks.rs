@@ -3,13 +3,21 @@ pub mod backlight;
 pub mod base_block;
 pub mod battery;
 pub mod bluetooth;
+pub mod calc;
+pub mod clipboard;
 pub mod cpu;
+pub mod cpu_online;
 pub mod custom;
 pub mod custom_dbus;
 pub mod disk_space;
+pub mod dns;
 pub mod docker;
+pub mod fcitx;
+pub mod fifo;
+pub mod focused_app;
 pub mod focused_window;
 pub mod github;
+pub mod gpu;
 pub mod hueshift;
 pub mod ibus;
 pub mod kdeconnect;
@@ -18,6 +26,7 @@ pub mod load;
 #[cfg(feature = "maildir")]
 pub mod maildir;
 pub mod memory;
+pub mod mpd;
 pub mod music;
 pub mod net;
 pub mod networkmanager;
@@ -25,11 +34,23 @@ pub mod notify;
 #[cfg(feature = "notmuch")]
 pub mod notmuch;
 pub mod nvidia_gpu;
+pub mod output_scale;
 pub mod pacman;
+pub mod pipewire_clock;
 pub mod pomodoro;
+pub mod prometheus;
+pub mod rapl;
+pub mod rfkill;
 pub mod sound;
 pub mod speedtest;
+pub mod sqlite;
+pub mod ssd_wear;
+pub mod ssh;
+pub mod storage_pool;
+pub mod sway_indicators;
+pub mod sway_mode;
 pub mod taskwarrior;
+pub mod tcp_connections;
 pub mod temperature;
 pub mod template;
 pub mod time;
@@ -37,20 +58,31 @@ pub mod toggle;
 pub mod uptime;
 pub mod watson;
 pub mod weather;
+pub mod windows;
+pub mod workspaces;
 pub mod xrandr;
+pub mod zram;
 
 use self::apt::*;
 use self::backlight::*;
 use self::base_block::*;
 use self::battery::*;
 use self::bluetooth::*;
+use self::calc::*;
+use self::clipboard::*;
 use self::cpu::*;
+use self::cpu_online::*;
 use self::custom::*;
 use self::custom_dbus::*;
 use self::disk_space::*;
+use self::dns::*;
 use self::docker::*;
+use self::fcitx::*;
+use self::fifo::*;
+use self::focused_app::*;
 use self::focused_window::*;
 use self::github::*;
+use self::gpu::*;
 use self::hueshift::*;
 use self::ibus::*;
 use self::kdeconnect::*;
@@ -59,6 +91,7 @@ use self::load::*;
 #[cfg(feature = "maildir")]
 use self::maildir::*;
 use self::memory::*;
+use self::mpd::*;
 use self::music::*;
 use self::net::*;
 use self::networkmanager::*;
@@ -66,11 +99,23 @@ use self::notify::*;
 #[cfg(feature = "notmuch")]
 use self::notmuch::*;
 use self::nvidia_gpu::*;
+use self::output_scale::*;
 use self::pacman::*;
+use self::pipewire_clock::*;
 use self::pomodoro::*;
+use self::prometheus::*;
+use self::rapl::*;
+use self::rfkill::*;
 use self::sound::*;
 use self::speedtest::*;
+use self::sqlite::*;
+use self::ssd_wear::*;
+use self::ssh::*;
+use self::storage_pool::*;
+use self::sway_indicators::*;
+use self::sway_mode::*;
 use self::taskwarrior::*;
+use self::tcp_connections::*;
 use self::temperature::*;
 use self::template::*;
 use self::time::*;
@@ -78,7 +123,10 @@ use self::toggle::*;
 use self::uptime::*;
 use self::watson::*;
 use self::weather::*;
+use self::windows::*;
+use self::workspaces::*;
 use self::xrandr::*;
+use self::zram::*;
 
 use std::time::Duration;
 
@@ -88,6 +136,7 @@ use toml::value::Value;
 
 use crate::config::SharedConfig;
 use crate::errors::*;
+use crate::formatting::with_shared_config;
 use crate::protocol::i3bar_event::I3BarEvent;
 use crate::scheduler::Task;
 use crate::widgets::I3BarWidget;
@@ -177,6 +226,11 @@ pub trait Block {
     fn click(&mut self, _event: &I3BarEvent) -> Result<()> {
         Ok(())
     }
+
+    /// Blocks with a lower priority are hidden first when the bar is too narrow
+    fn priority(&self) -> i64 {
+        0
+    }
 }
 
 macro_rules! block {
@@ -193,12 +247,15 @@ macro_rules! block {
         if let Some(overrides) = common_config.icons_format {
             $shared_config.icons_format_override(overrides);
         }
+        $shared_config.urgent_on_critical = common_config.urgent_on_critical;
 
         // Extract block-specific config
         let block_config = <$block_type as ConfigBlock>::Config::deserialize($block_config)
             .configuration_error("Failed to deserialize block config.")?;
 
-        let mut block = $block_type::new($id, block_config, $shared_config, $update_request)?;
+        let mut block = with_shared_config(&$shared_config, || {
+            $block_type::new($id, block_config, $shared_config.clone(), $update_request)
+        })?;
         if let Some(overrided) = block.override_on_click() {
             *overrided = common_config.on_click.take();
         }
@@ -207,6 +264,8 @@ macro_rules! block {
             name: stringify!($block_type).to_string(),
             inner: block,
             on_click: common_config.on_click,
+            priority: common_config.priority,
+            shared_config: $shared_config,
         }) as Box<dyn Block>)
     }};
 }
@@ -224,11 +283,16 @@ pub fn create_block(
         "backlight" => block!(Backlight, id, block_config, shared_config, update_request),
         "battery" => block!(Battery, id, block_config, shared_config, update_request),
         "bluetooth" => block!(Bluetooth, id, block_config, shared_config, update_request),
+        "calc" => block!(Calc, id, block_config, shared_config, update_request),
+        "clipboard" => block!(Clipboard, id, block_config, shared_config, update_request),
         "cpu" => block!(Cpu, id, block_config, shared_config, update_request),
+        "cpu_online" => block!(CpuOnline, id, block_config, shared_config, update_request),
         "custom" => block!(Custom, id, block_config, shared_config, update_request),
         "custom_dbus" => block!(CustomDBus, id, block_config, shared_config, update_request),
         "disk_space" => block!(DiskSpace, id, block_config, shared_config, update_request),
+        "dns" => block!(Dns, id, block_config, shared_config, update_request),
         "docker" => block!(Docker, id, block_config, shared_config, update_request), ///////
+        "focused_app" => block!(FocusedApp, id, block_config, shared_config, update_request),
         "focused_window" => block!(
             FocusedWindow,
             id,
@@ -236,7 +300,10 @@ pub fn create_block(
             shared_config,
             update_request
         ),
+        "fcitx" => block!(Fcitx, id, block_config, shared_config, update_request),
+        "fifo" => block!(Fifo, id, block_config, shared_config, update_request),
         "github" => block!(Github, id, block_config, shared_config, update_request),
+        "gpu" => block!(Gpu, id, block_config, shared_config, update_request),
         "hueshift" => block!(Hueshift, id, block_config, shared_config, update_request),
         "ibus" => block!(IBus, id, block_config, shared_config, update_request),
         "kdeconnect" => block!(KDEConnect, id, block_config, shared_config, update_request),
@@ -251,6 +318,7 @@ pub fn create_block(
         #[cfg(feature = "maildir")]
         "maildir" => block!(Maildir, id, block_config, shared_config, update_request),
         "memory" => block!(Memory, id, block_config, shared_config, update_request),
+        "mpd" => block!(Mpd, id, block_config, shared_config, update_request),
         "music" => block!(Music, id, block_config, shared_config, update_request),
         "net" => block!(Net, id, block_config, shared_config, update_request),
         "networkmanager" => block!(
@@ -264,11 +332,43 @@ pub fn create_block(
         #[cfg(feature = "notmuch")]
         "notmuch" => block!(Notmuch, id, block_config, shared_config, update_request),
         "nvidia_gpu" => block!(NvidiaGpu, id, block_config, shared_config, update_request),
+        "output_scale" => block!(OutputScale, id, block_config, shared_config, update_request),
         "pacman" => block!(Pacman, id, block_config, shared_config, update_request),
+        "pipewire_clock" => {
+            block!(
+                PipewireClock,
+                id,
+                block_config,
+                shared_config,
+                update_request
+            )
+        }
         "pomodoro" => block!(Pomodoro, id, block_config, shared_config, update_request),
+        "prometheus" => block!(Prometheus, id, block_config, shared_config, update_request),
+        "rapl" => block!(Rapl, id, block_config, shared_config, update_request),
+        "rfkill" => block!(Rfkill, id, block_config, shared_config, update_request),
         "sound" => block!(Sound, id, block_config, shared_config, update_request),
         "speedtest" => block!(SpeedTest, id, block_config, shared_config, update_request),
+        "sqlite" => block!(Sqlite, id, block_config, shared_config, update_request),
+        "ssd_wear" => block!(SsdWear, id, block_config, shared_config, update_request),
+        "ssh" => block!(Ssh, id, block_config, shared_config, update_request),
+        "storage_pool" => block!(StoragePool, id, block_config, shared_config, update_request),
+        "sway_indicators" => block!(
+            SwayIndicators,
+            id,
+            block_config,
+            shared_config,
+            update_request
+        ),
+        "sway_mode" => block!(SwayMode, id, block_config, shared_config, update_request),
         "taskwarrior" => block!(Taskwarrior, id, block_config, shared_config, update_request),
+        "tcp_connections" => block!(
+            TcpConnections,
+            id,
+            block_config,
+            shared_config,
+            update_request
+        ),
         "temperature" => block!(Temperature, id, block_config, shared_config, update_request),
         "template" => block!(Template, id, block_config, shared_config, update_request),
         "time" => block!(Time, id, block_config, shared_config, update_request), /////////
@@ -276,7 +376,10 @@ pub fn create_block(
         "uptime" => block!(Uptime, id, block_config, shared_config, update_request),
         "watson" => block!(Watson, id, block_config, shared_config, update_request),
         "weather" => block!(Weather, id, block_config, shared_config, update_request),
+        "windows" => block!(Windows, id, block_config, shared_config, update_request),
+        "workspaces" => block!(Workspaces, id, block_config, shared_config, update_request),
         "xrandr" => block!(Xrandr, id, block_config, shared_config, update_request),
+        "zram" => block!(Zram, id, block_config, shared_config, update_request),
         other => Err(BlockError(other.to_string(), "Unknown block!".to_string())),
     }
 }
@@ -21,6 +21,8 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::config::{LogicalDirection, Scrolling};
 use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
 use crate::protocol::i3bar_event::I3BarEvent;
 use crate::scheduler::Task;
 use crate::widgets::text::TextWidget;
@@ -44,6 +46,7 @@ fn read_brightness(device_file: &Path) -> Result<u64> {
 
 /// Represents a physical backlit device whose brightness level can be queried.
 pub struct BacklitDevice {
+    min_brightness: u64,
     max_brightness: u64,
     device_path: PathBuf,
     root_scaling: f64,
@@ -58,10 +61,30 @@ fn clamp_root_scaling(root_scaling: f64) -> f64 {
     }
 }
 
+/// Convert a raw brightness value into a perceptual percentage. `min_brightness` is displayed as
+/// 0% and `max_brightness` as 100%.
+fn raw_to_percent(raw: u64, min_brightness: u64, max_brightness: u64, root_scaling: f64) -> u64 {
+    let range = max_brightness.saturating_sub(min_brightness).max(1) as f64;
+    let ratio = (raw.saturating_sub(min_brightness) as f64 / range).min(1.0);
+    (ratio.powf(root_scaling.recip()) * 100.0).round() as u64
+}
+
+/// The inverse of `raw_to_percent`
+fn percent_to_raw(
+    percent: u64,
+    min_brightness: u64,
+    max_brightness: u64,
+    root_scaling: f64,
+) -> u64 {
+    let range = max_brightness.saturating_sub(min_brightness) as f64;
+    let ratio = (percent.min(100) as f64 / 100.0).powf(root_scaling);
+    min_brightness + (ratio * range).round() as u64
+}
+
 impl BacklitDevice {
     /// Use the default backlit device, i.e. the first one found in the
     /// `/sys/class/backlight` directory.
-    pub fn default(root_scaling: f64) -> Result<Self> {
+    pub fn default(min_brightness: u64, root_scaling: f64) -> Result<Self> {
         let devices = Path::new("/sys/class/backlight")
             .read_dir() // Iterate over entries in the directory.
             .block_error("backlight", "Failed to read backlight device directory")?;
@@ -75,6 +98,7 @@ impl BacklitDevice {
         let max_brightness = read_brightness(&first_device.path().join("max_brightness"))?;
 
         Ok(BacklitDevice {
+            min_brightness,
             max_brightness,
             device_path: first_device.path(),
             root_scaling: clamp_root_scaling(root_scaling),
@@ -83,7 +107,7 @@ impl BacklitDevice {
 
     /// Use the backlit device `device`. Returns an error if a directory for
     /// that device is not found.
-    pub fn from_device(device: String, root_scaling: f64) -> Result<Self> {
+    pub fn from_device(device: String, min_brightness: u64, root_scaling: f64) -> Result<Self> {
         let device_path = Path::new("/sys/class/backlight").join(device);
         if !device_path.exists() {
             return Err(BlockError(
@@ -98,6 +122,7 @@ impl BacklitDevice {
         let max_brightness = read_brightness(&device_path.join("max_brightness"))?;
 
         Ok(BacklitDevice {
+            min_brightness,
             max_brightness,
             device_path,
             root_scaling: clamp_root_scaling(root_scaling),
@@ -107,23 +132,25 @@ impl BacklitDevice {
     /// Query the brightness value for this backlit device, as a percent.
     pub fn brightness(&self) -> Result<u64> {
         let raw = read_brightness(&self.brightness_file())?;
-        let brightness_ratio =
-            (raw as f64 / self.max_brightness as f64).powf(self.root_scaling.recip());
-        let brightness = (brightness_ratio * 100.0).round() as u64;
-        match brightness {
-            0..=100 => Ok(brightness),
-            _ => Ok(100),
-        }
+        Ok(raw_to_percent(
+            raw,
+            self.min_brightness,
+            self.max_brightness,
+            self.root_scaling,
+        ))
     }
 
     /// Set the brightness value for this backlit device, as a percent.
     pub fn set_brightness(&self, value: u64) -> Result<()> {
-        let safe_value = match value {
-            0..=100 => value,
-            _ => 100,
-        };
-        let ratio = (safe_value as f64 / 100.0).powf(self.root_scaling);
-        let raw = std::cmp::max(1, (ratio * (self.max_brightness as f64)).round() as u64);
+        let raw = std::cmp::max(
+            1,
+            percent_to_raw(
+                value,
+                self.min_brightness,
+                self.max_brightness,
+                self.root_scaling,
+            ),
+        );
 
         let file = OpenOptions::new()
             .write(true)
@@ -183,6 +210,7 @@ pub struct Backlight {
     id: usize,
     output: TextWidget,
     device: BacklitDevice,
+    format: FormatTemplate,
     step_width: u64,
     scrolling: Scrolling,
     invert_icons: bool,
@@ -207,7 +235,12 @@ pub struct BacklightConfig {
     /// For devices with few discrete steps this should be 1.0 (linear).
     pub root_scaling: f64,
 
+    /// The raw brightness value that is displayed as 0%. Scrolling doesn't go below it.
+    pub minimum: u64,
+
     pub invert_icons: bool,
+
+    pub format: FormatTemplate,
 }
 
 impl Default for BacklightConfig {
@@ -216,7 +249,9 @@ impl Default for BacklightConfig {
             device: None,
             step_width: 5,
             root_scaling: 1f64,
+            minimum: 0,
             invert_icons: false,
+            format: FormatTemplate::default(),
         }
     }
 }
@@ -231,8 +266,10 @@ impl ConfigBlock for Backlight {
         tx_update_request: Sender<Task>,
     ) -> Result<Self> {
         let device = match block_config.device {
-            Some(path) => BacklitDevice::from_device(path, block_config.root_scaling),
-            None => BacklitDevice::default(block_config.root_scaling),
+            Some(path) => {
+                BacklitDevice::from_device(path, block_config.minimum, block_config.root_scaling)
+            }
+            None => BacklitDevice::default(block_config.minimum, block_config.root_scaling),
         }?;
 
         let brightness_file = device.brightness_file();
@@ -240,6 +277,7 @@ impl ConfigBlock for Backlight {
         let backlight = Backlight {
             id,
             device,
+            format: block_config.format.with_default("{brightness}")?,
             step_width: block_config.step_width,
             scrolling: shared_config.scrolling,
             output: TextWidget::new(id, 0, shared_config),
@@ -284,7 +322,10 @@ impl ConfigBlock for Backlight {
 impl Block for Backlight {
     fn update(&mut self) -> Result<Option<Update>> {
         let mut brightness = self.device.brightness()?;
-        self.output.set_text(format!("{}%", brightness));
+        let values = map!(
+            "brightness" => Value::from_integer(brightness as i64).percents(),
+        );
+        self.output.set_texts(self.format.render(&values)?);
         if self.invert_icons {
             brightness = 100 - brightness;
         }
@@ -337,3 +378,38 @@ impl Block for Backlight {
         self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear() {
+        assert_eq!(raw_to_percent(0, 0, 255, 1.0), 0);
+        assert_eq!(raw_to_percent(128, 0, 255, 1.0), 50);
+        assert_eq!(raw_to_percent(255, 0, 255, 1.0), 100);
+        assert_eq!(percent_to_raw(50, 0, 255, 1.0), 128);
+        assert_eq!(percent_to_raw(150, 0, 255, 1.0), 255);
+    }
+
+    #[test]
+    fn minimum() {
+        assert_eq!(raw_to_percent(10, 10, 110, 1.0), 0);
+        assert_eq!(raw_to_percent(5, 10, 110, 1.0), 0);
+        assert_eq!(raw_to_percent(60, 10, 110, 1.0), 50);
+        assert_eq!(percent_to_raw(0, 10, 110, 1.0), 10);
+        assert_eq!(percent_to_raw(25, 10, 110, 1.0), 35);
+    }
+
+    #[test]
+    fn root_scaling() {
+        // 1/8 of the luminance looks like half the brightness with a cube root curve
+        assert_eq!(raw_to_percent(1000, 0, 8000, 3.0), 50);
+        assert_eq!(percent_to_raw(50, 0, 8000, 3.0), 1000);
+        assert_eq!(raw_to_percent(1100, 100, 8100, 3.0), 50);
+        for percent in 0..=100 {
+            let raw = percent_to_raw(percent, 100, 100_000, 2.4);
+            assert_eq!(raw_to_percent(raw, 100, 100_000, 2.4), percent);
+        }
+    }
+}
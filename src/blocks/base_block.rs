@@ -2,7 +2,9 @@
 
 use std::collections::HashMap;
 
+use crate::config::SharedConfig;
 use crate::errors::*;
+use crate::formatting::with_shared_config;
 use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
 use crate::{blocks::Update, subprocess::spawn_child_async, widgets::I3BarWidget, Block};
 
@@ -13,6 +15,8 @@ pub(super) struct BaseBlock<T: Block> {
     pub name: String,
     pub inner: T,
     pub on_click: Option<String>,
+    pub priority: i64,
+    pub shared_config: SharedConfig,
 }
 
 impl<T: Block> Block for BaseBlock<T> {
@@ -25,14 +29,17 @@ impl<T: Block> Block for BaseBlock<T> {
     }
 
     fn update(&mut self) -> Result<Option<Update>> {
-        self.inner.update()
+        let inner = &mut self.inner;
+        with_shared_config(&self.shared_config, || inner.update())
     }
 
     fn signal(&mut self, signal: i32) -> Result<()> {
-        self.inner.signal(signal)
+        let inner = &mut self.inner;
+        with_shared_config(&self.shared_config, || inner.signal(signal))
     }
 
     fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        let inner = &mut self.inner;
         match &self.on_click {
             Some(cmd) => {
                 if let MouseButton::Left = e.button {
@@ -41,9 +48,13 @@ impl<T: Block> Block for BaseBlock<T> {
                 }
                 Ok(())
             }
-            None => self.inner.click(e),
+            None => with_shared_config(&self.shared_config, || inner.click(e)),
         }
     }
+
+    fn priority(&self) -> i64 {
+        self.priority
+    }
 }
 
 #[derive(Deserialize, Debug, Default, Clone)]
@@ -53,10 +64,24 @@ pub(super) struct BaseBlockConfig {
 
     pub theme_overrides: Option<HashMap<String, String>>,
     pub icons_format: Option<String>,
+
+    /// Mark the block as urgent when it is in the critical state
+    #[serde(default)]
+    pub urgent_on_critical: bool,
+
+    /// When the bar is too narrow, blocks with the lowest priority are hidden first
+    #[serde(default)]
+    pub priority: i64,
 }
 
 impl BaseBlockConfig {
-    const FIELDS: &'static [&'static str] = &["on_click", "theme_overrides", "icons_format"];
+    const FIELDS: &'static [&'static str] = &[
+        "on_click",
+        "theme_overrides",
+        "icons_format",
+        "urgent_on_critical",
+        "priority",
+    ];
 
     // FIXME: this function is to paper over https://github.com/serde-rs/serde/issues/1957
     pub(super) fn extract(config: &mut Value) -> Value {
@@ -52,6 +52,10 @@ pub trait BatteryDevice {
 
     /// Query the current power consumption, in uW.
     fn power_consumption(&self) -> Result<u64>;
+
+    /// Query the capacity of the fully charged battery, as a percent of its design capacity.
+    /// `None` if the design capacity is unknown.
+    fn health(&self) -> Result<Option<f64>>;
 }
 
 /// Represents a physical power supply device, as known to sysfs.
@@ -303,6 +307,25 @@ impl BatteryDevice for PowerSupplyDevice {
             ))
         }
     }
+
+    fn health(&self) -> Result<Option<f64>> {
+        let (full, design_file) = match (self.charge_full, self.energy_full) {
+            (Some(full), _) => (full, "charge_full_design"),
+            (None, Some(full)) => (full, "energy_full_design"),
+            (None, None) => return Ok(None),
+        };
+        let design_path = self.device_path.join(design_file);
+        if !design_path.exists() {
+            return Ok(None);
+        }
+        let design = read_file("battery", &design_path)?
+            .parse::<u64>()
+            .block_error("battery", &format!("failed to parse {}", design_file))?;
+        Ok(match design {
+            0 => None,
+            design => Some(full as f64 / design as f64 * 100.0),
+        })
+    }
 }
 
 /// Represents a battery known to UPower.
@@ -473,6 +496,24 @@ impl BatteryDevice for UpowerDevice {
         // FIXME: Might want to make the interface send Watts instead.
         Ok((energy_rate * 1_000_000.0) as u64)
     }
+
+    fn health(&self) -> Result<Option<f64>> {
+        let capacity: f64 = self
+            .con
+            .with_path("org.freedesktop.UPower", &self.device_path, 1000)
+            .get("org.freedesktop.UPower.Device", "Capacity")
+            .block_error("battery", "Failed to read UPower Capacity property.")?;
+        // UPower reports 0 if the design capacity is unknown
+        Ok(if capacity > 0.0 { Some(capacity) } else { None })
+    }
+}
+
+/// Whether the battery health is below the `threshold` (in percent)
+fn health_is_low(health: Option<f64>, threshold: Option<u64>) -> bool {
+    match (health, threshold) {
+        (Some(health), Some(threshold)) => health < threshold as f64,
+        _ => false,
+    }
 }
 
 /// A block for displaying information about an internal power supply.
@@ -492,6 +533,7 @@ pub struct Battery {
     info: u64,
     warning: u64,
     critical: u64,
+    health_warning: Option<u64>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -548,6 +590,9 @@ pub struct BatteryConfig {
     /// The threshold below which the remaining capacity is shown as critical
     pub critical: u64,
 
+    /// The health (in percent) below which the block is shown as warning
+    pub health_warning: Option<u64>,
+
     /// If the battery device cannot be found, do not fail and show the block anyway (sysfs only).
     pub allow_missing: bool,
 
@@ -587,6 +632,7 @@ impl Default for BatteryConfig {
             info: 60,
             warning: 30,
             critical: 15,
+            health_warning: None,
             allow_missing: false,
             hide_missing: false,
         }
@@ -630,6 +676,7 @@ impl ConfigBlock for Battery {
             info: block_config.info,
             warning: block_config.warning,
             critical: block_config.critical,
+            health_warning: block_config.health_warning,
         })
     }
 }
@@ -647,6 +694,7 @@ impl Block for Battery {
                 "percentage" => Value::from_string("X".to_string()),
                 "time" => Value::from_string("xx:xx".to_string()),
                 "power" => Value::from_string("N/A".to_string()),
+                "health" => Value::from_string(String::new()),
             );
 
             self.output.set_icon("bat_not_available")?;
@@ -665,6 +713,15 @@ impl Block for Battery {
 
         let status = self.device.status()?;
         let capacity = self.device.capacity();
+        // Many batteries report no design capacity, which doesn't stop the rest of the block
+        let health = if self.health_warning.is_some()
+            || self.format.contains("health")
+            || self.full_format.contains("health")
+        {
+            self.device.health().ok().flatten()
+        } else {
+            None
+        };
         let values = map!(
             "percentage" => match capacity {
                 Ok(capacity) => Value::from_integer(capacity as i64).percents(),
@@ -680,6 +737,10 @@ impl Block for Battery {
                 Ok(power) => Value::from_float(power as f64 * 1e-6).watts(),
                 _ => Value::from_string("×".into()),
             },
+            "health" => match health {
+                Some(health) => Value::from_integer(health.round() as i64).percents(),
+                None => Value::from_string(String::new()),
+            },
         );
 
         let capacity_is_above_full_threshold = match capacity {
@@ -687,10 +748,11 @@ impl Block for Battery {
             _ => false,
         };
 
+        let mut state;
         if status == "Full" || status == "Not charging" || capacity_is_above_full_threshold {
             self.output.set_icon("bat_full")?;
             self.output.set_texts(self.full_format.render(&values)?);
-            self.output.set_state(State::Good);
+            state = State::Good;
             self.output.set_spacing(Spacing::Hidden);
         } else {
             self.output.set_texts(self.format.render(&values)?);
@@ -699,10 +761,10 @@ impl Block for Battery {
             // Otherwise, adjust the state depeding the power percentance.
             match status.as_str() {
                 "Charging" => {
-                    self.output.set_state(State::Good);
+                    state = State::Good;
                 }
                 _ => {
-                    self.output.set_state(match capacity {
+                    state = match capacity {
                         Ok(capacity) => {
                             if capacity <= self.critical {
                                 State::Critical
@@ -717,7 +779,7 @@ impl Block for Battery {
                             }
                         }
                         Err(_) => State::Warning,
-                    });
+                    };
                 }
             }
 
@@ -729,6 +791,12 @@ impl Block for Battery {
             self.output.set_spacing(Spacing::Normal);
         }
 
+        // A worn out battery is shown as warning, unless the charge is more critical
+        if health_is_low(health, self.health_warning) {
+            state = state.max(State::Warning);
+        }
+        self.output.set_state(state);
+
         match self.driver {
             BatteryDriver::Sysfs => Ok(Some(self.update_interval.into())),
             BatteryDriver::Upower => Ok(None),
@@ -748,3 +816,71 @@ impl Block for Battery {
         self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    fn device(dir: &assert_fs::TempDir) -> PowerSupplyDevice {
+        PowerSupplyDevice {
+            device_path: dir.path().to_path_buf(),
+            allow_missing: false,
+            charge_full: None,
+            energy_full: None,
+        }
+    }
+
+    #[test]
+    fn health() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        dir.child("charge_full").write_str("4400000\n").unwrap();
+        dir.child("charge_full_design")
+            .write_str("5000000\n")
+            .unwrap();
+        let mut battery = device(&dir);
+        battery.refresh_device_info().unwrap();
+        assert_eq!(battery.health().unwrap(), Some(88.));
+
+        let dir = assert_fs::TempDir::new().unwrap();
+        dir.child("energy_full").write_str("39000000\n").unwrap();
+        dir.child("energy_full_design")
+            .write_str("52000000\n")
+            .unwrap();
+        let mut battery = device(&dir);
+        battery.refresh_device_info().unwrap();
+        assert_eq!(battery.health().unwrap(), Some(75.));
+    }
+
+    #[test]
+    fn health_without_design_capacity() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        dir.child("charge_full").write_str("4400000\n").unwrap();
+        let mut battery = device(&dir);
+        battery.refresh_device_info().unwrap();
+        assert_eq!(battery.health().unwrap(), None);
+
+        dir.child("charge_full_design").write_str("0\n").unwrap();
+        assert_eq!(battery.health().unwrap(), None);
+
+        dir.child("charge_full_design").write_str("n/a\n").unwrap();
+        assert!(battery.health().is_err());
+
+        // Neither charge_full nor energy_full
+        let dir = assert_fs::TempDir::new().unwrap();
+        dir.child("energy_full_design")
+            .write_str("52000000\n")
+            .unwrap();
+        let mut battery = device(&dir);
+        battery.refresh_device_info().unwrap();
+        assert_eq!(battery.health().unwrap(), None);
+    }
+
+    #[test]
+    fn low_health() {
+        assert!(health_is_low(Some(79.5), Some(80)));
+        assert!(!health_is_low(Some(80.), Some(80)));
+        assert!(!health_is_low(Some(50.), None));
+        assert!(!health_is_low(None, Some(80)));
+    }
+}
@@ -0,0 +1,228 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::errors::*;
+use crate::formatting::expression::Arithmetic;
+use crate::formatting::unit::Unit;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::scheduler::Task;
+use crate::widgets::text::TextWidget;
+use crate::widgets::I3BarWidget;
+
+thread_local! {
+    /// The values shared by blocks with `share_as`, and the `calc` blocks using them. Blocks are
+    /// created and updated on the main thread.
+    static SHARED: RefCell<SharedValues> = RefCell::default();
+}
+
+#[derive(Default)]
+struct SharedValues {
+    /// The last numeric values by `<share_as>.<placeholder>`
+    values: HashMap<String, f64>,
+    /// The `calc` blocks to update when one of the values they use changes
+    dependents: Vec<Dependent>,
+}
+
+struct Dependent {
+    id: usize,
+    vars: Vec<String>,
+    update_request: Sender<Task>,
+}
+
+/// Share the numeric values among `values` with `calc` blocks as `<share_as>.<placeholder>`, and
+/// request updates of the ones using a value that changed. Called by the blocks which support the
+/// `share_as` option.
+pub fn share_values(share_as: &str, values: &HashMap<&str, Value>) -> Result<()> {
+    SHARED.with(|shared| {
+        let mut shared = shared.borrow_mut();
+        let mut changed = Vec::new();
+        for (name, value) in values {
+            if let Some(value) = value.as_f64() {
+                let name = format!("{}.{}", share_as, name);
+                if shared.values.get(&name) != Some(&value) {
+                    shared.values.insert(name.clone(), value);
+                    changed.push(name);
+                }
+            }
+        }
+        for dependent in &shared.dependents {
+            if dependent.vars.iter().any(|var| changed.contains(var)) {
+                dependent.update_request.send(Task {
+                    id: dependent.id,
+                    update_time: Instant::now(),
+                })?;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// The last values shared by other blocks among `vars`
+fn shared_values<'a>(vars: &[&'a str]) -> HashMap<&'a str, Value> {
+    SHARED.with(|shared| {
+        let shared = shared.borrow();
+        vars.iter()
+            .filter_map(|&var| Some((var, Value::from_float(*shared.values.get(var)?))))
+            .collect()
+    })
+}
+
+pub struct Calc {
+    id: usize,
+    text: TextWidget,
+    expression: Arithmetic,
+    format: FormatTemplate,
+    unit: Unit,
+    visible: bool,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct CalcConfig {
+    /// The expression to compute, e.g. `{home.used} + {data.used}`
+    pub expression: String,
+
+    pub format: FormatTemplate,
+
+    /// The unit of the result, e.g. `B`
+    pub unit: String,
+}
+
+impl ConfigBlock for Calc {
+    type Config = CalcConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        update_request: Sender<Task>,
+    ) -> Result<Self> {
+        if block_config.expression.is_empty() {
+            return Err(ConfigurationError(
+                "calc".to_string(),
+                "'expression' must be set".to_string(),
+            ));
+        }
+        let expression = Arithmetic::parse(&block_config.expression)
+            .configuration_error("invalid expression")?;
+        // Updated whenever a value it uses is shared
+        SHARED.with(|shared| {
+            shared.borrow_mut().dependents.push(Dependent {
+                id,
+                vars: expression.vars().into_iter().map(String::from).collect(),
+                update_request,
+            })
+        });
+        Ok(Calc {
+            id,
+            text: TextWidget::new(id, 0, shared_config),
+            expression,
+            format: block_config.format.with_default("{value}")?,
+            unit: block_config
+                .unit
+                .parse()
+                .configuration_error("invalid unit")?,
+            visible: false,
+        })
+    }
+}
+
+impl Block for Calc {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        // Hidden until the blocks it depends on have shared their values, and while the result
+        // is not a number, e.g. after a division by zero
+        let vars = shared_values(&self.expression.vars());
+        match self.expression.eval(&vars) {
+            Some(value) if value.is_finite() => {
+                let values = map!(
+                    "value" => Value::from_float(value).with_unit(self.unit),
+                );
+                self.text.set_texts(self.format.render(&values)?);
+                self.visible = true;
+            }
+            _ => self.visible = false,
+        }
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        if self.visible {
+            vec![&self.text]
+        } else {
+            vec![]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(expression: &str) -> Option<f64> {
+        let expression = Arithmetic::parse(expression).unwrap();
+        expression.eval(&shared_values(&expression.vars()))
+    }
+
+    /// A block sharing its disk usage like `disk_space` does
+    fn share(name: &str, used: i64, total: i64) {
+        let values = map!(
+            "used" => Value::from_integer(used).bytes(),
+            "total" => Value::from_integer(total).bytes(),
+            "path" => Value::from_string("/".to_string()),
+        );
+        share_values(name, &values).unwrap();
+    }
+
+    #[test]
+    fn sum_and_ratio() {
+        share("home", 300, 1000);
+        share("data", 500, 1000);
+
+        assert_eq!(eval("{home.used} + {data.used}"), Some(800.));
+        assert_eq!(
+            eval("({ home.used } + {data.used}) / ({home.total} + {data.total})"),
+            Some(0.4)
+        );
+        assert_eq!(eval("{home.path} + 1"), None);
+        assert_eq!(eval("{other.used} + 1"), None);
+
+        // Updates replace the previous values
+        share("data", 700, 1000);
+        assert_eq!(eval("{home.used} + {data.used}"), Some(1000.));
+    }
+
+    #[test]
+    fn update_requests() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let config = CalcConfig {
+            expression: "{home.used} / {home.total}".to_string(),
+            ..CalcConfig::default()
+        };
+        let mut calc = Calc::new(3, config, SharedConfig::default(), tx).unwrap();
+        calc.update().unwrap();
+        assert!(calc.view().is_empty());
+
+        share("home", 300, 1000);
+        assert_eq!(rx.try_recv().unwrap().id, 3);
+        calc.update().unwrap();
+        assert_eq!(calc.view().len(), 1);
+
+        // Only changes of the values it uses update the block
+        share("home", 300, 1000);
+        share("data", 500, 1000);
+        assert!(rx.try_recv().is_err());
+        share("home", 400, 1000);
+        assert_eq!(rx.try_recv().unwrap().id, 3);
+    }
+}
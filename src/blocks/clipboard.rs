@@ -0,0 +1,249 @@
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::escape_pango_text;
+use crate::widgets::text::TextWidget;
+use crate::widgets::I3BarWidget;
+
+/// A tool to read and clear the clipboard
+trait ClipboardSource {
+    fn contents(&self) -> Result<String>;
+    fn clear(&self) -> Result<()>;
+    /// A command which prints a line every time the clipboard changes, if there is one
+    fn watch_command(&self) -> Option<Command>;
+}
+
+fn run(program: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .block_error("clipboard", &format!("failed to run {}", program))?;
+    // wl-paste exits with an error when the clipboard is empty
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+struct WlClipboard;
+
+impl ClipboardSource for WlClipboard {
+    fn contents(&self) -> Result<String> {
+        run("wl-paste", &["--no-newline"])
+    }
+
+    fn clear(&self) -> Result<()> {
+        run("wl-copy", &["--clear"]).map(|_| ())
+    }
+
+    fn watch_command(&self) -> Option<Command> {
+        let mut command = Command::new("wl-paste");
+        command.args(["--watch", "echo"]);
+        Some(command)
+    }
+}
+
+struct Xclip;
+
+impl ClipboardSource for Xclip {
+    fn contents(&self) -> Result<String> {
+        run("xclip", &["-out", "-selection", "clipboard"])
+    }
+
+    fn clear(&self) -> Result<()> {
+        run("xclip", &["-in", "-selection", "clipboard", "/dev/null"]).map(|_| ())
+    }
+
+    fn watch_command(&self) -> Option<Command> {
+        // X has no command line tool that reports selection changes continuously, so this
+        // block polls the clipboard instead
+        None
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardDriver {
+    Wayland,
+    X11,
+}
+
+pub struct Clipboard {
+    id: usize,
+    text: TextWidget,
+    source: Box<dyn ClipboardSource>,
+    format: FormatTemplate,
+    update_interval: Option<Duration>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct ClipboardConfig {
+    /// Which clipboard tools to use
+    pub driver: ClipboardDriver,
+
+    /// Update interval in seconds. Only used by drivers that can't watch the clipboard (x11).
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    pub format: FormatTemplate,
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            driver: ClipboardDriver::Wayland,
+            interval: Duration::from_secs(2),
+            format: FormatTemplate::default(),
+        }
+    }
+}
+
+impl ConfigBlock for Clipboard {
+    type Config = ClipboardConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        send: Sender<Task>,
+    ) -> Result<Self> {
+        let source: Box<dyn ClipboardSource> = match block_config.driver {
+            ClipboardDriver::Wayland => Box::new(WlClipboard),
+            ClipboardDriver::X11 => Box::new(Xclip),
+        };
+
+        let update_interval = match source.watch_command() {
+            Some(mut command) => {
+                let child = command
+                    .stdout(Stdio::piped())
+                    .stdin(Stdio::null())
+                    .spawn()
+                    .block_error("clipboard", "failed to watch the clipboard")?;
+                let stdout = child.stdout.unwrap();
+                thread::Builder::new()
+                    .name("clipboard".into())
+                    .spawn(move || {
+                        for _ in BufReader::new(stdout).lines() {
+                            // Tell block to update now.
+                            send.send(Task {
+                                id,
+                                update_time: Instant::now(),
+                            })
+                            .unwrap();
+                        }
+                    })
+                    .unwrap();
+                None
+            }
+            None => Some(block_config.interval),
+        };
+
+        Ok(Clipboard {
+            id,
+            text: TextWidget::new(id, 0, shared_config),
+            source,
+            format: block_config.format.with_default("{text.str(max_w:21)}")?,
+            update_interval,
+        })
+    }
+}
+
+impl Block for Clipboard {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        // Collapse whitespace, including newlines
+        let text = self
+            .source
+            .contents()?
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let values = map!(
+            "text" => Value::from_string(escape_pango_text(text)),
+        );
+        self.text.set_texts(self.format.render(&values)?);
+        Ok(self.update_interval.map(|interval| interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        if let MouseButton::Left = e.button {
+            self.source.clear()?;
+            // wl-paste reports the change, but the x11 driver has to be polled
+            if self.update_interval.is_some() {
+                self.update()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct CannedClipboard(&'static str);
+
+    impl ClipboardSource for CannedClipboard {
+        fn contents(&self) -> Result<String> {
+            Ok(self.0.to_string())
+        }
+
+        fn clear(&self) -> Result<()> {
+            Ok(())
+        }
+
+        fn watch_command(&self) -> Option<Command> {
+            None
+        }
+    }
+
+    fn render(contents: &'static str) -> String {
+        let mut clipboard = Clipboard {
+            id: 0,
+            text: TextWidget::new(0, 0, SharedConfig::default()),
+            source: Box::new(CannedClipboard(contents)),
+            format: FormatTemplate::default()
+                .with_default("{text.str(max_w:21)}")
+                .unwrap(),
+            update_interval: Some(Duration::from_secs(2)),
+        };
+        let update = clipboard.update().unwrap();
+        assert_eq!(update, Some(Duration::from_secs(2).into()));
+        clipboard.text.get_data().full_text.trim().to_string()
+    }
+
+    #[test]
+    fn update() {
+        assert_eq!(render("short"), "short");
+        assert_eq!(render("  multi\n\tline\n text "), "multi line text");
+        assert_eq!(
+            render("a rather long clipboard entry"),
+            "a rather long clipboa"
+        );
+        assert_eq!(render("<b>&</b>"), "&lt;b&gt;&amp;&lt;/b&gt;");
+        assert_eq!(
+            render("Tom & Jerry and their friends"),
+            "Tom &amp; Jerry and their"
+        );
+        assert_eq!(render(""), "");
+    }
+}
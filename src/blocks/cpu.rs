@@ -6,6 +6,7 @@ use std::time::Duration;
 use crossbeam_channel::Sender;
 use serde_derive::Deserialize;
 
+use crate::blocks::calc::share_values;
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
@@ -16,11 +17,61 @@ use crate::scheduler::Task;
 use crate::widgets::text::TextWidget;
 use crate::widgets::{I3BarWidget, State};
 
+/// A `/proc/stat` field which can be left out of the utilization
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CpuStatField {
+    Iowait,
+    Steal,
+}
+
+/// Split a `/proc/stat` cpu line into idle and non-idle time, leaving out the excluded fields
+fn cpu_times(data: &[u64], exclude: &[CpuStatField]) -> (u64, u64) {
+    let field = |index: usize| data.get(index).copied().unwrap_or(0);
+    let iowait = if exclude.contains(&CpuStatField::Iowait) {
+        0
+    } else {
+        field(4)
+    };
+    let steal = if exclude.contains(&CpuStatField::Steal) {
+        0
+    } else {
+        field(7)
+    };
+
+    let idle = field(3) + iowait;
+    let non_idle = field(0) + // user
+                   field(1) + // nice
+                   field(2) + // system
+                   field(5) + // irq
+                   field(6) + // softirq
+                   steal;
+    (idle, non_idle)
+}
+
+/// The utilization between two `(idle, non_idle)` snapshots, between 0 and 1
+fn utilization((prev_idle, prev_non_idle): (u64, u64), (idle, non_idle): (u64, u64)) -> f64 {
+    let prev_total = prev_idle + prev_non_idle;
+    let total = idle + non_idle;
+
+    // This check is needed because the new values may be reset, for
+    // example after hibernation.
+    let (total_delta, idle_delta) = if prev_total < total && prev_idle <= idle {
+        (total - prev_total, idle - prev_idle)
+    } else {
+        (1, 1)
+    };
+
+    ((total_delta - idle_delta) as f64 / total_delta as f64).clamp(0., 1.)
+}
+
 pub struct Cpu {
     id: usize,
     output: TextWidget,
     prev_util: Vec<(u64, u64)>,
+    exclude: Vec<CpuStatField>,
     update_interval: Duration,
+    share_as: Option<String>,
     minimum_info: u64,
     minimum_warning: u64,
     minimum_critical: u64,
@@ -36,6 +87,9 @@ pub struct CpuConfig {
     #[serde(deserialize_with = "deserialize_duration")]
     pub interval: Duration,
 
+    /// Share the numeric placeholders with `calc` blocks under this name
+    pub share_as: Option<String>,
+
     /// Minimum usage, where state is set to info
     pub info: u64,
 
@@ -47,16 +101,21 @@ pub struct CpuConfig {
 
     /// Format override
     pub format: FormatTemplate,
+
+    /// Fields of `/proc/stat` to leave out of the utilization
+    pub exclude: Vec<CpuStatField>,
 }
 
 impl Default for CpuConfig {
     fn default() -> Self {
         Self {
             interval: Duration::from_secs(1),
+            share_as: None,
             info: 30,
             warning: 60,
             critical: 90,
             format: FormatTemplate::default(),
+            exclude: Vec::new(),
         }
     }
 }
@@ -73,7 +132,9 @@ impl ConfigBlock for Cpu {
         Ok(Cpu {
             id,
             update_interval: block_config.interval,
+            share_as: block_config.share_as,
             prev_util: Vec::with_capacity(32),
+            exclude: block_config.exclude,
             minimum_info: block_config.info,
             minimum_warning: block_config.warning,
             minimum_critical: block_config.critical,
@@ -123,37 +184,14 @@ impl Block for Cpu {
                     .filter_map(|x| x.parse::<u64>().ok())
                     .collect();
 
-                // idle = idle + iowait
-                let idle = data[3] + data[4];
-                let non_idle = data[0] + // user
-                                data[1] + // nice
-                                data[2] + // system
-                                data[5] + // irq
-                                data[6] + // softirq
-                                data[7]; // steal
+                let times = cpu_times(&data, &self.exclude);
 
-                let (prev_idles, prev_non_idles) = if self.prev_util.len() <= i {
+                if self.prev_util.len() <= i {
                     self.prev_util.push((0, 0));
-                    (0, 0)
-                } else {
-                    self.prev_util[i]
-                };
-
-                let prev_total = prev_idles + prev_non_idles;
-                let total = idle + non_idle;
-
-                // This check is needed because the new values may be reset, for
-                // example after hibernation.
-                let (total_delta, idle_delta) = if prev_total < total && prev_idles <= idle {
-                    (total - prev_total, idle - prev_idles)
-                } else {
-                    (1, 1)
-                };
-
-                utilizations
-                    .push(((total_delta - idle_delta) as f64 / total_delta as f64).clamp(0., 1.));
-
-                self.prev_util[i] = (idle, non_idle);
+                }
+                utilizations.push(utilization(self.prev_util[i], times));
+
+                self.prev_util[i] = times;
             }
         }
 
@@ -204,6 +242,9 @@ impl Block for Cpu {
         }
 
         self.output.set_texts(self.format.render(&values)?);
+        if let Some(share_as) = &self.share_as {
+            share_values(share_as, &values)?;
+        }
 
         Ok(Some(self.update_interval.into()))
     }
@@ -227,3 +268,39 @@ fn boost_status() -> Option<bool> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Vec<u64> {
+        line.split_whitespace()
+            .filter_map(|x| x.parse().ok())
+            .collect()
+    }
+
+    #[test]
+    fn exclude_fields() {
+        //             user nice system idle iowait irq softirq steal
+        let prev = parse("cpu 100 0 100 700 50 0 0 50 0 0");
+        let cur = parse("cpu 200 0 100 800 100 0 0 150 0 0");
+
+        let util = |exclude: &[CpuStatField]| {
+            utilization(cpu_times(&prev, exclude), cpu_times(&cur, exclude))
+        };
+
+        // busy: 100 user + 100 steal out of 350
+        assert!((util(&[]) - 200. / 350.).abs() < 1e-9);
+        // busy: 100 user + 100 steal out of 300
+        assert!((util(&[CpuStatField::Iowait]) - 200. / 300.).abs() < 1e-9);
+        // busy: 100 user out of 250
+        assert!((util(&[CpuStatField::Steal]) - 100. / 250.).abs() < 1e-9);
+        // busy: 100 user out of 200
+        assert!((util(&[CpuStatField::Iowait, CpuStatField::Steal]) - 100. / 200.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn counter_reset() {
+        assert_eq!(utilization((1000, 1000), (10, 10)), 0.);
+    }
+}
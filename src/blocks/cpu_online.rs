@@ -0,0 +1,155 @@
+use std::path::Path;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::scheduler::Task;
+use crate::util::read_file;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// Count the CPUs in a cpulist such as `0-3,6`, the format of `/sys/devices/system/cpu/online`
+fn parse_cpulist(list: &str) -> Result<usize> {
+    let parse = |cpu: &str| -> Result<usize> {
+        cpu.trim()
+            .parse()
+            .block_error("cpu_online", &format!("invalid cpu list '{}'", list))
+    };
+    let mut count = 0;
+    for range in list.split(',').filter(|range| !range.trim().is_empty()) {
+        count += match range.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (parse(first)?, parse(last)?);
+                if last < first {
+                    return Err(BlockError(
+                        "cpu_online".to_string(),
+                        format!("invalid cpu list '{}'", list),
+                    ));
+                }
+                last - first + 1
+            }
+            None => parse(range).map(|_| 1)?,
+        };
+    }
+    Ok(count)
+}
+
+fn read_cpulist(name: &str) -> Result<usize> {
+    parse_cpulist(&read_file(
+        "cpu_online",
+        &Path::new("/sys/devices/system/cpu").join(name),
+    )?)
+}
+
+pub struct CpuOnline {
+    id: usize,
+    text: TextWidget,
+    format: FormatTemplate,
+    format_offline: FormatTemplate,
+    update_interval: Duration,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct CpuOnlineConfig {
+    /// Format used when all CPUs are online
+    pub format: FormatTemplate,
+
+    /// Format used when some CPUs are offline
+    pub format_offline: FormatTemplate,
+
+    /// Update interval in seconds
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+}
+
+impl Default for CpuOnlineConfig {
+    fn default() -> Self {
+        Self {
+            format: FormatTemplate::default(),
+            format_offline: FormatTemplate::default(),
+            interval: Duration::from_secs(10),
+        }
+    }
+}
+
+impl ConfigBlock for CpuOnline {
+    type Config = CpuOnlineConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        Ok(CpuOnline {
+            id,
+            text: TextWidget::new(id, 0, shared_config).with_icon("cpu")?,
+            format: block_config.format.with_default("{online}/{total}")?,
+            format_offline: block_config
+                .format_offline
+                .with_default("{online}/{total} ({offline} offline)")?,
+            update_interval: block_config.interval,
+        })
+    }
+}
+
+impl Block for CpuOnline {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        let online = read_cpulist("online")?;
+        let total = read_cpulist("present")?;
+        let offline = total.saturating_sub(online);
+
+        let values = map!(
+            "online" => Value::from_integer(online as i64),
+            "total" => Value::from_integer(total as i64),
+            "offline" => Value::from_integer(offline as i64),
+        );
+        let format = if offline > 0 {
+            &self.format_offline
+        } else {
+            &self.format
+        };
+        self.text.set_texts(format.render(&values)?);
+        self.text.set_state(if offline > 0 {
+            State::Info
+        } else {
+            State::Idle
+        });
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpulist() {
+        assert_eq!(parse_cpulist("0-3,6").unwrap(), 5);
+        assert_eq!(parse_cpulist("0-7").unwrap(), 8);
+        assert_eq!(parse_cpulist("0").unwrap(), 1);
+        assert_eq!(parse_cpulist("0,2,4-5,8-11").unwrap(), 8);
+        // `offline` is empty when all CPUs are online
+        assert_eq!(parse_cpulist("").unwrap(), 0);
+        assert!(parse_cpulist("0-").is_err());
+        assert!(parse_cpulist("3-1").is_err());
+        assert!(parse_cpulist("a,b").is_err());
+    }
+}
@@ -5,6 +5,7 @@ use crossbeam_channel::Sender;
 use nix::sys::statvfs::statvfs;
 use serde_derive::Deserialize;
 
+use crate::blocks::calc::share_values;
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
@@ -27,6 +28,7 @@ pub struct DiskSpace {
     id: usize,
     disk_space: TextWidget,
     update_interval: Duration,
+    share_as: Option<String>,
     path: String,
     unit: Prefix,
     info_type: InfoType,
@@ -62,6 +64,9 @@ pub struct DiskSpaceConfig {
     #[serde(deserialize_with = "deserialize_duration")]
     pub interval: Duration,
 
+    /// Share the numeric placeholders with `calc` blocks under this name
+    pub share_as: Option<String>,
+
     /// Diskspace warning (yellow)
     pub warning: f64,
 
@@ -85,6 +90,7 @@ impl Default for DiskSpaceConfig {
             format: FormatTemplate::default(),
             unit: "GB".to_string(),
             interval: Duration::from_secs(20),
+            share_as: None,
             warning: 20.,
             alert: 10.,
             alert_absolute: false,
@@ -137,6 +143,7 @@ impl ConfigBlock for DiskSpace {
         Ok(DiskSpace {
             id,
             update_interval: block_config.interval,
+            share_as: block_config.share_as,
             disk_space: TextWidget::new(id, 0, shared_config),
             path: block_config.path,
             format: block_config.format.with_default("{available}")?,
@@ -204,6 +211,9 @@ impl Block for DiskSpace {
             "alias" => Value::from_string(self.alias.clone()),
         );
         self.disk_space.set_texts(self.format.render(&values)?);
+        if let Some(share_as) = &self.share_as {
+            share_values(share_as, &values)?;
+        }
 
         // Send percentage to alert check if we don't want absolute alerts
         let alert_val = if self.alert_absolute {
@@ -0,0 +1,360 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use dbus::ffidisp::stdintf::org_freedesktop_dbus::Properties;
+use dbus::ffidisp::{BusType, Connection};
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::scheduler::Task;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// The address families of the D-Bus API of systemd-resolved
+const AF_INET: i32 = 2;
+const AF_INET6: i32 = 10;
+
+/// A DNS server as systemd-resolved describes it: the index of the network interface it belongs
+/// to (0 for global servers), the address family and the address
+type DnsServer = (i32, i32, Vec<u8>);
+
+/// The settings of a resolver, either the global ones (`link` is `None`) or those of a network
+/// interface, which systemd-resolved calls a link
+trait Resolver {
+    /// Whether DNS over TLS is used, e.g. `yes`, `opportunistic` or `no`. Empty for a link which
+    /// uses the global setting.
+    fn dns_over_tls(&self, link: Option<i32>) -> Result<String>;
+    /// The server which is currently used
+    fn current_server(&self, link: Option<i32>) -> Result<DnsServer>;
+    /// The index of the link with DNS servers of its own which is used for the default route
+    fn default_route_link(&self) -> Result<Option<i32>>;
+}
+
+struct Resolved {
+    connection: Connection,
+}
+
+impl Resolved {
+    const DESTINATION: &'static str = "org.freedesktop.resolve1";
+    const PATH: &'static str = "/org/freedesktop/resolve1";
+    const INTERFACE: &'static str = "org.freedesktop.resolve1.Manager";
+    const LINK_INTERFACE: &'static str = "org.freedesktop.resolve1.Link";
+
+    fn get<T>(&self, link: Option<i32>, property: &str, error: &str) -> Result<T>
+    where
+        T: for<'b> dbus::arg::Get<'b> + 'static,
+    {
+        let (path, interface) = match link {
+            Some(link) => {
+                let (path,): (dbus::Path<'static>,) = self
+                    .connection
+                    .with_path(Self::DESTINATION, Self::PATH, 5000)
+                    .method_call(Self::INTERFACE, "GetLink", (link,))
+                    .block_error("dns", "Failed to get a network interface")?;
+                (path, Self::LINK_INTERFACE)
+            }
+            None => (Self::PATH.into(), Self::INTERFACE),
+        };
+        self.connection
+            .with_path(Self::DESTINATION, path, 5000)
+            .get(interface, property)
+            .block_error("dns", error)
+    }
+}
+
+impl Resolver for Resolved {
+    fn dns_over_tls(&self, link: Option<i32>) -> Result<String> {
+        self.get(
+            link,
+            "DNSOverTLS",
+            "Failed to get resolver settings. Is systemd-resolved running?",
+        )
+    }
+
+    fn current_server(&self, link: Option<i32>) -> Result<DnsServer> {
+        self.get(
+            link,
+            "CurrentDNSServer",
+            "Failed to get the current DNS server",
+        )
+    }
+
+    fn default_route_link(&self) -> Result<Option<i32>> {
+        // All servers, including those of links
+        let servers: Vec<DnsServer> = self.get(None, "DNS", "Failed to get the DNS servers")?;
+        let mut links: Vec<i32> = servers
+            .into_iter()
+            .map(|(link, _, _)| link)
+            .filter(|&link| link > 0)
+            .collect();
+        links.sort_unstable();
+        links.dedup();
+        for link in links {
+            // Before systemd 240 links have no `DefaultRoute`
+            if let Ok(true) = self.get(Some(link), "DefaultRoute", "") {
+                return Ok(Some(link));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DnsMode {
+    /// Encrypted with DNS over TLS
+    Tls,
+    /// Encrypted with DNS over TLS if the server supports it, and plain otherwise
+    Opportunistic,
+    Plain,
+}
+
+impl DnsMode {
+    fn name(self) -> &'static str {
+        match self {
+            DnsMode::Tls => "DoT",
+            DnsMode::Opportunistic => "DoT?",
+            DnsMode::Plain => "plain",
+        }
+    }
+
+    fn state(self) -> State {
+        match self {
+            DnsMode::Tls => State::Good,
+            DnsMode::Opportunistic => State::Idle,
+            DnsMode::Plain => State::Warning,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ResolverStatus {
+    mode: DnsMode,
+    /// The address of the current server, if there is one
+    server: Option<IpAddr>,
+}
+
+/// The status of the resolver. With per-link DNS, e.g. as NetworkManager configures it, the
+/// settings of the default route link are used where there are no global ones.
+fn resolver_status(resolver: &dyn Resolver) -> Result<ResolverStatus> {
+    let mut dns_over_tls = resolver.dns_over_tls(None)?;
+    let mut server = resolver.current_server(None)?;
+    if let Some(link) = resolver.default_route_link()? {
+        let link_dns_over_tls = resolver.dns_over_tls(Some(link))?;
+        if !link_dns_over_tls.is_empty() {
+            dns_over_tls = link_dns_over_tls;
+        }
+        if server.2.is_empty() {
+            server = resolver.current_server(Some(link))?;
+        }
+    }
+    let mode = match dns_over_tls.as_str() {
+        "yes" => DnsMode::Tls,
+        "opportunistic" => DnsMode::Opportunistic,
+        _ => DnsMode::Plain,
+    };
+    // Without a server, e.g. while offline, the address is empty
+    let server = match server {
+        (_, AF_INET, address) if address.len() == 4 => Some(IpAddr::V4(Ipv4Addr::new(
+            address[0], address[1], address[2], address[3],
+        ))),
+        (_, AF_INET6, address) if address.len() == 16 => {
+            let mut octets = [0; 16];
+            octets.copy_from_slice(&address);
+            Some(IpAddr::V6(Ipv6Addr::from(octets)))
+        }
+        _ => None,
+    };
+    Ok(ResolverStatus { mode, server })
+}
+
+pub struct Dns {
+    id: usize,
+    text: TextWidget,
+    resolver: Box<dyn Resolver>,
+    format: FormatTemplate,
+    update_interval: Duration,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct DnsConfig {
+    pub format: FormatTemplate,
+
+    /// Update interval in seconds
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        Self {
+            format: FormatTemplate::default(),
+            interval: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ConfigBlock for Dns {
+    type Config = DnsConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        let connection = Connection::get_private(BusType::System)
+            .block_error("dns", "Failed to establish D-Bus connection")?;
+        Ok(Dns {
+            id,
+            text: TextWidget::new(id, 0, shared_config),
+            resolver: Box::new(Resolved { connection }),
+            format: block_config.format.with_default("{dns_mode} {resolver}")?,
+            update_interval: block_config.interval,
+        })
+    }
+}
+
+impl Block for Dns {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        let status = resolver_status(self.resolver.as_ref())?;
+        let resolver = status
+            .server
+            .map(|server| server.to_string())
+            .unwrap_or_default();
+        let values = map!(
+            "dns_mode" => Value::from_string(status.mode.name().to_string()),
+            "resolver" => Value::from_string(resolver),
+        );
+        self.text.set_texts(self.format.render(&values)?);
+        self.text.set_state(status.mode.state());
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The properties of the resolve1 manager as systemd-resolved sends them
+    struct MockResolved {
+        dns_over_tls: &'static str,
+        current_server: DnsServer,
+        /// `DNSOverTLS` and `CurrentDNSServer` of the default route link
+        link: Option<(&'static str, DnsServer)>,
+    }
+
+    const LINK: i32 = 3;
+
+    impl Resolver for MockResolved {
+        fn dns_over_tls(&self, link: Option<i32>) -> Result<String> {
+            Ok(match (link, &self.link) {
+                (None, _) => self.dns_over_tls,
+                (Some(_), Some((dns_over_tls, _))) => dns_over_tls,
+                (Some(_), None) => unreachable!("not the default route link"),
+            }
+            .to_string())
+        }
+
+        fn current_server(&self, link: Option<i32>) -> Result<DnsServer> {
+            Ok(match (link, &self.link) {
+                (None, _) => self.current_server.clone(),
+                (Some(_), Some((_, server))) => server.clone(),
+                (Some(_), None) => unreachable!("not the default route link"),
+            })
+        }
+
+        fn default_route_link(&self) -> Result<Option<i32>> {
+            Ok(self.link.as_ref().map(|_| LINK))
+        }
+    }
+
+    fn status(dns_over_tls: &'static str, current_server: DnsServer) -> ResolverStatus {
+        resolver_status(&MockResolved {
+            dns_over_tls,
+            current_server,
+            link: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn modes() {
+        let cloudflare = || (0, AF_INET, vec![1, 1, 1, 1]);
+        assert_eq!(status("yes", cloudflare()).mode, DnsMode::Tls);
+        assert_eq!(
+            status("opportunistic", cloudflare()).mode,
+            DnsMode::Opportunistic
+        );
+        assert_eq!(status("no", cloudflare()).mode, DnsMode::Plain);
+        assert_eq!(status("", cloudflare()).mode, DnsMode::Plain);
+
+        assert!(matches!(DnsMode::Tls.state(), State::Good));
+        assert!(matches!(DnsMode::Plain.state(), State::Warning));
+    }
+
+    #[test]
+    fn servers() {
+        assert_eq!(
+            status("yes", (0, AF_INET, vec![1, 1, 1, 1])).server,
+            Some("1.1.1.1".parse().unwrap())
+        );
+        // A server of a network interface
+        let quad9 = vec![0x26, 0x20, 0, 0xfe, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xfe];
+        assert_eq!(
+            status("no", (3, AF_INET6, quad9)).server,
+            Some("2620:fe::fe".parse().unwrap())
+        );
+        // No server, e.g. while offline
+        assert_eq!(status("no", (0, 0, vec![])).server, None);
+        assert_eq!(status("no", (0, AF_INET, vec![1, 1])).server, None);
+    }
+
+    #[test]
+    fn default_route_link() {
+        let status = |dns_over_tls, current_server, link| {
+            resolver_status(&MockResolved {
+                dns_over_tls,
+                current_server,
+                link: Some(link),
+            })
+            .unwrap()
+        };
+        let cloudflare = || (0, AF_INET, vec![1, 1, 1, 1]);
+        let router = || (LINK, AF_INET, vec![192, 168, 1, 1]);
+        let no_server = || (0, 0, vec![]);
+
+        // Only the link has a server and a DNS over TLS setting
+        assert_eq!(
+            status("no", no_server(), ("opportunistic", router())),
+            ResolverStatus {
+                mode: DnsMode::Opportunistic,
+                server: Some("192.168.1.1".parse().unwrap()),
+            }
+        );
+        // The link uses the global DNS over TLS setting
+        assert_eq!(
+            status("yes", no_server(), ("", router())).mode,
+            DnsMode::Tls
+        );
+        // The global server is preferred
+        assert_eq!(
+            status("no", cloudflare(), ("", router())).server,
+            Some("1.1.1.1".parse().unwrap())
+        );
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use dbus::ffidisp::{BusType, Connection, ConnectionItem};
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_opt_duration;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// The parts of the fcitx5 controller D-Bus interface used by this block
+trait Fcitx5Controller {
+    fn current_input_method(&self) -> Result<String>;
+    /// 0 - closed, 1 - inactive, 2 - active
+    fn state(&self) -> Result<i32>;
+    fn toggle(&self) -> Result<()>;
+}
+
+struct Fcitx5DBus {
+    connection: Connection,
+}
+
+impl Fcitx5DBus {
+    const DESTINATION: &'static str = "org.fcitx.Fcitx5";
+    const PATH: &'static str = "/controller";
+    const INTERFACE: &'static str = "org.fcitx.Fcitx.Controller1";
+}
+
+impl Fcitx5Controller for Fcitx5DBus {
+    fn current_input_method(&self) -> Result<String> {
+        let (name,): (String,) = self
+            .connection
+            .with_path(Self::DESTINATION, Self::PATH, 1000)
+            .method_call(Self::INTERFACE, "CurrentInputMethod", ())
+            .block_error("fcitx", "failed to get the current input method")?;
+        Ok(name)
+    }
+
+    fn state(&self) -> Result<i32> {
+        let (state,): (i32,) = self
+            .connection
+            .with_path(Self::DESTINATION, Self::PATH, 1000)
+            .method_call(Self::INTERFACE, "State", ())
+            .block_error("fcitx", "failed to get the input method state")?;
+        Ok(state)
+    }
+
+    fn toggle(&self) -> Result<()> {
+        self.connection
+            .with_path(Self::DESTINATION, Self::PATH, 1000)
+            .method_call(Self::INTERFACE, "Toggle", ())
+            .block_error("fcitx", "failed to toggle the input method")
+    }
+}
+
+/// Ask for an update on every signal of fcitx5, and when it starts or stops. fcitx5 has no signal
+/// for switching the input method, but its tray icon and its kimpanel module signal new icons and
+/// properties then.
+fn watch(id: usize, tx: Sender<Task>) -> Result<()> {
+    let subscribe = || {
+        let connection = Connection::get_private(BusType::Session).block_error(
+            "fcitx",
+            "failed to establish D-Bus connection to session bus",
+        )?;
+        let rules = [
+            format!("type='signal',sender='{}'", Fcitx5DBus::DESTINATION),
+            format!(
+                "type='signal',interface='org.freedesktop.DBus',member='NameOwnerChanged',\
+                 path='/org/freedesktop/DBus',arg0='{}'",
+                Fcitx5DBus::DESTINATION
+            ),
+        ];
+        for rule in &rules {
+            connection
+                .add_match(rule)
+                .block_error("fcitx", "failed to add D-Bus match rule")?;
+        }
+        Ok(connection)
+    };
+    // The connection can't be moved to another thread, so the thread reports whether it could
+    // subscribe to the signals
+    let (subscribed_tx, subscribed_rx) = crossbeam_channel::bounded(1);
+    thread::Builder::new()
+        .name("fcitx".into())
+        .spawn(move || {
+            let connection = match subscribe() {
+                Ok(connection) => connection,
+                Err(error) => {
+                    let _ = subscribed_tx.send(Err(error));
+                    return;
+                }
+            };
+            let _ = subscribed_tx.send(Ok(()));
+            for item in connection.iter(60_000) {
+                if let ConnectionItem::Signal(_) = item {
+                    let task = Task {
+                        id,
+                        update_time: Instant::now(),
+                    };
+                    if tx.send(task).is_err() {
+                        break;
+                    }
+                }
+            }
+        })
+        .block_error("fcitx", "failed to start watching thread")?;
+    subscribed_rx
+        .recv()
+        .block_error("fcitx", "failed to start watching thread")?
+}
+
+/// Query the current engine and mode. `None` if fcitx5 is not running.
+fn input_method_status(controller: &dyn Fcitx5Controller) -> Option<(String, &'static str)> {
+    let engine = controller.current_input_method().ok()?;
+    let mode = match controller.state().ok()? {
+        2 => "active",
+        1 => "inactive",
+        _ => "closed",
+    };
+    Some((engine, mode))
+}
+
+pub struct Fcitx {
+    id: usize,
+    text: TextWidget,
+    controller: Box<dyn Fcitx5Controller>,
+    mappings: Option<BTreeMap<String, String>>,
+    format: FormatTemplate,
+    update_interval: Option<Duration>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct FcitxConfig {
+    /// Update interval in seconds, in addition to the updates on signals of fcitx5
+    #[serde(deserialize_with = "deserialize_opt_duration")]
+    pub interval: Option<Duration>,
+
+    pub mappings: Option<BTreeMap<String, String>>,
+    pub format: FormatTemplate,
+}
+
+impl ConfigBlock for Fcitx {
+    type Config = FcitxConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        tx: Sender<Task>,
+    ) -> Result<Self> {
+        watch(id, tx)?;
+        let connection = Connection::get_private(BusType::Session).block_error(
+            "fcitx",
+            "failed to establish D-Bus connection to session bus",
+        )?;
+        Ok(Fcitx {
+            id,
+            text: TextWidget::new(id, 0, shared_config),
+            controller: Box::new(Fcitx5DBus { connection }),
+            mappings: block_config.mappings,
+            format: block_config.format.with_default("{engine}")?,
+            update_interval: block_config.interval,
+        })
+    }
+}
+
+impl Block for Fcitx {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        let (engine, mode) = match input_method_status(self.controller.as_ref()) {
+            Some((engine, mode)) => {
+                let engine = match self.mappings.as_ref().and_then(|m| m.get(&engine)) {
+                    Some(mapping) => mapping.to_string(),
+                    None => engine,
+                };
+                self.text.set_state(State::Idle);
+                (engine, mode)
+            }
+            None => {
+                self.text.set_state(State::Warning);
+                (String::new(), "absent")
+            }
+        };
+
+        let values = map!(
+            "engine" => Value::from_string(engine),
+            "mode" => Value::from_string(mode.to_string()),
+        );
+
+        self.text.set_texts(self.format.render(&values)?);
+        Ok(self.update_interval.map(Into::into))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        if let MouseButton::Left = e.button {
+            self.controller.toggle()?;
+            self.update()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockController(Option<(&'static str, i32)>);
+
+    impl Fcitx5Controller for MockController {
+        fn current_input_method(&self) -> Result<String> {
+            match self.0 {
+                Some((name, _)) => Ok(name.to_string()),
+                None => Err(BlockError("fcitx".to_string(), "not running".to_string())),
+            }
+        }
+
+        fn state(&self) -> Result<i32> {
+            match self.0 {
+                Some((_, state)) => Ok(state),
+                None => Err(BlockError("fcitx".to_string(), "not running".to_string())),
+            }
+        }
+
+        fn toggle(&self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn status() {
+        assert_eq!(
+            input_method_status(&MockController(Some(("mozc", 2)))),
+            Some(("mozc".to_string(), "active"))
+        );
+        assert_eq!(
+            input_method_status(&MockController(Some(("keyboard-us", 1)))),
+            Some(("keyboard-us".to_string(), "inactive"))
+        );
+        assert_eq!(input_method_status(&MockController(None)), None);
+    }
+}
@@ -0,0 +1,201 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use crossbeam_channel::Sender;
+use nix::sys::stat::Mode;
+use nix::unistd::mkfifo;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::scheduler::Task;
+use crate::util::escape_pango_text;
+use crate::widgets::text::TextWidget;
+use crate::widgets::I3BarWidget;
+
+/// Create the FIFO at `path` unless it already exists
+fn ensure_fifo(path: &Path) -> Result<()> {
+    match path.metadata() {
+        Ok(metadata) if metadata.file_type().is_fifo() => Ok(()),
+        Ok(_) => Err(ConfigurationError(
+            "fifo".to_string(),
+            format!("'{}' exists but is not a FIFO", path.display()),
+        )),
+        Err(_) => mkfifo(path, Mode::S_IRUSR | Mode::S_IWUSR)
+            .configuration_error(&format!("failed to create FIFO '{}'", path.display())),
+    }
+}
+
+/// Call `on_line` for every line written to the FIFO. When all writers have disconnected the
+/// FIFO is reopened, which blocks until the next writer connects. Lines which aren't valid UTF-8
+/// are converted lossily. Only returns on errors.
+fn read_lines(path: &Path, mut on_line: impl FnMut(String)) -> Result<()> {
+    let mut line = Vec::new();
+    loop {
+        let fifo = File::open(path).block_error("fifo", "failed to open FIFO")?;
+        let mut reader = BufReader::new(fifo);
+        while reader
+            .read_until(b'\n', &mut line)
+            .block_error("fifo", "failed to read from FIFO")?
+            > 0
+        {
+            if line.last() == Some(&b'\n') {
+                line.pop();
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+            }
+            on_line(String::from_utf8_lossy(&line).into_owned());
+            line.clear();
+        }
+    }
+}
+
+pub struct Fifo {
+    id: usize,
+    text: TextWidget,
+    /// The latest line, or the error which stopped reading the FIFO
+    line: Arc<Mutex<std::result::Result<String, String>>>,
+    format: FormatTemplate,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct FifoConfig {
+    /// Path of the FIFO, which is created if it doesn't exist
+    pub path: PathBuf,
+
+    pub format: FormatTemplate,
+}
+
+impl ConfigBlock for Fifo {
+    type Config = FifoConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        send: Sender<Task>,
+    ) -> Result<Self> {
+        if block_config.path.as_os_str().is_empty() {
+            return Err(ConfigurationError(
+                "fifo".to_string(),
+                "'path' must be set".to_string(),
+            ));
+        }
+        ensure_fifo(&block_config.path)?;
+
+        let line = Arc::new(Mutex::new(Ok(String::new())));
+        {
+            let line = line.clone();
+            let path = block_config.path;
+            thread::Builder::new()
+                .name("fifo".into())
+                .spawn(move || {
+                    let update = |new_line| {
+                        *line.lock().expect("lock has been poisoned in `fifo` block") = new_line;
+                        send.send(Task {
+                            id,
+                            update_time: Instant::now(),
+                        })
+                        .expect("could not communicate with channel in `fifo` block");
+                    };
+                    // The error is reported by the next update
+                    if let Err(BlockError(_, message)) =
+                        read_lines(&path, |new_line| update(Ok(new_line)))
+                    {
+                        update(Err(message));
+                    }
+                })
+                .expect("failed to start watching thread for `fifo` block");
+        }
+
+        Ok(Fifo {
+            id,
+            text: TextWidget::new(id, 0, shared_config),
+            line,
+            format: block_config.format.with_default("{line}")?,
+        })
+    }
+}
+
+impl Block for Fifo {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        let line = self
+            .line
+            .lock()
+            .block_error("fifo", "failed to acquire lock")?
+            .clone();
+        let line = line.map_err(|message| BlockError("fifo".to_string(), message))?;
+        let values = map!(
+            "line" => Value::from_string(escape_pango_text(line)),
+        );
+        self.text.set_texts(self.format.render(&values)?);
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use std::time::Duration;
+
+    #[test]
+    fn latest_line() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("status.fifo");
+        ensure_fifo(&path).unwrap();
+        // Creating it again is fine
+        ensure_fifo(&path).unwrap();
+
+        let (tx, rx) = crossbeam_channel::unbounded();
+        {
+            let path = path.clone();
+            thread::spawn(move || read_lines(&path, |line| tx.send(line).unwrap()));
+        }
+        let recv = || rx.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        let mut writer = OpenOptions::new().write(true).open(&path).unwrap();
+        writeln!(writer, "first\nsecond").unwrap();
+        assert_eq!(recv(), "first");
+        assert_eq!(recv(), "second");
+        drop(writer);
+
+        // A new writer after the previous one disconnected
+        let mut writer = OpenOptions::new().write(true).open(&path).unwrap();
+        writeln!(writer, "third").unwrap();
+        assert_eq!(recv(), "third");
+
+        // Invalid UTF-8 doesn't stop reading
+        writer.write_all(b"f\xffourth\r\nfifth").unwrap();
+        drop(writer);
+        assert_eq!(recv(), "f\u{fffd}ourth");
+        assert_eq!(recv(), "fifth");
+    }
+
+    #[test]
+    fn not_a_fifo() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("regular");
+        File::create(&path).unwrap();
+        assert!(ensure_fifo(&path).is_err());
+    }
+}
@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use nix::unistd::{sysconf, SysconfVar};
+use serde_derive::Deserialize;
+use swayipc::reply::Node;
+use swayipc::Connection;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::scheduler::Task;
+use crate::util::read_file;
+use crate::widgets::text::TextWidget;
+use crate::widgets::I3BarWidget;
+
+/// The PID of the focused window, if there is a focused window at all and the compositor knows
+/// its process. Windows of e.g. X11 applications under Xwayland may have none.
+fn focused_pid(tree: &Node) -> Option<i32> {
+    tree.find_focused_as_ref(|node| node.focused)?.pid
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ProcessStats {
+    name: String,
+    /// The CPU time used so far, in clock ticks
+    cpu_ticks: u64,
+    /// The resident memory in bytes
+    mem: u64,
+}
+
+/// Read the stats of process `pid` from `proc_dir`, usually `/proc`
+fn read_process(proc_dir: &Path, pid: i32) -> Result<ProcessStats> {
+    let process_dir = proc_dir.join(pid.to_string());
+    let stat = read_file("focused_app", &process_dir.join("stat"))?;
+    // The name is in parentheses and may contain anything, including spaces and parentheses
+    let (name, fields) = match (stat.find('('), stat.rfind(')')) {
+        (Some(start), Some(end)) if start < end => (&stat[start + 1..end], &stat[end + 1..]),
+        _ => {
+            return Err(BlockError(
+                "focused_app".to_string(),
+                format!("invalid stat '{}'", stat),
+            ))
+        }
+    };
+    // The fields after the name start with the third, and `utime` and `stime` are the 14th and
+    // the 15th
+    let fields: Vec<&str> = fields.split_whitespace().collect();
+    let ticks = |i: usize| -> Result<u64> {
+        fields
+            .get(i)
+            .and_then(|field| field.parse().ok())
+            .block_error("focused_app", "failed to parse the CPU time")
+    };
+    let cpu_ticks = ticks(11)? + ticks(12)?;
+
+    // Kernel threads have no memory of their own
+    let status = read_file("focused_app", &process_dir.join("status"))?;
+    let mem = match status.lines().find_map(|line| line.strip_prefix("VmRSS:")) {
+        Some(rss) => {
+            rss.trim()
+                .trim_end_matches("kB")
+                .trim()
+                .parse::<u64>()
+                .block_error("focused_app", "failed to parse the resident memory")?
+                * 1024
+        }
+        None => 0,
+    };
+
+    Ok(ProcessStats {
+        name: name.to_string(),
+        cpu_ticks,
+        mem,
+    })
+}
+
+/// A measurement of the CPU time of a process
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    pid: i32,
+    cpu_ticks: u64,
+    time: Instant,
+}
+
+/// The CPU usage in percent of one CPU between two samples of the same process
+fn cpu_usage(previous: &Sample, current: &Sample, ticks_per_second: f64) -> f64 {
+    let elapsed = current.time.duration_since(previous.time).as_secs_f64();
+    if previous.pid != current.pid || elapsed == 0. {
+        return 0.;
+    }
+    let ticks = current.cpu_ticks.saturating_sub(previous.cpu_ticks) as f64;
+    ticks / ticks_per_second / elapsed * 100.
+}
+
+/// The values of the process `pid` of the focused window, if there is one. `previous` is the
+/// last sample, which is replaced with the current one.
+fn process_values(
+    proc_dir: &Path,
+    pid: Option<i32>,
+    previous: &mut Option<Sample>,
+    now: Instant,
+    ticks_per_second: f64,
+) -> Option<HashMap<&'static str, Value>> {
+    // The process may have exited since the tree was read
+    let (pid, stats) = match pid.map(|pid| (pid, read_process(proc_dir, pid))) {
+        Some((pid, Ok(stats))) => (pid, stats),
+        _ => {
+            *previous = None;
+            return None;
+        }
+    };
+    let current = Sample {
+        pid,
+        cpu_ticks: stats.cpu_ticks,
+        time: now,
+    };
+    // The first sample of a process has nothing to compare with
+    let cpu = match previous {
+        Some(previous) => cpu_usage(previous, &current, ticks_per_second),
+        None => 0.,
+    };
+    *previous = Some(current);
+    Some(map!(
+        "name" => Value::from_string(stats.name),
+        "pid" => Value::from_integer(pid as i64),
+        "cpu" => Value::from_integer(cpu.round() as i64).percents(),
+        "mem" => Value::from_float(stats.mem as f64).bytes(),
+    ))
+}
+
+pub struct FocusedApp {
+    id: usize,
+    text: TextWidget,
+    format: FormatTemplate,
+    connection: Connection,
+    ticks_per_second: f64,
+    previous: Option<Sample>,
+    update_interval: Duration,
+    visible: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct FocusedAppConfig {
+    pub format: FormatTemplate,
+
+    /// Update interval in seconds
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+}
+
+impl Default for FocusedAppConfig {
+    fn default() -> Self {
+        Self {
+            format: FormatTemplate::default(),
+            interval: Duration::from_secs(2),
+        }
+    }
+}
+
+impl ConfigBlock for FocusedApp {
+    type Config = FocusedAppConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        let ticks_per_second = match sysconf(SysconfVar::CLK_TCK) {
+            Ok(Some(ticks)) if ticks > 0 => ticks as f64,
+            _ => 100.,
+        };
+        Ok(FocusedApp {
+            id,
+            text: TextWidget::new(id, 0, shared_config),
+            format: block_config.format.with_default("{name} {cpu} {mem}")?,
+            connection: Connection::new()
+                .block_error("focused_app", "failed to acquire connect to IPC")?,
+            ticks_per_second,
+            previous: None,
+            update_interval: block_config.interval,
+            visible: false,
+        })
+    }
+}
+
+impl Block for FocusedApp {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        let tree = self
+            .connection
+            .get_tree()
+            .block_error("focused_app", "failed to get the tree")?;
+        let values = process_values(
+            Path::new("/proc"),
+            focused_pid(&tree),
+            &mut self.previous,
+            Instant::now(),
+            self.ticks_per_second,
+        );
+        match values {
+            Some(values) => {
+                self.text.set_texts(self.format.render(&values)?);
+                self.visible = true;
+            }
+            None => self.visible = false,
+        }
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        if self.visible {
+            vec![&self.text]
+        } else {
+            vec![]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::sway_fixtures;
+    use assert_fs::prelude::*;
+    use serde_json::json;
+
+    fn node(
+        id: i64,
+        node_type: &str,
+        focused: bool,
+        pid: Option<i32>,
+        nodes: Vec<serde_json::Value>,
+    ) -> serde_json::Value {
+        let mut node = sway_fixtures::node(id, node_type, nodes, vec![]);
+        node["focused"] = json!(focused);
+        node["pid"] = json!(pid);
+        node
+    }
+
+    /// A tree with a single workspace holding `windows`, whose first one has the focus
+    fn tree(windows: Vec<serde_json::Value>) -> Node {
+        let workspace_focused = windows.is_empty();
+        let tree = node(
+            1,
+            "root",
+            false,
+            None,
+            vec![node(
+                2,
+                "output",
+                false,
+                None,
+                vec![node(3, "workspace", workspace_focused, None, windows)],
+            )],
+        );
+        serde_json::from_value(tree).unwrap()
+    }
+
+    fn write_process(proc_dir: &assert_fs::TempDir, pid: i32, cpu_ticks: u64, rss_kb: u64) {
+        let (utime, stime) = (cpu_ticks * 3 / 4, cpu_ticks - cpu_ticks * 3 / 4);
+        proc_dir
+            .child(format!("{}/stat", pid))
+            .write_str(&format!(
+                "{} (Web Content (2)) S 1 {} {} 0 -1 4194560 29 0 0 0 {} {} 0 0 20 0 1 0 4861 0\n",
+                pid, pid, pid, utime, stime
+            ))
+            .unwrap();
+        proc_dir
+            .child(format!("{}/status", pid))
+            .write_str(&format!(
+                "Name:\tWeb Content\nState:\tS (sleeping)\nVmRSS:\t  {} kB\nThreads:\t1\n",
+                rss_kb
+            ))
+            .unwrap();
+    }
+
+    #[test]
+    fn focused_process() {
+        let tree = tree(vec![
+            node(10, "con", true, Some(4242), vec![]),
+            node(11, "con", false, Some(1000), vec![]),
+        ]);
+        assert_eq!(focused_pid(&tree), Some(4242));
+
+        let proc_dir = assert_fs::TempDir::new().unwrap();
+        assert!(read_process(proc_dir.path(), 4242).is_err());
+        write_process(&proc_dir, 4242, 400, 2048);
+        assert_eq!(
+            read_process(proc_dir.path(), focused_pid(&tree).unwrap()).unwrap(),
+            ProcessStats {
+                name: "Web Content (2)".to_string(),
+                cpu_ticks: 400,
+                mem: 2048 * 1024,
+            }
+        );
+    }
+
+    #[test]
+    fn values() {
+        let proc_dir = assert_fs::TempDir::new().unwrap();
+        write_process(&proc_dir, 4242, 400, 2048);
+        write_process(&proc_dir, 1000, 50, 1024);
+        let render = |values: HashMap<&str, Value>| {
+            FormatTemplate::new("{name}:{pid} {cpu} {mem}", None)
+                .unwrap()
+                .render(&values)
+                .unwrap()
+                .full
+        };
+        let start = Instant::now();
+        let mut previous = None;
+        let mut values = |windows, ms| {
+            let pid = focused_pid(&tree(windows));
+            let now = start + Duration::from_millis(ms);
+            process_values(proc_dir.path(), pid, &mut previous, now, 100.)
+        };
+
+        let firefox = || node(10, "con", true, Some(4242), vec![]);
+        assert_eq!(
+            render(values(vec![firefox()], 0).unwrap()),
+            "Web Content (2):4242  0% 2.0MB"
+        );
+        write_process(&proc_dir, 4242, 450, 2048);
+        assert_eq!(
+            render(values(vec![firefox()], 1000).unwrap()),
+            "Web Content (2):4242 50% 2.0MB"
+        );
+        // Another window starts over
+        let terminal = node(11, "con", true, Some(1000), vec![]);
+        assert_eq!(
+            render(values(vec![terminal], 2000).unwrap()),
+            "Web Content (2):1000  0% 1.0MB"
+        );
+        // No process, or one that has exited
+        assert!(values(vec![], 3000).is_none());
+        let xwayland = node(12, "con", true, None, vec![]);
+        assert!(values(vec![xwayland], 4000).is_none());
+        let exited = node(13, "con", true, Some(31337), vec![]);
+        assert!(values(vec![exited], 5000).is_none());
+    }
+
+    #[test]
+    fn no_pid() {
+        // An empty workspace has the focus
+        assert_eq!(focused_pid(&tree(vec![])), None);
+        // A window without a process, e.g. under Xwayland
+        let tree = tree(vec![node(10, "con", true, None, vec![])]);
+        assert_eq!(focused_pid(&tree), None);
+    }
+
+    #[test]
+    fn usage() {
+        let start = Instant::now();
+        let sample = |pid, cpu_ticks, ms| Sample {
+            pid,
+            cpu_ticks,
+            time: start + Duration::from_millis(ms),
+        };
+        // 50 ticks in half a second are all of one CPU, and 100 ticks are two
+        assert_eq!(
+            cpu_usage(&sample(1, 100, 0), &sample(1, 150, 500), 100.),
+            100.
+        );
+        assert_eq!(
+            cpu_usage(&sample(1, 100, 0), &sample(1, 200, 500), 100.),
+            200.
+        );
+        assert_eq!(
+            cpu_usage(&sample(1, 100, 0), &sample(1, 110, 1000), 100.),
+            10.
+        );
+        // Another process can't be compared with
+        assert_eq!(
+            cpu_usage(&sample(1, 100, 0), &sample(2, 900, 1000), 100.),
+            0.
+        );
+        assert_eq!(cpu_usage(&sample(1, 100, 0), &sample(1, 150, 0), 100.), 0.);
+    }
+}
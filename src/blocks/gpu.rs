@@ -0,0 +1,256 @@
+use std::fs::{read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::scheduler::Task;
+use crate::widgets::text::TextWidget;
+use crate::widgets::I3BarWidget;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct GpuInfo {
+    /// Utilization in percent
+    usage: f64,
+    /// Temperature in degrees Celsius
+    temp: f64,
+    /// Used video memory in bytes
+    mem_used: f64,
+}
+
+trait GpuSource {
+    fn info(&self) -> Result<GpuInfo>;
+}
+
+struct NvidiaSmi {
+    index: usize,
+}
+
+/// Parse the output of
+/// `nvidia-smi --query-gpu=utilization.gpu,temperature.gpu,memory.used --format=csv,noheader,nounits`
+fn parse_nvidia_smi(output: &str) -> Result<GpuInfo> {
+    let fields: Vec<f64> = output
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split(',')
+        .map(|field| field.trim().parse())
+        .collect::<std::result::Result<_, _>>()
+        .block_error("gpu", "failed to parse nvidia-smi output")?;
+    match fields[..] {
+        [usage, temp, mem_used_mib] => Ok(GpuInfo {
+            usage,
+            temp,
+            mem_used: mem_used_mib * 1024. * 1024.,
+        }),
+        _ => Err(BlockError(
+            "gpu".to_string(),
+            "unexpected nvidia-smi output".to_string(),
+        )),
+    }
+}
+
+impl GpuSource for NvidiaSmi {
+    fn info(&self) -> Result<GpuInfo> {
+        let output = Command::new("nvidia-smi")
+            .args([
+                "-i",
+                &self.index.to_string(),
+                "--query-gpu=utilization.gpu,temperature.gpu,memory.used",
+                "--format=csv,noheader,nounits",
+            ])
+            .output()
+            .block_error("gpu", "no NVIDIA GPU detected: failed to run nvidia-smi")?;
+        if !output.status.success() {
+            return Err(BlockError(
+                "gpu".to_string(),
+                format!(
+                    "no NVIDIA GPU detected: {}",
+                    String::from_utf8_lossy(&output.stdout).trim()
+                ),
+            ));
+        }
+        parse_nvidia_smi(&String::from_utf8_lossy(&output.stdout))
+    }
+}
+
+/// An AMD GPU driven by amdgpu, e.g. `/sys/class/drm/card0/device`
+struct AmdSysfs {
+    device: PathBuf,
+}
+
+fn read_number(path: &Path) -> Result<f64> {
+    read_to_string(path)
+        .block_error("gpu", &format!("failed to read {}", path.display()))?
+        .trim()
+        .parse()
+        .block_error("gpu", &format!("failed to parse {}", path.display()))
+}
+
+impl GpuSource for AmdSysfs {
+    fn info(&self) -> Result<GpuInfo> {
+        if !self.device.join("gpu_busy_percent").exists() {
+            return Err(BlockError(
+                "gpu".to_string(),
+                format!("no AMD GPU detected at {}", self.device.display()),
+            ));
+        }
+        let hwmon = read_dir(self.device.join("hwmon"))
+            .block_error("gpu", "failed to find the hwmon directory of the GPU")?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .find(|path| path.join("temp1_input").exists())
+            .block_error("gpu", "failed to find the temperature sensor of the GPU")?;
+        Ok(GpuInfo {
+            usage: read_number(&self.device.join("gpu_busy_percent"))?,
+            temp: read_number(&hwmon.join("temp1_input"))? / 1000.,
+            mem_used: read_number(&self.device.join("mem_info_vram_used"))?,
+        })
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum GpuDriver {
+    Nvidia,
+    Amd,
+}
+
+pub struct Gpu {
+    id: usize,
+    text: TextWidget,
+    source: Box<dyn GpuSource>,
+    format: FormatTemplate,
+    update_interval: Duration,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct GpuConfig {
+    pub driver: GpuDriver,
+
+    /// The index of the GPU as reported by `nvidia-smi`, or the number of the DRM card (AMD)
+    pub gpu: usize,
+
+    pub format: FormatTemplate,
+
+    /// Update interval in seconds
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+}
+
+impl Default for GpuConfig {
+    fn default() -> Self {
+        Self {
+            driver: GpuDriver::Nvidia,
+            gpu: 0,
+            format: FormatTemplate::default(),
+            interval: Duration::from_secs(1),
+        }
+    }
+}
+
+impl ConfigBlock for Gpu {
+    type Config = GpuConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        let source: Box<dyn GpuSource> = match block_config.driver {
+            GpuDriver::Nvidia => Box::new(NvidiaSmi {
+                index: block_config.gpu,
+            }),
+            GpuDriver::Amd => Box::new(AmdSysfs {
+                device: PathBuf::from(format!("/sys/class/drm/card{}/device", block_config.gpu)),
+            }),
+        };
+        Ok(Gpu {
+            id,
+            text: TextWidget::new(id, 0, shared_config).with_icon("gpu")?,
+            source,
+            format: block_config.format.with_default("{usage} {temp}")?,
+            update_interval: block_config.interval,
+        })
+    }
+}
+
+impl Block for Gpu {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        let info = self.source.info()?;
+        let values = map!(
+            "usage" => Value::from_float(info.usage).percents(),
+            "temp" => Value::from_float(info.temp).degrees(),
+            "mem_used" => Value::from_float(info.mem_used).bytes(),
+        );
+        self.text.set_texts(self.format.render(&values)?);
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn nvidia() {
+        assert_eq!(
+            parse_nvidia_smi("37, 54, 1024\n").unwrap(),
+            GpuInfo {
+                usage: 37.,
+                temp: 54.,
+                mem_used: 1024. * 1024. * 1024.,
+            }
+        );
+        assert!(parse_nvidia_smi("[N/A], 54, 1024\n").is_err());
+        assert!(parse_nvidia_smi("37, 54\n").is_err());
+        assert!(parse_nvidia_smi("").is_err());
+    }
+
+    #[test]
+    fn amd() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let amd = AmdSysfs {
+            device: dir.path().to_path_buf(),
+        };
+        assert!(amd.info().is_err());
+
+        dir.child("gpu_busy_percent").write_str("12\n").unwrap();
+        dir.child("mem_info_vram_used")
+            .write_str("536870912\n")
+            .unwrap();
+        dir.child("hwmon/hwmon3/name")
+            .write_str("amdgpu\n")
+            .unwrap();
+        dir.child("hwmon/hwmon3/temp1_input")
+            .write_str("47000\n")
+            .unwrap();
+        assert_eq!(
+            amd.info().unwrap(),
+            GpuInfo {
+                usage: 12.,
+                temp: 47.,
+                mem_used: 536870912.,
+            }
+        );
+    }
+}
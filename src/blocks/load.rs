@@ -5,6 +5,7 @@ use std::time::Duration;
 use crossbeam_channel::Sender;
 use serde_derive::Deserialize;
 
+use crate::blocks::calc::share_values;
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
@@ -21,6 +22,7 @@ pub struct Load {
     logical_cores: u32,
     format: FormatTemplate,
     update_interval: Duration,
+    share_as: Option<String>,
     minimum_info: f64,
     minimum_warning: f64,
     minimum_critical: f64,
@@ -33,6 +35,9 @@ pub struct LoadConfig {
     #[serde(deserialize_with = "deserialize_duration")]
     pub interval: Duration,
 
+    /// Share the numeric placeholders with `calc` blocks under this name
+    pub share_as: Option<String>,
+
     /// Minimum load, where state is set to info
     pub info: f64,
 
@@ -48,6 +53,7 @@ impl Default for LoadConfig {
         Self {
             format: FormatTemplate::default(),
             interval: Duration::from_secs(5),
+            share_as: None,
             info: 0.3,
             warning: 0.6,
             critical: 0.9,
@@ -80,6 +86,7 @@ impl ConfigBlock for Load {
             id,
             logical_cores,
             update_interval: block_config.interval,
+            share_as: block_config.share_as,
             minimum_info: block_config.info,
             minimum_warning: block_config.warning,
             minimum_critical: block_config.critical,
@@ -124,6 +131,9 @@ impl Block for Load {
         });
 
         self.text.set_texts(self.format.render(&values)?);
+        if let Some(share_as) = &self.share_as {
+            share_values(share_as, &values)?;
+        }
 
         Ok(Some(self.update_interval.into()))
     }
@@ -7,12 +7,13 @@ use std::time::{Duration, Instant};
 use crossbeam_channel::Sender;
 use serde_derive::Deserialize;
 
+use crate::blocks::calc::share_values;
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
 use crate::formatting::value::Value;
-use crate::formatting::FormatTemplate;
+use crate::formatting::{FormatTemplate, Rendered};
 use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
 use crate::widgets::text::TextWidget;
@@ -110,6 +111,7 @@ pub struct Memory {
     clickable: bool,
     format: (FormatTemplate, FormatTemplate),
     update_interval: Duration,
+    share_as: Option<String>,
     tx_update_request: Sender<Task>,
     warning: (f64, f64),
     critical: (f64, f64),
@@ -137,6 +139,9 @@ pub struct MemoryConfig {
     #[serde(deserialize_with = "deserialize_duration")]
     pub interval: Duration,
 
+    /// Share the numeric placeholders with `calc` blocks under this name
+    pub share_as: Option<String>,
+
     /// Percentage of memory usage, where state is set to warning
     pub warning_mem: f64,
 
@@ -159,6 +164,7 @@ impl Default for MemoryConfig {
             icons: true,
             clickable: true,
             interval: Duration::from_secs(5),
+            share_as: None,
             warning_mem: 80.,
             warning_swap: 80.,
             critical_mem: 95.,
@@ -168,7 +174,7 @@ impl Default for MemoryConfig {
 }
 
 impl Memory {
-    fn format_insert_values(&mut self, mem_state: Memstate) -> Result<(String, Option<String>)> {
+    fn format_insert_values(&mut self, mem_state: Memstate) -> Result<Rendered> {
         let mem_total = mem_state.mem_total() as f64 * 1024.;
         let mem_free = mem_state.mem_free() as f64 * 1024.;
         let swap_total = mem_state.swap_total() as f64 * 1024.;
@@ -220,6 +226,10 @@ impl Memory {
                 }),
         };
 
+        if let Some(share_as) = &self.share_as {
+            share_values(share_as, &values)?;
+        }
+
         Ok(match self.memtype {
             Memtype::Memory => self.format.0.render(&values)?,
             Memtype::Swap => self.format.1.render(&values)?,
@@ -266,6 +276,7 @@ impl ConfigBlock for Memory {
                     .with_default("{swap_free;M}/{swap_total;M}({swap_used_percents})")?,
             ),
             update_interval: block_config.interval,
+            share_as: block_config.share_as,
             tx_update_request: tx,
             warning: (block_config.warning_mem, block_config.warning_swap),
             critical: (block_config.critical_mem, block_config.critical_swap),
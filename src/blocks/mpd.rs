@@ -0,0 +1,415 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::escape_pango_text;
+use crate::widgets::text::TextWidget;
+use crate::widgets::I3BarWidget;
+
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+trait Stream: Read + Write + Send {}
+impl<T: Read + Write + Send> Stream for T {}
+
+/// Why a command failed
+enum CommandError {
+    /// The connection broke, e.g. because MPD restarted
+    Io(Error),
+    /// MPD refused the command with an `ACK`
+    Ack(String),
+}
+
+impl From<CommandError> for Error {
+    fn from(error: CommandError) -> Self {
+        match error {
+            CommandError::Io(error) => error,
+            CommandError::Ack(error) => BlockError("mpd".to_string(), error),
+        }
+    }
+}
+
+fn connect_tcp(address: &str) -> Result<TcpStream> {
+    let error = || format!("failed to connect to {}", address);
+    let mut last_error = None;
+    for address in address.to_socket_addrs().block_error("mpd", &error())? {
+        match TcpStream::connect_timeout(&address, TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(BlockError(
+        "mpd".to_string(),
+        match last_error {
+            Some(e) => format!("{}: {}", error(), e),
+            None => format!("{}: no address", error()),
+        },
+    ))
+}
+
+/// A connection speaking the MPD protocol
+struct MpdConnection {
+    stream: BufReader<Box<dyn Stream>>,
+}
+
+impl MpdConnection {
+    /// Connect to `address`, which is either `host:port` or the path of a unix socket
+    fn connect(address: &str) -> Result<Self> {
+        let stream: Box<dyn Stream> = if address.starts_with('/') {
+            let stream = UnixStream::connect(address)
+                .block_error("mpd", &format!("failed to connect to {}", address))?;
+            stream
+                .set_read_timeout(Some(TIMEOUT))
+                .and_then(|_| stream.set_write_timeout(Some(TIMEOUT)))
+                .block_error("mpd", "failed to set timeout")?;
+            Box::new(stream)
+        } else {
+            let stream = connect_tcp(address)?;
+            stream
+                .set_read_timeout(Some(TIMEOUT))
+                .and_then(|_| stream.set_write_timeout(Some(TIMEOUT)))
+                .block_error("mpd", "failed to set timeout")?;
+            Box::new(stream)
+        };
+        let mut connection = Self {
+            stream: BufReader::new(stream),
+        };
+        if !connection.read_line()?.starts_with("OK MPD ") {
+            return Err(BlockError(
+                "mpd".to_string(),
+                format!("{} is not an MPD server", address),
+            ));
+        }
+        Ok(connection)
+    }
+
+    fn read_line(&mut self) -> Result<String> {
+        let mut line = String::new();
+        if self
+            .stream
+            .read_line(&mut line)
+            .block_error("mpd", "failed to read from MPD")?
+            == 0
+        {
+            return Err(BlockError(
+                "mpd".to_string(),
+                "connection closed by MPD".to_string(),
+            ));
+        }
+        Ok(line.trim_end_matches('\n').to_string())
+    }
+
+    /// Send a command and collect the `key: value` pairs of the response. Only the first
+    /// occurrence of a key is kept.
+    fn command(
+        &mut self,
+        command: &str,
+    ) -> std::result::Result<HashMap<String, String>, CommandError> {
+        writeln!(self.stream.get_mut(), "{}", command)
+            .block_error("mpd", "failed to write to MPD")
+            .map_err(CommandError::Io)?;
+        let mut response = HashMap::new();
+        loop {
+            let line = self.read_line().map_err(CommandError::Io)?;
+            if line == "OK" {
+                return Ok(response);
+            }
+            if let Some(error) = line.strip_prefix("ACK ") {
+                return Err(CommandError::Ack(error.to_string()));
+            }
+            if let Some((key, value)) = line.split_once(": ") {
+                response
+                    .entry(key.to_string())
+                    .or_insert_with(|| value.to_string());
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct MpdStatus {
+    /// `play`, `pause` or `stop`
+    state: String,
+    title: String,
+    artist: String,
+    /// Elapsed time of the current song in seconds
+    elapsed: f64,
+}
+
+/// Keeps a connection to MPD, reconnecting when it was lost
+struct MpdClient {
+    address: String,
+    connection: Option<MpdConnection>,
+}
+
+impl MpdClient {
+    fn new(address: String) -> Self {
+        Self {
+            address,
+            connection: None,
+        }
+    }
+
+    fn command(&mut self, command: &str) -> Result<HashMap<String, String>> {
+        if let Some(connection) = &mut self.connection {
+            match connection.command(command) {
+                Ok(response) => return Ok(response),
+                // The connection is fine, MPD just refused the command
+                Err(error @ CommandError::Ack(_)) => return Err(error.into()),
+                Err(CommandError::Io(_)) => (),
+            }
+        }
+        // Not connected yet, or MPD closed the connection (e.g. because it restarted or the
+        // connection was idle for too long)
+        self.connection = None;
+        let mut connection = MpdConnection::connect(&self.address)?;
+        let response = connection.command(command)?;
+        self.connection = Some(connection);
+        Ok(response)
+    }
+
+    fn status(&mut self) -> Result<MpdStatus> {
+        let status = self.command("status")?;
+        let mut song = self.command("currentsong")?;
+        let title = match song.remove("Title") {
+            Some(title) => title,
+            // Untagged files are shown by their file name
+            None => song
+                .remove("file")
+                .map(|file| file.rsplit('/').next().unwrap_or_default().to_string())
+                .unwrap_or_default(),
+        };
+        Ok(MpdStatus {
+            state: status.get("state").cloned().unwrap_or_default(),
+            title,
+            artist: song.remove("Artist").unwrap_or_default(),
+            elapsed: status
+                .get("elapsed")
+                .and_then(|elapsed| elapsed.parse().ok())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Pause when playing and play otherwise
+    fn toggle(&mut self) -> Result<()> {
+        let playing = self.command("status")?.get("state").map(String::as_str) == Some("play");
+        self.command(if playing { "pause 1" } else { "play" })?;
+        Ok(())
+    }
+}
+
+pub struct Mpd {
+    id: usize,
+    text: TextWidget,
+    client: MpdClient,
+    connected: bool,
+    format: FormatTemplate,
+    update_interval: Duration,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct MpdConfig {
+    /// `host:port` of the MPD server, or the path of its unix socket
+    pub address: String,
+
+    pub format: FormatTemplate,
+
+    /// Update interval in seconds
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+}
+
+impl Default for MpdConfig {
+    fn default() -> Self {
+        Self {
+            address: "localhost:6600".to_string(),
+            format: FormatTemplate::default(),
+            interval: Duration::from_secs(5),
+        }
+    }
+}
+
+impl ConfigBlock for Mpd {
+    type Config = MpdConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        Ok(Mpd {
+            id,
+            text: TextWidget::new(id, 0, shared_config).with_icon("music")?,
+            client: MpdClient::new(block_config.address),
+            connected: false,
+            format: block_config.format.with_default("{artist} - {title}")?,
+            update_interval: block_config.interval,
+        })
+    }
+}
+
+impl Block for Mpd {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        // The block is hidden while MPD can't be reached
+        self.connected = match self.client.status() {
+            Ok(status) => {
+                let values = map!(
+                    "title" => Value::from_string(escape_pango_text(status.title)),
+                    "artist" => Value::from_string(escape_pango_text(status.artist)),
+                    "state" => Value::from_string(status.state),
+                    "elapsed" => Value::from_integer(status.elapsed as i64).seconds(),
+                );
+                self.text.set_texts(self.format.render(&values)?);
+                true
+            }
+            Err(_) => false,
+        };
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        if self.connected {
+            vec![&self.text]
+        } else {
+            vec![]
+        }
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        if let MouseButton::Left = e.button {
+            if self.client.toggle().is_ok() {
+                self.update()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    const STATUS: &str =
+        "volume: 80\nrepeat: 0\nstate: play\nsong: 3\nelapsed: 83.402\nduration: 215.000\n";
+    const SONG: &str =
+        "file: music/queen/bohemian.flac\nArtist: Queen\nArtist: Freddie Mercury\nTitle: Bohemian Rhapsody\nAlbum: A Night at the Opera\n";
+
+    /// A fake MPD server, which closes every connection after `commands_per_connection`
+    /// commands. Returns its address and the commands it received.
+    fn mock_server(commands_per_connection: usize) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let log = received.clone();
+        thread::spawn(move || {
+            let state = Arc::new(Mutex::new("play".to_string()));
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                stream.write_all(b"OK MPD 0.23.5\n").unwrap();
+                for _ in 0..commands_per_connection {
+                    let mut command = String::new();
+                    if reader.read_line(&mut command).unwrap() == 0 {
+                        break;
+                    }
+                    let command = command.trim_end().to_string();
+                    log.lock().unwrap().push(command.clone());
+                    let mut state = state.lock().unwrap();
+                    let response = match command.as_str() {
+                        "status" => STATUS.replace("state: play", &format!("state: {}", state)),
+                        "currentsong" => SONG.to_string(),
+                        "pause 1" => {
+                            *state = "pause".to_string();
+                            String::new()
+                        }
+                        "play" => {
+                            *state = "play".to_string();
+                            String::new()
+                        }
+                        _ => {
+                            stream.write_all(b"ACK [5@0] {} unknown command\n").unwrap();
+                            continue;
+                        }
+                    };
+                    stream.write_all(response.as_bytes()).unwrap();
+                    stream.write_all(b"OK\n").unwrap();
+                }
+            }
+        });
+        (address, received)
+    }
+
+    #[test]
+    fn status() {
+        let (address, received) = mock_server(100);
+        let mut client = MpdClient::new(address);
+        assert_eq!(
+            client.status().unwrap(),
+            MpdStatus {
+                state: "play".to_string(),
+                title: "Bohemian Rhapsody".to_string(),
+                artist: "Queen".to_string(),
+                elapsed: 83.402,
+            }
+        );
+        // Refused commands are not sent again on a new connection
+        assert!(client.command("bogus").is_err());
+        assert_eq!(
+            received.lock().unwrap()[..],
+            ["status", "currentsong", "bogus"]
+        );
+    }
+
+    #[test]
+    fn toggle() {
+        let (address, received) = mock_server(100);
+        let mut client = MpdClient::new(address);
+        client.toggle().unwrap();
+        assert_eq!(client.status().unwrap().state, "pause");
+        client.toggle().unwrap();
+        assert_eq!(client.status().unwrap().state, "play");
+        assert_eq!(
+            received.lock().unwrap()[..4],
+            ["status", "pause 1", "status", "currentsong"]
+        );
+    }
+
+    #[test]
+    fn reconnect() {
+        // Every connection is dropped after a single command
+        let (address, received) = mock_server(1);
+        let mut client = MpdClient::new(address);
+        assert_eq!(client.status().unwrap().title, "Bohemian Rhapsody");
+        assert_eq!(client.status().unwrap().artist, "Queen");
+        assert_eq!(received.lock().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn unreachable() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        assert!(MpdClient::new(address).status().is_err());
+    }
+}
@@ -36,6 +36,7 @@ struct Player {
     playback_status: PlaybackStatus,
     artist: Option<String>,
     title: Option<String>,
+    art_url: Option<String>,
     //TODO
     //volume: u32,
 }
@@ -46,9 +47,9 @@ impl Player {
         let data = path
             .get("org.mpris.MediaPlayer2.Player", "Metadata")
             .map(|d: Box<dyn RefArg>| extract_from_metadata(d.as_ref()));
-        let (title, artist) = match data {
+        let metadata = match data {
             Ok(Ok(res)) => res,
-            _ => (None, None),
+            _ => Metadata::default(),
         };
 
         // Get current playback status
@@ -61,8 +62,9 @@ impl Player {
             bus_name: bus_name.to_string(),
             interface_name: name.to_string(),
             playback_status: status,
-            artist,
-            title,
+            artist: metadata.artist,
+            title: metadata.title,
+            art_url: metadata.art_url,
         }
     }
 }
@@ -354,10 +356,11 @@ impl ConfigBlock for Music {
                                 let sender = sender.to_string();
                                 if let Some(player) = players.iter_mut().find(|p| p.bus_name == sender) {
                                     if let Some(data) = prop_changed.changed_properties.get("Metadata") {
-                                        let (title, artist) = extract_from_metadata(&data.0).unwrap_or((None,None));
-                                        if player.title != title || player.artist != artist {
-                                            player.title = title;
-                                            player.artist = artist;
+                                        let metadata = extract_from_metadata(&data.0).unwrap_or_default();
+                                        if player.title != metadata.title || player.artist != metadata.artist || player.art_url != metadata.art_url {
+                                            player.title = metadata.title;
+                                            player.artist = metadata.artist;
+                                            player.art_url = metadata.art_url;
                                             updated = true;
                                         }
                                     }
@@ -381,6 +384,7 @@ impl ConfigBlock for Music {
                                         if data.0.as_iter().unwrap().peekable().peek().is_none() {
                                             player.artist = None;
                                             player.title = None;
+                                            player.art_url = None;
                                             updated = true;
                                         }
                                     }
@@ -540,6 +544,7 @@ impl Block for Music {
         let player_name = split[3].to_string();
         let artist = metadata.clone().artist.unwrap_or_else(|| String::from(""));
         let title = metadata.clone().title.unwrap_or_else(|| String::from(""));
+        let art_url = metadata.clone().art_url.unwrap_or_default();
         let combo =
             if (title.chars().count() + self.separator.chars().count() + artist.chars().count())
                 < self.max_width
@@ -553,6 +558,7 @@ impl Block for Music {
         let values = map!(
             "artist" => Value::from_string(artist.clone()),
             "title" => Value::from_string(title.clone()),
+            "art_url" => Value::from_string(art_url),
             "combo" => Value::from_string(combo),
             //TODO
             //"vol" => volume,
@@ -565,7 +571,7 @@ impl Block for Music {
                 self.current_song_widget.set_text(String::new());
             } else {
                 self.current_song_widget
-                    .set_text(self.format.render(&values)?.0);
+                    .set_text(self.format.render(&values)?.full);
             }
         }
 
@@ -749,9 +755,48 @@ fn extract_artist_from_value(value: &dyn RefArg) -> Result<&str> {
     }
 }
 
-fn extract_from_metadata(metadata: &dyn RefArg) -> Result<(Option<String>, Option<String>)> {
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Metadata {
+    title: Option<String>,
+    artist: Option<String>,
+    /// Location of the artwork, as a path for local files
+    art_url: Option<String>,
+}
+
+/// Turn `file://` URLs into paths, decoding percent-encoded characters. Other URLs are kept as
+/// they are.
+fn normalize_art_url(url: &str) -> String {
+    let path = match url.strip_prefix("file://") {
+        Some(path) => path,
+        None => return url.to_string(),
+    };
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let decoded = match tail {
+            [hi, lo, ..] if byte == b'%' => std::str::from_utf8(&[*hi, *lo])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+        match decoded {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn extract_from_metadata(metadata: &dyn RefArg) -> Result<Metadata> {
     let mut title = None;
     let mut artist = None;
+    let mut art_url = None;
 
     let mut iter = metadata
         .as_iter()
@@ -773,10 +818,20 @@ fn extract_from_metadata(metadata: &dyn RefArg) -> Result<(Option<String>, Optio
                         .block_error("music", "failed to extract metadata")?,
                 ))
             }
+            "mpris:artUrl" => {
+                art_url = value
+                    .as_str()
+                    .filter(|url| !url.is_empty())
+                    .map(normalize_art_url)
+            }
             _ => {}
         };
     }
-    Ok((title, artist))
+    Ok(Metadata {
+        title,
+        artist,
+        art_url,
+    })
 }
 
 fn ignored_player(
@@ -804,3 +859,64 @@ fn ignored_player(
 
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dbus::arg::{PropMap, Variant};
+
+    fn metadata(entries: Vec<(&str, Box<dyn RefArg>)>) -> PropMap {
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), Variant(value)))
+            .collect()
+    }
+
+    #[test]
+    fn art_url() {
+        let map = metadata(vec![
+            ("xesam:title", Box::new("Song".to_string())),
+            ("xesam:artist", Box::new(vec!["Band".to_string()])),
+            (
+                "mpris:artUrl",
+                Box::new("file:///home/me/Music/Cover%20Art/front%2Bback.jpg".to_string()),
+            ),
+            ("mpris:length", Box::new(180_000_000_i64)),
+        ]);
+        assert_eq!(
+            extract_from_metadata(&map).unwrap(),
+            Metadata {
+                title: Some("Song".to_string()),
+                artist: Some("Band".to_string()),
+                art_url: Some("/home/me/Music/Cover Art/front+back.jpg".to_string()),
+            }
+        );
+
+        let map = metadata(vec![(
+            "mpris:artUrl",
+            Box::new("https://i.scdn.co/image/ab67616d0000b273".to_string()),
+        )]);
+        assert_eq!(
+            extract_from_metadata(&map).unwrap().art_url.as_deref(),
+            Some("https://i.scdn.co/image/ab67616d0000b273")
+        );
+
+        let map = metadata(vec![("mpris:artUrl", Box::new(String::new()))]);
+        assert_eq!(extract_from_metadata(&map).unwrap().art_url, None);
+        let map = metadata(vec![("xesam:title", Box::new("Song".to_string()))]);
+        assert_eq!(extract_from_metadata(&map).unwrap().art_url, None);
+    }
+
+    #[test]
+    fn normalize() {
+        assert_eq!(normalize_art_url("file:///tmp/a.png"), "/tmp/a.png");
+        assert_eq!(
+            normalize_art_url("file:///tmp/%C3%A9t%C3%A9%2.png%"),
+            "/tmp/été%2.png%"
+        );
+        assert_eq!(
+            normalize_art_url("http://example.com/a%20b.png"),
+            "http://example.com/a%20b.png"
+        );
+    }
+}
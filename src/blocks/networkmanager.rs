@@ -721,7 +721,7 @@ impl Block for NetworkManager {
                                         "freq" => Value::from_string(freq).percents(),
                                     );
                                     if let Ok(s) = self.ap_format.render(&values) {
-                                        s.0
+                                        s.full
                                     } else {
                                         "[invalid device format string]".to_string()
                                     }
@@ -751,7 +751,7 @@ impl Block for NetworkManager {
                                 );
 
                                 if let Ok(s) = self.device_format.render(&values) {
-                                    devicevec.push(s.0);
+                                    devicevec.push(s.full);
                                 } else {
                                     devicevec.push("[invalid device format string]".to_string())
                                 }
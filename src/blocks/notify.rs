@@ -1,6 +1,5 @@
-use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use crossbeam_channel::Sender;
 use dbus::ffidisp::stdintf::org_freedesktop_dbus::{Properties, PropertiesPropertiesChanged};
@@ -10,22 +9,105 @@ use serde_derive::Deserialize;
 
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
 use crate::errors::*;
 use crate::formatting::value::Value;
 use crate::formatting::FormatTemplate;
 use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
 use crate::widgets::text::TextWidget;
-use crate::widgets::I3BarWidget;
+use crate::widgets::{I3BarWidget, State};
 
-// TODO
-// Add driver option so can choose between dunst, mako, etc.
+/// A notification daemon which supports a "do not disturb" mode
+trait DndDriver {
+    fn is_enabled(&self) -> Result<bool>;
+    fn set_enabled(&self, enabled: bool) -> Result<()>;
+}
+
+struct Dunst {
+    connection: Connection,
+}
+
+impl Dunst {
+    const DESTINATION: &'static str = "org.freedesktop.Notifications";
+    const PATH: &'static str = "/org/freedesktop/Notifications";
+    const INTERFACE: &'static str = "org.dunstproject.cmd0";
+}
+
+impl DndDriver for Dunst {
+    fn is_enabled(&self) -> Result<bool> {
+        self.connection
+            .with_path(Self::DESTINATION, Self::PATH, 5000)
+            .get(Self::INTERFACE, "paused")
+            .block_error("notify", "Failed to get dunst state. Is it running?")
+    }
+
+    fn set_enabled(&self, enabled: bool) -> Result<()> {
+        self.connection
+            .with_path(Self::DESTINATION, Self::PATH, 5000)
+            .set(Self::INTERFACE, "paused", enabled)
+            .block_error("notify", "Failed to query D-Bus")
+    }
+}
+
+struct Mako {
+    connection: Connection,
+}
+
+impl Mako {
+    const DESTINATION: &'static str = "fr.emersion.Mako";
+    const PATH: &'static str = "/fr/emersion/Mako";
+    const INTERFACE: &'static str = "fr.emersion.Mako";
+    const DND_MODE: &'static str = "do-not-disturb";
+
+    /// The modes which are currently active
+    fn modes(&self) -> Result<Vec<String>> {
+        let (modes,): (Vec<String>,) = self
+            .connection
+            .with_path(Self::DESTINATION, Self::PATH, 5000)
+            .method_call(Self::INTERFACE, "ListModes", ())
+            .block_error("notify", "Failed to get mako modes. Is it running?")?;
+        Ok(modes)
+    }
+}
+
+/// The active modes of mako with only the "do not disturb" mode added or removed, like
+/// `makoctl mode -t do-not-disturb` does
+fn with_dnd_mode(mut modes: Vec<String>, enabled: bool) -> Vec<String> {
+    modes.retain(|mode| mode != Mako::DND_MODE);
+    if enabled {
+        modes.push(Mako::DND_MODE.to_string());
+    }
+    modes
+}
+
+impl DndDriver for Mako {
+    fn is_enabled(&self) -> Result<bool> {
+        Ok(self.modes()?.iter().any(|mode| mode == Self::DND_MODE))
+    }
+
+    fn set_enabled(&self, enabled: bool) -> Result<()> {
+        let modes = with_dnd_mode(self.modes()?, enabled);
+        self.connection
+            .with_path(Self::DESTINATION, Self::PATH, 5000)
+            .method_call(Self::INTERFACE, "SetModes", (modes,))
+            .block_error("notify", "Failed to query D-Bus")
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyDriver {
+    Dunst,
+    Mako,
+}
 
 pub struct Notify {
     id: usize,
-    paused: Arc<Mutex<i64>>,
+    driver: Box<dyn DndDriver>,
     format: FormatTemplate,
     output: TextWidget,
+    update_interval: Option<Duration>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -33,6 +115,13 @@ pub struct Notify {
 pub struct NotifyConfig {
     /// Format string which describes the output of this block.
     pub format: FormatTemplate,
+
+    /// Which notification daemon is running
+    pub driver: NotifyDriver,
+
+    /// Update interval in seconds. Only used by drivers that don't signal changes (mako).
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
 }
 
 impl Default for NotifyConfig {
@@ -40,6 +129,8 @@ impl Default for NotifyConfig {
         Self {
             // display just the bell icon
             format: FormatTemplate::default(),
+            driver: NotifyDriver::Dunst,
+            interval: Duration::from_secs(5),
         }
     }
 }
@@ -53,89 +144,86 @@ impl ConfigBlock for Notify {
         shared_config: SharedConfig,
         send: Sender<Task>,
     ) -> Result<Self> {
-        let c = Connection::get_private(BusType::Session).block_error(
-            "notify",
-            &"Failed to establish D-Bus connection".to_string(),
-        )?;
-
-        let p = c.with_path(
-            "org.freedesktop.Notifications",
-            "/org/freedesktop/Notifications",
-            5000,
-        );
-        let initial_state: bool = p.get("org.dunstproject.cmd0", "paused").block_error(
-            "notify",
-            &"Failed to get dunst state. Is it running?".to_string(),
-        )?;
-
-        let icon = if initial_state { "bell-slash" } else { "bell" };
-
-        // TODO: revisit this lint
-        #[allow(clippy::mutex_atomic)]
-        let state = Arc::new(Mutex::new(initial_state as i64));
-        let state_copy = state.clone();
-
-        thread::Builder::new()
-            .name("notify".into())
-            .spawn(move || {
-                let c = Connection::get_private(BusType::Session)
-                    .expect("Failed to establish D-Bus connection in thread");
-
-                let matched_signal = PropertiesPropertiesChanged::match_str(
-                    Some(&"org.freedesktop.Notifications".into()),
-                    None,
-                );
-                c.add_match(&matched_signal).unwrap();
-                loop {
-                    for msg in c.incoming(1000) {
-                        if let Some(signal) = PropertiesPropertiesChanged::from_message(&msg) {
-                            let value = signal.changed_properties.get("paused").unwrap();
-                            let status = &value.0.as_i64().unwrap();
-                            let mut paused = state_copy.lock().unwrap();
-                            *paused = *status;
-
-                            // Tell block to update now.
-                            send.send(Task {
-                                id,
-                                update_time: Instant::now(),
-                            })
-                            .unwrap();
+        let connection = Connection::get_private(BusType::Session)
+            .block_error("notify", "Failed to establish D-Bus connection")?;
+
+        let (driver, update_interval): (Box<dyn DndDriver>, _) = match block_config.driver {
+            NotifyDriver::Dunst => {
+                thread::Builder::new()
+                    .name("notify".into())
+                    .spawn(move || {
+                        let c = Connection::get_private(BusType::Session)
+                            .expect("Failed to establish D-Bus connection in thread");
+
+                        let matched_signal = PropertiesPropertiesChanged::match_str(
+                            Some(&Dunst::DESTINATION.into()),
+                            None,
+                        );
+                        c.add_match(&matched_signal).unwrap();
+                        loop {
+                            for msg in c.incoming(1000) {
+                                if PropertiesPropertiesChanged::from_message(&msg).is_some() {
+                                    // Tell block to update now.
+                                    send.send(Task {
+                                        id,
+                                        update_time: Instant::now(),
+                                    })
+                                    .unwrap();
+                                }
+                            }
                         }
-                    }
-                }
-            })
-            .unwrap();
+                    })
+                    .unwrap();
+                (Box::new(Dunst { connection }), None)
+            }
+            NotifyDriver::Mako => (Box::new(Mako { connection }), Some(block_config.interval)),
+        };
+
+        // Fail early if the daemon is not running
+        driver.is_enabled()?;
 
         Ok(Notify {
             id,
-            paused: state,
+            driver,
             format: block_config.format.with_default("")?,
-            output: TextWidget::new(id, 0, shared_config).with_icon(icon)?,
+            output: TextWidget::new(id, 0, shared_config).with_icon("bell")?,
+            update_interval,
         })
     }
 }
 
+fn dnd_state(dnd: bool) -> State {
+    if dnd {
+        State::Warning
+    } else {
+        State::Good
+    }
+}
+
+fn toggle(driver: &dyn DndDriver) -> Result<()> {
+    driver.set_enabled(!driver.is_enabled()?)
+}
+
 impl Block for Notify {
     fn id(&self) -> usize {
         self.id
     }
 
     fn update(&mut self) -> Result<Option<Update>> {
-        let paused = *self
-            .paused
-            .lock()
-            .block_error("notify", "failed to acquire lock for `state`")?;
+        let dnd = self.driver.is_enabled()?;
 
         let values = map!(
-            "state" => Value::from_string(paused.to_string())
+            "state" => Value::from_string((dnd as i64).to_string()),
+            "dnd" => Value::from_string(if dnd { "on" } else { "off" }.to_string()),
         );
 
         self.output.set_texts(self.format.render(&values)?);
 
-        let icon = if paused == 1 { "bell-slash" } else { "bell" };
+        let icon = if dnd { "bell-slash" } else { "bell" };
         self.output.set_icon(icon)?;
+        self.output.set_state(dnd_state(dnd));
 
-        Ok(None)
+        Ok(self.update_interval.map(|interval| interval.into()))
     }
 
     // Returns the view of the block, comprised of widgets.
@@ -145,32 +233,72 @@ impl Block for Notify {
 
     fn click(&mut self, e: &I3BarEvent) -> Result<()> {
         if let MouseButton::Left = e.button {
-            let c = Connection::get_private(BusType::Session).block_error(
-                "notify",
-                &"Failed to establish D-Bus connection".to_string(),
-            )?;
-
-            let p = c.with_path(
-                "org.freedesktop.Notifications",
-                "/org/freedesktop/Notifications",
-                5000,
-            );
-
-            let paused = *self
-                .paused
-                .lock()
-                .block_error("notify", "failed to acquire lock")?;
-
-            if paused == 1 {
-                p.set("org.dunstproject.cmd0", "paused", false)
-                    .block_error("notify", &"Failed to query D-Bus".to_string())?;
-            } else {
-                p.set("org.dunstproject.cmd0", "paused", true)
-                    .block_error("notify", &"Failed to query D-Bus".to_string())?;
+            toggle(self.driver.as_ref())?;
+            // dunst signals the change, but mako has to be polled
+            if self.update_interval.is_some() {
+                self.update()?;
             }
-
-            // block will auto-update due to monitoring the bus
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    struct MockDriver {
+        enabled: Cell<bool>,
+        sets: Cell<usize>,
+    }
+
+    impl DndDriver for MockDriver {
+        fn is_enabled(&self) -> Result<bool> {
+            Ok(self.enabled.get())
+        }
+
+        fn set_enabled(&self, enabled: bool) -> Result<()> {
+            self.enabled.set(enabled);
+            self.sets.set(self.sets.get() + 1);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn toggle_dnd() {
+        let driver = MockDriver {
+            enabled: Cell::new(false),
+            sets: Cell::new(0),
+        };
+        toggle(&driver).unwrap();
+        assert!(driver.is_enabled().unwrap());
+        toggle(&driver).unwrap();
+        assert!(!driver.is_enabled().unwrap());
+        assert_eq!(driver.sets.get(), 2);
+    }
+
+    #[test]
+    fn mako_modes() {
+        let modes = |modes: &[&str]| modes.iter().map(|mode| mode.to_string()).collect();
+        assert_eq!(
+            with_dnd_mode(modes(&["default", "work"]), true),
+            modes(&["default", "work", "do-not-disturb"])
+        );
+        assert_eq!(
+            with_dnd_mode(modes(&["default", "do-not-disturb", "work"]), false),
+            modes(&["default", "work"])
+        );
+        // Enabling it twice doesn't add it twice
+        assert_eq!(
+            with_dnd_mode(modes(&["do-not-disturb"]), true),
+            modes(&["do-not-disturb"])
+        );
+    }
+
+    #[test]
+    fn state() {
+        assert!(matches!(dnd_state(false), State::Good));
+        assert!(matches!(dnd_state(true), State::Warning));
+    }
+}
@@ -0,0 +1,215 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+use swayipc::reply::{Output, Workspace};
+use swayipc::{Connection, EventIterator, EventType};
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::scheduler::Task;
+use crate::util::escape_pango_text;
+use crate::widgets::text::TextWidget;
+use crate::widgets::I3BarWidget;
+
+/// The name and scale of the focused output, if any
+type FocusedOutput = Option<(String, f64)>;
+
+/// The output of the focused workspace
+fn focused_output(workspaces: &[Workspace], outputs: &[Output]) -> FocusedOutput {
+    let workspace = workspaces.iter().find(|workspace| workspace.focused)?;
+    outputs
+        .iter()
+        .find(|output| output.active && output.name == workspace.output)
+        .map(|output| (output.name.clone(), output.scale.unwrap_or(1.)))
+}
+
+fn get_focused_output(connection: &mut Connection) -> Result<FocusedOutput> {
+    let workspaces = connection
+        .get_workspaces()
+        .block_error("output_scale", "failed to get the workspaces")?;
+    let outputs = connection
+        .get_outputs()
+        .block_error("output_scale", "failed to get the outputs")?;
+    Ok(focused_output(&workspaces, &outputs))
+}
+
+/// Call `on_event` with the focused output after every event. Only returns on errors.
+fn watch(
+    connection: &mut Connection,
+    events: EventIterator,
+    mut on_event: impl FnMut(FocusedOutput),
+) -> Result<()> {
+    for event in events {
+        event.block_error("output_scale", "failed to read the events of sway")?;
+        on_event(get_focused_output(connection)?);
+    }
+    Ok(())
+}
+
+pub struct OutputScale {
+    id: usize,
+    text: TextWidget,
+    /// The focused output, or the error which stopped watching sway
+    output: Arc<Mutex<std::result::Result<FocusedOutput, String>>>,
+    format: FormatTemplate,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct OutputScaleConfig {
+    pub format: FormatTemplate,
+}
+
+impl ConfigBlock for OutputScale {
+    type Config = OutputScaleConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        send: Sender<Task>,
+    ) -> Result<Self> {
+        let mut connection =
+            Connection::new().block_error("output_scale", "failed to connect to sway")?;
+        let output = Arc::new(Mutex::new(Ok(get_focused_output(&mut connection)?)));
+
+        // The focused output changes with the focused workspace. swayipc doesn't expose the
+        // output events, so a new scale shows up with the next workspace event, e.g. on reload.
+        let events = Connection::new()
+            .and_then(|events| events.subscribe(&[EventType::Workspace]))
+            .block_error("output_scale", "failed to subscribe to events")?;
+        {
+            let output = output.clone();
+            thread::Builder::new()
+                .name("output_scale".into())
+                .spawn(move || {
+                    let update = |focused| {
+                        let mut output = output
+                            .lock()
+                            .expect("lock has been poisoned in `output_scale` block");
+                        if *output != focused {
+                            *output = focused;
+                            send.send(Task {
+                                id,
+                                update_time: Instant::now(),
+                            })
+                            .expect("could not communicate with channel in `output_scale` block");
+                        }
+                    };
+                    // The error is reported by the next update
+                    if let Err(BlockError(_, message)) =
+                        watch(&mut connection, events, |focused| update(Ok(focused)))
+                    {
+                        update(Err(message));
+                    }
+                })
+                .expect("failed to start watching thread for `output_scale` block");
+        }
+
+        Ok(OutputScale {
+            id,
+            text: TextWidget::new(id, 0, shared_config),
+            output,
+            format: block_config.format.with_default("{scale:4}")?,
+        })
+    }
+}
+
+impl Block for OutputScale {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        let output = self
+            .output
+            .lock()
+            .block_error("output_scale", "failed to acquire lock")?
+            .clone()
+            .map_err(|message| BlockError("output_scale".to_string(), message))?;
+        if let Some((name, scale)) = output {
+            let values = map!(
+                "scale" => Value::from_float(scale),
+                "output" => Value::from_string(escape_pango_text(name)),
+            );
+            self.text.set_texts(self.format.render(&values)?);
+        }
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        let output = self
+            .output
+            .lock()
+            .expect("lock has been poisoned in `output_scale` block");
+        match *output {
+            Ok(Some(_)) => vec![&self.text],
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GET_WORKSPACES: &str = r#"[
+        {
+            "num": 1, "name": "1", "layout": "splith", "visible": true, "focused": false,
+            "urgent": false, "representation": "H[firefox]", "orientation": "horizontal",
+            "rect": { "x": 0, "y": 0, "width": 1829, "height": 1143 },
+            "output": "eDP-1", "focus": [7]
+        },
+        {
+            "num": 2, "name": "2", "layout": "splith", "visible": true, "focused": true,
+            "urgent": false, "representation": null, "orientation": "horizontal",
+            "rect": { "x": 1829, "y": 0, "width": 3072, "height": 1728 },
+            "output": "DP-2", "focus": []
+        }
+    ]"#;
+
+    const GET_OUTPUTS: &str = r#"[
+        {
+            "id": 3, "type": "output", "name": "eDP-1", "active": true, "dpms": true,
+            "primary": false, "make": "Sharp Corporation", "model": "0x1449", "serial": "",
+            "scale": 1.75, "scale_filter": "linear", "transform": "normal",
+            "current_workspace": "1", "focused": false,
+            "rect": { "x": 0, "y": 0, "width": 1829, "height": 1143 }
+        },
+        {
+            "id": 4, "type": "output", "name": "DP-2", "active": true, "dpms": true,
+            "primary": false, "make": "Dell Inc.", "model": "DELL U2720Q", "serial": "ABC",
+            "scale": 1.25, "scale_filter": "smart", "transform": "normal",
+            "current_workspace": "2", "focused": true,
+            "rect": { "x": 1829, "y": 0, "width": 3072, "height": 1728 }
+        },
+        {
+            "id": 5, "type": "output", "name": "HDMI-A-1", "active": false, "dpms": false,
+            "primary": false, "make": "Unknown", "model": "Unknown", "serial": "",
+            "scale": -1.0, "focused": false,
+            "rect": { "x": 0, "y": 0, "width": 0, "height": 0 }
+        }
+    ]"#;
+
+    #[test]
+    fn focused() {
+        let mut workspaces: Vec<Workspace> = serde_json::from_str(GET_WORKSPACES).unwrap();
+        let outputs: Vec<Output> = serde_json::from_str(GET_OUTPUTS).unwrap();
+        assert_eq!(
+            focused_output(&workspaces, &outputs),
+            Some(("DP-2".to_string(), 1.25))
+        );
+
+        // The workspaces of inactive outputs have moved elsewhere
+        workspaces[1].output = "HDMI-A-1".to_string();
+        assert_eq!(focused_output(&workspaces, &outputs), None);
+        workspaces[1].focused = false;
+        assert_eq!(focused_output(&workspaces, &outputs), None);
+    }
+}
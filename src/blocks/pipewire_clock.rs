@@ -0,0 +1,213 @@
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::scheduler::Task;
+use crate::widgets::text::TextWidget;
+use crate::widgets::I3BarWidget;
+
+/// The clock settings of the PipeWire graph, as published in the `settings` metadata
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct ClockSettings {
+    rate: u64,
+    force_rate: u64,
+    quantum: u64,
+    force_quantum: u64,
+}
+
+impl ClockSettings {
+    /// Apply a line of `pw-metadata` output, e.g.
+    /// `update: id:0 key:'clock.rate' value:'48000' type:''`. Returns whether anything changed.
+    fn update(&mut self, line: &str) -> bool {
+        let field = |name: &str| {
+            let start = line.find(&format!("{}:'", name))? + name.len() + 2;
+            let len = line[start..].find('\'')?;
+            Some(&line[start..start + len])
+        };
+        let (key, value) = match (field("key"), field("value")) {
+            (Some(key), Some(value)) => (key, value.parse().unwrap_or(0)),
+            _ => return false,
+        };
+        let setting = match key {
+            "clock.rate" => &mut self.rate,
+            "clock.force-rate" => &mut self.force_rate,
+            "clock.quantum" => &mut self.quantum,
+            "clock.force-quantum" => &mut self.force_quantum,
+            _ => return false,
+        };
+        let changed = *setting != value;
+        *setting = value;
+        changed
+    }
+
+    /// The sample rate in Hz, taking a forced rate into account
+    fn effective_rate(&self) -> u64 {
+        if self.force_rate > 0 {
+            self.force_rate
+        } else {
+            self.rate
+        }
+    }
+
+    /// The quantum in samples, taking a forced quantum into account
+    fn effective_quantum(&self) -> u64 {
+        if self.force_quantum > 0 {
+            self.force_quantum
+        } else {
+            self.quantum
+        }
+    }
+
+    /// The latency of one quantum in milliseconds
+    fn latency(&self) -> f64 {
+        match self.effective_rate() {
+            0 => 0.,
+            rate => self.effective_quantum() as f64 * 1000. / rate as f64,
+        }
+    }
+}
+
+pub struct PipewireClock {
+    id: usize,
+    text: TextWidget,
+    settings: Arc<Mutex<ClockSettings>>,
+    format: FormatTemplate,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct PipewireClockConfig {
+    pub format: FormatTemplate,
+}
+
+impl ConfigBlock for PipewireClock {
+    type Config = PipewireClockConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        send: Sender<Task>,
+    ) -> Result<Self> {
+        let settings = Arc::new(Mutex::new(ClockSettings::default()));
+
+        let child = Command::new("pw-metadata")
+            .args(["--name", "settings", "--monitor", "0"])
+            .stdout(Stdio::piped())
+            .stdin(Stdio::null())
+            .spawn()
+            .block_error("pipewire_clock", "failed to run pw-metadata")?;
+        let stdout = child.stdout.unwrap();
+        {
+            let settings = settings.clone();
+            thread::Builder::new()
+                .name("pipewire_clock".into())
+                .spawn(move || {
+                    for line in BufReader::new(stdout).lines().scan((), |_, x| x.ok()) {
+                        let changed = settings
+                            .lock()
+                            .expect("lock has been poisoned in `pipewire_clock` block")
+                            .update(&line);
+                        if changed {
+                            send.send(Task {
+                                id,
+                                update_time: Instant::now(),
+                            })
+                            .expect("could not communicate with channel in `pipewire_clock` block");
+                        }
+                    }
+                })
+                .expect("failed to start watching thread for `pipewire_clock` block");
+        }
+
+        Ok(PipewireClock {
+            id,
+            text: TextWidget::new(id, 0, shared_config),
+            settings,
+            format: block_config.format.with_default("{rate} {quantum}")?,
+        })
+    }
+}
+
+impl Block for PipewireClock {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        let settings = *self
+            .settings
+            .lock()
+            .block_error("pipewire_clock", "failed to acquire lock")?;
+        let values = map!(
+            "rate" => Value::from_integer(settings.effective_rate() as i64).hertz(),
+            "quantum" => Value::from_integer(settings.effective_quantum() as i64),
+            "latency" => Value::from_float(settings.latency()),
+        );
+        self.text.set_texts(self.format.render(&values)?);
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS: &str = "\
+Found \"settings\" metadata 32
+update: id:0 key:'log.level' value:'2' type:''
+update: id:0 key:'clock.rate' value:'48000' type:''
+update: id:0 key:'clock.allowed-rates' value:'[ 48000 ]' type:''
+update: id:0 key:'clock.quantum' value:'1024' type:''
+update: id:0 key:'clock.min-quantum' value:'32' type:''
+update: id:0 key:'clock.max-quantum' value:'2048' type:''
+update: id:0 key:'clock.force-quantum' value:'0' type:''
+update: id:0 key:'clock.force-rate' value:'0' type:''
+";
+
+    fn settings() -> ClockSettings {
+        let mut settings = ClockSettings::default();
+        for line in SETTINGS.lines() {
+            settings.update(line);
+        }
+        settings
+    }
+
+    #[test]
+    fn defaults() {
+        let settings = settings();
+        assert_eq!(settings.effective_rate(), 48000);
+        assert_eq!(settings.effective_quantum(), 1024);
+        assert!((settings.latency() - 21.333).abs() < 1e-3);
+    }
+
+    #[test]
+    fn forced() {
+        let mut settings = settings();
+        assert!(settings.update("update: id:0 key:'clock.force-rate' value:'96000' type:''"));
+        assert!(settings.update("update: id:0 key:'clock.force-quantum' value:'256' type:''"));
+        assert_eq!(settings.effective_rate(), 96000);
+        assert_eq!(settings.effective_quantum(), 256);
+        assert!(!settings.update("update: id:0 key:'clock.force-rate' value:'96000' type:''"));
+        assert!(!settings.update("update: id:0 key:'log.level' value:'3' type:''"));
+
+        // Removing a key resets it
+        assert!(settings.update("update: id:0 key:'clock.force-rate' value:'' type:''"));
+        assert_eq!(settings.effective_rate(), 48000);
+        assert_eq!(ClockSettings::default().latency(), 0.);
+    }
+}
@@ -1,12 +1,17 @@
 use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
+use chrono::{Local, NaiveDate};
 use crossbeam_channel::Sender;
 use serde_derive::Deserialize;
 
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
 use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
 use crate::subprocess::spawn_child_async;
@@ -51,6 +56,50 @@ impl fmt::Display for State {
     }
 }
 
+/// The number of pomodoros completed on a day
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DailyStats {
+    date: NaiveDate,
+    count: usize,
+}
+
+impl DailyStats {
+    fn new(date: NaiveDate) -> Self {
+        Self { date, count: 0 }
+    }
+
+    /// Read the stats from a file containing e.g. `2021-10-16 3`
+    fn load(path: &Path) -> Option<Self> {
+        let contents = fs::read_to_string(path).ok()?;
+        let mut fields = contents.split_whitespace();
+        Some(Self {
+            date: fields.next()?.parse().ok()?,
+            count: fields.next()?.parse().ok()?,
+        })
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).block_error("pomodoro", "failed to create state directory")?;
+        }
+        fs::write(path, format!("{} {}\n", self.date, self.count))
+            .block_error("pomodoro", "failed to write state file")
+    }
+
+    /// The number of pomodoros completed on `today`, starting over on a new day
+    fn count_on(&mut self, today: NaiveDate) -> usize {
+        if self.date != today {
+            *self = Self::new(today);
+        }
+        self.count
+    }
+
+    fn complete(&mut self, today: NaiveDate) {
+        self.count_on(today);
+        self.count += 1;
+    }
+}
+
 #[derive(Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum Notifier {
@@ -70,6 +119,9 @@ pub struct Pomodoro {
     message: String,
     break_message: String,
     count: usize,
+    daily: DailyStats,
+    state_file: Option<PathBuf>,
+    format: FormatTemplate,
     notifier: Notifier,
     notifier_path: std::path::PathBuf,
     shared_config: SharedConfig,
@@ -79,7 +131,7 @@ pub struct Pomodoro {
 }
 
 impl Pomodoro {
-    fn set_text(&mut self) {
+    fn set_text(&mut self) -> Result<()> {
         let state_icon = match &self.state {
             State::Stopped => "pomodoro_stopped".to_string(),
             State::Started(_) => "pomodoro_started".to_string(),
@@ -87,12 +139,24 @@ impl Pomodoro {
             State::Paused(_) => "pomodoro_paused".to_string(),
         };
 
-        self.time.set_text(format!(
-            "{} | {} {}",
-            self.count,
-            self.shared_config.get_icon(&state_icon).unwrap(),
-            self.state
-        ));
+        let today = self.daily.count_on(Local::today().naive_local());
+        let values = map!(
+            "count" => Value::from_integer(self.count as i64),
+            "today" => Value::from_integer(today as i64),
+            "icon" => Value::from_string(self.shared_config.get_icon(&state_icon)?),
+            "time" => Value::from_string(self.state.to_string()),
+        );
+        self.time.set_texts(self.format.render(&values)?);
+        Ok(())
+    }
+
+    /// Count a completed pomodoro for today and persist the stats
+    fn complete(&mut self) -> Result<()> {
+        self.daily.complete(Local::today().naive_local());
+        match &self.state_file {
+            Some(path) => self.daily.save(path),
+            None => Ok(()),
+        }
     }
 
     fn notify(&self, message: &str, level: String) {
@@ -126,6 +190,9 @@ pub struct PomodoroConfig {
     pub break_message: String,
     pub notifier: Notifier,
     pub notifier_path: Option<std::path::PathBuf>,
+    /// File in which the number of pomodoros completed today is kept across restarts
+    pub state_file: Option<PathBuf>,
+    pub format: FormatTemplate,
     // Following two are deprecated - remove in a later release
     pub use_nag: bool,
     pub nag_path: std::path::PathBuf,
@@ -140,6 +207,8 @@ impl Default for PomodoroConfig {
             break_message: "Break over! Time to work!".to_string(),
             notifier: Notifier::None,
             notifier_path: None,
+            state_file: None,
+            format: FormatTemplate::default(),
             // Following two are deprecated - remove in a later release
             use_nag: false,
             nag_path: std::path::PathBuf::from("i3-nagbar"),
@@ -156,6 +225,12 @@ impl ConfigBlock for Pomodoro {
         shared_config: SharedConfig,
         _send: Sender<Task>,
     ) -> Result<Self> {
+        let today = Local::today().naive_local();
+        let daily = block_config
+            .state_file
+            .as_deref()
+            .and_then(DailyStats::load)
+            .unwrap_or_else(|| DailyStats::new(today));
         Ok(Pomodoro {
             id,
             time: TextWidget::new(id, 0, shared_config.clone()).with_icon("pomodoro")?,
@@ -166,6 +241,11 @@ impl ConfigBlock for Pomodoro {
             message: block_config.message,
             break_message: block_config.break_message,
             count: 0,
+            daily,
+            state_file: block_config.state_file,
+            format: block_config
+                .format
+                .with_default("{count:1} | {icon} {time}")?,
             notifier: block_config.notifier.clone(),
             notifier_path: if let Some(p) = block_config.notifier_path {
                 p
@@ -191,7 +271,7 @@ impl Block for Pomodoro {
     }
 
     fn update(&mut self) -> Result<Option<Update>> {
-        self.set_text();
+        self.set_text()?;
         match &self.state {
             State::Started(_) => {
                 if self.state.elapsed() >= self.length {
@@ -200,6 +280,7 @@ impl Block for Pomodoro {
                     }
 
                     self.state = State::OnBreak(Instant::now());
+                    self.complete()?;
                 }
             }
             State::OnBreak(_) => {
@@ -239,12 +320,64 @@ impl Block for Pomodoro {
                 }
             },
         }
-        self.set_text();
-
-        Ok(())
+        self.set_text()
     }
 
     fn view(&self) -> Vec<&dyn I3BarWidget> {
         vec![&self.time]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn daily_count() {
+        let monday = NaiveDate::from_ymd(2021, 10, 11);
+        let tuesday = NaiveDate::from_ymd(2021, 10, 12);
+
+        let mut stats = DailyStats::new(monday);
+        assert_eq!(stats.count_on(monday), 0);
+        stats.complete(monday);
+        stats.complete(monday);
+        assert_eq!(stats.count_on(monday), 2);
+
+        // A new day starts over
+        assert_eq!(stats.count_on(tuesday), 0);
+        stats.complete(tuesday);
+        assert_eq!(stats.count_on(tuesday), 1);
+
+        // Also when the first completion of the day happens before anything is displayed
+        let mut stats = DailyStats {
+            date: monday,
+            count: 5,
+        };
+        stats.complete(tuesday);
+        assert_eq!(
+            stats,
+            DailyStats {
+                date: tuesday,
+                count: 1
+            }
+        );
+    }
+
+    #[test]
+    fn persist() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("i3status-rust/pomodoro");
+        assert_eq!(DailyStats::load(&path), None);
+
+        let stats = DailyStats {
+            date: NaiveDate::from_ymd(2021, 10, 11),
+            count: 4,
+        };
+        stats.save(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "2021-10-11 4\n");
+        assert_eq!(DailyStats::load(&path), Some(stats));
+
+        fs::write(&path, "garbage").unwrap();
+        assert_eq!(DailyStats::load(&path), None);
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::http;
+use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::escape_pango_text;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// A single sample of the Prometheus text exposition format
+#[derive(Debug, Clone, PartialEq)]
+struct Sample {
+    name: String,
+    labels: Vec<(String, String)>,
+    value: f64,
+}
+
+/// Parse the label set of a sample, starting right after the opening `{`. Returns the labels
+/// and the rest of the line after the closing `}`.
+fn parse_labels(mut rest: &str) -> Option<(Vec<(String, String)>, &str)> {
+    let mut labels = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix('}') {
+            return Some((labels, after));
+        }
+        let eq = rest.find('=')?;
+        let name = rest[..eq].trim().to_string();
+        rest = rest[eq + 1..].trim_start().strip_prefix('"')?;
+
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        let end = loop {
+            match chars.next()? {
+                (i, '"') => break i,
+                (_, '\\') => match chars.next()?.1 {
+                    'n' => value.push('\n'),
+                    c => value.push(c),
+                },
+                (_, c) => value.push(c),
+            }
+        };
+        labels.push((name, value));
+
+        rest = rest[end + 1..].trim_start();
+        rest = rest.strip_prefix(',').unwrap_or(rest);
+    }
+}
+
+/// Parse a line such as `http_requests_total{method="post",code="200"} 1027 1395066363000`.
+/// Comments, blank lines and malformed lines yield `None`.
+fn parse_sample(line: &str) -> Option<Sample> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let name_end = line.find(|c: char| c == '{' || c.is_whitespace())?;
+    let name = line[..name_end].to_string();
+    let (labels, rest) = match line[name_end..].strip_prefix('{') {
+        Some(rest) => parse_labels(rest)?,
+        None => (Vec::new(), &line[name_end..]),
+    };
+    // The optional timestamp is ignored
+    let value = rest.split_whitespace().next()?.parse().ok()?;
+    Some(Sample {
+        name,
+        labels,
+        value,
+    })
+}
+
+/// Find the value of the first series of `metric` whose labels match all of `matchers`
+fn find_sample(text: &str, metric: &str, matchers: &HashMap<String, String>) -> Option<f64> {
+    text.lines()
+        .filter_map(parse_sample)
+        .find(|sample| {
+            sample.name == metric
+                && matchers
+                    .iter()
+                    .all(|(name, value)| sample.labels.iter().any(|(n, v)| n == name && v == value))
+        })
+        .map(|sample| sample.value)
+}
+
+pub struct Prometheus {
+    id: usize,
+    text: TextWidget,
+    url: String,
+    metric: String,
+    labels: HashMap<String, String>,
+    format: FormatTemplate,
+    update_interval: Duration,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct PrometheusConfig {
+    /// URL of the metrics endpoint, e.g. `http://localhost:9100/metrics`
+    pub url: String,
+
+    /// Name of the metric to display
+    pub metric: String,
+
+    /// Labels the series must have, e.g. `{ mountpoint = "/" }`
+    pub labels: HashMap<String, String>,
+
+    pub format: FormatTemplate,
+
+    /// Update interval in seconds
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+}
+
+impl Default for PrometheusConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            metric: String::new(),
+            labels: HashMap::new(),
+            format: FormatTemplate::default(),
+            interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl ConfigBlock for Prometheus {
+    type Config = PrometheusConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        if block_config.url.is_empty() || block_config.metric.is_empty() {
+            return Err(ConfigurationError(
+                "prometheus".to_string(),
+                "'url' and 'metric' must be set".to_string(),
+            ));
+        }
+        Ok(Prometheus {
+            id,
+            text: TextWidget::new(id, 0, shared_config),
+            url: block_config.url,
+            metric: block_config.metric,
+            labels: block_config.labels,
+            format: block_config.format.with_default("{value}")?,
+            update_interval: block_config.interval,
+        })
+    }
+}
+
+impl Prometheus {
+    fn scrape(&self) -> Result<f64> {
+        let response = http::http_get_text(&self.url, Some(Duration::from_secs(3)))?;
+        if response.code != 200 {
+            return Err(BlockError(
+                "prometheus".to_string(),
+                format!("endpoint returned HTTP {}", response.code),
+            ));
+        }
+        find_sample(&response.content, &self.metric, &self.labels).block_error(
+            "prometheus",
+            &format!("no series of '{}' matches the labels", self.metric),
+        )
+    }
+}
+
+impl Block for Prometheus {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        match self.scrape() {
+            Ok(value) => {
+                let values = map!(
+                    "value" => Value::from_float(value),
+                );
+                self.text.set_texts(self.format.render(&values)?);
+                self.text.set_state(State::Idle);
+            }
+            Err(BlockError(_, message)) | Err(InternalError(_, message, _)) => {
+                self.text.set_text(escape_pango_text(message));
+                self.text.set_state(State::Critical);
+            }
+            Err(e) => return Err(e),
+        }
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        if let MouseButton::Left = e.button {
+            self.update()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METRICS: &str = r#"
+# HELP node_filesystem_avail_bytes Filesystem space available to non-root users in bytes.
+# TYPE node_filesystem_avail_bytes gauge
+node_filesystem_avail_bytes{device="/dev/sda1",fstype="ext4",mountpoint="/"} 1.2345e+10
+node_filesystem_avail_bytes{device="/dev/sdb1",fstype="ext4",mountpoint="/home"} 9.87e+11
+# HELP node_load1 1m load average.
+# TYPE node_load1 gauge
+node_load1 0.42
+http_requests_total{method="post",code="200"} 1027 1395066363000
+http_requests_total{method="post",code="400"} 3 1395066363000
+weird{path="C:\\dir\\",msg="say \"hi\"\n",} +Inf
+"#;
+
+    fn labels(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(parse_sample("# TYPE node_load1 gauge"), None);
+        assert_eq!(parse_sample(""), None);
+        assert_eq!(parse_sample("node_load1"), None);
+        assert_eq!(
+            parse_sample("node_load1 0.42"),
+            Some(Sample {
+                name: "node_load1".to_string(),
+                labels: vec![],
+                value: 0.42,
+            })
+        );
+        assert_eq!(
+            parse_sample(r#"weird{path="C:\\dir\\",msg="say \"hi\"\n",} +Inf"#),
+            Some(Sample {
+                name: "weird".to_string(),
+                labels: vec![
+                    ("path".to_string(), r"C:\dir\".to_string()),
+                    ("msg".to_string(), "say \"hi\"\n".to_string()),
+                ],
+                value: f64::INFINITY,
+            })
+        );
+        assert_eq!(parse_sample(r#"broken{a="b 1"#), None);
+    }
+
+    #[test]
+    fn select_series() {
+        assert_eq!(
+            find_sample(METRICS, "node_load1", &HashMap::new()),
+            Some(0.42)
+        );
+        assert_eq!(
+            find_sample(
+                METRICS,
+                "node_filesystem_avail_bytes",
+                &labels(&[("mountpoint", "/home")])
+            ),
+            Some(9.87e11)
+        );
+        // Without matchers the first series wins
+        assert_eq!(
+            find_sample(METRICS, "node_filesystem_avail_bytes", &HashMap::new()),
+            Some(1.2345e10)
+        );
+        assert_eq!(
+            find_sample(
+                METRICS,
+                "http_requests_total",
+                &labels(&[("method", "post"), ("code", "400")])
+            ),
+            Some(3.)
+        );
+        assert_eq!(
+            find_sample(METRICS, "http_requests_total", &labels(&[("code", "500")])),
+            None
+        );
+        assert_eq!(find_sample(METRICS, "node_load", &HashMap::new()), None);
+    }
+}
@@ -0,0 +1,182 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::scheduler::Task;
+use crate::util::read_file;
+use crate::widgets::text::TextWidget;
+use crate::widgets::I3BarWidget;
+
+const POWERCAP_PATH: &str = "/sys/class/powercap";
+
+#[derive(Debug, Clone, Copy)]
+struct EnergySample {
+    /// Energy counter in microjoules
+    energy: u64,
+    time: Instant,
+}
+
+/// Average power in watts between two samples. The counter wraps around after reaching
+/// `max_energy_range` microjoules.
+fn power_between(prev: EnergySample, cur: EnergySample, max_energy_range: u64) -> Option<f64> {
+    let elapsed = cur.time.checked_duration_since(prev.time)?.as_secs_f64();
+    if elapsed == 0. {
+        return None;
+    }
+    let energy = if cur.energy >= prev.energy {
+        cur.energy - prev.energy
+    } else {
+        max_energy_range - prev.energy + cur.energy
+    };
+    Some(energy as f64 / 1e6 / elapsed)
+}
+
+pub struct Rapl {
+    id: usize,
+    text: TextWidget,
+    zone_path: PathBuf,
+    max_energy_range: Option<u64>,
+    last_sample: Option<EnergySample>,
+    power: Option<f64>,
+    format: FormatTemplate,
+    update_interval: Duration,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct RaplConfig {
+    /// Update interval in seconds
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// The powercap zone to read, `intel-rapl:0` is the CPU package
+    pub zone: String,
+
+    pub format: FormatTemplate,
+}
+
+impl Default for RaplConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(5),
+            zone: "intel-rapl:0".to_string(),
+            format: FormatTemplate::default(),
+        }
+    }
+}
+
+impl Rapl {
+    fn read_counter(&self, name: &str) -> Result<u64> {
+        read_file("rapl", &self.zone_path.join(name))?
+            .parse()
+            .block_error("rapl", &format!("failed to parse {}", name))
+    }
+}
+
+impl ConfigBlock for Rapl {
+    type Config = RaplConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        Ok(Rapl {
+            id,
+            text: TextWidget::new(id, 0, shared_config).with_icon("cpu")?,
+            zone_path: PathBuf::from(POWERCAP_PATH).join(block_config.zone),
+            max_energy_range: None,
+            last_sample: None,
+            power: None,
+            format: block_config.format.with_default("{power}")?,
+            update_interval: block_config.interval,
+        })
+    }
+}
+
+impl Block for Rapl {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        if self.max_energy_range.is_none() {
+            self.max_energy_range = self.read_counter("max_energy_range_uj").ok();
+        }
+
+        // RAPL may be unavailable or only readable by root
+        let sample = match (self.max_energy_range, self.read_counter("energy_uj")) {
+            (Some(_), Ok(energy)) => Some(EnergySample {
+                energy,
+                time: Instant::now(),
+            }),
+            _ => None,
+        };
+
+        self.power = match (self.last_sample, sample, self.max_energy_range) {
+            (Some(prev), Some(cur), Some(range)) => power_between(prev, cur, range),
+            _ => None,
+        };
+        self.last_sample = sample;
+
+        if let Some(power) = self.power {
+            let values = map!(
+                "power" => Value::from_float(power).watts(),
+            );
+            self.text.set_texts(self.format.render(&values)?);
+        }
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        if self.power.is_some() {
+            vec![&self.text]
+        } else {
+            vec![]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn power() {
+        let start = Instant::now();
+        let prev = EnergySample {
+            energy: 1_000_000,
+            time: start,
+        };
+        let cur = EnergySample {
+            energy: 26_000_000,
+            time: start + Duration::from_secs(5),
+        };
+        assert_eq!(power_between(prev, cur, 100_000_000), Some(5.0));
+        assert_eq!(power_between(prev, prev, 100_000_000), None);
+    }
+
+    #[test]
+    fn power_wraparound() {
+        let start = Instant::now();
+        let prev = EnergySample {
+            energy: 95_000_000,
+            time: start,
+        };
+        let cur = EnergySample {
+            energy: 5_000_000,
+            time: start + Duration::from_secs(2),
+        };
+        assert_eq!(power_between(prev, cur, 100_000_000), Some(5.0));
+    }
+}
@@ -0,0 +1,288 @@
+use std::fs::{read_dir, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::read_file;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+const SYSFS_RFKILL: &str = "/sys/class/rfkill";
+const DEV_RFKILL: &str = "/dev/rfkill";
+
+/// The operation of `struct rfkill_event` which changes all devices of a type, see
+/// `include/uapi/linux/rfkill.h` of Linux
+const RFKILL_OP_CHANGE_ALL: u8 = 3;
+
+/// The kinds of radios which can be toggled
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RadioType {
+    All,
+    Wifi,
+    Bluetooth,
+}
+
+impl RadioType {
+    /// The type of `struct rfkill_event`
+    fn event_type(self) -> u8 {
+        match self {
+            RadioType::All => 0,
+            RadioType::Wifi => 1,
+            RadioType::Bluetooth => 2,
+        }
+    }
+
+    fn matches(self, device: &Device) -> bool {
+        match self {
+            RadioType::All => true,
+            RadioType::Wifi => device.kind == "wlan",
+            RadioType::Bluetooth => device.kind == "bluetooth",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Device {
+    /// The type as sysfs names it, e.g. `wlan` or `bluetooth`
+    kind: String,
+    /// Blocked in software, i.e. with `rfkill block`
+    soft: bool,
+    /// Blocked by a hardware switch
+    hard: bool,
+}
+
+/// Read the devices of `/sys/class/rfkill`, each of which is a directory like `rfkill0`
+fn read_devices(dir: &Path) -> Result<Vec<Device>> {
+    // Without any radios there are no devices
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let entries = read_dir(dir).block_error("rfkill", "failed to list rfkill devices")?;
+    let mut devices = Vec::new();
+    for entry in entries {
+        let path = entry
+            .block_error("rfkill", "failed to list rfkill devices")?
+            .path();
+        let blocked =
+            |name| -> Result<bool> { Ok(read_file("rfkill", &path.join(name))?.trim() == "1") };
+        devices.push(Device {
+            kind: read_file("rfkill", &path.join("type"))?.trim().to_string(),
+            soft: blocked("soft")?,
+            hard: blocked("hard")?,
+        });
+    }
+    Ok(devices)
+}
+
+/// Whether any radio of the type is on, i.e. blocked neither in software nor in hardware
+fn enabled(devices: &[Device], radio: RadioType) -> bool {
+    devices
+        .iter()
+        .any(|device| radio.matches(device) && !device.soft && !device.hard)
+}
+
+/// Block or unblock all radios of the type in software by writing a `struct rfkill_event`
+fn write_change_all(writer: &mut impl Write, radio: RadioType, block: bool) -> Result<()> {
+    let mut event = Vec::with_capacity(8);
+    // The index of the device is not used for all devices of a type
+    event.extend_from_slice(&0u32.to_ne_bytes());
+    event.push(radio.event_type());
+    event.push(RFKILL_OP_CHANGE_ALL);
+    event.push(block as u8);
+    // Hardware blocks can't be changed
+    event.push(0);
+    writer
+        .write_all(&event)
+        .block_error("rfkill", "failed to change the rfkill state")
+}
+
+pub struct Rfkill {
+    id: usize,
+    text: TextWidget,
+    format: FormatTemplate,
+    toggle: RadioType,
+    update_interval: Duration,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct RfkillConfig {
+    pub format: FormatTemplate,
+
+    /// The radios a left click turns on or off
+    pub toggle: RadioType,
+
+    /// Update interval in seconds
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+}
+
+impl Default for RfkillConfig {
+    fn default() -> Self {
+        Self {
+            format: FormatTemplate::default(),
+            toggle: RadioType::Wifi,
+            interval: Duration::from_secs(5),
+        }
+    }
+}
+
+impl ConfigBlock for Rfkill {
+    type Config = RfkillConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        Ok(Rfkill {
+            id,
+            text: TextWidget::new(id, 0, shared_config),
+            format: block_config.format.with_default(
+                "WiFi {wifi.truthy_icon(true:toggle_on,false:toggle_off)} \
+                 BT {bt.truthy_icon(true:toggle_on,false:toggle_off)}",
+            )?,
+            toggle: block_config.toggle,
+            update_interval: block_config.interval,
+        })
+    }
+}
+
+impl Block for Rfkill {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        let devices = read_devices(Path::new(SYSFS_RFKILL))?;
+        let values = map!(
+            "wifi" => Value::from_integer(enabled(&devices, RadioType::Wifi) as i64),
+            "bt" => Value::from_integer(enabled(&devices, RadioType::Bluetooth) as i64),
+        );
+        self.text.set_texts(self.format.render(&values)?);
+        // A hardware switch can't be toggled from here
+        let state = if devices.iter().any(|device| device.hard) {
+            State::Warning
+        } else {
+            State::Idle
+        };
+        self.text.set_state(state);
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        if let MouseButton::Left = e.button {
+            let devices = read_devices(Path::new(SYSFS_RFKILL))?;
+            let mut dev = OpenOptions::new()
+                .write(true)
+                .open(DEV_RFKILL)
+                .block_error("rfkill", "failed to open /dev/rfkill")?;
+            write_change_all(&mut dev, self.toggle, enabled(&devices, self.toggle))?;
+            self.update()?;
+        }
+        Ok(())
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+    use assert_fs::TempDir;
+
+    fn sysfs(devices: &[(&str, &str, &str)]) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for (i, (kind, soft, hard)) in devices.iter().enumerate() {
+            let device = dir.child(format!("rfkill{}", i));
+            device
+                .child("type")
+                .write_str(&format!("{}\n", kind))
+                .unwrap();
+            device
+                .child("soft")
+                .write_str(&format!("{}\n", soft))
+                .unwrap();
+            device
+                .child("hard")
+                .write_str(&format!("{}\n", hard))
+                .unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn parse() {
+        let dir = sysfs(&[("wlan", "1", "0"), ("bluetooth", "0", "1")]);
+        let mut devices = read_devices(dir.path()).unwrap();
+        devices.sort_by(|a, b| a.kind.cmp(&b.kind));
+        assert_eq!(
+            devices,
+            [
+                Device {
+                    kind: "bluetooth".to_string(),
+                    soft: false,
+                    hard: true,
+                },
+                Device {
+                    kind: "wlan".to_string(),
+                    soft: true,
+                    hard: false,
+                },
+            ]
+        );
+        assert_eq!(read_devices(&dir.path().join("missing")).unwrap(), []);
+
+        let dir = sysfs(&[("wlan", "1", "0")]);
+        std::fs::remove_file(dir.path().join("rfkill0/hard")).unwrap();
+        assert!(read_devices(dir.path()).is_err());
+    }
+
+    #[test]
+    fn radios() {
+        let dir = sysfs(&[
+            ("wlan", "1", "0"),
+            ("wlan", "0", "0"),
+            ("bluetooth", "0", "1"),
+        ]);
+        let devices = read_devices(dir.path()).unwrap();
+        // One of the wifi devices is on
+        assert!(enabled(&devices, RadioType::Wifi));
+        assert!(!enabled(&devices, RadioType::Bluetooth));
+        assert!(enabled(&devices, RadioType::All));
+        assert!(!enabled(&[], RadioType::Wifi));
+    }
+
+    #[test]
+    fn toggle() {
+        let mut event = Vec::new();
+        write_change_all(&mut event, RadioType::Wifi, true).unwrap();
+        let mut expected = 0u32.to_ne_bytes().to_vec();
+        expected.extend_from_slice(&[1, RFKILL_OP_CHANGE_ALL, 1, 0]);
+        assert_eq!(event, expected);
+
+        let mut event = Vec::new();
+        write_change_all(&mut event, RadioType::Bluetooth, false).unwrap();
+        assert_eq!(event[4..], [2, RFKILL_OP_CHANGE_ALL, 0, 0]);
+
+        let mut event = Vec::new();
+        write_change_all(&mut event, RadioType::All, true).unwrap();
+        assert_eq!(event[4..], [0, RFKILL_OP_CHANGE_ALL, 1, 0]);
+    }
+}
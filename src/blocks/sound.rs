@@ -22,7 +22,7 @@ use {
 
 use std::cmp::{max, min};
 use std::collections::BTreeMap;
-use std::io::Read;
+use std::io::{BufRead, BufReader, Read};
 use std::process::{Command, Stdio};
 use std::thread;
 use std::time::{Duration, Instant};
@@ -206,6 +206,157 @@ impl SoundDevice for AlsaSoundDevice {
     }
 }
 
+/// A playback stream of an application
+#[derive(Debug, PartialEq)]
+struct AppStream {
+    index: u32,
+    app: String,
+    volume: u32,
+    muted: bool,
+}
+
+/// Parse the output of `pactl list sink-inputs`
+fn parse_sink_inputs(output: &str) -> Vec<AppStream> {
+    let mut streams = Vec::new();
+    for section in output.split("Sink Input #").skip(1) {
+        let mut lines = section.lines();
+        let index = match lines.next().and_then(|index| index.trim().parse().ok()) {
+            Some(index) => index,
+            None => continue,
+        };
+        let mut stream = AppStream {
+            index,
+            app: String::new(),
+            volume: 0,
+            muted: false,
+        };
+        for line in lines.map(str::trim) {
+            if let Some(muted) = line.strip_prefix("Mute:") {
+                stream.muted = muted.trim() == "yes";
+            } else if let Some(volume) = line.strip_prefix("Volume:") {
+                stream.volume = volume
+                    .split_whitespace()
+                    .find_map(|x| x.strip_suffix('%'))
+                    .and_then(|x| x.parse().ok())
+                    .unwrap_or(0);
+            } else if let Some(app) = line.strip_prefix("application.name = ") {
+                stream.app = app.trim_matches('"').to_string();
+            }
+        }
+        streams.push(stream);
+    }
+    streams
+}
+
+/// Find the first stream of the application `app` (case-insensitive)
+fn find_app_stream(streams: Vec<AppStream>, app: &str) -> Option<AppStream> {
+    streams
+        .into_iter()
+        .find(|stream| stream.app.eq_ignore_ascii_case(app))
+}
+
+/// The playback stream of a single application. Uses `pactl`, so it works with PulseAudio and
+/// PipeWire (through pipewire-pulse).
+struct AppStreamSoundDevice {
+    app: String,
+    stream: Option<AppStream>,
+}
+
+impl AppStreamSoundDevice {
+    fn pactl(args: &[&str]) -> Result<String> {
+        Command::new("pactl")
+            .env("LC_ALL", "C")
+            .args(args)
+            .output()
+            .map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
+            .block_error("sound", "could not run pactl")
+    }
+}
+
+impl SoundDevice for AppStreamSoundDevice {
+    fn volume(&self) -> u32 {
+        self.stream.as_ref().map_or(0, |stream| stream.volume)
+    }
+    fn muted(&self) -> bool {
+        // An application that isn't playing anything is displayed as muted
+        match &self.stream {
+            Some(stream) => stream.muted,
+            None => true,
+        }
+    }
+    fn output_name(&self) -> String {
+        self.app.clone()
+    }
+    fn output_description(&self) -> Option<String> {
+        None
+    }
+
+    fn get_info(&mut self) -> Result<()> {
+        let output = Self::pactl(&["list", "sink-inputs"])?;
+        self.stream = find_app_stream(parse_sink_inputs(&output), &self.app);
+        Ok(())
+    }
+
+    fn set_volume(&mut self, step: i32, max_vol: Option<u32>) -> Result<()> {
+        let stream = match &mut self.stream {
+            Some(stream) => stream,
+            None => return Ok(()),
+        };
+        let new_vol = max(0, stream.volume as i32 + step) as u32;
+        let capped_volume = match max_vol {
+            Some(vol_cap) => min(new_vol, vol_cap),
+            None => new_vol,
+        };
+        Self::pactl(&[
+            "set-sink-input-volume",
+            &stream.index.to_string(),
+            &format!("{}%", capped_volume),
+        ])?;
+        stream.volume = capped_volume;
+        Ok(())
+    }
+
+    fn toggle(&mut self) -> Result<()> {
+        if let Some(stream) = &mut self.stream {
+            Self::pactl(&["set-sink-input-mute", &stream.index.to_string(), "toggle"])?;
+            stream.muted = !stream.muted;
+        }
+        Ok(())
+    }
+
+    fn monitor(&mut self, id: usize, tx_update_request: Sender<Task>) -> Result<()> {
+        let events = Command::new("pactl")
+            .env("LC_ALL", "C")
+            .arg("subscribe")
+            .stdout(Stdio::piped())
+            .spawn()
+            .block_error("sound", "failed to start pactl subscribe")?
+            .stdout
+            .block_error("sound", "failed to pipe pactl subscribe output")?;
+
+        thread::Builder::new()
+            .name("sound_app".into())
+            .spawn(move || {
+                // e.g. "Event 'change' on sink-input #42"
+                for line in BufReader::new(events).lines() {
+                    match line {
+                        Ok(line) if line.contains("sink-input") => tx_update_request
+                            .send(Task {
+                                id,
+                                update_time: Instant::now(),
+                            })
+                            .unwrap(),
+                        Ok(_) => (),
+                        Err(_) => break,
+                    }
+                }
+            })
+            .unwrap();
+
+        Ok(())
+    }
+}
+
 #[cfg(feature = "pulseaudio")]
 struct PulseAudioConnection {
     mainloop: Rc<RefCell<Mainloop>>,
@@ -708,6 +859,7 @@ pub struct Sound {
     show_volume_when_muted: bool,
     mappings: Option<BTreeMap<String, String>>,
     max_vol: Option<u32>,
+    app: Option<String>,
     scrolling: Scrolling,
 }
 
@@ -780,6 +932,9 @@ pub struct SoundConfig {
     pub mappings: Option<BTreeMap<String, String>>,
 
     pub max_vol: Option<u32>,
+
+    /// Control the playback stream of this application instead of a device
+    pub app: Option<String>,
 }
 
 impl Default for SoundConfig {
@@ -795,6 +950,7 @@ impl Default for SoundConfig {
             show_volume_when_muted: false,
             mappings: None,
             max_vol: None,
+            app: None,
         }
     }
 }
@@ -852,9 +1008,13 @@ impl ConfigBlock for Sound {
         };
 
         // prefer PulseAudio if available and selected, fallback to ALSA
-        let device: Box<dyn SoundDevice> = match pulseaudio_device {
-            Ok(dev) => Box::new(dev),
-            Err(_) => Box::new(AlsaSoundDevice::new(
+        let device: Box<dyn SoundDevice> = match (&block_config.app, pulseaudio_device) {
+            (Some(app), _) => Box::new(AppStreamSoundDevice {
+                app: app.clone(),
+                stream: None,
+            }),
+            (None, Ok(dev)) => Box::new(dev),
+            (None, Err(_)) => Box::new(AlsaSoundDevice::new(
                 block_config.name.unwrap_or_else(|| "Master".into()),
                 block_config.device.unwrap_or_else(|| "default".into()),
                 block_config.natural_mapping,
@@ -871,6 +1031,7 @@ impl ConfigBlock for Sound {
             show_volume_when_muted: block_config.show_volume_when_muted,
             mappings: block_config.mappings,
             max_vol: block_config.max_vol,
+            app: block_config.app,
             scrolling: shared_config.scrolling,
             text: TextWidget::new(id, 0, shared_config).with_icon("volume_empty")?,
         };
@@ -917,6 +1078,7 @@ impl Block for Sound {
             "volume" => Value::from_integer(volume as i64).percents(),
             "output_name" => Value::from_string(output_name),
             "output_description" => Value::from_string(output_description),
+            "app" => Value::from_string(self.app.clone().unwrap_or_default()),
         );
         let texts = self.format.render(&values)?;
 
@@ -973,3 +1135,65 @@ impl Block for Sound {
         self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SINK_INPUTS: &str = r#"Sink Input #42
+	Driver: protocol-native.c
+	Owner Module: 10
+	Client: 55
+	Sink: 0
+	Sample Specification: float32le 2ch 48000Hz
+	Channel Map: front-left,front-right
+	Corked: no
+	Mute: no
+	Volume: front-left: 42598 /  65% / -11.23 dB,   front-right: 42598 /  65% / -11.23 dB
+	        balance 0.00
+	Properties:
+		application.name = "Firefox"
+		application.process.binary = "firefox"
+		media.name = "AudioStream"
+
+Sink Input #57
+	Driver: PipeWire
+	Sink: 0
+	Mute: yes
+	Volume: mono: 65536 / 100% / 0.00 dB
+	Properties:
+		application.name = "spotify"
+		media.name = "Spotify"
+"#;
+
+    #[test]
+    fn parse_streams() {
+        let streams = parse_sink_inputs(SINK_INPUTS);
+        assert_eq!(
+            streams,
+            [
+                AppStream {
+                    index: 42,
+                    app: "Firefox".to_string(),
+                    volume: 65,
+                    muted: false,
+                },
+                AppStream {
+                    index: 57,
+                    app: "spotify".to_string(),
+                    volume: 100,
+                    muted: true,
+                },
+            ]
+        );
+        assert!(parse_sink_inputs("").is_empty());
+    }
+
+    #[test]
+    fn find_stream() {
+        let find = |app| find_app_stream(parse_sink_inputs(SINK_INPUTS), app);
+        assert_eq!(find("firefox").map(|s| s.index), Some(42));
+        assert_eq!(find("Spotify").map(|s| s.index), Some(57));
+        assert_eq!(find("mpv"), None);
+    }
+}
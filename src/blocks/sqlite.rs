@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+use std::process::Command;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::escape_pango_text;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// The columns of a row, by name
+type Row = Vec<(String, String)>;
+
+/// The separators of the `ascii` output mode of `sqlite3`
+const COLUMN_SEPARATOR: char = '\x1f';
+const ROW_SEPARATOR: char = '\x1e';
+
+/// Run `query` against the database at `path`, opened read-only, and return the first row
+fn query_first_row(path: &str, query: &str) -> Result<Option<Row>> {
+    // The `ascii` mode is available in every version of `sqlite3`, unlike `-json` which needs 3.33
+    let output = Command::new("sqlite3")
+        .args([
+            "-readonly",
+            "-batch",
+            "-bail",
+            "-ascii",
+            "-header",
+            path,
+            query,
+        ])
+        .output()
+        .block_error("sqlite", "failed to run sqlite3")?;
+    if !output.status.success() {
+        return Err(BlockError(
+            "sqlite".to_string(),
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    parse_rows(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse the first row of the output of `sqlite3 -ascii -header`, which starts with the names
+/// of the columns and is empty if there are no rows
+fn parse_rows(output: &str) -> Result<Option<Row>> {
+    let mut rows = output.split(ROW_SEPARATOR);
+    let names = match rows.next() {
+        Some(names) if !names.is_empty() => names,
+        _ => return Ok(None),
+    };
+    let row = match rows.next() {
+        Some(row) if rows.next().is_some() => row,
+        _ => {
+            return Err(BlockError(
+                "sqlite".to_string(),
+                "failed to parse sqlite3 output".to_string(),
+            ))
+        }
+    };
+    Ok(Some(
+        names
+            .split(COLUMN_SEPARATOR)
+            .map(String::from)
+            .zip(row.split(COLUMN_SEPARATOR).map(String::from))
+            .collect(),
+    ))
+}
+
+/// The text output loses the type of a column, so anything that looks like a number is one.
+/// `NULL` is an empty string.
+fn column_value(column: &str) -> Value {
+    if let Ok(i) = column.parse() {
+        Value::from_integer(i)
+    } else if let Some(f) = column
+        .parse::<f64>()
+        .ok()
+        .filter(|f| f.is_finite() && column.bytes().any(|b| b.is_ascii_digit()))
+    {
+        Value::from_float(f)
+    } else {
+        Value::from_string(escape_pango_text(column.to_string()))
+    }
+}
+
+pub struct Sqlite {
+    id: usize,
+    text: TextWidget,
+    path: String,
+    query: String,
+    format: FormatTemplate,
+    update_interval: Duration,
+    has_row: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct SqliteConfig {
+    /// Path to the database file
+    pub path: String,
+
+    /// The query whose first row is displayed
+    pub query: String,
+
+    /// Format string, the columns of the first row are the placeholders
+    pub format: FormatTemplate,
+
+    /// Update interval in seconds
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+}
+
+impl Default for SqliteConfig {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            query: String::new(),
+            format: FormatTemplate::default(),
+            interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl ConfigBlock for Sqlite {
+    type Config = SqliteConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        if block_config.path.is_empty() || block_config.query.is_empty() {
+            return Err(ConfigurationError(
+                "sqlite".to_string(),
+                "'path' and 'query' must be set".to_string(),
+            ));
+        }
+        Ok(Sqlite {
+            id,
+            text: TextWidget::new(id, 0, shared_config),
+            path: block_config.path,
+            query: block_config.query,
+            format: block_config.format.with_default("")?,
+            update_interval: block_config.interval,
+            has_row: false,
+        })
+    }
+}
+
+impl Block for Sqlite {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        match query_first_row(&self.path, &self.query) {
+            Ok(Some(row)) => {
+                let values: HashMap<&str, Value> = row
+                    .iter()
+                    .map(|(name, column)| (name.as_str(), column_value(column)))
+                    .collect();
+                self.text.set_texts(self.format.render(&values)?);
+                self.text.set_state(State::Idle);
+                self.has_row = true;
+            }
+            Ok(None) => self.has_row = false,
+            Err(BlockError(_, message)) => {
+                self.text.set_text(escape_pango_text(message));
+                self.text.set_state(State::Critical);
+                self.has_row = true;
+            }
+            Err(e) => return Err(e),
+        }
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        if self.has_row {
+            vec![&self.text]
+        } else {
+            vec![]
+        }
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        if let MouseButton::Left = e.button {
+            self.update()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    /// The tests which need the `sqlite3` program are skipped without it
+    fn sqlite3_installed() -> bool {
+        Command::new("sqlite3").arg("-version").output().is_ok()
+    }
+
+    fn create_db(dir: &assert_fs::TempDir) -> String {
+        let path = dir.child("stats.db").path().to_string_lossy().into_owned();
+        let status = Command::new("sqlite3")
+            .args([
+                path.as_str(),
+                "CREATE TABLE stats (name TEXT, count INTEGER, ratio REAL, note TEXT);\
+                 INSERT INTO stats VALUES ('builds', 42, 0.75, NULL);\
+                 INSERT INTO stats VALUES ('deploys', 3, 1.5, 'ok');",
+            ])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        path
+    }
+
+    #[test]
+    fn first_row() {
+        if !sqlite3_installed() {
+            return;
+        }
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = create_db(&dir);
+
+        let row = query_first_row(&path, "SELECT * FROM stats ORDER BY count DESC")
+            .unwrap()
+            .unwrap();
+        let values: HashMap<&str, Value> = row
+            .iter()
+            .map(|(name, column)| (name.as_str(), column_value(column)))
+            .collect();
+        let format = FormatTemplate::new("{name} {count} {ratio} [{note}]", None).unwrap();
+        assert_eq!(format.render(&values).unwrap().full, "builds 42 0.8 []");
+
+        assert_eq!(
+            query_first_row(&path, "SELECT * FROM stats WHERE count > 100").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn read_only() {
+        if !sqlite3_installed() {
+            return;
+        }
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = create_db(&dir);
+
+        assert!(query_first_row(&path, "DELETE FROM stats").is_err());
+        assert!(query_first_row(&path, "SELECT count(*) AS n FROM stats")
+            .unwrap()
+            .is_some());
+        assert!(query_first_row(&path, "SELECT * FROM no_such_table").is_err());
+        assert!(query_first_row(
+            &dir.child("missing.db").path().to_string_lossy(),
+            "SELECT 1"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(parse_rows("").unwrap(), None);
+        assert!(parse_rows("a\x1fb").is_err());
+        let row = parse_rows("a\x1fb\x1fc\x1fd\x1e1\x1f2.5\x1fx\x1f\x1e")
+            .unwrap()
+            .unwrap();
+        let values: HashMap<&str, Value> = row
+            .iter()
+            .map(|(name, column)| (name.as_str(), column_value(column)))
+            .collect();
+        let format = FormatTemplate::new("{a}|{c}|{d}", None).unwrap();
+        assert_eq!(format.render(&values).unwrap().full, " 1|x|");
+
+        let format = FormatTemplate::new("{a}", None).unwrap();
+        for (column, text) in [("inf", "inf"), ("-3", "-3"), ("a<b", "a&lt;b")] {
+            let values = map!("a" => column_value(column));
+            assert_eq!(format.render(&values).unwrap().full, text);
+        }
+    }
+}
@@ -0,0 +1,351 @@
+use std::process::Command;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::escape_pango_text;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// ATA attributes whose normalized value is the remaining life in percent, in order of
+/// preference: 231 `SSD_Life_Left`, 233 `Media_Wearout_Indicator`, 202
+/// `Percent_Lifetime_Remain`, 177 `Wear_Leveling_Count` and 169 `Remaining_Lifetime_Perc`
+const ATA_LIFE_ATTRIBUTES: &[u32] = &[231, 233, 202, 177, 169];
+
+/// The parts of the output of `smartctl --json -i -A` used by this block
+#[derive(Deserialize, Debug)]
+struct SmartctlOutput {
+    #[serde(default)]
+    smartctl: SmartctlInfo,
+    #[serde(default)]
+    model_name: String,
+    nvme_smart_health_information_log: Option<NvmeHealth>,
+    ata_smart_attributes: Option<AtaAttributes>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct SmartctlInfo {
+    #[serde(default)]
+    messages: Vec<SmartctlMessage>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SmartctlMessage {
+    string: String,
+    #[serde(default)]
+    severity: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct NvmeHealth {
+    percentage_used: f64,
+}
+
+#[derive(Deserialize, Debug)]
+struct AtaAttributes {
+    table: Vec<AtaAttribute>,
+}
+
+#[derive(Deserialize, Debug)]
+struct AtaAttribute {
+    id: u32,
+    value: f64,
+}
+
+#[derive(Debug, PartialEq)]
+struct Wear {
+    model: String,
+    /// The remaining write endurance in percent
+    life_remaining: f64,
+}
+
+fn parse_smartctl(output: &str) -> Result<Wear> {
+    let output: SmartctlOutput = serde_json::from_str(output)
+        .block_error("ssd_wear", "failed to parse the output of smartctl")?;
+
+    let nvme = output
+        .nvme_smart_health_information_log
+        .map(|health| (100. - health.percentage_used).max(0.));
+    let ata = output.ata_smart_attributes.and_then(|attributes| {
+        ATA_LIFE_ATTRIBUTES.iter().find_map(|id| {
+            attributes
+                .table
+                .iter()
+                .find(|attribute| attribute.id == *id)
+                .map(|attribute| attribute.value.min(100.))
+        })
+    });
+
+    match nvme.or(ata) {
+        Some(life_remaining) => Ok(Wear {
+            model: output.model_name,
+            life_remaining,
+        }),
+        None => Err(BlockError(
+            "ssd_wear".to_string(),
+            output
+                .smartctl
+                .messages
+                .into_iter()
+                .find(|message| message.severity == "error")
+                .map(|message| message.string)
+                .unwrap_or_else(|| "the device reports no wear indicator".to_string()),
+        )),
+    }
+}
+
+pub struct SsdWear {
+    id: usize,
+    text: TextWidget,
+    device: String,
+    /// The program and its first arguments, see `SsdWearConfig::command`
+    command: Vec<String>,
+    format: FormatTemplate,
+    update_interval: Duration,
+    warning: f64,
+    critical: f64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct SsdWearConfig {
+    /// The path of the device, e.g. `/dev/nvme0` or `/dev/sda`
+    pub device: String,
+
+    /// The command which runs smartctl, split at whitespace, e.g. `sudo -n smartctl` as smartctl
+    /// usually needs root privileges
+    pub command: String,
+
+    pub format: FormatTemplate,
+
+    /// Update interval in seconds
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// Remaining life in percent below which the block is in the warning state
+    pub warning: f64,
+
+    /// Remaining life in percent below which the block is in the critical state
+    pub critical: f64,
+}
+
+impl Default for SsdWearConfig {
+    fn default() -> Self {
+        Self {
+            device: String::new(),
+            command: "smartctl".to_string(),
+            format: FormatTemplate::default(),
+            interval: Duration::from_secs(3600),
+            warning: 20.,
+            critical: 10.,
+        }
+    }
+}
+
+impl ConfigBlock for SsdWear {
+    type Config = SsdWearConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        if block_config.device.is_empty() {
+            return Err(ConfigurationError(
+                "ssd_wear".to_string(),
+                "'device' must be set".to_string(),
+            ));
+        }
+        let command: Vec<String> = block_config
+            .command
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+        if command.is_empty() {
+            return Err(ConfigurationError(
+                "ssd_wear".to_string(),
+                "'command' must not be empty".to_string(),
+            ));
+        }
+        Ok(SsdWear {
+            id,
+            text: TextWidget::new(id, 0, shared_config).with_icon("disk_drive")?,
+            device: block_config.device,
+            command,
+            format: block_config.format.with_default("{life_remaining}")?,
+            update_interval: block_config.interval,
+            warning: block_config.warning,
+            critical: block_config.critical,
+        })
+    }
+}
+
+impl Block for SsdWear {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        // The exit status of smartctl is a bit mask which is also set for e.g. failing SMART
+        // checks, so only the output tells whether it succeeded
+        let output = Command::new(&self.command[0])
+            .args(&self.command[1..])
+            .args(["--json", "-i", "-A", &self.device])
+            .output()
+            .block_error(
+                "ssd_wear",
+                &format!("failed to run {}", self.command.join(" ")),
+            )?;
+        let wear = parse_smartctl(&String::from_utf8_lossy(&output.stdout))?;
+
+        self.text.set_state(match wear.life_remaining {
+            life if life < self.critical => State::Critical,
+            life if life < self.warning => State::Warning,
+            _ => State::Idle,
+        });
+
+        let values = map!(
+            "life_remaining" => Value::from_integer(wear.life_remaining as i64).percents(),
+            "model" => Value::from_string(escape_pango_text(wear.model)),
+        );
+        self.text.set_texts(self.format.render(&values)?);
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        if let MouseButton::Left = e.button {
+            self.update()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NVME: &str = r#"{
+  "json_format_version": [1, 0],
+  "smartctl": { "version": [7, 2], "exit_status": 0 },
+  "device": { "name": "/dev/nvme0", "type": "nvme", "protocol": "NVMe" },
+  "model_name": "Samsung SSD 970 EVO Plus 1TB",
+  "serial_number": "S4EWNX0R123456",
+  "nvme_smart_health_information_log": {
+    "critical_warning": 0,
+    "temperature": 38,
+    "available_spare": 100,
+    "available_spare_threshold": 10,
+    "percentage_used": 7,
+    "data_units_read": 43785625,
+    "data_units_written": 61029134,
+    "power_on_hours": 9120
+  },
+  "temperature": { "current": 38 },
+  "power_on_time": { "hours": 9120 }
+}"#;
+
+    const ATA: &str = r#"{
+  "json_format_version": [1, 0],
+  "smartctl": { "version": [7, 2], "exit_status": 0 },
+  "device": { "name": "/dev/sda", "type": "sat", "protocol": "ATA" },
+  "model_name": "Samsung SSD 860 EVO 500GB",
+  "ata_smart_attributes": {
+    "revision": 1,
+    "table": [
+      { "id": 5, "name": "Reallocated_Sector_Ct", "value": 100, "worst": 100, "thresh": 10,
+        "raw": { "value": 0, "string": "0" } },
+      { "id": 9, "name": "Power_On_Hours", "value": 95, "worst": 95, "thresh": 0,
+        "raw": { "value": 21374, "string": "21374" } },
+      { "id": 177, "name": "Wear_Leveling_Count", "value": 86, "worst": 86, "thresh": 0,
+        "raw": { "value": 163, "string": "163" } },
+      { "id": 241, "name": "Total_LBAs_Written", "value": 99, "worst": 99, "thresh": 0,
+        "raw": { "value": 52359417349, "string": "52359417349" } }
+    ]
+  }
+}"#;
+
+    const HDD: &str = r#"{
+  "smartctl": { "version": [7, 2], "exit_status": 0 },
+  "model_name": "WDC WD40EFRX-68N32N0",
+  "ata_smart_attributes": {
+    "table": [
+      { "id": 1, "name": "Raw_Read_Error_Rate", "value": 200 },
+      { "id": 9, "name": "Power_On_Hours", "value": 41 }
+    ]
+  }
+}"#;
+
+    const DENIED: &str = r#"{
+  "smartctl": {
+    "version": [7, 2],
+    "messages": [
+      { "string": "Smartctl open device: /dev/sda failed: Permission denied", "severity": "error" }
+    ],
+    "exit_status": 2
+  }
+}"#;
+
+    #[test]
+    fn nvme() {
+        assert_eq!(
+            parse_smartctl(NVME).unwrap(),
+            Wear {
+                model: "Samsung SSD 970 EVO Plus 1TB".to_string(),
+                life_remaining: 93.,
+            }
+        );
+        // The percentage used may exceed 100
+        let worn = NVME.replace(r#""percentage_used": 7"#, r#""percentage_used": 112"#);
+        assert_eq!(parse_smartctl(&worn).unwrap().life_remaining, 0.);
+    }
+
+    #[test]
+    fn ata() {
+        assert_eq!(
+            parse_smartctl(ATA).unwrap(),
+            Wear {
+                model: "Samsung SSD 860 EVO 500GB".to_string(),
+                life_remaining: 86.,
+            }
+        );
+        // `SSD_Life_Left` is preferred over `Wear_Leveling_Count`
+        let with_life_left = ATA.replace(
+            r#"{ "id": 5,"#,
+            r#"{ "id": 231, "name": "SSD_Life_Left", "value": 91 }, { "id": 5,"#,
+        );
+        assert_eq!(parse_smartctl(&with_life_left).unwrap().life_remaining, 91.);
+    }
+
+    #[test]
+    fn errors() {
+        match parse_smartctl(DENIED) {
+            Err(BlockError(_, message)) => assert_eq!(
+                message,
+                "Smartctl open device: /dev/sda failed: Permission denied"
+            ),
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+        match parse_smartctl(HDD) {
+            Err(BlockError(_, message)) => {
+                assert_eq!(message, "the device reports no wear indicator")
+            }
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+        assert!(parse_smartctl("").is_err());
+    }
+}
@@ -0,0 +1,146 @@
+use std::process::Command;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::scheduler::Task;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// The port of an address printed by `ss`, e.g. `192.168.1.5:22` or `[::1]:22`
+fn port(address: &str) -> Option<u16> {
+    address.rsplit(':').next()?.parse().ok()
+}
+
+/// Count the SSH connections in the output of `ss -Htn state established`
+fn count_sessions(output: &str, ssh_port: u16, inbound_only: bool) -> usize {
+    output
+        .lines()
+        .filter(|line| {
+            // Recv-Q Send-Q Local-Address:Port Peer-Address:Port [Process]
+            let mut columns = line.split_whitespace().skip(2);
+            let local = columns.next().and_then(port);
+            let peer = columns.next().and_then(port);
+            local == Some(ssh_port) || (!inbound_only && peer == Some(ssh_port))
+        })
+        .count()
+}
+
+pub struct Ssh {
+    id: usize,
+    text: TextWidget,
+    port: u16,
+    inbound_only: bool,
+    format: FormatTemplate,
+    update_interval: Duration,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct SshConfig {
+    /// Update interval in seconds
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+
+    /// The port SSH connections are made to
+    pub port: u16,
+
+    /// Only count connections to this machine, not the ones made from it
+    pub inbound_only: bool,
+
+    pub format: FormatTemplate,
+}
+
+impl Default for SshConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(10),
+            port: 22,
+            inbound_only: false,
+            format: FormatTemplate::default(),
+        }
+    }
+}
+
+impl ConfigBlock for Ssh {
+    type Config = SshConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        Ok(Ssh {
+            id,
+            text: TextWidget::new(id, 0, shared_config),
+            port: block_config.port,
+            inbound_only: block_config.inbound_only,
+            format: block_config.format.with_default("SSH {count}")?,
+            update_interval: block_config.interval,
+        })
+    }
+}
+
+impl Block for Ssh {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        let output = Command::new("ss")
+            .args(["-Htn", "state", "established"])
+            .output()
+            .block_error("ssh", "failed to run ss")?;
+        let count = count_sessions(
+            &String::from_utf8_lossy(&output.stdout),
+            self.port,
+            self.inbound_only,
+        );
+
+        let values = map!(
+            "count" => Value::from_integer(count as i64),
+        );
+        self.text.set_texts(self.format.render(&values)?);
+        self.text.set_state(if count > 0 {
+            State::Warning
+        } else {
+            State::Idle
+        });
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SS_OUTPUT: &str = "\
+0      0        192.168.1.5:22      192.168.1.10:51234
+0      36       192.168.1.5:22      10.0.0.7:40022
+0      0        192.168.1.5:48812   140.82.121.4:22
+0      0        192.168.1.5:443     10.0.0.9:22222
+0      0        [::1]:22            [::1]:39874
+0      0        192.168.1.5:22222   93.184.216.34:443
+";
+
+    #[test]
+    fn count() {
+        assert_eq!(count_sessions(SS_OUTPUT, 22, true), 3);
+        assert_eq!(count_sessions(SS_OUTPUT, 22, false), 4);
+        assert_eq!(count_sessions(SS_OUTPUT, 22222, false), 2);
+        assert_eq!(count_sessions("", 22, false), 0);
+    }
+}
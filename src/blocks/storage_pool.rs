@@ -0,0 +1,302 @@
+use std::process::Command;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum StoragePoolDriver {
+    Zfs,
+    Btrfs,
+}
+
+#[derive(Debug, PartialEq)]
+struct PoolStatus {
+    state: String,
+    scrub: String,
+}
+
+impl PoolStatus {
+    fn is_healthy(&self) -> bool {
+        self.state == "ONLINE"
+    }
+}
+
+fn run(program: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .block_error("storage_pool", &format!("failed to run {}", program))?;
+    if !output.status.success() {
+        return Err(BlockError(
+            "storage_pool".to_string(),
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    String::from_utf8(output.stdout).block_error(
+        "storage_pool",
+        &format!("{} returned invalid UTF-8", program),
+    )
+}
+
+/// Parse the output of `zpool status <pool>`
+fn parse_zpool_status(output: &str) -> Result<PoolStatus> {
+    let field = |name: &str| {
+        output.lines().find_map(|line| {
+            line.trim_start()
+                .strip_prefix(name)
+                .and_then(|rest| rest.strip_prefix(':'))
+                .map(|value| value.trim().to_string())
+        })
+    };
+    Ok(PoolStatus {
+        state: field("state").block_error("storage_pool", "missing pool state")?,
+        scrub: field("scan").unwrap_or_default(),
+    })
+}
+
+/// Parse the output of `zpool list -H -o capacity <pool>`
+fn parse_zpool_capacity(output: &str) -> Result<f64> {
+    output
+        .trim()
+        .trim_end_matches('%')
+        .parse()
+        .block_error("storage_pool", "failed to parse pool capacity")
+}
+
+/// Parse the output of `btrfs filesystem show <path>`
+fn parse_btrfs_show(output: &str) -> PoolStatus {
+    let state = if output.contains("missing") {
+        "DEGRADED"
+    } else {
+        "ONLINE"
+    };
+    PoolStatus {
+        state: state.to_string(),
+        scrub: String::new(),
+    }
+}
+
+/// Parse the output of `btrfs filesystem usage -b <path>`
+fn parse_btrfs_usage(output: &str) -> Result<f64> {
+    let field = |name: &str| -> Option<f64> {
+        output.lines().find_map(|line| {
+            line.trim_start()
+                .strip_prefix(name)
+                .and_then(|rest| rest.strip_prefix(':'))
+                .and_then(|value| value.trim().parse().ok())
+        })
+    };
+    match (field("Device size"), field("Used")) {
+        (Some(size), Some(used)) if size > 0. => Ok(used / size * 100.),
+        _ => Err(BlockError(
+            "storage_pool".to_string(),
+            "failed to parse filesystem usage".to_string(),
+        )),
+    }
+}
+
+pub struct StoragePool {
+    id: usize,
+    text: TextWidget,
+    driver: StoragePoolDriver,
+    pool: String,
+    format: FormatTemplate,
+    update_interval: Duration,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct StoragePoolConfig {
+    pub driver: StoragePoolDriver,
+
+    /// The name of a ZFS pool, or the mount point of a btrfs filesystem
+    pub pool: String,
+
+    pub format: FormatTemplate,
+
+    /// Update interval in seconds
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+}
+
+impl Default for StoragePoolConfig {
+    fn default() -> Self {
+        Self {
+            driver: StoragePoolDriver::Zfs,
+            pool: String::new(),
+            format: FormatTemplate::default(),
+            interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl ConfigBlock for StoragePool {
+    type Config = StoragePoolConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        if block_config.pool.is_empty() {
+            return Err(ConfigurationError(
+                "storage_pool".to_string(),
+                "'pool' must be set".to_string(),
+            ));
+        }
+        Ok(StoragePool {
+            id,
+            text: TextWidget::new(id, 0, shared_config).with_icon("disk_drive")?,
+            driver: block_config.driver,
+            pool: block_config.pool,
+            format: block_config
+                .format
+                .with_default("{name} {state} {used_percent}")?,
+            update_interval: block_config.interval,
+        })
+    }
+}
+
+impl Block for StoragePool {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        let (status, used_percent) = match self.driver {
+            StoragePoolDriver::Zfs => (
+                parse_zpool_status(&run("zpool", &["status", &self.pool])?)?,
+                parse_zpool_capacity(&run(
+                    "zpool",
+                    &["list", "-H", "-o", "capacity", &self.pool],
+                )?)?,
+            ),
+            StoragePoolDriver::Btrfs => (
+                parse_btrfs_show(&run("btrfs", &["filesystem", "show", &self.pool])?),
+                parse_btrfs_usage(&run("btrfs", &["filesystem", "usage", "-b", &self.pool])?)?,
+            ),
+        };
+
+        self.text.set_state(if status.is_healthy() {
+            State::Idle
+        } else {
+            State::Critical
+        });
+
+        let values = map!(
+            "name" => Value::from_string(self.pool.clone()),
+            "state" => Value::from_string(status.state),
+            "scrub" => Value::from_string(status.scrub),
+            "used_percent" => Value::from_float(used_percent).percents(),
+        );
+        self.text.set_texts(self.format.render(&values)?);
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        if let MouseButton::Left = e.button {
+            self.update()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ZPOOL_HEALTHY: &str = "  pool: tank
+ state: ONLINE
+  scan: scrub repaired 0B in 00:10:12 with 0 errors on Sun Oct 10 00:34:13 2021
+config:
+
+\tNAME        STATE     READ WRITE CKSUM
+\ttank        ONLINE       0     0     0
+\t  mirror-0  ONLINE       0     0     0
+\t    sda     ONLINE       0     0     0
+\t    sdb     ONLINE       0     0     0
+
+errors: No known data errors
+";
+
+    const ZPOOL_DEGRADED: &str = "  pool: tank
+ state: DEGRADED
+status: One or more devices could not be used because the label is missing or
+\tinvalid.  Sufficient replicas exist for the pool to continue
+\tfunctioning in a degraded state.
+action: Replace the device using 'zpool replace'.
+   see: https://openzfs.github.io/openzfs-docs/msg/ZFS-8000-4J
+  scan: none requested
+config:
+
+\tNAME        STATE     READ WRITE CKSUM
+\ttank        DEGRADED     0     0     0
+\t  mirror-0  DEGRADED     0     0     0
+\t    sda     ONLINE       0     0     0
+\t    sdb     UNAVAIL      0     0     0
+
+errors: No known data errors
+";
+
+    #[test]
+    fn zfs() {
+        let healthy = parse_zpool_status(ZPOOL_HEALTHY).unwrap();
+        assert_eq!(healthy.state, "ONLINE");
+        assert!(healthy.scrub.starts_with("scrub repaired 0B"));
+        assert!(healthy.is_healthy());
+
+        let degraded = parse_zpool_status(ZPOOL_DEGRADED).unwrap();
+        assert_eq!(degraded.state, "DEGRADED");
+        assert_eq!(degraded.scrub, "none requested");
+        assert!(!degraded.is_healthy());
+
+        assert_eq!(parse_zpool_capacity("42%\n").unwrap(), 42.);
+        assert!(parse_zpool_status("no pools available").is_err());
+    }
+
+    #[test]
+    fn btrfs() {
+        let healthy = "Label: 'data'  uuid: 0d4d2a4e-6b1a-4b8e-9d0e-7f4c1c3f2a11
+\tTotal devices 2 FS bytes used 1.00GiB
+\tdevid    1 size 10.00GiB used 2.01GiB path /dev/sda
+\tdevid    2 size 10.00GiB used 2.01GiB path /dev/sdb
+";
+        let degraded = "Label: 'data'  uuid: 0d4d2a4e-6b1a-4b8e-9d0e-7f4c1c3f2a11
+\tTotal devices 2 FS bytes used 1.00GiB
+\tdevid    1 size 10.00GiB used 2.01GiB path /dev/sda
+\t*** Some devices missing
+";
+        assert!(parse_btrfs_show(healthy).is_healthy());
+        assert_eq!(parse_btrfs_show(degraded).state, "DEGRADED");
+
+        let usage = "Overall:
+    Device size:                 21474836480
+    Device allocated:             4316987392
+    Device unallocated:          17157849088
+    Device missing:                        0
+    Used:                         5368709120
+    Free (estimated):            16106127360      (min: 7527514112)
+";
+        assert_eq!(parse_btrfs_usage(usage).unwrap(), 25.);
+        assert!(parse_btrfs_usage("").is_err());
+    }
+}
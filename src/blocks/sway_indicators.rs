@@ -0,0 +1,275 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+use swayipc::reply::{Node, NodeType};
+use swayipc::{Connection, EventType};
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::scheduler::Task;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// The hidden workspace that holds the windows of the scratchpad
+const SCRATCHPAD_WORKSPACE: &str = "__i3_scratch";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Indicators {
+    /// The number of windows that demand attention
+    urgent: usize,
+    /// The number of windows in the scratchpad
+    scratchpad: usize,
+}
+
+/// Windows are the leaves of the tree. In i3, floating windows are wrapped in a floating
+/// container, in sway they are floating containers themselves.
+fn count_windows(node: &Node, predicate: fn(&Node) -> bool) -> usize {
+    if node.nodes.is_empty() && node.floating_nodes.is_empty() {
+        match node.node_type {
+            NodeType::Con | NodeType::FloatingCon => predicate(node) as usize,
+            _ => 0,
+        }
+    } else {
+        node.nodes
+            .iter()
+            .chain(&node.floating_nodes)
+            .map(|child| count_windows(child, predicate))
+            .sum()
+    }
+}
+
+fn indicators(tree: &Node) -> Indicators {
+    let scratchpad = tree.find_as_ref(|node| {
+        node.node_type == NodeType::Workspace && node.name.as_deref() == Some(SCRATCHPAD_WORKSPACE)
+    });
+    Indicators {
+        urgent: count_windows(tree, |window| window.urgent),
+        scratchpad: scratchpad.map_or(0, |workspace| count_windows(workspace, |_| true)),
+    }
+}
+
+fn get_indicators(connection: &mut Connection) -> Result<Indicators> {
+    let tree = connection
+        .get_tree()
+        .block_error("sway_indicators", "failed to get the tree")?;
+    Ok(indicators(&tree))
+}
+
+pub struct SwayIndicators {
+    id: usize,
+    text: TextWidget,
+    indicators: Arc<Mutex<Indicators>>,
+    format: FormatTemplate,
+    urgent_icon: String,
+    scratchpad_icon: String,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct SwayIndicatorsConfig {
+    pub format: FormatTemplate,
+}
+
+impl ConfigBlock for SwayIndicators {
+    type Config = SwayIndicatorsConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        send: Sender<Task>,
+    ) -> Result<Self> {
+        let mut queries =
+            Connection::new().block_error("sway_indicators", "failed to acquire connect to IPC")?;
+        let indicators = Arc::new(Mutex::new(get_indicators(&mut queries)?));
+
+        // Windows become urgent, and move to and from the scratchpad, with window events
+        let events = Connection::new()
+            .block_error("sway_indicators", "failed to acquire connect to IPC")?
+            .subscribe(&[EventType::Window])
+            .block_error("sway_indicators", "could not subscribe to window events")?;
+        {
+            let indicators = indicators.clone();
+            thread::Builder::new()
+                .name("sway_indicators".into())
+                .spawn(move || {
+                    for event in events {
+                        event.expect("could not read event in `sway_indicators` block");
+                        let current = get_indicators(&mut queries)
+                            .expect("failed to get the tree in `sway_indicators` block");
+                        let mut indicators = indicators
+                            .lock()
+                            .expect("lock has been poisoned in `sway_indicators` block");
+                        if *indicators != current {
+                            *indicators = current;
+                            send.send(Task {
+                                id,
+                                update_time: Instant::now(),
+                            })
+                            .expect(
+                                "could not communicate with channel in `sway_indicators` block",
+                            );
+                        }
+                    }
+                })
+                .expect("failed to start watching thread for `sway_indicators` block");
+        }
+
+        Ok(SwayIndicators {
+            id,
+            urgent_icon: shared_config.get_icon("urgent")?,
+            scratchpad_icon: shared_config.get_icon("scratchpad")?,
+            text: TextWidget::new(id, 0, shared_config),
+            indicators,
+            format: block_config
+                .format
+                .with_default("{urgent_icon}{scratchpad_icon}")?,
+        })
+    }
+}
+
+impl Block for SwayIndicators {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        let indicators = *self
+            .indicators
+            .lock()
+            .block_error("sway_indicators", "failed to acquire lock")?;
+        let icon = |count: usize, icon: &str| {
+            Value::from_string(if count > 0 {
+                icon.to_string()
+            } else {
+                String::new()
+            })
+        };
+        let values = map!(
+            "urgent" => Value::from_integer(indicators.urgent as i64),
+            "urgent_icon" => icon(indicators.urgent, &self.urgent_icon),
+            "scratchpad" => Value::from_integer(indicators.scratchpad as i64),
+            "scratchpad_icon" => icon(indicators.scratchpad, &self.scratchpad_icon),
+        );
+        self.text.set_texts(self.format.render(&values)?);
+        self.text.set_state(if indicators.urgent > 0 {
+            State::Warning
+        } else {
+            State::Idle
+        });
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        let indicators = self
+            .indicators
+            .lock()
+            .expect("lock has been poisoned in `sway_indicators` block");
+        if *indicators == Indicators::default() {
+            vec![]
+        } else {
+            vec![&self.text]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::sway_fixtures;
+    use serde_json::json;
+
+    fn node(
+        node_type: &str,
+        name: &str,
+        urgent: bool,
+        nodes: Vec<serde_json::Value>,
+        floating_nodes: Vec<serde_json::Value>,
+    ) -> serde_json::Value {
+        let mut node = sway_fixtures::node(1, node_type, nodes, floating_nodes);
+        node["name"] = json!(name);
+        node["urgent"] = json!(urgent);
+        node
+    }
+
+    fn window(name: &str, urgent: bool) -> serde_json::Value {
+        node("con", name, urgent, vec![], vec![])
+    }
+
+    fn tree(scratchpad: Vec<serde_json::Value>, urgent: bool) -> Node {
+        let tree = node(
+            "root",
+            "root",
+            false,
+            vec![
+                node(
+                    "output",
+                    "__i3",
+                    false,
+                    vec![node("workspace", "__i3_scratch", false, vec![], scratchpad)],
+                    vec![],
+                ),
+                node(
+                    "output",
+                    "eDP-1",
+                    false,
+                    vec![
+                        node(
+                            "workspace",
+                            "1",
+                            urgent,
+                            vec![
+                                window("kitty", false),
+                                node(
+                                    "con",
+                                    "",
+                                    urgent,
+                                    vec![window("firefox", false), window("chat", urgent)],
+                                    vec![],
+                                ),
+                            ],
+                            vec![node("floating_con", "pavucontrol", false, vec![], vec![])],
+                        ),
+                        node("workspace", "2", false, vec![], vec![]),
+                    ],
+                    vec![],
+                ),
+            ],
+            vec![],
+        );
+        serde_json::from_value(tree).unwrap()
+    }
+
+    #[test]
+    fn urgent_and_scratchpad() {
+        let scratchpad = vec![
+            node("floating_con", "notes", false, vec![], vec![]),
+            // i3 wraps scratchpad windows in a floating container
+            node(
+                "floating_con",
+                "",
+                false,
+                vec![window("music", true)],
+                vec![],
+            ),
+        ];
+        assert_eq!(
+            indicators(&tree(scratchpad, true)),
+            Indicators {
+                urgent: 2,
+                scratchpad: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn nothing_to_show() {
+        assert_eq!(indicators(&tree(vec![], false)), Indicators::default());
+    }
+}
@@ -0,0 +1,233 @@
+use std::io::{BufRead, BufReader};
+use std::os::unix::net::UnixStream;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+use swayipc::reply::{Event, ModeEvent};
+use swayipc::{Connection, EventType};
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::scheduler::Task;
+use crate::util::{escape_pango_text, hyprland_socket, HYPRLAND_EVENTS};
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// The mode to display, or `None` in the default mode
+fn displayed_mode(event: ModeEvent) -> Option<String> {
+    match event.change.as_str() {
+        "default" => None,
+        _ if event.pango_markup => Some(event.change),
+        _ => Some(escape_pango_text(event.change)),
+    }
+}
+
+/// Parse a line of Hyprland's event socket. Returns `None` for events other than submap
+/// changes, and `Some(None)` when the default submap is restored.
+fn hyprland_submap(line: &str) -> Option<Option<String>> {
+    let submap = line.strip_prefix("submap>>")?;
+    Some(match submap {
+        "" | "default" => None,
+        submap => Some(escape_pango_text(submap.to_string())),
+    })
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum WmDriver {
+    /// sway and i3 share the same IPC protocol
+    Sway,
+    I3,
+    Hyprland,
+}
+
+pub struct SwayMode {
+    id: usize,
+    text: TextWidget,
+    mode: Arc<Mutex<Option<String>>>,
+    format: FormatTemplate,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct SwayModeConfig {
+    /// The window manager to get the mode from
+    pub driver: WmDriver,
+
+    pub format: FormatTemplate,
+}
+
+impl Default for SwayModeConfig {
+    fn default() -> Self {
+        Self {
+            driver: WmDriver::Sway,
+            format: FormatTemplate::default(),
+        }
+    }
+}
+
+impl ConfigBlock for SwayMode {
+    type Config = SwayModeConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        tx: Sender<Task>,
+    ) -> Result<Self> {
+        let mode = Arc::new(Mutex::new(None));
+
+        if let WmDriver::Hyprland = block_config.driver {
+            let socket = UnixStream::connect(hyprland_socket("sway_mode", HYPRLAND_EVENTS)?)
+                .block_error("sway_mode", "failed to connect to Hyprland's event socket")?;
+            let mode = mode.clone();
+            thread::Builder::new()
+                .name("sway_mode".into())
+                .spawn(move || {
+                    for line in BufReader::new(socket).lines().scan((), |_, x| x.ok()) {
+                        if let Some(submap) = hyprland_submap(&line) {
+                            *mode
+                                .lock()
+                                .expect("lock has been poisoned in `sway_mode` block") = submap;
+                            tx.send(Task {
+                                id,
+                                update_time: Instant::now(),
+                            })
+                            .expect("could not communicate with channel in `sway_mode` block");
+                        }
+                    }
+                })
+                .expect("failed to start watching thread for `sway_mode` block");
+        } else {
+            let _test_conn =
+                Connection::new().block_error("sway_mode", "failed to acquire connect to IPC")?;
+
+            let mode = mode.clone();
+            thread::Builder::new()
+                .name("sway_mode".into())
+                .spawn(move || {
+                    let conn = Connection::new().expect("failed to open connection with swayipc");
+
+                    let events = conn
+                        .subscribe(&[EventType::Mode])
+                        .expect("could not subscribe to mode events");
+
+                    for event in events {
+                        if let Event::Mode(e) =
+                            event.expect("could not read event in `sway_mode` block")
+                        {
+                            *mode
+                                .lock()
+                                .expect("lock has been poisoned in `sway_mode` block") =
+                                displayed_mode(e);
+                            tx.send(Task {
+                                id,
+                                update_time: Instant::now(),
+                            })
+                            .expect("could not communicate with channel in `sway_mode` block");
+                        }
+                    }
+                })
+                .expect("failed to start watching thread for `sway_mode` block");
+        }
+
+        Ok(SwayMode {
+            id,
+            text: TextWidget::new(id, 0, shared_config).with_state(State::Warning),
+            mode,
+            format: block_config.format.with_default("{mode}")?,
+        })
+    }
+}
+
+impl Block for SwayMode {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let mode = self
+            .mode
+            .lock()
+            .block_error("sway_mode", "failed to acquire lock")?
+            .clone();
+        if let Some(mode) = mode {
+            let values = map!(
+                "mode" => Value::from_string(mode),
+            );
+            self.text.set_texts(self.format.render(&values)?);
+        }
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        let mode = self
+            .mode
+            .lock()
+            .expect("lock has been poisoned in `sway_mode` block");
+        match *mode {
+            Some(_) => vec![&self.text],
+            None => vec![],
+        }
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mode_event(json: &str) -> ModeEvent {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn mode() {
+        assert_eq!(
+            displayed_mode(mode_event(r#"{"change": "resize", "pango_markup": false}"#)),
+            Some("resize".to_string())
+        );
+        assert_eq!(
+            displayed_mode(mode_event(
+                r#"{"change": "<b>move</b>", "pango_markup": false}"#
+            )),
+            Some("&lt;b&gt;move&lt;/b&gt;".to_string())
+        );
+        assert_eq!(
+            displayed_mode(mode_event(
+                r#"{"change": "<b>move</b>", "pango_markup": true}"#
+            )),
+            Some("<b>move</b>".to_string())
+        );
+    }
+
+    #[test]
+    fn hyprland() {
+        assert_eq!(
+            hyprland_submap("submap>>resize"),
+            Some(Some("resize".to_string()))
+        );
+        assert_eq!(
+            hyprland_submap("submap>>move & <b>"),
+            Some(Some("move &amp; &lt;b&gt;".to_string()))
+        );
+        assert_eq!(hyprland_submap("submap>>"), Some(None));
+        assert_eq!(hyprland_submap("workspace>>2"), None);
+        assert_eq!(hyprland_submap("activewindow>>kitty,submap>>x"), None);
+    }
+
+    #[test]
+    fn hidden_in_default_mode() {
+        assert_eq!(
+            displayed_mode(mode_event(
+                r#"{"change": "default", "pango_markup": false}"#
+            )),
+            None
+        );
+    }
+}
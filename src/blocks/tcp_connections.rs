@@ -0,0 +1,254 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::scheduler::Task;
+use crate::util::read_file;
+use crate::widgets::text::TextWidget;
+use crate::widgets::I3BarWidget;
+
+/// The states of sockets in `/proc/net/tcp`, see `include/net/tcp_states.h` of Linux
+const TCP_ESTABLISHED: u8 = 0x01;
+const TCP_LISTEN: u8 = 0x0A;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Socket {
+    local_port: u16,
+    remote_port: u16,
+    state: u8,
+}
+
+/// Parse a socket of `/proc/net/tcp` or `/proc/net/tcp6`, e.g.
+/// `0: 0100007F:0277 00000000:0000 0A 00000000:00000000 00:00000000 00000000 0 0 12345 ...`
+fn parse_socket(line: &str) -> Option<Socket> {
+    let port = |address: &str| {
+        address
+            .rsplit_once(':')
+            .and_then(|(_, port)| u16::from_str_radix(port, 16).ok())
+    };
+    let mut fields = line.split_whitespace().skip(1);
+    Some(Socket {
+        local_port: port(fields.next()?)?,
+        remote_port: port(fields.next()?)?,
+        state: u8::from_str_radix(fields.next()?, 16).ok()?,
+    })
+}
+
+fn parse_sockets(content: &str) -> Result<Vec<Socket>> {
+    // The first line is a header
+    content
+        .lines()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            parse_socket(line).block_error(
+                "tcp_connections",
+                &format!("invalid socket '{}'", line.trim()),
+            )
+        })
+        .collect()
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Connections {
+    established: usize,
+    /// Established connections to a port which is listened on
+    inbound: usize,
+    outbound: usize,
+}
+
+fn count_connections(
+    sockets: &[Socket],
+    local_port: Option<u16>,
+    remote_port: Option<u16>,
+) -> Connections {
+    let listening: HashSet<u16> = sockets
+        .iter()
+        .filter(|socket| socket.state == TCP_LISTEN)
+        .map(|socket| socket.local_port)
+        .collect();
+    let mut connections = Connections::default();
+    for socket in sockets {
+        if socket.state != TCP_ESTABLISHED
+            || matches!(local_port, Some(port) if port != socket.local_port)
+            || matches!(remote_port, Some(port) if port != socket.remote_port)
+        {
+            continue;
+        }
+        connections.established += 1;
+        if listening.contains(&socket.local_port) {
+            connections.inbound += 1;
+        } else {
+            connections.outbound += 1;
+        }
+    }
+    connections
+}
+
+pub struct TcpConnections {
+    id: usize,
+    text: TextWidget,
+    format: FormatTemplate,
+    local_port: Option<u16>,
+    remote_port: Option<u16>,
+    update_interval: Duration,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct TcpConnectionsConfig {
+    pub format: FormatTemplate,
+
+    /// Only count connections to or from this local port
+    pub local_port: Option<u16>,
+
+    /// Only count connections to or from this remote port
+    pub remote_port: Option<u16>,
+
+    /// Update interval in seconds
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+}
+
+impl Default for TcpConnectionsConfig {
+    fn default() -> Self {
+        Self {
+            format: FormatTemplate::default(),
+            local_port: None,
+            remote_port: None,
+            interval: Duration::from_secs(10),
+        }
+    }
+}
+
+impl ConfigBlock for TcpConnections {
+    type Config = TcpConnectionsConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        Ok(TcpConnections {
+            id,
+            text: TextWidget::new(id, 0, shared_config).with_icon("net_wired")?,
+            format: block_config.format.with_default("{established}")?,
+            local_port: block_config.local_port,
+            remote_port: block_config.remote_port,
+            update_interval: block_config.interval,
+        })
+    }
+}
+
+impl Block for TcpConnections {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        let mut sockets =
+            parse_sockets(&read_file("tcp_connections", Path::new("/proc/net/tcp"))?)?;
+        // Without IPv6 there is no `tcp6`
+        let tcp6 = Path::new("/proc/net/tcp6");
+        if tcp6.exists() {
+            sockets.extend(parse_sockets(&read_file("tcp_connections", tcp6)?)?);
+        }
+        let connections = count_connections(&sockets, self.local_port, self.remote_port);
+
+        let values = map!(
+            "established" => Value::from_integer(connections.established as i64),
+            "inbound" => Value::from_integer(connections.inbound as i64),
+            "outbound" => Value::from_integer(connections.outbound as i64),
+        );
+        self.text.set_texts(self.format.render(&values)?);
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TCP: &str = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0016 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 21334 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:0277 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 23467 1 0000000000000000 100 0 0 10 0
+   2: 0202A8C0:0016 0A02A8C0:C5D2 01 00000000:00000000 02:0009C8E2 00000000     0        0 80127 2 0000000000000000 20 4 31 10 -1
+   3: 0202A8C0:9A4C 22D8B85D:01BB 01 00000000:00000000 02:00000A3D 00000000  1000        0 91544 1 0000000000000000 24 4 30 10 -1
+   4: 0202A8C0:9A4E 22D8B85D:01BB 06 00000000:00000000 03:000013F6 00000000     0        0 0 3 0000000000000000
+   5: 0202A8C0:B3D0 8C52721A:01BB 01 00000000:00000000 02:00000E9A 00000000  1000        0 92011 1 0000000000000000 22 4 28 10 -1
+";
+
+    const TCP6: &str = "  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000000000000000000000000000:0016 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 21336 1 0000000000000000 100 0 0 10 0
+   1: 00000000000000000000000001000000:A2C4 00000000000000000000000001000000:1F90 01 00000000:00000000 00:00000000 00000000  1000        0 93115 1 0000000000000000 20 4 30 10 -1
+";
+
+    #[test]
+    fn parse() {
+        let sockets = parse_sockets(TCP).unwrap();
+        assert_eq!(sockets.len(), 6);
+        assert_eq!(
+            sockets[3],
+            Socket {
+                local_port: 39500,
+                remote_port: 443,
+                state: TCP_ESTABLISHED,
+            }
+        );
+        assert_eq!(parse_sockets(TCP6).unwrap()[1].remote_port, 8080);
+        assert!(parse_sockets("header\n   0: 0100007F:0277\n").is_err());
+        assert!(parse_sockets("header\n   0: 0100007F 00000000:0000 0A\n").is_err());
+        assert_eq!(parse_sockets("header\n").unwrap(), []);
+    }
+
+    #[test]
+    fn count() {
+        let mut sockets = parse_sockets(TCP).unwrap();
+        sockets.extend(parse_sockets(TCP6).unwrap());
+        // The TIME_WAIT socket and the listening ones are not connections, and the SSH session
+        // is to a port listened on
+        assert_eq!(
+            count_connections(&sockets, None, None),
+            Connections {
+                established: 4,
+                inbound: 1,
+                outbound: 3,
+            }
+        );
+        assert_eq!(
+            count_connections(&sockets, None, Some(443)),
+            Connections {
+                established: 2,
+                inbound: 0,
+                outbound: 2,
+            }
+        );
+        assert_eq!(
+            count_connections(&sockets, Some(22), None),
+            Connections {
+                established: 1,
+                inbound: 1,
+                outbound: 0,
+            }
+        );
+        assert_eq!(
+            count_connections(&sockets, Some(22), Some(443)),
+            Connections::default()
+        );
+    }
+}
@@ -10,7 +10,7 @@ use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
 use crate::formatting::value::Value;
-use crate::formatting::FormatTemplate;
+use crate::formatting::{FormatTemplate, Rendered};
 use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
 use crate::util::has_command;
@@ -32,7 +32,7 @@ impl Default for TemperatureScale {
 pub struct Temperature {
     id: usize,
     text: TextWidget,
-    output: (String, Option<String>),
+    output: Rendered,
     collapsed: bool,
     update_interval: Duration,
     scale: TemperatureScale,
@@ -122,7 +122,7 @@ impl ConfigBlock for Temperature {
                 } else {
                     Spacing::Normal
                 }),
-            output: (String::new(), None),
+            output: Rendered::default(),
             collapsed: block_config.collapsed,
             scale: block_config.scale,
             maximum_good: block_config
@@ -3,17 +3,18 @@ use std::time::Duration;
 
 use chrono::{
     offset::{Local, Utc},
-    Locale,
+    DateTime, Locale,
 };
 use chrono_tz::Tz;
 use crossbeam_channel::Sender;
 use serde_derive::Deserialize;
 
 use crate::blocks::{Block, ConfigBlock, Update};
-use crate::config::SharedConfig;
+use crate::config::{LogicalDirection, Scrolling, SharedConfig};
 use crate::de::deserialize_duration;
 use crate::errors::*;
-use crate::formatting::FormatTemplate;
+use crate::formatting::{FormatTemplate, Rendered};
+use crate::protocol::i3bar_event::I3BarEvent;
 use crate::scheduler::Task;
 use crate::widgets::text::TextWidget;
 use crate::widgets::I3BarWidget;
@@ -22,9 +23,19 @@ pub struct Time {
     id: usize,
     time: TextWidget,
     update_interval: Duration,
-    formats: (String, Option<String>),
-    timezone: Option<Tz>,
+    formats: Rendered,
+    timezones: Vec<Tz>,
+    current_timezone: usize,
     locale: Option<String>,
+    scrolling: Scrolling,
+}
+
+/// Either a single timezone or a list of timezones to cycle through
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Timezones {
+    One(Tz),
+    Many(Vec<Tz>),
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -39,7 +50,8 @@ pub struct TimeConfig {
     #[serde(deserialize_with = "deserialize_duration")]
     pub interval: Duration,
 
-    pub timezone: Option<Tz>,
+    /// A timezone or a list of timezones. Scrolling cycles through the list.
+    pub timezone: Option<Timezones>,
 
     pub locale: Option<String>,
 }
@@ -64,8 +76,14 @@ impl ConfigBlock for Time {
         shared_config: SharedConfig,
         _tx_update_request: Sender<Task>,
     ) -> Result<Self> {
+        let timezones = match block_config.timezone {
+            Some(Timezones::One(tz)) => vec![tz],
+            Some(Timezones::Many(tzs)) => tzs,
+            None => Vec::new(),
+        };
         Ok(Time {
             id,
+            scrolling: shared_config.scrolling,
             time: TextWidget::new(id, 0, shared_config)
                 .with_text("")
                 .with_icon("time")?,
@@ -74,7 +92,8 @@ impl ConfigBlock for Time {
                 .format
                 .with_default("%a %d/%m %R")?
                 .render(&::std::collections::HashMap::new())?,
-            timezone: block_config.timezone,
+            timezones,
+            current_timezone: 0,
             locale: block_config.locale,
         })
     }
@@ -82,32 +101,42 @@ impl ConfigBlock for Time {
 
 impl Time {
     fn get_formatted_time(&self, format: &str) -> Result<String> {
-        let time = match &self.locale {
-            Some(l) => {
-                let locale: Locale = l
-                    .as_str()
+        let locale = match &self.locale {
+            Some(l) => Some(
+                l.as_str()
                     .try_into()
-                    .block_error("time", "invalid locale")?;
-                match self.timezone {
-                    Some(tz) => Utc::now()
-                        .with_timezone(&tz)
-                        .format_localized(format, locale),
-                    None => Local::now().format_localized(format, locale),
-                }
-            }
-            None => match self.timezone {
-                Some(tz) => Utc::now().with_timezone(&tz).format(format),
-                None => Local::now().format(format),
-            },
+                    .block_error("time", "invalid locale")?,
+            ),
+            None => None,
         };
-        Ok(format!("{}", time))
+        let timezone = self.timezones.get(self.current_timezone).copied();
+        Ok(format_time(Utc::now(), timezone, locale, format))
     }
 }
 
+fn format_time(
+    now: DateTime<Utc>,
+    timezone: Option<Tz>,
+    locale: Option<Locale>,
+    format: &str,
+) -> String {
+    let time = match locale {
+        Some(locale) => match timezone {
+            Some(tz) => now.with_timezone(&tz).format_localized(format, locale),
+            None => now.with_timezone(&Local).format_localized(format, locale),
+        },
+        None => match timezone {
+            Some(tz) => now.with_timezone(&tz).format(format),
+            None => now.with_timezone(&Local).format(format),
+        },
+    };
+    format!("{}", time)
+}
+
 impl Block for Time {
     fn update(&mut self) -> Result<Option<Update>> {
-        let full = self.get_formatted_time(&self.formats.0)?;
-        let short = match &self.formats.1 {
+        let full = self.get_formatted_time(&self.formats.full)?;
+        let short = match &self.formats.short {
             Some(short_fmt) => Some(self.get_formatted_time(&short_fmt)?),
             None => None,
         };
@@ -119,7 +148,51 @@ impl Block for Time {
         vec![&self.time]
     }
 
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if self.timezones.len() > 1 {
+            let len = self.timezones.len();
+            match self.scrolling.to_logical_direction(event.button) {
+                Some(LogicalDirection::Up) => {
+                    self.current_timezone = (self.current_timezone + 1) % len
+                }
+                Some(LogicalDirection::Down) => {
+                    self.current_timezone = (self.current_timezone + len - 1) % len
+                }
+                None => return Ok(()),
+            }
+            self.update()?;
+        }
+        Ok(())
+    }
+
     fn id(&self) -> usize {
         self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn format_in_timezones() {
+        let now = Utc.ymd(2021, 6, 1).and_hms(12, 30, 0);
+        let zones: Vec<Tz> = vec![
+            "Europe/Lisbon".parse().unwrap(),
+            "America/New_York".parse().unwrap(),
+            "Asia/Tokyo".parse().unwrap(),
+        ];
+        let rendered: Vec<String> = zones
+            .into_iter()
+            .map(|tz| format_time(now, Some(tz), None, "%R %Z"))
+            .collect();
+        assert_eq!(rendered, ["13:30 WEST", "08:30 EDT", "21:30 JST"]);
+
+        let de_de: Locale = "de_DE".try_into().unwrap();
+        assert_eq!(
+            format_time(now, Some(Tz::Europe__Berlin), Some(de_de), "%A %R"),
+            "Dienstag 14:30"
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use std::env;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use crossbeam_channel::Sender;
 use serde_derive::Deserialize;
@@ -67,6 +67,8 @@ pub struct Weather {
     service: WeatherService,
     update_interval: Duration,
     autolocate: bool,
+    alerts: bool,
+    has_alert: bool,
 }
 
 fn malformed_json_error() -> Error {
@@ -146,6 +148,42 @@ fn convert_wind_direction(direction_opt: Option<f64>) -> String {
     }
 }
 
+/// How severe an alert is, judging by the usual naming of its event, e.g. "Tornado Warning" is
+/// more severe than "Tornado Watch". The alerts of OpenWeatherMap have no severity of their own.
+fn alert_severity(event: &str) -> u8 {
+    let event = event.to_lowercase();
+    if event.contains("warning") {
+        3
+    } else if event.contains("watch") {
+        2
+    } else if event.contains("advisory") {
+        1
+    } else {
+        0
+    }
+}
+
+/// The event of the most severe alert in a One Call API response that is active at `now` (a UNIX
+/// timestamp). Of equally severe alerts the first one is chosen.
+fn most_severe_alert(json: &serde_json::Value, now: i64) -> Option<String> {
+    json.pointer("/alerts")?
+        .as_array()?
+        .iter()
+        .filter(|alert| {
+            let time = |key| alert.get(key).and_then(|v| v.as_i64());
+            !matches!(time("start"), Some(start) if now < start)
+                && !matches!(time("end"), Some(end) if end <= now)
+        })
+        .filter_map(|alert| alert.get("event").and_then(|v| v.as_str()))
+        .fold(None, |most_severe: Option<&str>, event| match most_severe {
+            Some(most_severe) if alert_severity(most_severe) >= alert_severity(event) => {
+                Some(most_severe)
+            }
+            _ => Some(event),
+        })
+        .map(|event| event.to_string())
+}
+
 fn configuration_error(msg: &str) -> Result<()> {
     Err(ConfigurationError("weather".to_owned(), msg.to_owned()))
 }
@@ -267,6 +305,46 @@ impl Weather {
                     .map(|s| s.to_string())
                     .ok_or_else(malformed_json_error)?;
 
+                let fetch_alert = || {
+                    let coordinate = |key| {
+                        json.pointer(key)
+                            .and_then(|v| v.as_f64())
+                            .ok_or_else(malformed_json_error)
+                    };
+                    // Alerts are only available through the "One Call" API endpoint
+                    // Refer to https://openweathermap.org/api/one-call-3
+                    let alerts_url = &format!(
+                        "https://api.openweathermap.org/data/3.0/onecall?lat={lat}&lon={lon}&exclude=current,minutely,hourly,daily&appid={api_key}&lang={lang}",
+                        lat = coordinate("/coord/lat")?,
+                        lon = coordinate("/coord/lon")?,
+                        api_key = api_key,
+                        lang = lang.as_ref().unwrap(),
+                    );
+                    let output =
+                        http::http_get_json(alerts_url, Some(Duration::from_secs(3)), vec![])?;
+                    if let Some(val) = output.content.get("message") {
+                        return Err(BlockError(
+                            "weather".to_string(),
+                            format!("API Error: {}", val.as_str().unwrap_or_default()),
+                        ));
+                    };
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|now| now.as_secs() as i64)
+                        .unwrap_or_default();
+                    Ok(most_severe_alert(&output.content, now))
+                };
+                // Alerts need a separate subscription, so failing to get them doesn't hide the
+                // weather
+                let alert = if self.alerts {
+                    fetch_alert().unwrap_or_else(|error| {
+                        eprintln!("Weather: failed to fetch alerts: {}", error);
+                        None
+                    })
+                } else {
+                    None
+                };
+
                 self.weather.set_icon(match raw_weather.as_str() {
                     "Clear" => "weather_sun",
                     "Rain" | "Drizzle" => "weather_rain",
@@ -296,7 +374,9 @@ impl Weather {
                     "wind_kmh" => Value::from_float(kmh_wind_speed),
                     "direction" => Value::from_string(convert_wind_direction(raw_wind_direction)),
                     "location" => Value::from_string(raw_location),
+                    "alert" => Value::from_string(alert.clone().unwrap_or_default()),
                 );
+                self.has_alert = alert.is_some();
                 Ok(())
             }
         }
@@ -316,6 +396,8 @@ pub struct WeatherConfig {
     pub service: WeatherService,
     #[serde(default)]
     pub autolocate: bool,
+    #[serde(default)]
+    pub alerts: bool,
 }
 
 impl WeatherConfig {
@@ -343,6 +425,8 @@ impl ConfigBlock for Weather {
             service: block_config.service,
             update_interval: block_config.interval,
             autolocate: block_config.autolocate,
+            alerts: block_config.alerts,
+            has_alert: false,
         })
     }
 }
@@ -353,7 +437,11 @@ impl Block for Weather {
             Ok(_) => {
                 self.weather
                     .set_texts(self.format.render(&self.weather_keys)?);
-                self.weather.set_state(State::Idle)
+                self.weather.set_state(if self.has_alert {
+                    State::Critical
+                } else {
+                    State::Idle
+                })
             }
             Err(BlockError(block, _)) | Err(InternalError(block, _, _)) if block == "curl" => {
                 // Ignore curl/api errors
@@ -385,3 +473,88 @@ impl Block for Weather {
         self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alerts() {
+        let response = serde_json::json!({
+            "lat": 35.47,
+            "lon": -97.52,
+            "timezone": "America/Chicago",
+            "alerts": [
+                {
+                    "sender_name": "NWS Norman (Central and Western Oklahoma)",
+                    "event": "Heat Advisory",
+                    "start": 1000,
+                    "end": 5000,
+                    "description": "...",
+                    "tags": ["Extreme temperature value"]
+                },
+                {
+                    "sender_name": "NWS Norman (Central and Western Oklahoma)",
+                    "event": "Severe Thunderstorm Watch",
+                    "start": 2000,
+                    "end": 4000,
+                    "description": "...",
+                    "tags": ["Thunderstorm"]
+                },
+                {
+                    "sender_name": "NWS Norman (Central and Western Oklahoma)",
+                    "event": "Tornado Warning",
+                    "start": 3000,
+                    "end": 3500,
+                    "description": "...",
+                    "tags": ["Tornado"]
+                }
+            ]
+        });
+        assert_eq!(most_severe_alert(&response, 500), None);
+        assert_eq!(
+            most_severe_alert(&response, 1500),
+            Some("Heat Advisory".to_string())
+        );
+        assert_eq!(
+            most_severe_alert(&response, 2500),
+            Some("Severe Thunderstorm Watch".to_string())
+        );
+        assert_eq!(
+            most_severe_alert(&response, 3200),
+            Some("Tornado Warning".to_string())
+        );
+        // Alerts end at their end time
+        assert_eq!(
+            most_severe_alert(&response, 3500),
+            Some("Severe Thunderstorm Watch".to_string())
+        );
+        assert_eq!(most_severe_alert(&response, 5000), None);
+
+        // Of equally severe alerts the first one is chosen
+        let response = serde_json::json!({
+            "alerts": [
+                { "event": "Flood Warning", "start": 0, "end": 100 },
+                { "event": "Wind warning", "start": 0, "end": 100 },
+                { "event": "Fog", "start": 0, "end": 100 }
+            ]
+        });
+        assert_eq!(
+            most_severe_alert(&response, 50),
+            Some("Flood Warning".to_string())
+        );
+
+        // Without alerts the response has no `alerts` at all
+        let response = serde_json::json!({
+            "lat": 35.47,
+            "lon": -97.52,
+            "timezone": "America/Chicago",
+            "timezone_offset": -18000
+        });
+        assert_eq!(most_severe_alert(&response, 1500), None);
+        assert_eq!(
+            most_severe_alert(&serde_json::json!({ "alerts": [] }), 0),
+            None
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use std::thread;
+use std::time::Instant;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+use swayipc::reply::{Node, NodeLayout, NodeType};
+use swayipc::{Connection, EventType};
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::scheduler::Task;
+use crate::widgets::text::TextWidget;
+use crate::widgets::I3BarWidget;
+
+#[derive(Debug, Default, PartialEq)]
+struct WindowCounts {
+    /// Tiled windows which are shown
+    visible: usize,
+    /// Tiled windows behind a tab, in a stack or behind a fullscreen window
+    hidden: usize,
+    floating: usize,
+}
+
+/// Whether the node is a window rather than a container of other nodes
+fn is_window(node: &Node) -> bool {
+    matches!(node.node_type, NodeType::Con | NodeType::FloatingCon)
+        && node.nodes.is_empty()
+        && node.floating_nodes.is_empty()
+}
+
+/// Count the tiled windows under `node`. Only the focused child of a tabbed or stacked
+/// container is shown.
+fn count_tiled(node: &Node, shown: bool, counts: &mut WindowCounts) {
+    if is_window(node) {
+        if shown {
+            counts.visible += 1;
+        } else {
+            counts.hidden += 1;
+        }
+        return;
+    }
+    let one_shown = matches!(node.layout, NodeLayout::Tabbed | NodeLayout::Stacked);
+    for child in &node.nodes {
+        let child_shown = shown && (!one_shown || node.focus.first() == Some(&child.id));
+        count_tiled(child, child_shown, counts);
+    }
+}
+
+fn count_floating(node: &Node) -> usize {
+    if is_window(node) {
+        return 1;
+    }
+    node.nodes
+        .iter()
+        .chain(&node.floating_nodes)
+        .map(count_floating)
+        .sum()
+}
+
+fn fullscreen_window(node: &Node) -> bool {
+    if is_window(node) {
+        return node.fullscreen_mode == Some(1);
+    }
+    node.nodes.iter().any(fullscreen_window)
+}
+
+/// Count the windows of the focused workspace, which is on the focused output
+fn count_windows(tree: &Node) -> WindowCounts {
+    let mut counts = WindowCounts::default();
+    let workspace = match tree.find_focused_as_ref(|node| node.node_type == NodeType::Workspace) {
+        Some(workspace) => workspace,
+        None => return counts,
+    };
+    count_tiled(workspace, true, &mut counts);
+    // A fullscreen window covers all other tiled windows
+    if counts.visible > 1 && fullscreen_window(workspace) {
+        counts.hidden += counts.visible - 1;
+        counts.visible = 1;
+    }
+    counts.floating = workspace.floating_nodes.iter().map(count_floating).sum();
+    counts
+}
+
+pub struct Windows {
+    id: usize,
+    text: TextWidget,
+    format: FormatTemplate,
+    connection: Connection,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct WindowsConfig {
+    pub format: FormatTemplate,
+}
+
+impl ConfigBlock for Windows {
+    type Config = WindowsConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        tx: Sender<Task>,
+    ) -> Result<Self> {
+        let connection =
+            Connection::new().block_error("windows", "failed to acquire connect to IPC")?;
+
+        thread::Builder::new()
+            .name("windows".into())
+            .spawn(move || {
+                let conn = Connection::new().expect("failed to open connection with swayipc");
+                let events = conn
+                    .subscribe(&[EventType::Window, EventType::Workspace])
+                    .expect("could not subscribe to window events");
+                // Any change of a window or of the focused workspace may change the counts
+                for event in events {
+                    event.expect("could not read event in `windows` block");
+                    tx.send(Task {
+                        id,
+                        update_time: Instant::now(),
+                    })
+                    .expect("could not communicate with channel in `windows` block");
+                }
+            })
+            .expect("failed to start watching thread for `windows` block");
+
+        Ok(Windows {
+            id,
+            text: TextWidget::new(id, 0, shared_config),
+            format: block_config.format.with_default("{visible:1}+{hidden:1}")?,
+            connection,
+        })
+    }
+}
+
+impl Block for Windows {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        let tree = self
+            .connection
+            .get_tree()
+            .block_error("windows", "failed to get the tree")?;
+        let counts = count_windows(&tree);
+        let values = map!(
+            "visible" => Value::from_integer(counts.visible as i64),
+            "hidden" => Value::from_integer(counts.hidden as i64),
+            "floating" => Value::from_integer(counts.floating as i64),
+        );
+        self.text.set_texts(self.format.render(&values)?);
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::sway_fixtures;
+    use serde_json::json;
+
+    /// A node whose first child has the focus
+    fn node(
+        id: i64,
+        node_type: &str,
+        layout: &str,
+        nodes: Vec<serde_json::Value>,
+        floating_nodes: Vec<serde_json::Value>,
+    ) -> serde_json::Value {
+        let mut node = sway_fixtures::node(id, node_type, nodes, floating_nodes);
+        node["layout"] = json!(layout);
+        node
+    }
+
+    fn window(id: i64) -> serde_json::Value {
+        node(id, "con", "none", vec![], vec![])
+    }
+
+    fn container(id: i64, layout: &str, nodes: Vec<serde_json::Value>) -> serde_json::Value {
+        node(id, "con", layout, nodes, vec![])
+    }
+
+    /// A tree with two outputs, whose first one has the focus, with a workspace each
+    fn tree(workspace: serde_json::Value) -> Node {
+        let other = node(
+            90,
+            "workspace",
+            "splith",
+            vec![window(91), window(92)],
+            vec![],
+        );
+        let tree = node(
+            1,
+            "root",
+            "splith",
+            vec![
+                node(2, "output", "output", vec![workspace], vec![]),
+                node(3, "output", "output", vec![other], vec![]),
+            ],
+            vec![],
+        );
+        serde_json::from_value(tree).unwrap()
+    }
+
+    #[test]
+    fn tiled_and_floating() {
+        // Two windows side by side, next to three tabs and a stack of two, plus a floating
+        // window and a floating container with two windows
+        let workspace = node(
+            10,
+            "workspace",
+            "splith",
+            vec![
+                window(11),
+                container(
+                    12,
+                    "splitv",
+                    vec![
+                        window(13),
+                        container(14, "tabbed", vec![window(15), window(16), window(17)]),
+                    ],
+                ),
+                container(18, "stacked", vec![window(19), window(20)]),
+            ],
+            vec![
+                node(21, "floating_con", "none", vec![], vec![]),
+                node(
+                    22,
+                    "floating_con",
+                    "splith",
+                    vec![window(23), window(24)],
+                    vec![],
+                ),
+            ],
+        );
+        assert_eq!(
+            count_windows(&tree(workspace)),
+            WindowCounts {
+                visible: 4,
+                hidden: 3,
+                floating: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn nested_tabs() {
+        // The tabs of a hidden tab are hidden too
+        let workspace = node(
+            10,
+            "workspace",
+            "tabbed",
+            vec![
+                window(11),
+                container(12, "tabbed", vec![window(13), window(14)]),
+            ],
+            vec![],
+        );
+        assert_eq!(
+            count_windows(&tree(workspace)),
+            WindowCounts {
+                visible: 1,
+                hidden: 2,
+                floating: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn fullscreen() {
+        let mut fullscreen = window(12);
+        fullscreen["fullscreen_mode"] = json!(1);
+        let workspace = node(
+            10,
+            "workspace",
+            "splith",
+            vec![window(11), fullscreen, window(13)],
+            vec![],
+        );
+        assert_eq!(
+            count_windows(&tree(workspace)),
+            WindowCounts {
+                visible: 1,
+                hidden: 2,
+                floating: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn empty_workspace() {
+        let workspace = node(10, "workspace", "splith", vec![], vec![]);
+        assert_eq!(count_windows(&tree(workspace)), WindowCounts::default());
+    }
+}
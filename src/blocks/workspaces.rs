@@ -0,0 +1,482 @@
+use std::env;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::protocol::i3bar_event::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::{escape_pango_text, hyprland_socket, HYPRLAND_EVENTS, HYPRLAND_REQUESTS};
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct Workspace {
+    id: i64,
+    name: String,
+}
+
+/// The workspaces in the order they are shown, and the focused one
+#[derive(Debug, Clone, Default, PartialEq)]
+struct WorkspaceList {
+    workspaces: Vec<Workspace>,
+    focused: Option<i64>,
+}
+
+/// A change of the workspaces, from the events of the compositor
+#[derive(Debug, PartialEq)]
+enum WorkspaceEvent {
+    Focus(i64),
+    /// Focus a workspace by name, for events which don't carry the id
+    FocusName(String),
+    Create(Workspace),
+    Destroy(i64),
+    Rename(Workspace),
+    /// The whole list is sent again
+    Replace(WorkspaceList),
+}
+
+impl WorkspaceList {
+    fn apply(&mut self, event: WorkspaceEvent) {
+        match event {
+            WorkspaceEvent::Focus(id) => self.focused = Some(id),
+            WorkspaceEvent::FocusName(name) => {
+                if let Some(workspace) = self.workspaces.iter().find(|w| w.name == name) {
+                    self.focused = Some(workspace.id);
+                }
+            }
+            WorkspaceEvent::Create(workspace) => {
+                if !self.workspaces.iter().any(|w| w.id == workspace.id) {
+                    self.workspaces.push(workspace);
+                    self.workspaces.sort_by_key(|w| w.id);
+                }
+            }
+            WorkspaceEvent::Destroy(id) => self.workspaces.retain(|w| w.id != id),
+            WorkspaceEvent::Rename(workspace) => {
+                if let Some(renamed) = self.workspaces.iter_mut().find(|w| w.id == workspace.id) {
+                    renamed.name = workspace.name;
+                }
+            }
+            WorkspaceEvent::Replace(list) => *self = list,
+        }
+    }
+}
+
+/// Parse `<id>,<name>`. Names may contain commas.
+fn hyprland_workspace(data: &str) -> Option<Workspace> {
+    let (id, name) = data.split_once(',')?;
+    Some(Workspace {
+        id: id.parse().ok()?,
+        name: name.to_string(),
+    })
+}
+
+/// Parse a line of Hyprland's event socket. Returns `None` for other events, and for special
+/// workspaces (scratchpads), whose ids are negative.
+fn hyprland_event(line: &str) -> Option<WorkspaceEvent> {
+    let (event, data) = line.split_once(">>")?;
+    let event = match event {
+        "workspacev2" => WorkspaceEvent::Focus(hyprland_workspace(data)?.id),
+        "focusedmon" => WorkspaceEvent::FocusName(data.split_once(',')?.1.to_string()),
+        "focusedmonv2" => WorkspaceEvent::Focus(data.split_once(',')?.1.parse().ok()?),
+        "createworkspacev2" => WorkspaceEvent::Create(hyprland_workspace(data)?),
+        "destroyworkspacev2" => WorkspaceEvent::Destroy(hyprland_workspace(data)?.id),
+        "renameworkspace" => WorkspaceEvent::Rename(hyprland_workspace(data)?),
+        _ => return None,
+    };
+    match &event {
+        WorkspaceEvent::Focus(id) | WorkspaceEvent::Destroy(id) if *id < 0 => None,
+        WorkspaceEvent::Create(w) | WorkspaceEvent::Rename(w) if w.id < 0 => None,
+        _ => Some(event),
+    }
+}
+
+/// Send a request to Hyprland, e.g. `j/workspaces`, and read the whole reply
+fn hyprland_request(request: &str) -> Result<String> {
+    let mut socket = UnixStream::connect(hyprland_socket("workspaces", HYPRLAND_REQUESTS)?)
+        .block_error("workspaces", "failed to connect to Hyprland's socket")?;
+    socket
+        .write_all(request.as_bytes())
+        .block_error("workspaces", "failed to send a request to Hyprland")?;
+    let mut reply = String::new();
+    socket
+        .read_to_string(&mut reply)
+        .block_error("workspaces", "failed to read the reply of Hyprland")?;
+    Ok(reply)
+}
+
+/// Parse the replies to `j/workspaces` and `j/activeworkspace`
+fn hyprland_workspaces(workspaces: &str, active: &str) -> Result<WorkspaceList> {
+    let mut workspaces: Vec<Workspace> = serde_json::from_str(workspaces)
+        .block_error("workspaces", "failed to parse the workspaces of Hyprland")?;
+    let active: Workspace = serde_json::from_str(active).block_error(
+        "workspaces",
+        "failed to parse the active workspace of Hyprland",
+    )?;
+    workspaces.retain(|w| w.id >= 0);
+    workspaces.sort_by_key(|w| w.id);
+    Ok(WorkspaceList {
+        workspaces,
+        focused: Some(active.id),
+    })
+}
+
+#[derive(Deserialize, Debug)]
+struct NiriWorkspace {
+    id: i64,
+    idx: i64,
+    name: Option<String>,
+    output: Option<String>,
+    is_focused: bool,
+}
+
+#[derive(Deserialize, Debug)]
+enum NiriEvent {
+    WorkspacesChanged { workspaces: Vec<NiriWorkspace> },
+    WorkspaceActivated { id: i64, focused: bool },
+}
+
+/// Parse a line of niri's event stream. Returns `None` for other events.
+fn niri_event(line: &str) -> Option<WorkspaceEvent> {
+    match serde_json::from_str(line).ok()? {
+        NiriEvent::WorkspacesChanged { mut workspaces } => {
+            // Unnamed workspaces are shown by their index on their output
+            workspaces.sort_by(|a, b| (&a.output, a.idx).cmp(&(&b.output, b.idx)));
+            Some(WorkspaceEvent::Replace(WorkspaceList {
+                focused: workspaces.iter().find(|w| w.is_focused).map(|w| w.id),
+                workspaces: workspaces
+                    .into_iter()
+                    .map(|NiriWorkspace { id, idx, name, .. }| Workspace {
+                        id,
+                        name: name.unwrap_or_else(|| idx.to_string()),
+                    })
+                    .collect(),
+            }))
+        }
+        NiriEvent::WorkspaceActivated { id, focused: true } => Some(WorkspaceEvent::Focus(id)),
+        NiriEvent::WorkspaceActivated { .. } => None,
+    }
+}
+
+/// Connect to niri and send a request, e.g. `"EventStream"`. Returns the socket to read the reply
+/// from.
+fn niri_request(request: &str) -> Result<BufReader<UnixStream>> {
+    let path = env::var("NIRI_SOCKET").block_error("workspaces", "NIRI_SOCKET is not set")?;
+    let mut socket =
+        UnixStream::connect(path).block_error("workspaces", "failed to connect to niri")?;
+    writeln!(socket, "{}", request)
+        .block_error("workspaces", "failed to send a request to niri")?;
+    let mut reader = BufReader::new(socket);
+    let mut reply = String::new();
+    reader
+        .read_line(&mut reply)
+        .block_error("workspaces", "failed to read the reply of niri")?;
+    if !reply.starts_with(r#"{"Ok""#) {
+        return Err(BlockError(
+            "workspaces".to_string(),
+            format!("niri refused the request: {}", reply.trim()),
+        ));
+    }
+    Ok(reader)
+}
+
+/// Parses a line of the events of a compositor
+type ParseEvent = fn(&str) -> Option<WorkspaceEvent>;
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkspacesDriver {
+    Hyprland,
+    Niri,
+}
+
+impl WorkspacesDriver {
+    /// The workspaces before any event, if the events don't start with them
+    fn workspaces(self) -> Result<WorkspaceList> {
+        match self {
+            WorkspacesDriver::Hyprland => hyprland_workspaces(
+                &hyprland_request("j/workspaces")?,
+                &hyprland_request("j/activeworkspace")?,
+            ),
+            WorkspacesDriver::Niri => Ok(WorkspaceList::default()),
+        }
+    }
+
+    /// The lines of the events, and how to parse them
+    fn events(self) -> Result<(BufReader<UnixStream>, ParseEvent)> {
+        match self {
+            WorkspacesDriver::Hyprland => {
+                let socket = UnixStream::connect(hyprland_socket("workspaces", HYPRLAND_EVENTS)?)
+                    .block_error(
+                    "workspaces",
+                    "failed to connect to Hyprland's event socket",
+                )?;
+                Ok((BufReader::new(socket), hyprland_event))
+            }
+            WorkspacesDriver::Niri => Ok((niri_request(r#""EventStream""#)?, niri_event)),
+        }
+    }
+
+    fn focus(self, id: i64) -> Result<()> {
+        match self {
+            WorkspacesDriver::Hyprland => {
+                hyprland_request(&format!("dispatch workspace {}", id))?;
+            }
+            WorkspacesDriver::Niri => {
+                niri_request(&format!(
+                    r#"{{"Action":{{"FocusWorkspace":{{"reference":{{"Id":{}}}}}}}}}"#,
+                    id
+                ))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct Workspaces {
+    id: usize,
+    driver: WorkspacesDriver,
+    workspaces: Arc<Mutex<WorkspaceList>>,
+    format: FormatTemplate,
+    shared_config: SharedConfig,
+    buttons: Vec<TextWidget>,
+    /// The ids of the workspaces shown by `buttons`
+    button_ids: Vec<i64>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct WorkspacesConfig {
+    /// The compositor to get the workspaces from
+    pub driver: WorkspacesDriver,
+
+    /// The format of each workspace
+    pub format: FormatTemplate,
+}
+
+impl Default for WorkspacesConfig {
+    fn default() -> Self {
+        Self {
+            driver: WorkspacesDriver::Hyprland,
+            format: FormatTemplate::default(),
+        }
+    }
+}
+
+impl ConfigBlock for Workspaces {
+    type Config = WorkspacesConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        send: Sender<Task>,
+    ) -> Result<Self> {
+        let driver = block_config.driver;
+        let workspaces = Arc::new(Mutex::new(driver.workspaces()?));
+        let (events, parse) = driver.events()?;
+        {
+            let workspaces = workspaces.clone();
+            thread::Builder::new()
+                .name("workspaces".into())
+                .spawn(move || {
+                    for line in events.lines().scan((), |_, x| x.ok()) {
+                        if let Some(event) = parse(&line) {
+                            workspaces
+                                .lock()
+                                .expect("lock has been poisoned in `workspaces` block")
+                                .apply(event);
+                            send.send(Task {
+                                id,
+                                update_time: Instant::now(),
+                            })
+                            .expect("could not communicate with channel in `workspaces` block");
+                        }
+                    }
+                })
+                .expect("failed to start watching thread for `workspaces` block");
+        }
+
+        Ok(Workspaces {
+            id,
+            driver,
+            workspaces,
+            format: block_config.format.with_default("{name}")?,
+            shared_config,
+            buttons: Vec::new(),
+            button_ids: Vec::new(),
+        })
+    }
+}
+
+impl Block for Workspaces {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        let workspaces = self
+            .workspaces
+            .lock()
+            .block_error("workspaces", "failed to acquire lock")?
+            .clone();
+        self.buttons.clear();
+        self.button_ids.clear();
+        for (instance, workspace) in workspaces.workspaces.into_iter().enumerate() {
+            let values = map!(
+                "id" => Value::from_integer(workspace.id),
+                "name" => Value::from_string(escape_pango_text(workspace.name)),
+            );
+            let mut button = TextWidget::new(self.id, instance, self.shared_config.clone());
+            button.set_texts(self.format.render(&values)?);
+            button.set_state(if workspaces.focused == Some(workspace.id) {
+                State::Info
+            } else {
+                State::Idle
+            });
+            self.buttons.push(button);
+            self.button_ids.push(workspace.id);
+        }
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        self.buttons
+            .iter()
+            .map(|button| button as &dyn I3BarWidget)
+            .collect()
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        if let MouseButton::Left = e.button {
+            if let Some(&id) = e.instance.and_then(|i| self.button_ids.get(i)) {
+                self.driver.focus(id)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace(id: i64, name: &str) -> Workspace {
+        Workspace {
+            id,
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn hyprland_events() {
+        assert_eq!(
+            hyprland_event("workspacev2>>2,2"),
+            Some(WorkspaceEvent::Focus(2))
+        );
+        assert_eq!(
+            hyprland_event("createworkspacev2>>4,web, mail"),
+            Some(WorkspaceEvent::Create(workspace(4, "web, mail")))
+        );
+        assert_eq!(
+            hyprland_event("destroyworkspacev2>>4,web"),
+            Some(WorkspaceEvent::Destroy(4))
+        );
+        assert_eq!(
+            hyprland_event("renameworkspace>>3,chat"),
+            Some(WorkspaceEvent::Rename(workspace(3, "chat")))
+        );
+        assert_eq!(
+            hyprland_event("focusedmon>>DP-1,web"),
+            Some(WorkspaceEvent::FocusName("web".to_string()))
+        );
+        assert_eq!(
+            hyprland_event("focusedmonv2>>DP-1,5"),
+            Some(WorkspaceEvent::Focus(5))
+        );
+        // Special workspaces, the v1 events sent alongside the v2 ones, and other events
+        assert_eq!(
+            hyprland_event("createworkspacev2>>-98,special:scratch"),
+            None
+        );
+        assert_eq!(hyprland_event("workspace>>2"), None);
+        assert_eq!(hyprland_event("activewindow>>kitty,~"), None);
+        assert_eq!(hyprland_event("workspacev2>>x,2"), None);
+    }
+
+    #[test]
+    fn hyprland_state() {
+        let workspaces = r#"[
+            {"id": 3, "name": "mail", "monitor": "DP-1", "monitorID": 1, "windows": 1,
+             "hasfullscreen": false, "lastwindow": "0x1", "lastwindowtitle": "Inbox"},
+            {"id": 1, "name": "1", "monitor": "eDP-1", "monitorID": 0, "windows": 2,
+             "hasfullscreen": false, "lastwindow": "0x2", "lastwindowtitle": "vim"},
+            {"id": -98, "name": "special:scratch", "monitor": "eDP-1", "monitorID": 0,
+             "windows": 1, "hasfullscreen": false, "lastwindow": "0x3", "lastwindowtitle": ""}
+        ]"#;
+        let active = r#"{"id": 1, "name": "1", "monitor": "eDP-1", "monitorID": 0,
+            "windows": 2, "hasfullscreen": false, "lastwindow": "0x2", "lastwindowtitle": "vim"}"#;
+        let mut list = hyprland_workspaces(workspaces, active).unwrap();
+        assert_eq!(
+            list,
+            WorkspaceList {
+                workspaces: vec![workspace(1, "1"), workspace(3, "mail")],
+                focused: Some(1),
+            }
+        );
+
+        for line in &[
+            "createworkspacev2>>2,web",
+            "workspacev2>>2,web",
+            "renameworkspace>>3,chat",
+            "destroyworkspacev2>>1,1",
+            "focusedmon>>DP-1,chat",
+        ] {
+            list.apply(hyprland_event(line).unwrap());
+        }
+        assert_eq!(
+            list,
+            WorkspaceList {
+                workspaces: vec![workspace(2, "web"), workspace(3, "chat")],
+                focused: Some(3),
+            }
+        );
+    }
+
+    #[test]
+    fn niri_events() {
+        let changed = r#"{"WorkspacesChanged":{"workspaces":[
+            {"id":5,"idx":2,"name":null,"output":"eDP-1","is_urgent":false,"is_active":false,
+             "is_focused":false,"active_window_id":null},
+            {"id":4,"idx":1,"name":"web","output":"eDP-1","is_urgent":false,"is_active":true,
+             "is_focused":true,"active_window_id":12}
+        ]}}"#;
+        let mut list = WorkspaceList::default();
+        list.apply(niri_event(&changed.replace('\n', "")).unwrap());
+        assert_eq!(
+            list,
+            WorkspaceList {
+                workspaces: vec![workspace(4, "web"), workspace(5, "2")],
+                focused: Some(4),
+            }
+        );
+
+        list.apply(niri_event(r#"{"WorkspaceActivated":{"id":5,"focused":true}}"#).unwrap());
+        assert_eq!(list.focused, Some(5));
+        // Workspaces activated on other outputs don't take the focus
+        assert_eq!(
+            niri_event(r#"{"WorkspaceActivated":{"id":6,"focused":false}}"#),
+            None
+        );
+        assert_eq!(niri_event(r#"{"WindowFocusChanged":{"id":12}}"#), None);
+    }
+}
@@ -0,0 +1,172 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
+use crate::scheduler::Task;
+use crate::util::read_file;
+use crate::widgets::text::TextWidget;
+use crate::widgets::I3BarWidget;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct MmStat {
+    /// Uncompressed size of the stored data in bytes
+    orig_data_size: u64,
+    /// Compressed size of the stored data in bytes
+    compr_data_size: u64,
+    /// Memory used by the device, including overhead, in bytes
+    mem_used_total: u64,
+}
+
+impl MmStat {
+    /// Parse `/sys/block/zram<N>/mm_stat`
+    fn parse(mm_stat: &str) -> Result<Self> {
+        let fields: Vec<u64> = mm_stat
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<std::result::Result<_, _>>()
+            .block_error("zram", "failed to parse mm_stat")?;
+        match fields[..] {
+            [orig_data_size, compr_data_size, mem_used_total, ..] => Ok(Self {
+                orig_data_size,
+                compr_data_size,
+                mem_used_total,
+            }),
+            _ => Err(BlockError(
+                "zram".to_string(),
+                "mm_stat has too few fields".to_string(),
+            )),
+        }
+    }
+
+    /// The compression ratio, or 0 if the device is empty
+    fn ratio(&self) -> f64 {
+        if self.compr_data_size == 0 {
+            0.
+        } else {
+            self.orig_data_size as f64 / self.compr_data_size as f64
+        }
+    }
+}
+
+pub struct Zram {
+    id: usize,
+    text: TextWidget,
+    mm_stat_path: PathBuf,
+    present: bool,
+    format: FormatTemplate,
+    update_interval: Duration,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct ZramConfig {
+    /// The zram device, e.g. `zram0`
+    pub device: String,
+
+    pub format: FormatTemplate,
+
+    /// Update interval in seconds
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub interval: Duration,
+}
+
+impl Default for ZramConfig {
+    fn default() -> Self {
+        Self {
+            device: "zram0".to_string(),
+            format: FormatTemplate::default(),
+            interval: Duration::from_secs(5),
+        }
+    }
+}
+
+impl ConfigBlock for Zram {
+    type Config = ZramConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        Ok(Zram {
+            id,
+            text: TextWidget::new(id, 0, shared_config).with_icon("memory_swap")?,
+            mm_stat_path: PathBuf::from("/sys/block")
+                .join(block_config.device)
+                .join("mm_stat"),
+            present: false,
+            format: block_config
+                .format
+                .with_default("{compressed}/{uncompressed}")?,
+            update_interval: block_config.interval,
+        })
+    }
+}
+
+impl Block for Zram {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        // The device may not exist (yet)
+        self.present = self.mm_stat_path.exists();
+        if self.present {
+            let stat = MmStat::parse(&read_file("zram", &self.mm_stat_path)?)?;
+            let values = map!(
+                "compressed" => Value::from_float(stat.compr_data_size as f64).bytes(),
+                "uncompressed" => Value::from_float(stat.orig_data_size as f64).bytes(),
+                "mem_used" => Value::from_float(stat.mem_used_total as f64).bytes(),
+                "ratio" => Value::from_float(stat.ratio()),
+            );
+            self.text.set_texts(self.format.render(&values)?);
+        }
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        if self.present {
+            vec![&self.text]
+        } else {
+            vec![]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mm_stat() {
+        let stat = MmStat::parse(
+            "  4194304000  1048576000  1073741824        0  1073741824     1234        0      100       20\n",
+        )
+        .unwrap();
+        assert_eq!(
+            stat,
+            MmStat {
+                orig_data_size: 4194304000,
+                compr_data_size: 1048576000,
+                mem_used_total: 1073741824,
+            }
+        );
+        assert_eq!(stat.ratio(), 4.);
+
+        let empty = MmStat::parse("0 0 0 0 0 0 0 0\n").unwrap();
+        assert_eq!(empty.ratio(), 0.);
+
+        assert!(MmStat::parse("").is_err());
+        assert!(MmStat::parse("1 2").is_err());
+        assert!(MmStat::parse("a b c").is_err());
+    }
+}
@@ -1,11 +1,12 @@
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 use serde::de::{Deserialize, Deserializer};
 use serde_derive::Deserialize;
 use toml::value;
 
-use crate::errors;
+use crate::errors::{self, ResultExtInternal};
 use crate::icons::Icons;
 use crate::protocol::i3bar_event::MouseButton;
 use crate::themes::Theme;
@@ -16,6 +17,8 @@ pub struct SharedConfig {
     icons: Rc<Icons>,
     icons_format: String,
     pub scrolling: Scrolling,
+    pub urgent_on_critical: bool,
+    pub bar_width: Option<usize>,
 }
 
 impl SharedConfig {
@@ -25,6 +28,8 @@ impl SharedConfig {
             icons: Rc::new(config.icons.clone()),
             icons_format: config.icons_format.clone(),
             scrolling: config.scrolling,
+            urgent_on_critical: false,
+            bar_width: config.bar_width,
         }
     }
 
@@ -77,6 +82,8 @@ impl Default for SharedConfig {
             icons: Rc::new(Icons::default()),
             icons_format: " {icon} ".to_string(),
             scrolling: Scrolling::default(),
+            urgent_on_critical: false,
+            bar_width: None,
         }
     }
 }
@@ -88,6 +95,8 @@ impl Clone for SharedConfig {
             icons: Rc::clone(&self.icons),
             icons_format: self.icons_format.clone(),
             scrolling: self.scrolling,
+            urgent_on_critical: self.urgent_on_critical,
+            bar_width: self.bar_width,
         }
     }
 }
@@ -111,6 +120,16 @@ pub struct Config {
     #[serde(default)]
     pub scrolling: Scrolling,
 
+    /// The width of the bar in characters. When the blocks don't fit, the blocks with the lowest
+    /// priority are hidden.
+    #[serde(default)]
+    pub bar_width: Option<usize>,
+
+    /// A real time signal (counted from SIGRTMIN) which toggles privacy mode, see the `redact`
+    /// formatter
+    #[serde(default)]
+    pub privacy_signal: Option<i32>,
+
     #[serde(rename = "block", deserialize_with = "deserialize_blocks")]
     pub blocks: Vec<(String, value::Value)>,
 }
@@ -119,6 +138,94 @@ impl Config {
     fn default_icons_format() -> String {
         " {icon} ".to_string()
     }
+
+    /// Read the config file, resolving `include`s
+    pub fn from_file(path: &Path) -> errors::Result<Self> {
+        let table = read_config_table(path, &mut Vec::new())?;
+        let config: Self = value::Value::Table(table)
+            .try_into()
+            .configuration_error("failed to parse TOML from file contents")?;
+        config.check_privacy_signal()?;
+        Ok(config)
+    }
+
+    /// The privacy signal toggles privacy mode instead of being passed to the blocks, so no
+    /// block may listen for it
+    fn check_privacy_signal(&self) -> errors::Result<()> {
+        let privacy_signal = match self.privacy_signal {
+            Some(signal) => signal,
+            None => return Ok(()),
+        };
+        for (name, block) in &self.blocks {
+            let signal = block.get("signal").and_then(value::Value::as_integer);
+            if signal == Some(privacy_signal.into()) {
+                return Err(errors::ConfigurationError(
+                    "signal of a block is the privacy signal".to_string(),
+                    format!(
+                        "block '{}' uses signal {}, which is also the privacy_signal",
+                        name, privacy_signal
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Read a config file as a TOML table and splice in the files listed in its `include` array.
+///
+/// Paths are relative to the including file. The blocks of the included files are placed before
+/// the blocks of the including file; for all other keys the including file takes precedence.
+fn read_config_table(path: &Path, stack: &mut Vec<PathBuf>) -> errors::Result<value::Table> {
+    let canonical = path
+        .canonicalize()
+        .configuration_error(&format!("failed to open config file '{}'", path.display()))?;
+    if let Some(pos) = stack.iter().position(|p| *p == canonical) {
+        let cycle: Vec<String> = stack[pos..]
+            .iter()
+            .chain(std::iter::once(&canonical))
+            .map(|p| p.display().to_string())
+            .collect();
+        return Err(errors::ConfigurationError(
+            "cyclic include in config".to_string(),
+            cycle.join(" -> "),
+        ));
+    }
+
+    let contents = std::fs::read_to_string(&canonical)
+        .configuration_error(&format!("failed to read config file '{}'", path.display()))?;
+    let mut table: value::Table = toml::from_str(&contents)
+        .configuration_error(&format!("failed to parse TOML from '{}'", path.display()))?;
+
+    let includes: Vec<String> = match table.remove("include") {
+        Some(includes) => includes.try_into().configuration_error(&format!(
+            "'include' in '{}' must be an array of paths",
+            path.display()
+        ))?,
+        None => return Ok(table),
+    };
+
+    stack.push(canonical.clone());
+    let dir = canonical.parent().unwrap_or_else(|| Path::new("/"));
+    let mut blocks = Vec::new();
+    for include in includes {
+        let mut included = read_config_table(&dir.join(include), stack)?;
+        if let Some(value::Value::Array(included_blocks)) = included.remove("block") {
+            blocks.extend(included_blocks);
+        }
+        for (key, val) in included {
+            table.entry(key).or_insert(val);
+        }
+    }
+    stack.pop();
+
+    if let Some(value::Value::Array(own_blocks)) = table.remove("block") {
+        blocks.extend(own_blocks);
+    }
+    if !blocks.is_empty() {
+        table.insert("block".to_string(), value::Value::Array(blocks));
+    }
+    Ok(table)
 }
 
 impl Default for Config {
@@ -128,6 +235,8 @@ impl Default for Config {
             theme: Theme::default(),
             icons_format: Config::default_icons_format(),
             scrolling: Scrolling::default(),
+            bar_width: None,
+            privacy_signal: None,
             blocks: Vec::new(),
         }
     }
@@ -181,3 +290,76 @@ where
 
     Ok(blocks)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn include() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        dir.child("config.toml")
+            .write_str(
+                "include = [\"blocks/extra.toml\"]\n\
+                 [[block]]\n\
+                 block = \"time\"\n",
+            )
+            .unwrap();
+        dir.child("blocks/extra.toml")
+            .write_str(
+                "scrolling = \"natural\"\n\
+                 [[block]]\n\
+                 block = \"load\"\n\
+                 [[block]]\n\
+                 block = \"cpu\"\n",
+            )
+            .unwrap();
+
+        let config = Config::from_file(&dir.path().join("config.toml")).unwrap();
+        let names: Vec<&str> = config
+            .blocks
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(names, ["load", "cpu", "time"]);
+        assert!(matches!(config.scrolling, Scrolling::Natural));
+    }
+
+    #[test]
+    fn cyclic_include() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        dir.child("a.toml")
+            .write_str("include = [\"b.toml\"]\n")
+            .unwrap();
+        dir.child("b.toml")
+            .write_str("include = [\"a.toml\"]\n")
+            .unwrap();
+
+        let err = Config::from_file(&dir.path().join("a.toml")).unwrap_err();
+        let message = format!("{:?}", err);
+        assert!(message.contains("cyclic include"));
+        assert!(message.contains("a.toml -> "));
+    }
+
+    #[test]
+    fn privacy_signal_clash() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let config = |signal: i32| {
+            let path = dir.child(format!("config{}.toml", signal));
+            path.write_str(&format!(
+                "privacy_signal = 4\n\
+                 [[block]]\n\
+                 block = \"custom\"\n\
+                 command = \"date\"\n\
+                 signal = {}\n",
+                signal
+            ))
+            .unwrap();
+            Config::from_file(path.path())
+        };
+        assert!(config(5).is_ok());
+        let message = format!("{:?}", config(4).unwrap_err());
+        assert!(message.contains("block 'custom' uses signal 4"));
+    }
+}
@@ -1,29 +1,166 @@
+pub mod expression;
+pub mod formatter;
 pub mod placeholder;
 pub mod prefix;
 pub mod unit;
 pub mod value;
 
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
 use serde::de::{MapAccess, Visitor};
 use serde::{de, Deserialize, Deserializer};
 
+use crate::config::SharedConfig;
 use crate::errors::*;
+use crate::util::pango_text_width;
+use crate::widgets::State;
+use expression::{Condition, Expression};
+use formatter::{Formatter, FORMATTER_TOKEN};
 use placeholder::unexpected_token;
 use placeholder::Placeholder;
+use placeholder::UnitConfig;
 use value::Value;
 
+thread_local! {
+    /// The configuration of the block that is currently being updated
+    static SHARED_CONFIG: RefCell<Option<SharedConfig>> = RefCell::new(None);
+}
+
+/// Run `f` with the block's `shared_config` available to formatters (e.g. to look up icons).
+pub fn with_shared_config<T>(shared_config: &SharedConfig, f: impl FnOnce() -> T) -> T {
+    let previous = SHARED_CONFIG.with(|c| c.replace(Some(shared_config.clone())));
+    let result = f();
+    SHARED_CONFIG.with(|c| c.replace(previous));
+    result
+}
+
+/// Access the configuration of the block that is currently being updated. Falls back to the
+/// default configuration.
+fn shared_config<T>(f: impl FnOnce(&SharedConfig) -> T) -> T {
+    SHARED_CONFIG.with(|c| match &*c.borrow() {
+        Some(shared_config) => f(shared_config),
+        None => f(&SharedConfig::default()),
+    })
+}
+
+/// Whether the `redact` formatter hides values
+static PRIVACY_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Turn privacy mode on or off. Returns the new state.
+pub fn toggle_privacy_mode() -> bool {
+    !PRIVACY_MODE.fetch_xor(true, Ordering::Relaxed)
+}
+
+fn privacy_mode() -> bool {
+    PRIVACY_MODE.load(Ordering::Relaxed)
+}
+
 #[derive(Debug, Clone, PartialEq)]
 enum Token {
     Text(String),
     Var(Placeholder),
+    /// `{^tab(col:N)}` pads the output with spaces up to the display column `N`
+    Tab(usize),
+    /// `{# text #}` renders to nothing. The text is kept as written, escapes included.
+    Comment(String),
+    /// `{^if(load,gt:2)}` renders what follows it up to the matching `{^elif(...)}`, `{^else}`
+    /// or `{^end}` only if the condition holds
+    If(Condition),
+    Elif(Condition),
+    Else,
+    /// `{^end}` may carry formatters, e.g. `{^end.str(max_w:10)}`, which are applied to the
+    /// output of the conditional as a whole
+    End(Vec<Formatter>),
+}
+
+const TAB_TOKEN: &str = "^tab";
+const IF_TOKEN: &str = "^if";
+const ELIF_TOKEN: &str = "^elif";
+const ELSE_TOKEN: &str = "^else";
+const END_TOKEN: &str = "^end";
+const COMMENT_START: &str = "#";
+const COMMENT_END: &str = "#}";
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Text(text) => f.write_str(text),
+            Token::Var(placeholder) => write!(f, "{{{}}}", placeholder),
+            Token::Tab(col) => write!(f, "{{{}(col:{})}}", TAB_TOKEN, col),
+            Token::Comment(text) => write!(f, "{{{}{}{}", COMMENT_START, text, COMMENT_END),
+            Token::If(condition) => write!(f, "{{{}{}}}", IF_TOKEN, condition),
+            Token::Elif(condition) => write!(f, "{{{}{}}}", ELIF_TOKEN, condition),
+            Token::Else => write!(f, "{{{}}}", ELSE_TOKEN),
+            Token::End(formatters) => {
+                write!(f, "{{{}", END_TOKEN)?;
+                for formatter in formatters {
+                    write!(f, "{}{}", FORMATTER_TOKEN, formatter)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+/// Append the location of `rest`, which must be a suffix of `full`, to the message of a parse
+/// error, as a 1-based `line:column`
+fn at_location(error: Error, full: &str, rest: &str) -> Error {
+    match error {
+        InternalError(context, message, cause) => {
+            let offset = rest.as_ptr() as usize - full.as_ptr() as usize;
+            let before = &full[..offset];
+            let line = before.matches('\n').count() + 1;
+            let column = before
+                .rsplit('\n')
+                .next()
+                .unwrap_or_default()
+                .chars()
+                .count()
+                + 1;
+            InternalError(
+                context,
+                format!("{} at {}:{}", message, line, column),
+                cause,
+            )
+        }
+        other => other,
+    }
+}
+
+/// The output of `FormatTemplate::render`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Rendered {
+    pub full: String,
+    pub short: Option<String>,
+    /// The most severe state requested by `state` or `as_state` formatters, if any. It's up to
+    /// the block what to do with it, `TextWidget::set_texts` shows the more severe of it and the
+    /// state of the widget.
+    pub state: Option<State>,
+    /// How long until formatters such as `rot` change the output, so that the block should be
+    /// updated then even if its values stay the same
+    pub tick: Option<Duration>,
+}
+
+impl From<(String, Option<String>)> for Rendered {
+    fn from((full, short): (String, Option<String>)) -> Self {
+        Self {
+            full,
+            short,
+            ..Self::default()
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct FormatTemplate {
     full: Option<Vec<Token>>,
     short: Option<Vec<Token>>,
+    /// Computes the color of the text from the placeholders
+    text_color: Option<Expression>,
 }
 
 impl FormatTemplate {
@@ -40,7 +177,31 @@ impl FormatTemplate {
             Some(short) => Some(Self::tokens_from_string(short)?),
             None => None,
         };
-        Ok(Self { full, short })
+        Ok(Self {
+            full,
+            short,
+            text_color: None,
+        })
+    }
+
+    /// Parse a full format string, reporting all of its errors rather than just the first, e.g.
+    /// for tools checking configurations. The template is `None` if there are errors.
+    pub fn parse_full_recover(i: &str) -> (Option<Self>, Vec<Error>) {
+        let (tokens, errors) = Self::tokens_from_string_recover(i);
+        if !errors.is_empty() {
+            return (None, errors);
+        }
+        let template = Self {
+            full: Some(tokens),
+            short: None,
+            text_color: None,
+        };
+        (Some(template), errors)
+    }
+
+    pub fn with_text_color(mut self, text_color: &str) -> Result<Self> {
+        self.text_color = Some(Expression::parse(text_color)?);
+        Ok(self)
     }
 
     /// Initialize `full` field if it is `None`
@@ -51,69 +212,124 @@ impl FormatTemplate {
         Ok(self)
     }
 
-    /// Whether the format string contains a given placeholder
+    /// Whether the format strings or the text color use a given placeholder
     pub fn contains(&self, var: &str) -> bool {
-        Self::format_contains(&self.full, var) || Self::format_contains(&self.short, var)
+        self.placeholders().contains(&var)
     }
 
-    fn format_contains(format: &Option<Vec<Token>>, var: &str) -> bool {
-        if let Some(tokens) = format {
-            for token in tokens {
-                if let Token::Var(ref placeholder) = token {
-                    if placeholder.name == var {
-                        return true;
-                    }
+    /// The names of all placeholders used by the format strings and the text color, including
+    /// the ones referenced by the arguments of formatters. Blocks may skip computing values
+    /// which are not used. Each name appears once, in order of first use.
+    pub fn placeholders(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        for token in self.full.iter().chain(&self.short).flatten() {
+            match token {
+                Token::Var(placeholder) => {
+                    names.push(placeholder.name.as_str());
+                    names.extend(placeholder.formatters.iter().flat_map(Formatter::vars));
                 }
+                Token::If(condition) | Token::Elif(condition) => names.push(&condition.var),
+                _ => (),
             }
         }
-        false
+        if let Some(text_color) = &self.text_color {
+            names.extend(text_color.vars());
+        }
+        let mut seen = Vec::new();
+        names.retain(|name| {
+            let first = !seen.contains(name);
+            seen.push(*name);
+            first
+        });
+        names
+    }
+
+    fn tokens_from_string(full: &str) -> Result<Vec<Token>> {
+        let (tokens, errors) = Self::tokens_from_string_recover(full);
+        match errors.into_iter().next() {
+            None => Ok(tokens),
+            Some(error) => Err(error),
+        }
     }
 
-    fn tokens_from_string(mut s: &str) -> Result<Vec<Token>> {
+    /// Like `tokens_from_string`, but keeps parsing after an error. A placeholder with an error
+    /// is skipped up to its closing `}`, and a stray `}` on its own. Errors which leave nothing
+    /// to recover from, such as a missing `}`, end the parsing.
+    fn tokens_from_string_recover(full: &str) -> (Vec<Token>, Vec<Error>) {
         let mut tokens = vec![];
+        let mut errors = vec![];
+        let mut s = full;
+        // For each `{^if(...)}` which is not closed yet, whether it has an `{^else}`
+        let mut open_ifs: Vec<bool> = vec![];
 
         // Push text into tokens vector. Check the text for correctness and don't push empty strings
-        let push_text = |tokens: &mut Vec<Token>, x: &str| {
-            if x.contains('{') {
-                unexpected_token('{')
-            } else if x.contains('}') {
-                unexpected_token('}')
-            } else if !x.is_empty() {
+        let push_text = |tokens: &mut Vec<Token>, errors: &mut Vec<Error>, x: &str| {
+            for (i, c) in x.match_indices(&['{', '}'][..]) {
+                if let Err(e) = unexpected_token::<()>(c.chars().next().unwrap_or_default()) {
+                    errors.push(at_location(e, full, &x[i..]));
+                }
+            }
+            if !x.is_empty() {
                 tokens.push(Token::Text(x.to_string()));
-                Ok(())
-            } else {
-                Ok(())
             }
         };
+        let missing = |token: &str, opening: &str| {
+            at_location(
+                InternalError(
+                    "format parser".to_string(),
+                    format!("missing '{}'", token),
+                    None,
+                ),
+                full,
+                opening,
+            )
+        };
 
         while !s.is_empty() {
             // Split `"text {key:1} {key}"` into `"text "` and `"key:1} {key}"`
             match s.split_once('{') {
                 // No placeholders found -> the whole string is just text
                 None => {
-                    push_text(&mut tokens, s)?;
+                    push_text(&mut tokens, &mut errors, s);
                     break;
                 }
                 // Found placeholder
                 Some((before, after)) => {
                     // `before` is just a text
-                    push_text(&mut tokens, before)?;
+                    push_text(&mut tokens, &mut errors, before);
+                    // Errors in the placeholder are reported at its opening `{`
+                    let opening = &s[before.len()..];
+                    if let Some(comment) = after.strip_prefix(COMMENT_START) {
+                        match Self::split_comment(comment) {
+                            Some((text, rest)) => {
+                                tokens.push(Token::Comment(text.to_string()));
+                                s = rest;
+                                continue;
+                            }
+                            None => {
+                                errors.push(missing(COMMENT_END, opening));
+                                break;
+                            }
+                        }
+                    }
                     // Split `"key:1} {key}"` into `"key:1"` and `" {key}"`
-                    match after.split_once('}') {
+                    match Self::split_placeholder(after) {
                         // No matching `}`!
                         None => {
-                            return Err(InternalError(
-                                "format parser".to_string(),
-                                "missing '}'".to_string(),
-                                None,
-                            ));
+                            errors.push(missing("}", opening));
+                            break;
                         }
                         // Found the entire placeholder
                         Some((placeholder, rest)) => {
                             // `placeholder.parse()` parses the placeholder's configuration string
                             // (e.g. something like `"key:1;K"`) into `Placeholder` struct. We don't
                             // need to think about that in this code.
-                            tokens.push(Token::Var(placeholder.parse()?));
+                            let token = Self::parse_token(placeholder)
+                                .and_then(|token| Self::check_nesting(token, &mut open_ifs));
+                            match token {
+                                Ok(token) => tokens.push(token),
+                                Err(e) => errors.push(at_location(e, full, opening)),
+                            }
                             s = rest;
                         }
                     }
@@ -121,51 +337,321 @@ impl FormatTemplate {
             }
         }
 
-        Ok(tokens)
+        if !open_ifs.is_empty() {
+            errors.push(missing(&format!("{{{}}}", END_TOKEN), &full[full.len()..]));
+        }
+
+        (tokens, errors)
     }
 
-    pub fn render(&self, vars: &HashMap<&str, Value>) -> Result<(String, Option<String>)> {
+    /// Parse what is between the braces of a placeholder, a tab or a conditional
+    fn parse_token(s: &str) -> Result<Token> {
+        if let Some(args) = s.strip_prefix(TAB_TOKEN) {
+            Self::parse_tab(args).map(Token::Tab)
+        } else if let Some(args) = s.strip_prefix(IF_TOKEN) {
+            Condition::parse(args).map(Token::If)
+        } else if let Some(args) = s.strip_prefix(ELIF_TOKEN) {
+            Condition::parse(args).map(Token::Elif)
+        } else if s.trim() == ELSE_TOKEN {
+            Ok(Token::Else)
+        } else if let Some(mut rest) = s.trim().strip_prefix(END_TOKEN) {
+            let mut formatters = Vec::new();
+            while let Some(next) = rest.strip_prefix(FORMATTER_TOKEN) {
+                let (formatter, next) = Formatter::parse(next)?;
+                formatters.push(formatter);
+                rest = next;
+            }
+            match rest.chars().next() {
+                Some(c) => unexpected_token(c),
+                None => Ok(Token::End(formatters)),
+            }
+        } else {
+            s.parse().map(Token::Var)
+        }
+    }
+
+    /// Check that `{^elif(...)}`, `{^else}` and `{^end}` belong to an `{^if(...)}`, with the
+    /// `{^else}` last
+    fn check_nesting(token: Token, open_ifs: &mut Vec<bool>) -> Result<Token> {
+        let valid = match token {
+            Token::If(_) => {
+                open_ifs.push(false);
+                true
+            }
+            Token::Elif(_) => matches!(open_ifs.last(), Some(false)),
+            Token::Else => match open_ifs.last_mut() {
+                Some(has_else) if !*has_else => {
+                    *has_else = true;
+                    true
+                }
+                _ => false,
+            },
+            Token::End(_) => open_ifs.pop().is_some(),
+            _ => true,
+        };
+        if valid {
+            return Ok(token);
+        }
+        let name = match token {
+            Token::Elif(_) => ELIF_TOKEN,
+            Token::Else => ELSE_TOKEN,
+            _ => END_TOKEN,
+        };
+        Err(InternalError(
+            "format parser".to_string(),
+            format!(
+                "unexpected '{{{}}}' without a matching '{{{}}}'",
+                name, IF_TOKEN
+            ),
+            None,
+        ))
+    }
+
+    /// Parse the arguments of a tab token, e.g. `(col:20)` or `(20)`
+    fn parse_tab(args: &str) -> Result<usize> {
+        let col = args
+            .trim()
+            .strip_prefix('(')
+            .and_then(|args| args.strip_suffix(')'))
+            .map(|args| args.trim())
+            .map(|args| args.strip_prefix("col:").unwrap_or(args).trim());
+        match col.map(str::parse) {
+            Some(Ok(col)) => Ok(col),
+            _ => Err(InternalError(
+                "format parser".to_string(),
+                format!(
+                    "invalid tab: '{}{}', expected e.g. '{}(col:20)'",
+                    TAB_TOKEN, args, TAB_TOKEN
+                ),
+                None,
+            )),
+        }
+    }
+
+    /// Like `s.split_once('}')`, but ignores braces inside of quoted formatter arguments
+    fn split_placeholder(s: &str) -> Option<(&str, &str)> {
+        let mut quote = None;
+        let mut chars = s.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match (c, quote) {
+                ('\'', None) | ('"', None) => quote = Some(c),
+                // Skip escaped quotes
+                ('\\', Some(_)) => {
+                    chars.next();
+                }
+                (c, Some(q)) if c == q => quote = None,
+                ('}', None) => return Some((&s[..i], &s[i + 1..])),
+                _ => (),
+            }
+        }
+        None
+    }
+
+    /// Split a comment after its opening `{#` at the first `#}` which is not escaped with a
+    /// backslash, e.g. in `{# a \#} b #}`
+    fn split_comment(s: &str) -> Option<(&str, &str)> {
+        let mut chars = s.char_indices();
+        while let Some((i, c)) = chars.next() {
+            if c == '\\' {
+                chars.next();
+            } else if s[i..].starts_with(COMMENT_END) {
+                return Some((&s[..i], &s[i + COMMENT_END.len()..]));
+            }
+        }
+        None
+    }
+
+    pub fn render(&self, vars: &HashMap<&str, Value>) -> Result<Rendered> {
+        let mut state = None;
+        let mut tick = None;
         let full = match &self.full {
-            Some(tokens) => Self::render_tokens(tokens, vars)?,
+            Some(tokens) => Self::render_tokens(tokens, vars, &mut state, &mut tick)?,
             None => String::new(), // TODO: throw an error that says that it's a bug?
         };
         let short = match &self.short {
-            Some(short) => Some(Self::render_tokens(short, vars)?),
+            Some(short) => Some(Self::render_tokens(short, vars, &mut state, &mut tick)?),
             None => None,
         };
-        Ok((full, short))
+        let text_color = match &self.text_color {
+            Some(text_color) => text_color.eval(vars)?,
+            None => None,
+        };
+        match text_color {
+            Some(color) => {
+                if color.contains(&['\'', '<', '>', '&'][..]) {
+                    return Err(InternalError(
+                        "format".to_string(),
+                        format!("invalid text color: '{}'", color),
+                        None,
+                    ));
+                }
+                let colored = |text| format!("<span foreground='{}'>{}</span>", color, text);
+                Ok(Rendered {
+                    full: colored(full),
+                    short: short.map(colored),
+                    state,
+                    tick,
+                })
+            }
+            None => Ok(Rendered {
+                full,
+                short,
+                state,
+                tick,
+            }),
+        }
     }
 
-    fn render_tokens(tokens: &[Token], vars: &HashMap<&str, Value>) -> Result<String> {
+    /// Render the tokens, raising `state` to the most severe state requested by formatters and
+    /// lowering `tick` to the earliest time a formatter changes its output
+    fn render_tokens(
+        tokens: &[Token],
+        vars: &HashMap<&str, Value>,
+        state: &mut Option<State>,
+        tick: &mut Option<Duration>,
+    ) -> Result<String> {
         let mut rendered = String::new();
+        // Set after an empty placeholder whose surrounding whitespace collapses
+        let mut collapse = false;
+        // For each enclosing `{^if(...)}`, whether one of its branches was taken already, whether
+        // the current one is and where its output starts
+        let mut branches: Vec<(bool, bool, usize)> = Vec::new();
         for token in tokens {
+            let parent_active = branches.iter().all(|&(_, active, _)| active);
             match token {
-                Token::Text(text) => rendered.push_str(&text),
-                Token::Var(var) => rendered.push_str(
-                    &vars
-                        .get(&*var.name)
-                        .internal_error(
-                            "util",
-                            &format!("Unknown placeholder in format string: '{}'", var.name),
-                        )?
-                        .format(&var)?,
-                ),
+                Token::If(condition) => {
+                    // Conditions inside of branches not taken are not evaluated
+                    let holds = parent_active && condition.eval(vars);
+                    branches.push((holds, holds, rendered.len()));
+                    continue;
+                }
+                Token::Elif(_) | Token::Else => {
+                    let (taken, _, start) = branches.pop().unwrap_or_default();
+                    let parent_active = branches.iter().all(|&(_, active, _)| active);
+                    let holds = !taken
+                        && parent_active
+                        && match token {
+                            Token::Elif(condition) => condition.eval(vars),
+                            _ => true,
+                        };
+                    branches.push((taken || holds, holds, start));
+                    continue;
+                }
+                Token::End(formatters) => {
+                    let (_, _, start) = branches.pop().unwrap_or_default();
+                    let parent_active = branches.iter().all(|&(_, active, _)| active);
+                    if parent_active && !formatters.is_empty() {
+                        let mut value = Value::from_string(rendered.split_off(start));
+                        for formatter in formatters {
+                            if let Some(requested) = formatter.requested_state(&value, vars)? {
+                                *state =
+                                    Some(state.map_or(requested, |state| state.max(requested)));
+                            }
+                            let applied = formatter.apply(&value, vars)?;
+                            if let Some(next) = formatter.next_tick(&value, vars, Instant::now())? {
+                                *tick = Some(tick.map_or(next, |tick| tick.min(next)));
+                            }
+                            value = applied;
+                        }
+                        rendered.push_str(&value.as_string());
+                    }
+                    continue;
+                }
+                _ if !parent_active => continue,
+                _ => (),
+            }
+            match token {
+                Token::Text(text) => {
+                    if collapse && (rendered.is_empty() || rendered.ends_with(char::is_whitespace))
+                    {
+                        rendered.push_str(text.trim_start());
+                    } else {
+                        rendered.push_str(&text);
+                    }
+                    collapse = false;
+                }
+                Token::Var(var) => {
+                    let value = vars.get(&*var.name).internal_error(
+                        "util",
+                        &format!("Unknown placeholder in format string: '{}'", var.name),
+                    )?;
+                    let mut value = value.clone();
+                    // Set once a number was formatted for a formatter that works on text, which
+                    // then already includes the unit
+                    let mut formatted = false;
+                    for formatter in &var.formatters {
+                        if let Some(requested) = formatter.requested_state(&value, vars)? {
+                            *state = Some(state.map_or(requested, |state| state.max(requested)));
+                        }
+                        if formatter.formats_text() {
+                            if let Some(text) = value.formatted_text(var)? {
+                                value = text;
+                                formatted = true;
+                            }
+                        }
+                        let applied = formatter.apply(&value, vars)?;
+                        if let Some(next) = formatter.next_tick(&value, vars, Instant::now())? {
+                            *tick = Some(tick.map_or(next, |tick| tick.min(next)));
+                        }
+                        value = applied;
+                    }
+                    let text = if formatted {
+                        value.format(&Placeholder {
+                            unit: UnitConfig {
+                                unit: None,
+                                hidden: true,
+                            },
+                            ..var.clone()
+                        })?
+                    } else {
+                        value.format(var)?
+                    };
+                    if text.is_empty() {
+                        collapse |= var.formatters.iter().any(Formatter::collapses_whitespace);
+                    } else {
+                        collapse = false;
+                    }
+                    rendered.push_str(&text);
+                }
+                Token::Tab(col) => {
+                    for _ in pango_text_width(&rendered)..*col {
+                        rendered.push(' ');
+                    }
+                    collapse = false;
+                }
+                Token::Comment(_) | Token::If(_) | Token::Elif(_) | Token::Else | Token::End(_) => {
+                }
             }
         }
+        if collapse {
+            rendered.truncate(rendered.trim_end().len());
+        }
         Ok(rendered)
     }
 }
 
+/// Writes the full format string in a form that parses back to an equal template. The short
+/// format and the text color are separate options, so they are not included.
+impl fmt::Display for FormatTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for token in self.full.iter().flatten() {
+            write!(f, "{}", token)?;
+        }
+        Ok(())
+    }
+}
+
 impl<'de> Deserialize<'de> for FormatTemplate {
     fn deserialize<D>(deserializer: D) -> StdResult<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
         #[derive(Deserialize)]
-        #[serde(field_identifier, rename_all = "lowercase")]
+        #[serde(field_identifier, rename_all = "snake_case")]
         enum Field {
             Full,
             Short,
+            TextColor,
         }
 
         struct FormatTemplateVisitor;
@@ -195,6 +681,7 @@ impl<'de> Deserialize<'de> for FormatTemplate {
             /// [block.format]
             /// full = "{layout}"
             /// short = "{layout^2}"
+            /// text_color = "{layout} != us ? #ff0000"
             /// ```
             fn visit_map<V>(self, mut map: V) -> StdResult<FormatTemplate, V::Error>
             where
@@ -202,6 +689,7 @@ impl<'de> Deserialize<'de> for FormatTemplate {
             {
                 let mut full: Option<String> = None;
                 let mut short: Option<String> = None;
+                let mut text_color: Option<String> = None;
                 while let Some(key) = map.next_key()? {
                     match key {
                         Field::Full => {
@@ -216,11 +704,21 @@ impl<'de> Deserialize<'de> for FormatTemplate {
                             }
                             short = Some(map.next_value()?);
                         }
+                        Field::TextColor => {
+                            if text_color.is_some() {
+                                return Err(de::Error::duplicate_field("text_color"));
+                            }
+                            text_color = Some(map.next_value()?);
+                        }
                     }
                 }
 
-                FormatTemplate::new_opt(full.as_deref(), short.as_deref())
-                    .map_err(de::Error::custom)
+                let template = FormatTemplate::new_opt(full.as_deref(), short.as_deref());
+                match text_color {
+                    Some(text_color) => template.and_then(|t| t.with_text_color(&text_color)),
+                    None => template,
+                }
+                .map_err(de::Error::custom)
             }
         }
 
@@ -248,11 +746,439 @@ mod tests {
         );
 
         assert_eq!(
-            ft.unwrap().render(&values).unwrap().0.as_str(),
+            ft.unwrap().render(&values).unwrap().full.as_str(),
             "some text |var value| var again |var value| 12 \u{258c}  0.0Hz."
         );
     }
 
+    #[test]
+    fn placeholders() {
+        let ft = FormatTemplate::new(
+            "{cpu} {mem.max(value:$limit):3} {cpu.progress(pos:$used,len:$total)} {^tab(col:9)}{mem}",
+            Some("{cpu}{swap}"),
+        )
+        .unwrap()
+        .with_text_color("{load} > 2 ? #ff0000 : {cpu} > {limit} ? #ffff00")
+        .unwrap();
+        assert_eq!(
+            ft.placeholders(),
+            ["cpu", "mem", "limit", "used", "total", "swap", "load"]
+        );
+        assert!(ft.contains("total"));
+        assert!(ft.contains("load"));
+        assert!(!ft.contains("tab"));
+
+        assert!(FormatTemplate::new("just text", None)
+            .unwrap()
+            .placeholders()
+            .is_empty());
+        assert!(FormatTemplate::default().placeholders().is_empty());
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let formats = [
+            "plain text only",
+            "{var} and {var:3^5;K*B}",
+            "{speed:05; _M*_b#1000} {temp*deg} {load;1} {bar#0.5}",
+            "{level.index(list:'low,medium,high',default:'n/a').snake()}{^tab(col:20)}|",
+            "{x.cap(9,suffix:$more)} {x.schedule(h9_17:'at work',default:'')}",
+            "{title.csv(field:1, sep:';', default:' ')} {x.json_get(path:'a.b}')}",
+            r#"{x.cap(9,suffix:"'s}")} {x.cap(9,suffix:'"')}"#,
+            r#"{x.cap(9,suffix:'\'s "quoted" \\ \n')}"#,
+            "line one\n{ws.normalize_ws_icons():0}\nline two",
+            r"{# note: {x} \#} #}{x}",
+            "{^if(load,gt:2)}high{^elif(state,eq:'on battery')}{x}{^else}low{^end}",
+            r#"{^if(name,eq:"it's {x}")}{x}{^elif(name,ne:'\\')}{^end}"#,
+            "{^if(x,gt:1)}{x} items{^else}none{^end.str(max_w:5,ellipsis:'~').snake()}",
+        ];
+        for format in &formats {
+            let template = FormatTemplate::new(format, None).unwrap();
+            let printed = template.to_string();
+            let reparsed = FormatTemplate::new(&printed, None).unwrap();
+            assert_eq!(
+                reparsed.full, template.full,
+                "{:?} was printed as {:?}",
+                format, printed
+            );
+            assert_eq!(reparsed.to_string(), printed);
+        }
+
+        // Keys are written out and literals are quoted only where needed
+        assert_eq!(
+            FormatTemplate::new("{x.index('a,b', default : c):2}", None)
+                .unwrap()
+                .to_string(),
+            "{x.index(list:'a,b', default:c):2}"
+        );
+    }
+
+    #[test]
+    fn parse_full_recover() {
+        let format = "{a} {b.cap(9,suffix:$more)} {^tab(col:4)}";
+        let (template, errors) = FormatTemplate::parse_full_recover(format);
+        assert!(errors.is_empty());
+        assert_eq!(
+            template.unwrap().full,
+            FormatTemplate::new(format, None).unwrap().full
+        );
+
+        let messages = |format: &str| {
+            let (template, errors) = FormatTemplate::parse_full_recover(format);
+            assert!(template.is_none());
+            errors
+                .into_iter()
+                .map(|error| match error {
+                    InternalError(_, message, _) => message,
+                    other => panic!("unexpected {:?}", other),
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            messages("{a.nope()} ok {b} {c:x}\n} {d}"),
+            [
+                "unknown formatter: 'nope' at 1:1",
+                "failed to parse min_width 'x' at 1:19",
+                "unexpected token '}' at 2:1",
+            ]
+        );
+        // The first error is the one `new` reports
+        assert_eq!(
+            messages("{a.cap(max:x,y:1)} {^tab} {b"),
+            [
+                "formatter 'cap' does not accept argument 'y' at 1:1",
+                "invalid tab: '^tab', expected e.g. '^tab(col:20)' at 1:20",
+                "missing '}' at 1:27",
+            ]
+        );
+        match FormatTemplate::new("{a.cap(max:x,y:1)} {^tab} {b", None) {
+            Err(InternalError(_, message, _)) => assert_eq!(
+                message,
+                "formatter 'cap' does not accept argument 'y' at 1:1"
+            ),
+            _ => panic!("expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn comments() {
+        let values = map!(
+            "load" => Value::from_string("0.5".to_string()),
+            "x" => Value::from_string("a".to_string()),
+        );
+        let render = |format: &str| {
+            FormatTemplate::new(format, None)
+                .unwrap()
+                .render(&values)
+                .unwrap()
+                .full
+        };
+        assert_eq!(render("{# the load average #}"), "");
+        assert_eq!(render("{load}{# this shows load #}/{x}"), "0.5/a");
+        // Placeholders, braces and quotes inside of comments are just text
+        assert_eq!(render("{# {missing} } { 'it's' #}{x}"), "a");
+        assert_eq!(render(r"{# ends with \#} instead #}{x}"), "a");
+        // A `#` only starts a comment right after a `{`
+        assert_eq!(render("#1 {x} # {x#10}"), "#1 a # a");
+        let template = FormatTemplate::new("{# {missing} #}{x}", None).unwrap();
+        assert_eq!(template.placeholders(), ["x"]);
+
+        match FormatTemplate::new("{x}\n  {# unterminated #", None) {
+            Err(InternalError(_, message, _)) => assert_eq!(message, "missing '#}' at 2:3"),
+            _ => panic!("expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn unicode_names() {
+        let ft = FormatTemplate::new(
+            "{température}° {niveau_1.index(list:$étiquettes)} {1m}",
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            ft.placeholders(),
+            ["température", "niveau_1", "étiquettes", "1m"]
+        );
+        let values = map!(
+            "température" => Value::from_integer(21),
+            "niveau_1" => Value::from_integer(1),
+            "étiquettes" => Value::from_string("bas,haut".to_string()),
+            "1m" => Value::from_float(0.5),
+        );
+        assert_eq!(ft.render(&values).unwrap().full, "21° haut 0.5");
+
+        let message = |format: &str| match FormatTemplate::new(format, None) {
+            Err(InternalError(_, message, _)) => message,
+            _ => panic!("expected a parse error for {:?}", format),
+        };
+        assert_eq!(
+            message("{temp-c}"),
+            "invalid placeholder name: 'temp-c' at 1:1"
+        );
+        assert_eq!(message("{}"), "invalid placeholder name: '' at 1:1");
+        assert_eq!(message("{x.1up()}"), "invalid formatter name: '1up' at 1:1");
+        assert_eq!(
+            message("{x.index(list:$a b)}"),
+            "invalid placeholder name: 'a b' at 1:1"
+        );
+    }
+
+    #[test]
+    fn error_location() {
+        let message = |format: &str| match FormatTemplate::new(format, None) {
+            Err(InternalError(_, message, _)) => message,
+            _ => panic!("expected a parse error for {:?}", format),
+        };
+        assert_eq!(
+            message("{a} {b.nope()}"),
+            "unknown formatter: 'nope' at 1:5"
+        );
+        assert_eq!(message("ok {a} } {b}"), "unexpected token '}' at 1:8");
+        assert_eq!(message("{a} {b:1"), "missing '}' at 1:5");
+        assert_eq!(
+            message("{a}\nsecond {b.index(lst:x)}\nthird"),
+            "formatter 'index' does not accept argument 'lst' at 2:8"
+        );
+        assert_eq!(
+            message("\n\n  \u{e4}\u{f6} {^tab(col:x)}"),
+            "invalid tab: '^tab(col:x)', expected e.g. '^tab(col:20)' at 3:6"
+        );
+    }
+
+    #[test]
+    fn normalize_ws_icons() {
+        let ft = FormatTemplate::new("{a} {x.normalize_ws_icons()} {b}", None).unwrap();
+        let render = |x: &str| {
+            let values = map!(
+                "a" => Value::from_string("A".to_string()),
+                "b" => Value::from_string("B".to_string()),
+                "x" => Value::from_string(x.to_string()),
+            );
+            ft.render(&values).unwrap().full
+        };
+        assert_eq!(render(""), "A B");
+        assert_eq!(render("X"), "A X B");
+
+        let values = map!(
+            "x" => Value::from_string(String::new()),
+            "y" => Value::from_string(String::new()),
+            "z" => Value::from_string(String::new()),
+        );
+        let render = |format: &str| FormatTemplate::new(format, None).unwrap().render(&values);
+        assert_eq!(
+            render("a {x.normalize_ws_icons()} {y.normalize_ws_icons()} b")
+                .unwrap()
+                .full,
+            "a b"
+        );
+        assert_eq!(render("{x.normalize_ws_icons()} b").unwrap().full, "b");
+        assert_eq!(render("a {x.normalize_ws_icons()}").unwrap().full, "a");
+        // Only whitespace around the placeholder collapses
+        assert_eq!(render("a{x.normalize_ws_icons()}  b").unwrap().full, "a  b");
+        // Other empty placeholders are left alone
+        assert_eq!(render("a {z} b").unwrap().full, "a  b");
+    }
+
+    #[test]
+    fn render_formatter() {
+        let ft = FormatTemplate::new("{level.index(list:'low,{med},high'):5}!", None).unwrap();
+        let values = map!("level" => Value::from_integer(1));
+        assert_eq!(ft.render(&values).unwrap().full.as_str(), "{med}!");
+    }
+
+    #[test]
+    fn render_formatter_chain() {
+        let ft =
+            FormatTemplate::new("{level.min(2).index(list:'low,med,high').snake()}", None).unwrap();
+        let render = |level| {
+            let values = map!("level" => Value::from_integer(level));
+            ft.render(&values).unwrap().full
+        };
+        assert_eq!(render(0), "low");
+        assert_eq!(render(5), "high");
+    }
+
+    #[test]
+    fn render_text_formatter_of_number() {
+        let values = map!(
+            "freq" => Value::from_float(1_200_000.).hertz(),
+            "load" => Value::from_integer(7).percents(),
+        );
+        let render = |format| FormatTemplate::new(format, None).unwrap().render(&values);
+        // The unit, the prefix and the width are kept, and the unit is not repeated
+        assert_eq!(render("{freq}").unwrap().full, "1.2MHz");
+        assert_eq!(
+            render("{freq.localize_number(arabic)}").unwrap().full,
+            "\u{661}.\u{662}MHz"
+        );
+        assert_eq!(render("{load.csv(0):3}").unwrap().full, "  7%");
+        assert_eq!(render("{load.kebab()*%}").unwrap().full, "7%");
+        assert_eq!(render("{load.snake()*_}").unwrap().full, "7");
+    }
+
+    #[test]
+    fn render_tab() {
+        let ft = FormatTemplate::new("{key}:{^tab(col:10)}{value}", None).unwrap();
+        let render = |key: &str| {
+            let values = map!(
+                "key" => Value::from_string(key.to_string()),
+                "value" => Value::from_string("v".to_string()),
+            );
+            ft.render(&values).unwrap().full
+        };
+        assert_eq!(render("cpu"), "cpu:      v");
+        assert_eq!(render("cpu").find('v'), Some(10));
+        assert_eq!(render("<b>ab</b>"), "<b>ab</b>:       v");
+        assert_eq!(render("very long key"), "very long key:v");
+
+        assert!(FormatTemplate::new("{^tab( 3 )}", None).is_ok());
+        assert!(FormatTemplate::new("{^tab(col:x)}", None).is_err());
+        assert!(FormatTemplate::new("{^tab}", None).is_err());
+    }
+
+    #[test]
+    fn render_conditionals() {
+        let ft = FormatTemplate::new(
+            "{^if(load,gt:2.0)}high {load}{^elif(load,ge:1)}busy{^else}normal{^end}!",
+            None,
+        )
+        .unwrap();
+        let render = |load: Value| ft.render(&map!("load" => load)).unwrap().full;
+        assert_eq!(render(Value::from_float(2.5)), "high 2.5!");
+        assert_eq!(render(Value::from_float(2.0)), "busy!");
+        assert_eq!(render(Value::from_integer(1)), "busy!");
+        assert_eq!(render(Value::from_float(0.5)), "normal!");
+        // A missing or empty value takes the else branch
+        assert_eq!(ft.render(&HashMap::new()).unwrap().full, "normal!");
+        assert_eq!(render(Value::from_string(String::new())), "normal!");
+        assert_eq!(ft.placeholders(), ["load"]);
+
+        // Without an else branch nothing is rendered, and branches not taken may refer to
+        // missing placeholders
+        let ft = FormatTemplate::new("a{^if(n,ne:0)} {missing}{^end} b", None).unwrap();
+        assert_eq!(
+            ft.render(&map!("n" => Value::from_integer(0)))
+                .unwrap()
+                .full,
+            "a b"
+        );
+        assert!(ft.render(&map!("n" => Value::from_integer(1))).is_err());
+
+        // Nested conditionals
+        let ft = FormatTemplate::new(
+            "{^if(a,eq:1)}{^if(b,eq:1)}both{^else}a{^end}{^elif(b,eq:1)}b{^else}none{^end}",
+            None,
+        )
+        .unwrap();
+        let render = |a, b| {
+            let values = map!("a" => Value::from_integer(a), "b" => Value::from_integer(b));
+            ft.render(&values).unwrap().full
+        };
+        assert_eq!(render(1, 1), "both");
+        assert_eq!(render(1, 0), "a");
+        assert_eq!(render(0, 1), "b");
+        assert_eq!(render(0, 0), "none");
+
+        // Quoted values may contain quotes and braces
+        let ft = FormatTemplate::new(r#"{^if(name,eq:"it's {x}")}match{^end}"#, None).unwrap();
+        let values = map!("name" => Value::from_string("it's {x}".to_string()));
+        assert_eq!(ft.render(&values).unwrap().full, "match");
+
+        // Formatters on `{^end}` apply to the output of whichever branch was taken
+        let ft = FormatTemplate::new(
+            "[{^if(n,gt:0)}{n} new messages{^else}no mail{^end.str(max_w:5)}]",
+            None,
+        )
+        .unwrap();
+        let render = |n| {
+            ft.render(&map!("n" => Value::from_integer(n)))
+                .unwrap()
+                .full
+        };
+        assert_eq!(render(12), "[12 ne]");
+        assert_eq!(render(0), "[no ma]");
+        let ft = FormatTemplate::new(
+            "{^if(a,eq:1)}{^if(b,eq:1)}both{^end.str(max_w:2)} a{^end.str(max_w:6)}",
+            None,
+        )
+        .unwrap();
+        let render = |a, b| {
+            let values = map!("a" => Value::from_integer(a), "b" => Value::from_integer(b));
+            ft.render(&values).unwrap().full
+        };
+        assert_eq!(render(1, 1), "bo a");
+        assert_eq!(render(1, 0), " a");
+        assert_eq!(render(0, 1), "");
+    }
+
+    #[test]
+    fn parse_conditionals() {
+        let message = |format: &str| match FormatTemplate::new(format, None) {
+            Err(InternalError(_, message, _)) => message,
+            _ => panic!("expected a parse error for {:?}", format),
+        };
+        assert_eq!(message("{^if(x,gt:1)}a"), "missing '{^end}' at 1:15");
+        assert_eq!(
+            message("a{^else}b"),
+            "unexpected '{^else}' without a matching '{^if}' at 1:2"
+        );
+        assert_eq!(
+            message("{^if(x,gt:1)}a{^end}{^end}"),
+            "unexpected '{^end}' without a matching '{^if}' at 1:21"
+        );
+        assert_eq!(
+            message("{^if(x,gt:1)}a{^else}b{^elif(x,lt:0)}c{^end}"),
+            "unexpected '{^elif}' without a matching '{^if}' at 1:23"
+        );
+        assert_eq!(
+            message("{^if(x,gt:1)}a{^else}b{^else}c{^end}"),
+            "unexpected '{^else}' without a matching '{^if}' at 1:23"
+        );
+        assert_eq!(
+            message("{^if(x,over:1)}{^end}"),
+            "unknown comparison: 'over', expected one of lt, le, gt, ge, eq and ne at 1:1"
+        );
+        assert_eq!(
+            message("{^if(x,gt:1)}a{^end:3}"),
+            "unexpected token ':' at 1:15"
+        );
+        assert!(FormatTemplate::new("{^if(x,gt:1)}a{^end.nope()}", None).is_err());
+        assert!(FormatTemplate::new("{^if(x,gt:1)}a{^end.snake().str()}", None).is_ok());
+        assert_eq!(
+            message("{^if(x > 1)}{^end}"),
+            "invalid condition: '(x > 1)', expected e.g. '(load,gt:2)' at 1:1"
+        );
+    }
+
+    #[test]
+    fn render_text_color() {
+        let ft = FormatTemplate::new("{errors} errors", Some("{errors}"))
+            .unwrap()
+            .with_text_color("{errors} > 0 ? #ff0000")
+            .unwrap();
+        let render = |errors| {
+            let rendered = ft
+                .render(&map!("errors" => Value::from_integer(errors)))
+                .unwrap();
+            (rendered.full, rendered.short)
+        };
+        assert_eq!(
+            render(3),
+            (
+                "<span foreground='#ff0000'> 3 errors</span>".to_string(),
+                Some("<span foreground='#ff0000'> 3</span>".to_string())
+            )
+        );
+        assert_eq!(render(0), (" 0 errors".to_string(), Some(" 0".to_string())));
+
+        let ft = FormatTemplate::new("x", None)
+            .unwrap()
+            .with_text_color("'a<b'")
+            .unwrap();
+        assert!(ft.render(&HashMap::new()).is_err());
+    }
+
     #[test]
     fn contains() {
         let format = FormatTemplate::new("some text {foo} {bar:1} foobar", None);
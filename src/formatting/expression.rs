@@ -0,0 +1,633 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::errors::*;
+
+use super::formatter::{check_name, parse_quoted, write_quoted};
+use super::value::Value;
+
+/// A conditional expression over placeholders, e.g.
+/// `{errors} > 0 ? #ff0000 : {warnings} > 0 ? #ffff00`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Value(Operand),
+    If {
+        lhs: Operand,
+        op: Comparison,
+        rhs: Operand,
+        then: Operand,
+        otherwise: Option<Box<Expression>>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Literal(String),
+    Var(String),
+}
+
+/// An arithmetic expression over numbers and placeholders, e.g. `({home.used} + {data.used}) / 2`.
+/// Unlike in other expressions, the names of placeholders may contain dots.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arithmetic {
+    Number(f64),
+    Var(String),
+    Neg(Box<Arithmetic>),
+    Binary(Box<Arithmetic>, Operator, Box<Arithmetic>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+/// The condition of a `{^if(...)}` in a format string, comparing a placeholder to a value, e.g.
+/// `(load,gt:2.0)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub var: String,
+    op: Comparison,
+    rhs: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Operand(Operand),
+    Comparison(Comparison),
+    Then,
+    Else,
+    Operator(Operator),
+    Open,
+    Close,
+}
+
+fn parser_error<T>(message: String) -> Result<T> {
+    Err(InternalError(
+        "expression parser".to_string(),
+        message,
+        None,
+    ))
+}
+
+/// Split an expression into tokens. The operators and parentheses of `arithmetic` expressions are
+/// only tokens if it is set, and are part of literals otherwise.
+fn tokenize(mut s: &str, arithmetic: bool) -> Result<Vec<Token>> {
+    const COMPARISONS: &[(&str, Comparison)] = &[
+        ("<=", Comparison::LessOrEqual),
+        (">=", Comparison::GreaterOrEqual),
+        ("==", Comparison::Equal),
+        ("!=", Comparison::NotEqual),
+        ("<", Comparison::Less),
+        (">", Comparison::Greater),
+    ];
+    const SPECIAL: &[char] = &['<', '>', '=', '!', '?', ':', '{', '\''];
+    const ARITHMETIC: &[(char, Token)] = &[
+        ('+', Token::Operator(Operator::Add)),
+        ('-', Token::Operator(Operator::Sub)),
+        ('*', Token::Operator(Operator::Mul)),
+        ('/', Token::Operator(Operator::Div)),
+        ('(', Token::Open),
+        (')', Token::Close),
+    ];
+    let special = |c| SPECIAL.contains(&c) || arithmetic && ARITHMETIC.iter().any(|(a, _)| *a == c);
+
+    let mut tokens = Vec::new();
+    loop {
+        s = s.trim_start();
+        let c = match s.chars().next() {
+            Some(c) => c,
+            None => break,
+        };
+        if let Some((_, token)) = ARITHMETIC.iter().find(|(a, _)| arithmetic && *a == c) {
+            tokens.push(token.clone());
+            s = &s[1..];
+        } else if let Some(&(op, comparison)) = COMPARISONS.iter().find(|(op, _)| s.starts_with(op))
+        {
+            tokens.push(Token::Comparison(comparison));
+            s = &s[op.len()..];
+        } else if c == '?' {
+            tokens.push(Token::Then);
+            s = &s[1..];
+        } else if c == ':' {
+            tokens.push(Token::Else);
+            s = &s[1..];
+        } else if c == '{' {
+            let (name, rest) = s[1..]
+                .split_once('}')
+                .internal_error("expression parser", "missing '}'")?;
+            let name = name.trim();
+            if arithmetic {
+                for part in name.split('.') {
+                    check_name("placeholder", part)?;
+                }
+            } else {
+                check_name("placeholder", name)?;
+            }
+            tokens.push(Token::Operand(Operand::Var(name.to_string())));
+            s = rest;
+        } else if c == '\'' {
+            let (text, rest) = s[1..]
+                .split_once('\'')
+                .internal_error("expression parser", "unterminated string")?;
+            tokens.push(Token::Operand(Operand::Literal(text.to_string())));
+            s = rest;
+        } else if SPECIAL.contains(&c) {
+            return parser_error(format!("unexpected '{}'", c));
+        } else {
+            let len = s
+                .find(|c: char| c.is_whitespace() || special(c))
+                .unwrap_or(s.len());
+            tokens.push(Token::Operand(Operand::Literal(s[..len].to_string())));
+            s = &s[len..];
+        }
+    }
+    Ok(tokens)
+}
+
+impl Expression {
+    pub fn parse(s: &str) -> Result<Self> {
+        let tokens = tokenize(s, false)?;
+        let (expression, rest) = Self::parse_tokens(&tokens)?;
+        if !rest.is_empty() {
+            return parser_error(format!("unexpected {:?} in '{}'", rest[0], s));
+        }
+        Ok(expression)
+    }
+
+    /// The names of the placeholders the expression refers to
+    pub fn vars(&self) -> Vec<&str> {
+        let mut operands = Vec::new();
+        let mut expression = Some(self);
+        while let Some(e) = expression {
+            expression = match e {
+                Self::Value(value) => {
+                    operands.push(value);
+                    None
+                }
+                Self::If {
+                    lhs,
+                    rhs,
+                    then,
+                    otherwise,
+                    ..
+                } => {
+                    operands.extend(vec![lhs, rhs, then]);
+                    otherwise.as_deref()
+                }
+            };
+        }
+        operands
+            .into_iter()
+            .filter_map(|operand| match operand {
+                Operand::Var(var) => Some(var.as_str()),
+                Operand::Literal(_) => None,
+            })
+            .collect()
+    }
+
+    fn parse_tokens(tokens: &[Token]) -> Result<(Self, &[Token])> {
+        match tokens {
+            [Token::Operand(value), rest @ ..]
+                if !matches!(rest.first(), Some(Token::Comparison(_))) =>
+            {
+                Ok((Self::Value(value.clone()), rest))
+            }
+            [Token::Operand(lhs), Token::Comparison(op), Token::Operand(rhs), Token::Then, Token::Operand(then), rest @ ..] =>
+            {
+                let (otherwise, rest) = match rest {
+                    [Token::Else, rest @ ..] => {
+                        let (otherwise, rest) = Self::parse_tokens(rest)?;
+                        (Some(Box::new(otherwise)), rest)
+                    }
+                    _ => (None, rest),
+                };
+                Ok((
+                    Self::If {
+                        lhs: lhs.clone(),
+                        op: *op,
+                        rhs: rhs.clone(),
+                        then: then.clone(),
+                        otherwise,
+                    },
+                    rest,
+                ))
+            }
+            _ => parser_error(
+                "expected '<value>' or '<value> <comparison> <value> ? <value> [: <expression>]'"
+                    .to_string(),
+            ),
+        }
+    }
+
+    /// Evaluate the expression. Returns `None` if no branch matched.
+    pub fn eval(&self, vars: &HashMap<&str, Value>) -> Result<Option<String>> {
+        match self {
+            Self::Value(value) => value.eval(vars).map(Some),
+            Self::If {
+                lhs,
+                op,
+                rhs,
+                then,
+                otherwise,
+            } => {
+                if op.compare(&lhs.eval(vars)?, &rhs.eval(vars)?) {
+                    then.eval(vars).map(Some)
+                } else {
+                    match otherwise {
+                        Some(otherwise) => otherwise.eval(vars),
+                        None => Ok(None),
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Arithmetic {
+    pub fn parse(s: &str) -> Result<Self> {
+        let tokens = tokenize(s, true)?;
+        let (arithmetic, rest) = Self::parse_sum(&tokens)?;
+        if !rest.is_empty() {
+            return parser_error(format!("unexpected {:?} in '{}'", rest[0], s));
+        }
+        Ok(arithmetic)
+    }
+
+    /// Parse terms separated by `+` and `-`, which are left associative
+    fn parse_sum(tokens: &[Token]) -> Result<(Self, &[Token])> {
+        let (mut arithmetic, mut tokens) = Self::parse_product(tokens)?;
+        while let [Token::Operator(op @ (Operator::Add | Operator::Sub)), rest @ ..] = tokens {
+            let (rhs, rest) = Self::parse_product(rest)?;
+            arithmetic = Self::Binary(Box::new(arithmetic), *op, Box::new(rhs));
+            tokens = rest;
+        }
+        Ok((arithmetic, tokens))
+    }
+
+    /// Parse factors separated by `*` and `/`, which are left associative
+    fn parse_product(tokens: &[Token]) -> Result<(Self, &[Token])> {
+        let (mut arithmetic, mut tokens) = Self::parse_factor(tokens)?;
+        while let [Token::Operator(op @ (Operator::Mul | Operator::Div)), rest @ ..] = tokens {
+            let (rhs, rest) = Self::parse_factor(rest)?;
+            arithmetic = Self::Binary(Box::new(arithmetic), *op, Box::new(rhs));
+            tokens = rest;
+        }
+        Ok((arithmetic, tokens))
+    }
+
+    /// Parse a number, a placeholder, a negation or an expression in parentheses
+    fn parse_factor(tokens: &[Token]) -> Result<(Self, &[Token])> {
+        match tokens {
+            [Token::Operator(Operator::Sub), rest @ ..] => {
+                let (arithmetic, rest) = Self::parse_factor(rest)?;
+                Ok((Self::Neg(Box::new(arithmetic)), rest))
+            }
+            [Token::Open, rest @ ..] => match Self::parse_sum(rest)? {
+                (arithmetic, [Token::Close, rest @ ..]) => Ok((arithmetic, rest)),
+                _ => parser_error("missing ')'".to_string()),
+            },
+            [Token::Operand(Operand::Var(name)), rest @ ..] => Ok((Self::Var(name.clone()), rest)),
+            [Token::Operand(Operand::Literal(number)), rest @ ..] => match number.parse() {
+                Ok(number) => Ok((Self::Number(number), rest)),
+                Err(_) => parser_error(format!("invalid number '{}'", number)),
+            },
+            [token, ..] => parser_error(format!("unexpected {:?}", token)),
+            [] => parser_error("unexpected end of expression".to_string()),
+        }
+    }
+
+    /// The names of the placeholders the expression refers to
+    pub fn vars(&self) -> Vec<&str> {
+        match self {
+            Self::Number(_) => Vec::new(),
+            Self::Var(name) => vec![name.as_str()],
+            Self::Neg(arithmetic) => arithmetic.vars(),
+            Self::Binary(lhs, _, rhs) => {
+                let mut vars = lhs.vars();
+                vars.extend(rhs.vars());
+                vars
+            }
+        }
+    }
+
+    /// The value of the expression, or `None` if a placeholder is missing or not a number
+    pub fn eval(&self, vars: &HashMap<&str, Value>) -> Option<f64> {
+        match self {
+            Self::Number(number) => Some(*number),
+            Self::Var(name) => vars.get(name.as_str()).and_then(Value::as_f64),
+            Self::Neg(arithmetic) => arithmetic.eval(vars).map(|value| -value),
+            Self::Binary(lhs, op, rhs) => Some(op.apply(lhs.eval(vars)?, rhs.eval(vars)?)),
+        }
+    }
+}
+
+impl Operator {
+    fn apply(self, lhs: f64, rhs: f64) -> f64 {
+        match self {
+            Self::Add => lhs + rhs,
+            Self::Sub => lhs - rhs,
+            Self::Mul => lhs * rhs,
+            Self::Div => lhs / rhs,
+        }
+    }
+}
+
+impl Condition {
+    /// Parse the parentheses of e.g. `{^if(load,gt:2.0)}`. The value may be quoted like the
+    /// arguments of formatters, e.g. `(state,eq:'on battery')` or `(name,eq:"it's")`.
+    pub fn parse(s: &str) -> Result<Self> {
+        let invalid = || {
+            parser_error(format!(
+                "invalid condition: '{}', expected e.g. '(load,gt:2)'",
+                s
+            ))
+        };
+        let (var, comparison) = match s
+            .trim()
+            .strip_prefix('(')
+            .and_then(|args| args.strip_suffix(')'))
+            .and_then(|args| args.split_once(','))
+        {
+            Some(args) => args,
+            None => return invalid(),
+        };
+        let var = var.trim();
+        check_name("placeholder", var)?;
+        let (op, rhs) = match comparison.split_once(':') {
+            Some(comparison) => comparison,
+            None => return invalid(),
+        };
+        let op = match Comparison::from_name(op.trim()) {
+            Some(op) => op,
+            None => {
+                return parser_error(format!(
+                    "unknown comparison: '{}', expected one of lt, le, gt, ge, eq and ne",
+                    op.trim()
+                ))
+            }
+        };
+        let rhs = rhs.trim();
+        let rhs = match rhs.chars().next() {
+            Some(quote @ ('\'' | '"')) => match parse_quoted(&rhs[1..], quote)? {
+                (rhs, "") => rhs,
+                _ => return invalid(),
+            },
+            _ => rhs.to_string(),
+        };
+        Ok(Self {
+            var: var.to_string(),
+            op,
+            rhs,
+        })
+    }
+
+    /// Whether the condition holds. It doesn't if the placeholder is missing or empty.
+    pub fn eval(&self, vars: &HashMap<&str, Value>) -> bool {
+        match vars.get(self.var.as_str()).map(Value::as_string) {
+            Some(lhs) if !lhs.is_empty() => self.op.compare(&lhs, &self.rhs),
+            _ => false,
+        }
+    }
+}
+
+/// Writes the condition as it is parsed, e.g. `(load,gt:2)`
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let quoted = self.rhs.is_empty()
+            || self
+                .rhs
+                .contains(|c: char| c.is_whitespace() || matches!(c, ',' | ')' | '}' | '\'' | '"'));
+        write!(f, "({},{}:", self.var, self.op.name())?;
+        if quoted {
+            write_quoted(f, &self.rhs)?;
+        } else {
+            f.write_str(&self.rhs)?;
+        }
+        f.write_str(")")
+    }
+}
+
+impl Operand {
+    fn eval(&self, vars: &HashMap<&str, Value>) -> Result<String> {
+        match self {
+            Self::Literal(text) => Ok(text.clone()),
+            Self::Var(name) => vars
+                .get(name.as_str())
+                .map(Value::as_string)
+                .internal_error(
+                    "expression",
+                    &format!("Unknown placeholder in expression: '{}'", name),
+                ),
+        }
+    }
+}
+
+impl Comparison {
+    /// The names of the comparisons in conditions of format strings
+    const NAMES: &'static [(&'static str, Comparison)] = &[
+        ("lt", Comparison::Less),
+        ("le", Comparison::LessOrEqual),
+        ("gt", Comparison::Greater),
+        ("ge", Comparison::GreaterOrEqual),
+        ("eq", Comparison::Equal),
+        ("ne", Comparison::NotEqual),
+    ];
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|&(_, comparison)| comparison)
+    }
+
+    fn name(self) -> &'static str {
+        Self::NAMES
+            .iter()
+            .find(|(_, comparison)| *comparison == self)
+            .map_or("", |(name, _)| name)
+    }
+
+    /// Compare numerically if both sides are numbers and as strings otherwise
+    fn compare(self, lhs: &str, rhs: &str) -> bool {
+        let ordering = match (lhs.trim().parse::<f64>(), rhs.trim().parse::<f64>()) {
+            (Ok(lhs), Ok(rhs)) => lhs.partial_cmp(&rhs),
+            _ => Some(lhs.cmp(rhs)),
+        };
+        let ordering = match ordering {
+            Some(ordering) => ordering,
+            None => return self == Self::NotEqual,
+        };
+        match self {
+            Self::Less => ordering.is_lt(),
+            Self::LessOrEqual => ordering.is_le(),
+            Self::Greater => ordering.is_gt(),
+            Self::GreaterOrEqual => ordering.is_ge(),
+            Self::Equal => ordering.is_eq(),
+            Self::NotEqual => ordering.is_ne(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(expression: &str, vars: &HashMap<&str, Value>) -> Option<String> {
+        Expression::parse(expression).unwrap().eval(vars).unwrap()
+    }
+
+    #[test]
+    fn conditions() {
+        let expression = "{errors} > 0 ? #ff0000 : {warnings} >= 1 ? #ffff00 : #00ff00";
+        let vars = |errors, warnings| {
+            map!(
+                "errors" => Value::from_integer(errors),
+                "warnings" => Value::from_float(warnings),
+            )
+        };
+        assert_eq!(eval(expression, &vars(2, 0.)).as_deref(), Some("#ff0000"));
+        assert_eq!(eval(expression, &vars(0, 1.)).as_deref(), Some("#ffff00"));
+        assert_eq!(eval(expression, &vars(0, 0.5)).as_deref(), Some("#00ff00"));
+    }
+
+    #[test]
+    fn no_match() {
+        let vars = map!("state" => Value::from_string("ok".to_string()));
+        assert_eq!(eval("{state} != ok ? red", &vars), None);
+        assert_eq!(
+            eval("{state} == 'ok' ? green", &vars).as_deref(),
+            Some("green")
+        );
+        assert_eq!(eval("{state}", &vars).as_deref(), Some("ok"));
+
+        let vars = map!("état" => Value::from_string("ok".to_string()));
+        assert_eq!(
+            eval("{ état } == ok ? green", &vars).as_deref(),
+            Some("green")
+        );
+    }
+
+    #[test]
+    fn numbers_and_strings() {
+        assert!(Comparison::Less.compare("9", "10"));
+        assert!(!Comparison::Less.compare("9a", "10a"));
+        assert!(Comparison::Equal.compare("1.0", "1"));
+    }
+
+    #[test]
+    fn conditions_of_templates() {
+        let condition = |s: &str| Condition::parse(s).unwrap();
+        let vars = map!(
+            "load" => Value::from_float(2.5),
+            "state" => Value::from_string("on battery".to_string()),
+            "empty" => Value::from_string(String::new()),
+        );
+        assert!(condition("(load,gt:2.0)").eval(&vars));
+        assert!(condition(" ( load , ge : 2.5 ) ").eval(&vars));
+        assert!(!condition("(load,lt:2)").eval(&vars));
+        assert!(condition("(load,le:10)").eval(&vars));
+        assert!(condition("(state,eq:'on battery')").eval(&vars));
+        assert!(!condition("(state,ne:'on battery')").eval(&vars));
+        // Missing and empty values never match
+        assert!(!condition("(missing,ne:1)").eval(&vars));
+        assert!(!condition("(empty,ne:1)").eval(&vars));
+        assert!(!condition("(empty,eq:'')").eval(&vars));
+        // Quotes and escapes work like in the arguments of formatters
+        let vars = map!("name" => Value::from_string("it's".to_string()));
+        assert!(condition(r#"(name,eq:"it's")"#).eval(&vars));
+        assert!(condition(r"(name,eq:'it\'s')").eval(&vars));
+
+        for s in &[
+            "(load,gt:2)",
+            "(state,eq:'on battery')",
+            "(x,ne:'')",
+            "(x,eq:'a,b')",
+            r#"(x,eq:"it's")"#,
+            r#"(x,eq:'say "hi"')"#,
+            r#"(x,eq:"it's \"it\"")"#,
+            r"(x,eq:'a\\b c')",
+        ] {
+            assert_eq!(condition(s).to_string(), *s);
+        }
+
+        assert!(Condition::parse("").is_err());
+        assert!(Condition::parse("(load)").is_err());
+        assert!(Condition::parse("(load,gt)").is_err());
+        assert!(Condition::parse("(load,>:2)").is_err());
+        assert!(Condition::parse("(load,gt:'2)").is_err());
+        assert!(Condition::parse("(load,gt:'2'3)").is_err());
+        assert!(Condition::parse("(lo-ad,gt:2)").is_err());
+        assert!(Condition::parse("load,gt:2").is_err());
+    }
+
+    #[test]
+    fn arithmetic() {
+        let vars = map!(
+            "home.used" => Value::from_integer(300),
+            "data.used" => Value::from_float(500.),
+            "name" => Value::from_string("home".to_string()),
+        );
+        let eval = |s: &str| Arithmetic::parse(s).unwrap().eval(&vars);
+        assert_eq!(eval("1 + 2 * 3"), Some(7.));
+        assert_eq!(eval("(1 + 2) * 3"), Some(9.));
+        assert_eq!(eval("10 - 4 - 3"), Some(3.));
+        assert_eq!(eval("8 / 4 / 2"), Some(1.));
+        assert_eq!(eval("-2 * -(1.5 + .5)"), Some(4.));
+        assert_eq!(eval("1 / 0"), Some(f64::INFINITY));
+        assert_eq!(eval("({ home.used } + {data.used}) / 1000"), Some(0.8));
+        assert_eq!(eval("{missing.used} + 1"), None);
+        assert_eq!(eval("{name} + 1"), None);
+        assert_eq!(
+            Arithmetic::parse("{a.b} * ({c} - 1)").unwrap().vars(),
+            ["a.b", "c"]
+        );
+
+        assert!(Arithmetic::parse("").is_err());
+        assert!(Arithmetic::parse("1 +").is_err());
+        assert!(Arithmetic::parse("(1 + 2").is_err());
+        assert!(Arithmetic::parse("1 2").is_err());
+        assert!(Arithmetic::parse("{a.b + 1").is_err());
+        assert!(Arithmetic::parse("{} + 1").is_err());
+        assert!(Arithmetic::parse("{a..b} + 1").is_err());
+        assert!(Arithmetic::parse("1..2").is_err());
+        assert!(Arithmetic::parse("1 > 2").is_err());
+
+        // Operators are literals in other expressions
+        let vars = map!("x" => Value::from_integer(1));
+        assert_eq!(
+            Expression::parse("{x} > 0 ? a-b/c")
+                .unwrap()
+                .eval(&vars)
+                .unwrap()
+                .as_deref(),
+            Some("a-b/c")
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Expression::parse("").is_err());
+        assert!(Expression::parse("{a} > ? red").is_err());
+        assert!(Expression::parse("{a} > 1 red").is_err());
+        assert!(Expression::parse("{a > 1 ? red").is_err());
+        assert!(Expression::parse("red blue").is_err());
+        assert!(Expression::parse("{a-b} > 1 ? red").is_err());
+        assert!(Expression::parse("{x} > 1 ? red")
+            .unwrap()
+            .eval(&HashMap::new())
+            .is_err());
+    }
+}
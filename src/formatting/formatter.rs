@@ -0,0 +1,3621 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::env;
+use std::fmt::{self, Write};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Datelike, Local, Locale, NaiveDateTime, TimeZone, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::errors::*;
+
+use super::prefix::Prefix;
+use super::unit::Unit;
+use super::value::{format_bar, ForcedPrefix, PrefixBase, Value, BAR_GLYPHS};
+use super::{privacy_mode, shared_config, FormatTemplate};
+use crate::util::{color_from_rgba, color_to_rgba, escape_pango_text, unescape_pango_text};
+use crate::widgets::State;
+
+pub const FORMATTER_TOKEN: char = '.';
+const ARGS_START_TOKEN: char = '(';
+const ARGS_END_TOKEN: char = ')';
+const ARGS_SEPARATOR: char = ',';
+const ARG_KEY_VALUE_SEPARATOR: char = ':';
+const QUOTE: char = '\'';
+const DOUBLE_QUOTE: char = '"';
+const VAR_TOKEN: char = '$';
+const LABEL_SUFFIX: &str = "_label";
+const ESCAPE: char = '\\';
+
+/// Used in place of the list of arguments by formatters which accept arbitrary argument names
+const ANY_ARGS: &[&str] = &["*"];
+
+/// The list of known formatters and the arguments each of them accepts. An argument without a
+/// key is assigned to the first argument in the list.
+const FORMATTERS: &[(&str, &[&str])] = &[
+    ("abbr_unit", ANY_ARGS),
+    ("ago", &[]),
+    ("as_state", ANY_ARGS),
+    ("bar", &["width", "max", "glyphs", "vertical"]),
+    ("camel", &[]),
+    ("cap", &["max", "suffix"]),
+    ("clock", &["drop_hours"]),
+    ("coalesce", ANY_ARGS),
+    ("color", &["fg", "gradient", "min", "max"]),
+    ("colorlerp", &["from", "to"]),
+    ("compact", &["places"]),
+    ("csv", &["field", "sep", "default"]),
+    ("datetime", &["format", "tz", "locale"]),
+    ("delta_bytes", &[]),
+    ("dur", &["max_unit", "min_unit", "units", "hms"]),
+    ("elide_mid", &["max", "ellipsis"]),
+    ("eng", &["prefix", "base", "unit"]),
+    ("escape", &[]),
+    ("fit", &["width"]),
+    ("grade", ANY_ARGS),
+    ("highlight", &["pattern", "color", "regex"]),
+    ("icon_or", &["fallback"]),
+    ("index", &["list", "default"]),
+    ("json_get", &["path"]),
+    ("kebab", &[]),
+    ("lerp", &["from", "to", "places"]),
+    ("ljust_icons", &["width"]),
+    ("localize_number", &["digits"]),
+    ("max", &["value"]),
+    ("min", &["value"]),
+    ("normalize_ws_icons", &[]),
+    ("number", &[]),
+    ("pad", &["width", "align", "fill"]),
+    ("plural", &["rules", "one", "few", "many", "other"]),
+    ("progress", &["pos", "len", "width"]),
+    (
+        "ramp",
+        &[
+            "idle", "info", "good", "warning", "critical", "warn", "crit",
+        ],
+    ),
+    ("redact", &["text"]),
+    ("rot", &["width", "step", "interval", "sep"]),
+    ("schedule", ANY_ARGS),
+    ("since_change", &[]),
+    ("snake", &[]),
+    (
+        "state",
+        &[
+            "idle", "info", "good", "warning", "critical", "warn", "crit",
+        ],
+    ),
+    ("str", &["max_w", "min_w", "ellipsis", "east_asian"]),
+    ("strip_ansi", &[]),
+    (
+        "style",
+        &[
+            "weight",
+            "style",
+            "underline",
+            "strikethrough",
+            "size",
+            "variant",
+        ],
+    ),
+    ("tally", &["one", "many", "zero", "few", "rules"]),
+    ("truthy_icon", &["true", "false"]),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArgValue {
+    Literal(String),
+    /// A reference to another placeholder, e.g. `$cap`
+    Var(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arg {
+    pub key: String,
+    pub val: ArgValue,
+}
+
+/// A formatter is an optional transformation applied to a placeholder's value before it gets
+/// formatted as usual, e.g. `{level.index(list:'low,med,high')}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Formatter {
+    pub name: String,
+    pub args: Vec<Arg>,
+    changes: ChangeTracker,
+    largest: LargestValue,
+}
+
+/// Remembers when the value of a placeholder last changed, for `since_change` and `rot`.
+/// Formatters are owned by the block's format template, so this state lives as long as the
+/// block, and each placeholder has its own.
+#[derive(Debug, Clone, Default)]
+struct ChangeTracker(RefCell<Option<(String, Instant)>>);
+
+impl ChangeTracker {
+    /// Record the current value and return for how long it has been unchanged
+    fn track(&self, value: String, now: Instant) -> Duration {
+        let mut last = self.0.borrow_mut();
+        match &*last {
+            Some((last_value, since)) if *last_value == value => {
+                now.saturating_duration_since(*since)
+            }
+            _ => {
+                *last = Some((value, now));
+                Duration::from_secs(0)
+            }
+        }
+    }
+
+    /// For how long the last recorded value has been unchanged, without recording a new one
+    fn unchanged_for(&self, now: Instant) -> Duration {
+        match &*self.0.borrow() {
+            Some((_, since)) => now.saturating_duration_since(*since),
+            None => Duration::from_secs(0),
+        }
+    }
+}
+
+/// The largest (absolute) value a placeholder has had, for `eng(prefix:fixed)`
+#[derive(Debug, Clone, Default)]
+struct LargestValue(Cell<f64>);
+
+impl LargestValue {
+    /// Record the current value and return the largest one so far
+    fn track(&self, value: f64) -> f64 {
+        let largest = self.0.get().max(value.abs());
+        self.0.set(largest);
+        largest
+    }
+}
+
+impl PartialEq for LargestValue {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+/// The state of a formatter is not a part of its identity
+impl PartialEq for ChangeTracker {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+fn parser_error<T>(message: String) -> Result<T> {
+    Err(InternalError("format parser".to_string(), message, None))
+}
+
+/// Whether `c` may appear in the name of a placeholder, a formatter or an argument: any Unicode
+/// letter or digit, so that e.g. `température` is a valid name, and `_`. Other characters, such
+/// as `-`, are left to the syntax of formats. Combining marks are neither, so accented letters
+/// must be written precomposed (NFC), as keyboards do.
+pub(super) fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Check that `name` is a valid name of a `kind` (e.g. "placeholder"). Placeholder names may
+/// start with a digit, like the `1m` of `load`, but formatter names may not.
+pub(super) fn check_name(kind: &str, name: &str) -> Result<()> {
+    let valid = match name.chars().next() {
+        None => false,
+        Some(c) if c.is_numeric() && kind == "formatter" => false,
+        Some(_) => name.chars().all(is_name_char),
+    };
+    if valid {
+        Ok(())
+    } else {
+        parser_error(format!("invalid {} name: '{}'", kind, name))
+    }
+}
+
+/// Parse a quoted argument after its opening `quote`, resolving the escapes `\'`, `\"`, `\\`
+/// and `\n`. Other escapes, such as the `\,` of lists, are kept as they are. Returns the value
+/// and the rest of the string after the closing quote.
+pub(super) fn parse_quoted(s: &str, quote: char) -> Result<(String, &str)> {
+    let mut val = String::new();
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            c if c == quote => return Ok((val, &s[i + 1..])),
+            ESCAPE => match chars.next() {
+                Some((_, 'n')) => val.push('\n'),
+                Some((_, c)) if c == QUOTE || c == DOUBLE_QUOTE || c == ESCAPE => val.push(c),
+                Some((_, c)) => {
+                    val.push(ESCAPE);
+                    val.push(c);
+                }
+                None => return parser_error("unterminated escape sequence".to_string()),
+            },
+            c => val.push(c),
+        }
+    }
+    parser_error("unterminated quoted argument".to_string())
+}
+
+/// Whether a literal argument must be quoted to be parsed back as is
+fn needs_quotes(val: &str) -> bool {
+    val.is_empty()
+        || val.starts_with(&[VAR_TOKEN, QUOTE, DOUBLE_QUOTE][..])
+        || val.trim() != val
+        || val.contains(&[ARGS_SEPARATOR, ARGS_END_TOKEN, '}', '\n'][..])
+}
+
+/// Write `val` so that `parse_quoted` reads it back, in double quotes if it contains single ones
+pub(super) fn write_quoted(f: &mut fmt::Formatter, val: &str) -> fmt::Result {
+    let quote = if val.contains(QUOTE) {
+        DOUBLE_QUOTE
+    } else {
+        QUOTE
+    };
+    f.write_char(quote)?;
+    for c in val.chars() {
+        match c {
+            '\n' => f.write_str("\\n")?,
+            c if c == quote || c == ESCAPE => write!(f, "{}{}", ESCAPE, c)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char(quote)
+}
+
+impl fmt::Display for ArgValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Literal(val) if needs_quotes(val) => write_quoted(f, val),
+            Self::Literal(val) => f.write_str(val),
+            Self::Var(var) => write!(f, "{}{}", VAR_TOKEN, var),
+        }
+    }
+}
+
+impl fmt::Display for Arg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}{}", self.key, ARG_KEY_VALUE_SEPARATOR, self.val)
+    }
+}
+
+/// Writes the formatter without the leading `FORMATTER_TOKEN`, with the keys of all arguments
+impl fmt::Display for Formatter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.name, ARGS_START_TOKEN)?;
+        for (i, arg) in self.args.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{} ", ARGS_SEPARATOR)?;
+            }
+            write!(f, "{}", arg)?;
+        }
+        f.write_char(ARGS_END_TOKEN)
+    }
+}
+
+impl Formatter {
+    pub fn new(name: &str, mut args: Vec<Arg>) -> Result<Self> {
+        let known_args = match FORMATTERS.iter().find(|(n, _)| *n == name) {
+            Some((_, known_args)) => known_args,
+            None => return parser_error(format!("unknown formatter: '{}'", name)),
+        };
+        for arg in &mut args {
+            if *known_args == ANY_ARGS {
+                if arg.key.is_empty() {
+                    return parser_error(format!(
+                        "all arguments of formatter '{}' must be named",
+                        name
+                    ));
+                }
+                continue;
+            }
+            if arg.key.is_empty() {
+                match known_args.first() {
+                    Some(key) => arg.key = key.to_string(),
+                    None => {
+                        return parser_error(format!("formatter '{}' takes no arguments", name))
+                    }
+                }
+            }
+            if !known_args.contains(&arg.key.as_str()) {
+                return parser_error(format!(
+                    "formatter '{}' does not accept argument '{}'",
+                    name, arg.key
+                ));
+            }
+        }
+        Ok(Self {
+            name: name.to_string(),
+            args,
+            changes: ChangeTracker::default(),
+            largest: LargestValue::default(),
+        })
+    }
+
+    /// Parse a formatter from the beginning of `s` (which must not include the leading
+    /// `FORMATTER_TOKEN`). Returns the formatter and the rest of the string.
+    pub fn parse(s: &str) -> Result<(Self, &str)> {
+        let name_len = s.find(|c| !is_name_char(c)).unwrap_or(s.len());
+        let (name, rest) = s.split_at(name_len);
+        if name.is_empty() {
+            return parser_error("missing formatter name".to_string());
+        }
+        check_name("formatter", name)?;
+        let mut rest = match rest.strip_prefix(ARGS_START_TOKEN) {
+            Some(rest) => rest,
+            None => return parser_error(format!("expected '{}'", ARGS_START_TOKEN)),
+        };
+
+        let mut args = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(ARGS_END_TOKEN) {
+                rest = after;
+                break;
+            }
+            let (arg, after) = Self::parse_arg(rest)?;
+            args.push(arg);
+            rest = after.trim_start();
+            if let Some(after) = rest.strip_prefix(ARGS_SEPARATOR) {
+                rest = after;
+            } else if !rest.starts_with(ARGS_END_TOKEN) {
+                return parser_error(format!(
+                    "expected '{}' or '{}'",
+                    ARGS_SEPARATOR, ARGS_END_TOKEN
+                ));
+            }
+        }
+
+        Ok((Self::new(name, args)?, rest))
+    }
+
+    fn parse_arg(s: &str) -> Result<(Arg, &str)> {
+        // The key is optional
+        let (key, rest) = match s.find(
+            &[
+                ARG_KEY_VALUE_SEPARATOR,
+                ARGS_SEPARATOR,
+                ARGS_END_TOKEN,
+                QUOTE,
+                DOUBLE_QUOTE,
+            ][..],
+        ) {
+            Some(i) if s[i..].starts_with(ARG_KEY_VALUE_SEPARATOR) => {
+                (s[..i].trim(), s[i + 1..].trim_start())
+            }
+            _ => ("", s),
+        };
+        // Unlike formatters, arguments may be named after numbers, e.g. the labels of `grade`
+        if !key.chars().all(is_name_char) {
+            return parser_error(format!("invalid argument name: '{}'", key));
+        }
+
+        // Inside either kind of quotes the other kind is an ordinary character
+        let quote = rest
+            .chars()
+            .next()
+            .filter(|c| *c == QUOTE || *c == DOUBLE_QUOTE);
+        let (val, rest) = if let Some(quote) = quote {
+            let (val, rest) = parse_quoted(&rest[1..], quote)?;
+            (ArgValue::Literal(val), rest)
+        } else {
+            let len = rest
+                .find(&[ARGS_SEPARATOR, ARGS_END_TOKEN][..])
+                .unwrap_or(rest.len());
+            let (val, rest) = rest.split_at(len);
+            let val = val.trim_end();
+            match val.strip_prefix(VAR_TOKEN) {
+                Some(var) => {
+                    check_name("placeholder", var)?;
+                    (ArgValue::Var(var.to_string()), rest)
+                }
+                None => (ArgValue::Literal(val.to_string()), rest),
+            }
+        };
+
+        Ok((
+            Arg {
+                key: key.to_string(),
+                val,
+            },
+            rest,
+        ))
+    }
+
+    /// The value of an argument, resolving a reference to another placeholder
+    fn arg_value(&self, arg: &Arg, vars: &HashMap<&str, Value>) -> Result<Value> {
+        match &arg.val {
+            ArgValue::Literal(text) => Ok(Value::from_string(text.clone())),
+            ArgValue::Var(var) => vars.get(var.as_str()).cloned().internal_error(
+                "formatter",
+                &format!(
+                    "argument '{}' of formatter '{}' references unknown placeholder '{}{}'",
+                    arg.key, self.name, VAR_TOKEN, var
+                ),
+            ),
+        }
+    }
+
+    /// Get an argument as text, which may be a reference to another placeholder
+    fn arg(&self, key: &str, vars: &HashMap<&str, Value>) -> Result<Option<String>> {
+        match self.args.iter().find(|arg| arg.key == key) {
+            Some(arg) => Ok(Some(self.arg_value(arg, vars)?.as_string())),
+            None => Ok(None),
+        }
+    }
+
+    fn required_arg(&self, key: &str, vars: &HashMap<&str, Value>) -> Result<String> {
+        self.arg(key, vars)?.internal_error(
+            "formatter",
+            &format!("formatter '{}' requires argument '{}'", self.name, key),
+        )
+    }
+
+    /// The error for an argument that isn't e.g. `a number`. Names the value of the referenced
+    /// placeholder, if any.
+    fn invalid_arg(&self, key: &str, vars: &HashMap<&str, Value>, expected: &str) -> Error {
+        let mut message = format!(
+            "argument '{}' of formatter '{}' must be {}",
+            key, self.name, expected
+        );
+        let val = self
+            .args
+            .iter()
+            .find(|arg| arg.key == key)
+            .map(|arg| &arg.val);
+        if let Some(ArgValue::Var(var)) = val {
+            if let Some(value) = vars.get(var.as_str()) {
+                message += &format!(", but '{}{}' is '{}'", VAR_TOKEN, var, value.as_string());
+            }
+        }
+        InternalError("formatter".to_string(), message, None)
+    }
+
+    fn bool_arg(&self, key: &str, vars: &HashMap<&str, Value>, default: bool) -> Result<bool> {
+        match self.arg(key, vars)? {
+            None => Ok(default),
+            Some(val) => val
+                .parse()
+                .map_err(|_| self.invalid_arg(key, vars, "'true' or 'false'")),
+        }
+    }
+
+    /// Get a numeric argument, which may be a reference to another placeholder
+    fn f64_arg(&self, key: &str, vars: &HashMap<&str, Value>) -> Result<Option<f64>> {
+        let arg = match self.args.iter().find(|arg| arg.key == key) {
+            Some(arg) => arg,
+            None => return Ok(None),
+        };
+        match self.arg_value(arg, vars)?.as_f64() {
+            Some(number) => Ok(Some(number)),
+            None => Err(self.invalid_arg(key, vars, "a number")),
+        }
+    }
+
+    /// Get a non-negative integer argument, such as a width
+    fn usize_arg(&self, key: &str, vars: &HashMap<&str, Value>) -> Result<Option<usize>> {
+        match self.f64_arg(key, vars)? {
+            Some(number) if number >= 0. && number.fract() == 0. => Ok(Some(number as usize)),
+            Some(_) => Err(self.invalid_arg(key, vars, "a non-negative integer")),
+            None => Ok(None),
+        }
+    }
+
+    fn required_usize_arg(&self, key: &str, vars: &HashMap<&str, Value>) -> Result<usize> {
+        self.usize_arg(key, vars)?.internal_error(
+            "formatter",
+            &format!("formatter '{}' requires argument '{}'", self.name, key),
+        )
+    }
+
+    fn required_f64_arg(&self, key: &str, vars: &HashMap<&str, Value>) -> Result<f64> {
+        self.f64_arg(key, vars)?.internal_error(
+            "formatter",
+            &format!("formatter '{}' requires argument '{}'", self.name, key),
+        )
+    }
+
+    /// Apply the formatter to a value
+    pub fn apply(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        match self.name.as_str() {
+            "abbr_unit" => self.abbr_unit(value, vars),
+            "ago" => {
+                let timestamp = self.numeric_value(value)? as i64;
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|now| now.as_secs() as i64)
+                    .unwrap_or_default();
+                Ok(value.with_text(ago(now - timestamp)))
+            }
+            "bar" => self.bar(value, vars),
+            // See `requested_state`
+            "as_state" => Ok(value.clone()),
+            "camel" => Ok(value.with_text(camel_case(&value.as_string()))),
+            "cap" => self.cap(value, vars),
+            "clock" => self.clock(value, vars),
+            "coalesce" => self.coalesce(value, vars),
+            "color" => self.color(value, vars),
+            "colorlerp" => self.colorlerp(value, vars),
+            "compact" => {
+                let places = self.usize_arg("places", vars)?.unwrap_or(1);
+                Ok(match compact(self.numeric_value(value)?, places) {
+                    Some(text) => value.with_text(text),
+                    None => value.clone(),
+                })
+            }
+            "csv" => self.csv(value, vars),
+            "datetime" => self.datetime(value, vars),
+            "delta_bytes" => {
+                let bytes = self.numeric_value(value)? as i64;
+                Ok(value.with_text(delta_bytes(bytes)))
+            }
+            "dur" => self.dur(value, vars),
+            "elide_mid" => {
+                let max_width = self.required_usize_arg("max", vars)?;
+                let ellipsis = self
+                    .arg("ellipsis", vars)?
+                    .unwrap_or_else(|| "\u{2026}".to_string());
+                Ok(value.with_text(elide_middle(&value.as_string(), max_width, &ellipsis)))
+            }
+            "eng" => self.eng(value, vars),
+            "escape" => Ok(value.with_text(escape_pango_text(value.as_string()))),
+            "fit" => {
+                let width = self.required_usize_arg("width", vars)?;
+                Ok(value.with_text(fit(self.numeric_value(value)?, width)))
+            }
+            "grade" => self.grade(value, vars),
+            "highlight" => self.highlight(value, vars),
+            "icon_or" => self.icon_or(value, vars),
+            "index" => self.index(value, vars),
+            "json_get" => self.json_get(value, vars),
+            "kebab" => Ok(value.with_text(words(&value.as_string()).join("-"))),
+            "lerp" => self.lerp(value, vars),
+            "ljust_icons" => {
+                let width = self.required_usize_arg("width", vars)?;
+                Ok(value.with_icon_width(width))
+            }
+            "localize_number" => self.localize_number(value, vars),
+            "max" => self.clamp(value, vars, f64::max),
+            "min" => self.clamp(value, vars, f64::min),
+            // Applied by the template, see `collapses_whitespace`
+            "normalize_ws_icons" => Ok(value.clone()),
+            "number" => {
+                let text = value.as_string();
+                Ok(match first_number(&text) {
+                    Some(number) => match number.parse() {
+                        Ok(integer) => Value::from_integer(integer),
+                        Err(_) => Value::from_float(number.parse().unwrap_or_default()),
+                    },
+                    None => value.with_text(String::new()),
+                })
+            }
+            "pad" => self.pad(value, vars),
+            "plural" => self.plural(value, vars),
+            "progress" => self.progress(value, vars),
+            "ramp" => self.ramp(value, vars),
+            "redact" => self.redact(value, vars, privacy_mode()),
+            "schedule" => self.schedule(value, vars, Local::now().naive_local()),
+            "rot" => self.rot(value, vars, Instant::now()),
+            "since_change" => {
+                let stable = self.changes.track(value.as_string(), Instant::now());
+                Ok(Value::from_integer(stable.as_secs() as i64).seconds())
+            }
+            "snake" => Ok(value.with_text(words(&value.as_string()).join("_"))),
+            // See `requested_state`
+            "state" => Ok(value.clone()),
+            "str" => self.str(value, vars),
+            "strip_ansi" => Ok(value.with_text(strip_ansi(&value.as_string()))),
+            "style" => self.style(value, vars),
+            "tally" => self.tally(value, vars),
+            "truthy_icon" => self.truthy_icon(value, vars),
+            _ => unreachable!("formatter names are validated at parse time"),
+        }
+    }
+
+    /// The state of the block requested by the `state` and `as_state` formatters for a value, which
+    /// they display unchanged
+    pub fn requested_state(
+        &self,
+        value: &Value,
+        vars: &HashMap<&str, Value>,
+    ) -> Result<Option<State>> {
+        match self.name.as_str() {
+            "as_state" => {
+                let text = value.as_string();
+                let arg = self
+                    .args
+                    .iter()
+                    .find(|arg| arg.key == text.trim())
+                    .or_else(|| self.args.iter().find(|arg| arg.key == "default"));
+                match arg {
+                    Some(arg) => {
+                        let name = self.arg_value(arg, vars)?.as_string();
+                        let state = state_from_name(&name).ok_or_else(|| {
+                            self.invalid_arg(&arg.key, vars, "a state like 'good' or 'critical'")
+                        })?;
+                        Ok(Some(state))
+                    }
+                    None => Ok(None),
+                }
+            }
+            "state" => Ok(self.ramp_state(value, vars)?.and_then(state_from_name)),
+            _ => Ok(None),
+        }
+    }
+
+    /// How long until the formatter changes its output for the same value, e.g. the next step of
+    /// `rot`. Must be called after `apply` with the value it was applied to.
+    pub fn next_tick(
+        &self,
+        value: &Value,
+        vars: &HashMap<&str, Value>,
+        now: Instant,
+    ) -> Result<Option<Duration>> {
+        match self.name.as_str() {
+            "rot" => {
+                // Text which fits doesn't scroll. The arguments were checked by `apply`.
+                let width = self.usize_arg("width", vars)?.unwrap_or_default();
+                if text_width(&value.as_string(), false) <= width {
+                    return Ok(None);
+                }
+                let interval = self.f64_arg("interval", vars)?.unwrap_or(1.);
+                let scrolled = self.changes.unchanged_for(now).as_secs_f64();
+                Ok(Some(Duration::from_secs_f64(
+                    interval - scrolled % interval,
+                )))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// The names of the placeholders referenced by the arguments
+    pub fn vars(&self) -> impl Iterator<Item = &str> {
+        self.args.iter().filter_map(|arg| match &arg.val {
+            ArgValue::Var(var) => Some(var.as_str()),
+            ArgValue::Literal(_) => None,
+        })
+    }
+
+    /// Whether the whitespace around the placeholder collapses when it is rendered empty, so that
+    /// e.g. `"a {icon} b"` becomes `"a b"`
+    pub fn collapses_whitespace(&self) -> bool {
+        self.name == "normalize_ws_icons"
+    }
+
+    /// Whether the formatter works on the text of the value, so that a number is formatted with
+    /// the placeholder's width, prefix and unit first
+    pub fn formats_text(&self) -> bool {
+        matches!(
+            self.name.as_str(),
+            "camel"
+                | "coalesce"
+                | "csv"
+                | "escape"
+                | "kebab"
+                | "localize_number"
+                | "snake"
+                | "strip_ansi"
+        )
+    }
+
+    fn numeric_value(&self, value: &Value) -> Result<f64> {
+        value.as_f64().internal_error(
+            "formatter",
+            &format!("'{}' formatter requires a numeric value", self.name),
+        )
+    }
+
+    /// Format the number with a fixed prefix, e.g. `M`, or with the prefix of the largest value so
+    /// far, so that it doesn't jump between e.g. `999K` and `1.0M`. Prefixes are powers of
+    /// `base`, and the value can be given a unit.
+    fn eng(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let number = self.numeric_value(value)?;
+        let mut value = value.prefixable();
+        if let Some(unit) = self.arg("unit", vars)? {
+            let unit: Unit = unit
+                .parse()
+                .map_err(|_| self.invalid_arg("unit", vars, "a unit such as B, b or Hz"))?;
+            value = value.with_unit(unit);
+        }
+        match self.arg("base", vars)?.as_deref() {
+            None => (),
+            Some("1000") => value = value.with_base(PrefixBase::Decimal),
+            Some("1024") => value = value.with_base(PrefixBase::Binary),
+            Some(_) => return Err(self.invalid_arg("base", vars, "1000 or 1024")),
+        }
+        let prefix = match self.arg("prefix", vars)?.as_deref() {
+            None | Some("auto") => return Ok(value),
+            Some("fixed") => ForcedPrefix::Of(self.largest.track(number)),
+            Some(prefix) => ForcedPrefix::Exactly(prefix.parse::<Prefix>().map_err(|_| {
+                self.invalid_arg(
+                    "prefix",
+                    vars,
+                    "'auto', 'fixed' or one of n, u, m, 1, K, M, G and T",
+                )
+            })?),
+        };
+        Ok(value.with_prefix(prefix))
+    }
+
+    /// Show values above `max` as e.g. `99+`
+    fn cap(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let max = self.required_f64_arg("max", vars)? as i64;
+        if self.numeric_value(value)? as i64 <= max {
+            return Ok(value.clone());
+        }
+        let suffix = self.arg("suffix", vars)?.unwrap_or_else(|| "+".to_string());
+        Ok(value.with_text(format!("{}{}", max, suffix)))
+    }
+
+    /// Show a duration in seconds as e.g. `3h 07m 05s`, limited to the units from `max_unit` to
+    /// `min_unit` and to `units` of them, or as e.g. `1:02:03` with `hms`
+    fn dur(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let seconds = self.numeric_value(value)?.max(0.);
+        if self.bool_arg("hms", vars, false)? {
+            return Ok(value.with_text(format_hms(seconds as u64)));
+        }
+        let unit = |key: &str, default: usize| match self.arg(key, vars)? {
+            Some(name) => DURATION_UNITS
+                .iter()
+                .position(|unit| unit.names.contains(&name.as_str()))
+                .ok_or_else(|| self.invalid_arg(key, vars, "a unit like 'hours' or 'h'")),
+            None => Ok(default),
+        };
+        let max_unit = unit("max_unit", 0)?;
+        let min_unit = unit("min_unit", DURATION_UNITS.len() - 2)?;
+        if max_unit > min_unit {
+            return Err(self.invalid_arg("max_unit", vars, "larger than 'min_unit'"));
+        }
+        let units = match self.usize_arg("units", vars)? {
+            Some(0) => return Err(self.invalid_arg("units", vars, "a positive integer")),
+            Some(units) => units,
+            None => usize::MAX,
+        };
+        let millis = (seconds * 1000.) as u64;
+        let text = format_duration(millis, &DURATION_UNITS[max_unit..=min_unit], units);
+        Ok(value.with_text(text))
+    }
+
+    fn clock(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let seconds = self.numeric_value(value)?.max(0.) as u64;
+        let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+        let text = if hours == 0 && self.bool_arg("drop_hours", vars, false)? {
+            format!("{:02}:{:02}", minutes, seconds)
+        } else {
+            format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
+        };
+        Ok(value.with_text(text))
+    }
+
+    /// Format a UNIX timestamp with `format`, in the timezone `tz` or the local one, and with the
+    /// names of days and months of `locale` or the one of the environment
+    fn datetime(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let timestamp = self.numeric_value(value)?;
+        let time = Utc
+            .timestamp_opt(timestamp.floor() as i64, (timestamp.fract() * 1e9) as u32)
+            .single()
+            .internal_error(
+                "formatter",
+                &format!("'{}' is not a valid UNIX timestamp", timestamp),
+            )?;
+        let format = self
+            .arg("format", vars)?
+            .unwrap_or_else(|| "%a %d/%m %R".to_string());
+        let locale = match self.arg("locale", vars)? {
+            Some(locale) => parse_locale(&locale).internal_error(
+                "formatter",
+                &format!(
+                    "unknown locale '{}' in formatter 'datetime', expected a name like 'de_DE'",
+                    locale
+                ),
+            )?,
+            None => env_locale(|var| env::var(var).ok()),
+        };
+        let written = match self.arg("tz", vars)? {
+            Some(tz) => {
+                let tz: Tz = tz.parse().map_err(|_| {
+                    InternalError(
+                        "formatter".to_string(),
+                        format!(
+                            "unknown timezone '{}' in formatter 'datetime', expected an IANA name like 'America/New_York'",
+                            tz
+                        ),
+                        None,
+                    )
+                })?;
+                format_datetime(time.with_timezone(&tz), &format, locale)
+            }
+            None => format_datetime(time.with_timezone(&Local), &format, locale),
+        };
+        let text =
+            written.map_err(|_| self.invalid_arg("format", vars, "a valid strftime format"))?;
+        Ok(value.with_text(text))
+    }
+
+    /// Replace the names of units with their symbols. The arguments add to or override the
+    /// built-in `UNIT_ABBREVIATIONS`.
+    fn abbr_unit(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let mut abbreviations = Vec::new();
+        for arg in &self.args {
+            let abbreviation = self.arg_value(arg, vars)?.as_string();
+            abbreviations.push((arg.key.to_lowercase(), abbreviation));
+        }
+        abbreviations.extend(
+            UNIT_ABBREVIATIONS
+                .iter()
+                .map(|(word, abbreviation)| (word.to_string(), abbreviation.to_string())),
+        );
+        Ok(value.with_text(abbreviate_units(&value.as_string(), &abbreviations)))
+    }
+
+    /// Limit the text to `max_w` columns, ending it with an ellipsis if it is cut off, and pad
+    /// it with spaces to `min_w` columns
+    fn str(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let mut text = value.as_string();
+        let east_asian = self.bool_arg("east_asian", vars, false)?;
+        if let Some(max_width) = self.usize_arg("max_w", vars)? {
+            let ellipsis = match self.arg("ellipsis", vars)?.as_deref() {
+                None | Some("false") => String::new(),
+                Some("true") => "\u{2026}".to_string(),
+                Some(ellipsis) => ellipsis.to_string(),
+            };
+            text = truncate(&text, max_width, &ellipsis, east_asian);
+        }
+        if let Some(min_width) = self.usize_arg("min_w", vars)? {
+            for _ in text_width(&text, east_asian)..min_width {
+                text.push(' ');
+            }
+        }
+        Ok(value.with_text(text))
+    }
+
+    /// Pad the text with `fill` to `width` columns, aligning it to the left, center or right.
+    /// Centered text gets the extra column on the right.
+    fn pad(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let width = self.required_usize_arg("width", vars)?;
+        let fill = match self.arg("fill", vars)? {
+            None => ' ',
+            Some(fill) => {
+                let mut chars = fill.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(fill), None) => fill,
+                    _ => return Err(self.invalid_arg("fill", vars, "a single character")),
+                }
+            }
+        };
+        let text = value.as_string();
+        let missing = width.saturating_sub(text_width(&text, false));
+        let left = match self.arg("align", vars)?.as_deref() {
+            None | Some("left") => 0,
+            Some("center") => missing / 2,
+            Some("right") => missing,
+            Some(_) => return Err(self.invalid_arg("align", vars, "'left', 'center' or 'right'")),
+        };
+        let fill = |columns| fill.to_string().repeat(columns);
+        Ok(value.with_text(format!("{}{}{}", fill(left), text, fill(missing - left))))
+    }
+
+    /// Scroll text wider than `width` by `step` characters every `interval` seconds, starting
+    /// over whenever the text changes
+    fn rot(&self, value: &Value, vars: &HashMap<&str, Value>, now: Instant) -> Result<Value> {
+        let width = self.required_usize_arg("width", vars)?;
+        let step = self.usize_arg("step", vars)?.unwrap_or(1);
+        let interval = self.f64_arg("interval", vars)?.unwrap_or(1.);
+        if interval <= 0. {
+            return Err(self.invalid_arg("interval", vars, "a positive number"));
+        }
+        let sep = self.arg("sep", vars)?.unwrap_or_else(|| " | ".to_string());
+        let text = value.as_string();
+        let scrolled = self.changes.track(text.clone(), now).as_secs_f64();
+        let offset = (scrolled / interval) as usize * step;
+        Ok(value.with_text(marquee(&text, width, offset, &sep)))
+    }
+
+    /// Use the first non-empty value among the value itself and the arguments, in order
+    fn coalesce(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        if !value.as_string().is_empty() {
+            return Ok(value.clone());
+        }
+        for arg in &self.args {
+            let candidate = self.arg_value(arg, vars)?;
+            if !candidate.as_string().is_empty() {
+                return Ok(candidate);
+            }
+        }
+        Ok(value.clone())
+    }
+
+    /// Pick the first argument whose name matches `now`, e.g. `sat_sun` or `h9_17`, falling back
+    /// to `default` and then to the value itself
+    fn schedule(
+        &self,
+        value: &Value,
+        vars: &HashMap<&str, Value>,
+        now: NaiveDateTime,
+    ) -> Result<Value> {
+        let mut default = None;
+        for arg in &self.args {
+            if arg.key == "default" {
+                default = Some(arg);
+                continue;
+            }
+            let matches = schedule_matches(&arg.key, now).internal_error(
+                "formatter",
+                &format!(
+                    "invalid schedule '{}' in formatter '{}'",
+                    arg.key, self.name
+                ),
+            )?;
+            if matches {
+                return self.arg_value(arg, vars);
+            }
+        }
+        match default {
+            Some(arg) => self.arg_value(arg, vars),
+            None => Ok(value.clone()),
+        }
+    }
+
+    /// Select a (0-based) field of a line of CSV
+    fn csv(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let field = self.required_f64_arg("field", vars)?;
+        let sep = self.arg("sep", vars)?.unwrap_or_else(|| ",".to_string());
+        let mut sep = sep.chars();
+        let sep = match (sep.next(), sep.next()) {
+            (Some(sep), None) => sep,
+            _ => {
+                return Err(InternalError(
+                    "formatter".to_string(),
+                    format!(
+                        "argument 'sep' of formatter '{}' must be a single character",
+                        self.name
+                    ),
+                    None,
+                ))
+            }
+        };
+        let fields = csv_fields(&value.as_string(), sep);
+        let text = if field >= 0. {
+            fields.get(field as usize).map(String::as_str)
+        } else {
+            None
+        };
+        let default = self.arg("default", vars)?.unwrap_or_default();
+        Ok(value.with_text(text.unwrap_or(&default).to_string()))
+    }
+
+    /// Pick the label with the highest threshold that the value reaches. The label is the name of
+    /// the threshold argument, unless a `<name>_label` argument gives a format string for it.
+    fn grade(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let name = match self.highest_threshold(value, vars)? {
+            Some(name) => name,
+            None => return Ok(Value::from_string(String::new())),
+        };
+        let label = match self.arg(&format!("{}{}", name, LABEL_SUFFIX), vars)? {
+            Some(template) => FormatTemplate::new(&template, None)?.render(vars)?.full,
+            None => name.to_string(),
+        };
+        Ok(Value::from_string(label))
+    }
+
+    /// The name of the argument with the highest threshold that the value reaches
+    fn highest_threshold(
+        &self,
+        value: &Value,
+        vars: &HashMap<&str, Value>,
+    ) -> Result<Option<&str>> {
+        let value = self.numeric_value(value)?;
+        let mut best: Option<(f64, &str)> = None;
+        // The labels of `grade` aren't thresholds
+        for arg in self.args.iter().filter(|a| !a.key.ends_with(LABEL_SUFFIX)) {
+            let threshold = self.required_f64_arg(&arg.key, vars)?;
+            let is_better = match best {
+                Some((best, _)) => threshold > best,
+                None => true,
+            };
+            if value >= threshold && is_better {
+                best = Some((threshold, &arg.key));
+            }
+        }
+        Ok(best.map(|(_, name)| name))
+    }
+
+    /// The theme state whose threshold the value reaches, e.g. `good` or `critical`
+    fn ramp_state(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Option<&str>> {
+        Ok(self
+            .highest_threshold(value, vars)?
+            .map(|state| match state {
+                "warn" => "warning",
+                "crit" => "critical",
+                state => state,
+            }))
+    }
+
+    /// Color the value with the foreground color of a theme state picked by thresholds
+    fn ramp(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let state = match self.ramp_state(value, vars)? {
+            Some(state) => state,
+            None => return Ok(value.clone()),
+        };
+        let color = shared_config(|c| match state {
+            "idle" => c.theme.idle_fg.clone(),
+            "info" => c.theme.info_fg.clone(),
+            "good" => c.theme.good_fg.clone(),
+            "warning" => c.theme.warning_fg.clone(),
+            _ => c.theme.critical_fg.clone(),
+        });
+        Ok(match color {
+            Some(color) => value.with_color(color),
+            None => value.clone(),
+        })
+    }
+
+    /// Pick the argument named after the plural category of the value, falling back to `other`
+    fn plural(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let rules = self.arg("rules", vars)?.unwrap_or_else(|| "en".to_string());
+        let category = plural_category(&rules, self.numeric_value(value)?).internal_error(
+            "formatter",
+            &format!(
+                "unknown plural rules '{}' in formatter '{}'",
+                rules, self.name
+            ),
+        )?;
+        let text = match self.arg(category, vars)? {
+            Some(text) => text,
+            None => self.required_arg("other", vars)?,
+        };
+        Ok(value.with_text(text))
+    }
+
+    /// Display the value followed by the word for that many, e.g. `1 message` or `3 messages`,
+    /// or just `zero` for none. With `rules` the word is picked by the plural category of the
+    /// value in that language.
+    fn tally(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let count = self.numeric_value(value)?;
+        let one = self.required_arg("one", vars)?;
+        let many = self.required_arg("many", vars)?;
+        if count == 0. {
+            if let Some(zero) = self.arg("zero", vars)? {
+                return Ok(value.with_text(zero));
+            }
+        }
+        let category = match self.arg("rules", vars)? {
+            // Negative counts, e.g. of a difference, are never singular
+            _ if count < 0. => "many",
+            Some(rules) => plural_category(&rules, count).internal_error(
+                "formatter",
+                &format!(
+                    "unknown plural rules '{}' in formatter '{}'",
+                    rules, self.name
+                ),
+            )?,
+            None if count == 1. => "one",
+            None => "many",
+        };
+        let word = match category {
+            "one" => one,
+            "few" => self.arg("few", vars)?.unwrap_or(many),
+            _ => many,
+        };
+        Ok(value.with_text(format!("{} {}", value.as_string(), word)))
+    }
+
+    /// Draw the value, from 0 to `max`, as a horizontal bar of `width` cells, or as a single
+    /// vertical cell. `glyphs` fill a cell from the emptiest to the fullest.
+    fn bar(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let max = self.f64_arg("max", vars)?.unwrap_or(100.);
+        if max <= 0. {
+            return Err(self.invalid_arg("max", vars, "a positive number"));
+        }
+        let ratio = (self.numeric_value(value)? / max).clamp(0., 1.);
+        let vertical = self.bool_arg("vertical", vars, false)?;
+        let glyphs: Vec<char> = match self.arg("glyphs", vars)? {
+            Some(glyphs) => glyphs.chars().collect(),
+            None if vertical => VERTICAL_BAR_GLYPHS.to_vec(),
+            None => BAR_GLYPHS.to_vec(),
+        };
+        if glyphs.is_empty() {
+            return Err(self.invalid_arg("glyphs", vars, "at least one character"));
+        }
+        let bar = if vertical {
+            glyphs[(ratio * (glyphs.len() - 1) as f64) as usize].to_string()
+        } else {
+            let width = self.usize_arg("width", vars)?.unwrap_or(5);
+            format_bar(ratio, width, &glyphs)
+        };
+        Ok(value.with_text(bar))
+    }
+
+    /// Draw a bar showing how far `pos` is into `len`. `pos` defaults to the value.
+    fn progress(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let pos = match self.f64_arg("pos", vars)? {
+            Some(pos) => pos,
+            None => self.numeric_value(value)?,
+        };
+        let len = self.required_f64_arg("len", vars)?;
+        let width = self.usize_arg("width", vars)?.unwrap_or(10);
+        let ratio = if len > 0. {
+            (pos / len).clamp(0., 1.)
+        } else {
+            0.
+        };
+        let filled = (ratio * width as f64).round() as usize;
+        let bar: String = (0..width)
+            .map(|i| if i < filled { '\u{2588}' } else { '\u{2591}' })
+            .collect();
+        Ok(value.with_text(bar))
+    }
+
+    /// Use the value as the name of an icon
+    fn icon_or(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let text = match shared_config(|c| c.get_icon(&value.as_string())) {
+            Ok(icon) => icon,
+            Err(_) => self.arg("fallback", vars)?.unwrap_or_default(),
+        };
+        Ok(Value::from_string(text))
+    }
+
+    fn index(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let list = split_escaped(&self.required_arg("list", vars)?, ARGS_SEPARATOR);
+        let default = self.arg("default", vars)?.unwrap_or_default();
+        let index = self.numeric_value(value)?;
+        let text = if index >= 0. {
+            list.get(index as usize).map(String::as_str)
+        } else {
+            None
+        };
+        Ok(value.with_text(text.unwrap_or(&default).to_string()))
+    }
+
+    fn json_get(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let json: serde_json::Value = serde_json::from_str(&value.as_string()).internal_error(
+            "formatter",
+            &format!("'{}' formatter requires a JSON value", self.name),
+        )?;
+        let path = self.required_arg("path", vars)?;
+        let found = path
+            .split('.')
+            .filter(|key| !key.is_empty())
+            .try_fold(&json, |json, key| match json {
+                serde_json::Value::Object(map) => map.get(key),
+                serde_json::Value::Array(list) => key.parse().ok().and_then(|i: usize| list.get(i)),
+                _ => None,
+            });
+        let text = match found {
+            None | Some(serde_json::Value::Null) => String::new(),
+            Some(serde_json::Value::String(text)) => text.clone(),
+            Some(other) => other.to_string(),
+        };
+        Ok(value.with_text(text))
+    }
+
+    /// Replace the ASCII digits with the digits of another script
+    fn localize_number(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let digits = self.required_arg("digits", vars)?;
+        let digits: Vec<char> = match DIGIT_SETS.iter().find(|(name, _)| *name == digits) {
+            Some((_, set)) => set.chars().collect(),
+            None => digits.chars().collect(),
+        };
+        if digits.len() != 10 {
+            return Err(InternalError(
+                "formatter".to_string(),
+                format!(
+                    "argument 'digits' of formatter '{}' must be the name of a digit set or ten characters",
+                    self.name
+                ),
+                None,
+            ));
+        }
+        let text = value
+            .as_string()
+            .chars()
+            .map(|c| match c.to_digit(10) {
+                Some(d) if c.is_ascii_digit() => digits[d as usize],
+                _ => c,
+            })
+            .collect();
+        Ok(value.with_text(text))
+    }
+
+    /// Replace the value with `text` while the privacy mode is on
+    fn redact(&self, value: &Value, vars: &HashMap<&str, Value>, private: bool) -> Result<Value> {
+        if !private {
+            return Ok(value.clone());
+        }
+        Ok(value.with_text(
+            self.arg("text", vars)?
+                .unwrap_or_else(|| "\u{2022}\u{2022}\u{2022}\u{2022}".to_string()),
+        ))
+    }
+
+    fn truthy_icon(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let truthy = self.numeric_value(value)? != 0.;
+        let icon = match self.arg(if truthy { "true" } else { "false" }, vars)? {
+            Some(icon) => shared_config(|c| c.get_icon(&icon))?,
+            None => String::new(),
+        };
+        Ok(Value::from_string(icon))
+    }
+
+    fn clamp(
+        &self,
+        value: &Value,
+        vars: &HashMap<&str, Value>,
+        op: fn(f64, f64) -> f64,
+    ) -> Result<Value> {
+        let bound = self.required_f64_arg("value", vars)?;
+        Ok(value.with_f64(op(self.numeric_value(value)?, bound)))
+    }
+
+    /// A color argument such as `#ff0000`, `ff0000` or `#ff000080`
+    fn color_arg(&self, key: &str, vars: &HashMap<&str, Value>) -> Result<(u8, u8, u8, u8)> {
+        let color = self.required_arg(key, vars)?;
+        let color = if color.starts_with('#') {
+            color
+        } else {
+            format!("#{}", color)
+        };
+        color_from_rgba(&color).map_err(|_| self.invalid_arg(key, vars, "a color like '#ff0000'"))
+    }
+
+    /// Color the value with `fg`, or with the color of a `gradient` like `green-red` at the
+    /// position of the (numeric) value between `min` and `max`
+    fn color(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let parse = |key: &str, color: &str| self.parse_color(key, color, vars);
+        let color = match (self.arg("fg", vars)?, self.arg("gradient", vars)?) {
+            (Some(fg), None) => parse("fg", &fg)?,
+            (None, Some(gradient)) => {
+                let (from, to) = gradient.split_once('-').ok_or_else(|| {
+                    self.invalid_arg("gradient", vars, "two colors like 'green-red'")
+                })?;
+                let min = self.f64_arg("min", vars)?.unwrap_or(0.);
+                let max = self.f64_arg("max", vars)?.unwrap_or(100.);
+                if max <= min {
+                    return Err(self.invalid_arg("max", vars, "greater than 'min'"));
+                }
+                let fraction = ((self.numeric_value(value)? - min) / (max - min)).clamp(0., 1.);
+                color_lerp(parse("gradient", from)?, parse("gradient", to)?, fraction)
+            }
+            _ => {
+                return Err(InternalError(
+                    "formatter".to_string(),
+                    "formatter 'color' requires either a color or a gradient".to_string(),
+                    None,
+                ))
+            }
+        };
+        Ok(value.with_color(color_to_rgba(color)))
+    }
+
+    /// Parse the color of argument `key`, a hex color or one of `NAMED_COLORS`
+    fn parse_color(
+        &self,
+        key: &str,
+        color: &str,
+        vars: &HashMap<&str, Value>,
+    ) -> Result<(u8, u8, u8, u8)> {
+        let color = match NAMED_COLORS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(color))
+        {
+            Some((_, hex)) => hex.to_string(),
+            None if color.starts_with('#') => color.to_string(),
+            None => format!("#{}", color),
+        };
+        color_from_rgba(&color)
+            .map_err(|_| self.invalid_arg(key, vars, "a color like '#ff0000' or 'red'"))
+    }
+
+    /// Color the parts of the text matching `pattern`, which is literal text unless `regex` is set.
+    /// The pattern is matched against the text with its entities decoded, e.g. `&amp;` as `&`.
+    fn highlight(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let pattern = self.required_arg("pattern", vars)?;
+        let color = self.parse_color("color", &self.required_arg("color", vars)?, vars)?;
+        let regex = if self.bool_arg("regex", vars, false)? {
+            Regex::new(&pattern)
+                .map_err(|_| self.invalid_arg("pattern", vars, "a regular expression"))?
+        } else {
+            Regex::new(&regex::escape(&pattern))
+                .internal_error("formatter", "escaped pattern is a valid regular expression")?
+        };
+        let text = unescape_pango_text(&value.as_string());
+        let mut highlighted = String::new();
+        let mut end = 0;
+        // Empty matches, e.g. of `x*`, are not wrapped
+        for matched in regex.find_iter(&text).filter(|m| !m.as_str().is_empty()) {
+            highlighted.push_str(&escape_pango_text(text[end..matched.start()].to_string()));
+            highlighted.push_str(&format!(
+                "<span foreground='{}'>{}</span>",
+                color_to_rgba(color),
+                escape_pango_text(matched.as_str().to_string())
+            ));
+            end = matched.end();
+        }
+        highlighted.push_str(&escape_pango_text(text[end..].to_string()));
+        Ok(value.with_text(highlighted))
+    }
+
+    /// Wrap the value in a Pango span with the given font attributes
+    fn style(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let mut attributes = String::new();
+        for (key, keywords) in STYLE_ATTRIBUTES {
+            let val = match self.arg(key, vars)? {
+                Some(val) => val,
+                None => continue,
+            };
+            // Weights and sizes (in 1024ths of a point) may also be numbers
+            let numeric = match *key {
+                "weight" => matches!(val.parse::<u32>(), Ok(100..=1000)),
+                "size" => matches!(val.parse::<u32>(), Ok(size) if size > 0),
+                _ => false,
+            };
+            if !numeric && !keywords.contains(&val.as_str()) {
+                let mut expected = format!("one of {}", keywords.join(", "));
+                match *key {
+                    "weight" => expected.push_str(" or a number from 100 to 1000"),
+                    "size" => expected.push_str(" or a positive number"),
+                    _ => (),
+                }
+                return Err(self.invalid_arg(key, vars, &expected));
+            }
+            attributes.push_str(&format!(" {}='{}'", key, val));
+        }
+        if attributes.is_empty() {
+            return Ok(value.clone());
+        }
+        Ok(value.with_text(format!("<span{}>{}</span>", attributes, value.as_string())))
+    }
+
+    /// Color the value with a color between `from` and `to`, picked by a fraction between 0 and 1
+    fn colorlerp(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let from = self.color_arg("from", vars)?;
+        let to = self.color_arg("to", vars)?;
+        let fraction = self.numeric_value(value)?.clamp(0., 1.);
+        Ok(value.with_color(color_to_rgba(color_lerp(from, to, fraction))))
+    }
+
+    /// Map a fraction between 0 and 1 linearly to the range `from..to`
+    fn lerp(&self, value: &Value, vars: &HashMap<&str, Value>) -> Result<Value> {
+        let from = self.required_f64_arg("from", vars)?;
+        let to = self.required_f64_arg("to", vars)?;
+        let places = self.usize_arg("places", vars)?.unwrap_or(0);
+        let fraction = self.numeric_value(value)?.clamp(0., 1.);
+        let result = from + (to - from) * fraction;
+        Ok(value.with_text(format!("{:.*}", places, result)))
+    }
+}
+
+/// The CLDR plural category of a number in a language, e.g. `few` for 3 in Russian. Returns
+/// `None` for unknown languages.
+fn plural_category(rules: &str, number: f64) -> Option<&'static str> {
+    let n = number.abs();
+    let integer = n.fract() == 0.;
+    let i = n.trunc() as u64;
+    let (mod10, mod100) = (i % 10, i % 100);
+    let few = integer && (2..=4).contains(&mod10) && !(12..=14).contains(&mod100);
+    Some(match rules {
+        "en" if n == 1. => "one",
+        "en" => "other",
+        "fr" if i <= 1 => "one",
+        "fr" => "other",
+        "pl" | "ru" | "uk" if !integer => "other",
+        "pl" if i == 1 => "one",
+        "ru" | "uk" if mod10 == 1 && mod100 != 11 => "one",
+        "pl" | "ru" | "uk" if few => "few",
+        "pl" | "ru" | "uk" => "many",
+        _ => return None,
+    })
+}
+
+/// Interpolate between two sRGB colors in linear RGB, so that e.g. the midpoint of green and red
+/// is as bright as they are
+fn color_lerp(from: (u8, u8, u8, u8), to: (u8, u8, u8, u8), fraction: f64) -> (u8, u8, u8, u8) {
+    let to_linear = |c: u8| {
+        let c = c as f64 / 255.;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let to_srgb = |l: f64| {
+        let c = if l <= 0.0031308 {
+            l * 12.92
+        } else {
+            1.055 * l.powf(1. / 2.4) - 0.055
+        };
+        (c * 255.).round() as u8
+    };
+    let channel = |a: u8, b: u8| to_srgb(to_linear(a) + (to_linear(b) - to_linear(a)) * fraction);
+    // Alpha is linear already
+    let alpha = (from.3 as f64 + (to.3 as f64 - from.3 as f64) * fraction).round() as u8;
+    (
+        channel(from.0, to.0),
+        channel(from.1, to.1),
+        channel(from.2, to.2),
+        alpha,
+    )
+}
+
+/// Parse a locale like `de_DE`, or `C` and `POSIX` for none. Returns `None` for unknown locales.
+fn parse_locale(name: &str) -> Option<Option<Locale>> {
+    match name {
+        "C" | "POSIX" => Some(None),
+        name => name.try_into().ok().map(Some),
+    }
+}
+
+/// The locale for dates and times of the environment, from the first of `LC_ALL`, `LC_TIME` and
+/// `LANG` that is set, e.g. `de_DE.UTF-8`. Falls back to none, i.e. `C`.
+fn env_locale(var: impl Fn(&str) -> Option<String>) -> Option<Locale> {
+    let value = ["LC_ALL", "LC_TIME", "LANG"]
+        .iter()
+        .filter_map(|name| var(name))
+        .find(|value| !value.is_empty())?;
+    // Without the encoding and the modifier, e.g. `.UTF-8` or `@euro`
+    let name = value.split(&['.', '@'][..]).next().unwrap_or_default();
+    parse_locale(name).flatten()
+}
+
+fn format_datetime<Z: TimeZone>(
+    time: DateTime<Z>,
+    format: &str,
+    locale: Option<Locale>,
+) -> std::result::Result<String, fmt::Error>
+where
+    Z::Offset: fmt::Display,
+{
+    let mut text = String::new();
+    match locale {
+        Some(locale) => write!(text, "{}", time.format_localized(format, locale))?,
+        None => write!(text, "{}", time.format(format))?,
+    }
+    Ok(text)
+}
+
+/// The theme state of a name, e.g. `good`, or `warn` and `crit` for short
+fn state_from_name(name: &str) -> Option<State> {
+    match name {
+        "idle" => Some(State::Idle),
+        "info" => Some(State::Info),
+        "good" => Some(State::Good),
+        "warning" | "warn" => Some(State::Warning),
+        "critical" | "crit" => Some(State::Critical),
+        _ => None,
+    }
+}
+
+/// The colors `color` accepts by name, as Pango defines them
+const NAMED_COLORS: &[(&str, &str)] = &[
+    ("black", "#000000"),
+    ("blue", "#0000ff"),
+    ("cyan", "#00ffff"),
+    ("gray", "#bebebe"),
+    ("green", "#00ff00"),
+    ("grey", "#bebebe"),
+    ("magenta", "#ff00ff"),
+    ("orange", "#ffa500"),
+    ("red", "#ff0000"),
+    ("white", "#ffffff"),
+    ("yellow", "#ffff00"),
+];
+
+/// The Pango attributes `style` accepts, with their values other than numbers
+const STYLE_ATTRIBUTES: &[(&str, &[&str])] = &[
+    (
+        "weight",
+        &[
+            "ultralight",
+            "light",
+            "normal",
+            "bold",
+            "ultrabold",
+            "heavy",
+        ],
+    ),
+    ("style", &["normal", "oblique", "italic"]),
+    ("underline", &["none", "single", "double", "low", "error"]),
+    ("strikethrough", &["true", "false"]),
+    (
+        "size",
+        &[
+            "xx-small", "x-small", "small", "medium", "large", "x-large", "xx-large", "smaller",
+            "larger",
+        ],
+    ),
+    ("variant", &["normal", "smallcaps"]),
+];
+
+/// Glyphs filling a single cell from the bottom by eighths
+const VERTICAL_BAR_GLYPHS: &[char] = &[
+    ' ', '\u{2581}', '\u{2582}', '\u{2583}', '\u{2584}', '\u{2585}', '\u{2586}', '\u{2587}',
+    '\u{2588}',
+];
+
+/// Named digit sets for the `localize_number` formatter
+const DIGIT_SETS: &[(&str, &str)] = &[
+    ("arabic", "٠١٢٣٤٥٦٧٨٩"),
+    ("bengali", "০১২৩৪৫৬৭৮৯"),
+    ("devanagari", "०१२३४५६७८९"),
+    ("fullwidth", "０１２３４５６７８９"),
+    ("persian", "۰۱۲۳۴۵۶۷۸۹"),
+    ("thai", "๐๑๒๓๔๕๖๗๘๙"),
+];
+
+/// Units and their symbols for the `abbr_unit` formatter. Plurals ending in `s` are found too.
+const UNIT_ABBREVIATIONS: &[(&str, &str)] = &[
+    ("bit", "b"),
+    ("byte", "B"),
+    ("kilobyte", "KB"),
+    ("megabyte", "MB"),
+    ("gigabyte", "GB"),
+    ("terabyte", "TB"),
+    ("kibibyte", "KiB"),
+    ("mebibyte", "MiB"),
+    ("gibibyte", "GiB"),
+    ("tebibyte", "TiB"),
+    ("millisecond", "ms"),
+    ("second", "s"),
+    ("minute", "min"),
+    ("hour", "h"),
+    ("day", "d"),
+    ("percent", "%"),
+    ("degree", "°"),
+    ("watt", "W"),
+    ("kilowatt", "kW"),
+    ("volt", "V"),
+    ("ampere", "A"),
+    ("hertz", "Hz"),
+    ("kilohertz", "kHz"),
+    ("megahertz", "MHz"),
+    ("gigahertz", "GHz"),
+];
+
+/// Replace the words of `text` found in `abbreviations`, which must be lowercase. Words are
+/// matched regardless of case, and a plural ending in `s` also matches the singular.
+fn abbreviate_units(text: &str, abbreviations: &[(String, String)]) -> String {
+    let abbreviation = |word: &str| {
+        let word = word.to_lowercase();
+        let singular = word.strip_suffix('s');
+        abbreviations
+            .iter()
+            .find(|(unit, _)| *unit == word || Some(unit.as_str()) == singular)
+            .map(|(_, abbreviation)| abbreviation.as_str())
+    };
+    let mut abbreviated = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(char::is_alphabetic) {
+        abbreviated.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let len = rest
+            .find(|c: char| !c.is_alphabetic())
+            .unwrap_or(rest.len());
+        let (word, after) = rest.split_at(len);
+        abbreviated.push_str(abbreviation(word).unwrap_or(word));
+        rest = after;
+    }
+    abbreviated.push_str(rest);
+    abbreviated
+}
+
+/// The time elapsed in its most significant unit, e.g. `5m`. Less than a minute is `now`.
+fn ago(seconds: i64) -> String {
+    match seconds {
+        s if s < 60 => "now".to_string(),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86400 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86400),
+    }
+}
+
+/// Whether a schedule matches `now`. A schedule is an hour (`h9`), a range of hours (`h9_17`,
+/// excluding the last hour), a weekday (`mon`) or a range of weekdays (`mon_fri`, including the
+/// last day). Ranges may wrap around, e.g. `h22_6` or `sat_mon`. Returns `None` if the schedule
+/// is invalid.
+fn schedule_matches(schedule: &str, now: NaiveDateTime) -> Option<bool> {
+    let (from, to) = match schedule.split_once('_') {
+        Some((from, to)) => (from, Some(to)),
+        None => (schedule, None),
+    };
+    if let Some(from) = from.strip_prefix('h') {
+        let from: u32 = from.parse().ok().filter(|hour| *hour < 24)?;
+        Some(match to {
+            None => now.hour() == from,
+            Some(to) => {
+                let to: u32 = to.parse().ok().filter(|hour| *hour <= 24)?;
+                in_cyclic_range(now.hour(), from, to)
+            }
+        })
+    } else {
+        let day = |name: &str| {
+            name.parse::<Weekday>()
+                .ok()
+                .map(|day| day.num_days_from_monday())
+        };
+        let today = now.weekday().num_days_from_monday();
+        let from = day(from)?;
+        Some(match to {
+            None => today == from,
+            Some(to) => in_cyclic_range(today, from, day(to)? + 1),
+        })
+    }
+}
+
+/// Whether `x` is in `from..to`, wrapping around if `to` is not after `from`
+fn in_cyclic_range(x: u32, from: u32, to: u32) -> bool {
+    if from < to {
+        from <= x && x < to
+    } else {
+        x >= from || x < to
+    }
+}
+
+struct DurationUnit {
+    /// The symbol, followed by the other names
+    names: &'static [&'static str],
+    millis: u64,
+    /// The number of digits of the unit if it follows a larger one, e.g. two for `07m`
+    digits: usize,
+}
+
+/// The units of `dur`, from the largest to the smallest
+const DURATION_UNITS: &[DurationUnit] = &[
+    DurationUnit {
+        names: &["d", "day", "days"],
+        millis: 86_400_000,
+        digits: 1,
+    },
+    DurationUnit {
+        names: &["h", "hour", "hours"],
+        millis: 3_600_000,
+        digits: 2,
+    },
+    DurationUnit {
+        names: &["m", "min", "minute", "minutes"],
+        millis: 60_000,
+        digits: 2,
+    },
+    DurationUnit {
+        names: &["s", "sec", "second", "seconds"],
+        millis: 1000,
+        digits: 2,
+    },
+    DurationUnit {
+        names: &["ms", "millisecond", "milliseconds"],
+        millis: 1,
+        digits: 3,
+    },
+];
+
+/// Write a duration with up to `max_units` of `units`, starting with the largest unit it reaches.
+/// The first unit holds everything larger too, e.g. `26h` without days, and the rest of the
+/// smallest unit shown is cut off.
+fn format_duration(millis: u64, units: &[DurationUnit], max_units: usize) -> String {
+    let first = units
+        .iter()
+        .position(|unit| millis >= unit.millis)
+        .unwrap_or(units.len() - 1);
+    let mut rest = millis;
+    let mut parts = Vec::new();
+    for (i, unit) in units[first..].iter().take(max_units).enumerate() {
+        let count = rest / unit.millis;
+        rest -= count * unit.millis;
+        let digits = if i == 0 { 1 } else { unit.digits };
+        parts.push(format!(
+            "{:0digits$}{}",
+            count,
+            unit.names[0],
+            digits = digits
+        ));
+    }
+    parts.join(" ")
+}
+
+/// Write a duration in seconds like a clock, e.g. `1:02:03`, or `2:03` below an hour
+fn format_hms(seconds: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+/// A signed byte count with a binary prefix, e.g. `+1.2 MiB` or `-300 KiB`
+fn delta_bytes(bytes: i64) -> String {
+    const PREFIXES: &[&str] = &["", "Ki", "Mi", "Gi", "Ti", "Pi", "Ei"];
+    let sign = match bytes {
+        b if b > 0 => "+",
+        b if b < 0 => "-",
+        _ => "",
+    };
+    let mut magnitude = bytes.unsigned_abs() as f64;
+    let mut prefix = 0;
+    while magnitude >= 1024. && prefix < PREFIXES.len() - 1 {
+        magnitude /= 1024.;
+        prefix += 1;
+    }
+    if prefix == 0 || magnitude >= 10. {
+        format!("{}{:.0} {}B", sign, magnitude, PREFIXES[prefix])
+    } else {
+        format!("{}{:.1} {}B", sign, magnitude, PREFIXES[prefix])
+    }
+}
+
+/// Abbreviate a number of at least a thousand with a suffix, e.g. `12.3K` or `4.5M`. Returns
+/// `None` for smaller numbers.
+fn compact(number: f64, places: usize) -> Option<String> {
+    const SUFFIXES: &[&str] = &["K", "M", "B", "T"];
+    let rounded = |x: f64| {
+        let factor = 10f64.powi(places as i32);
+        (x * factor).round() / factor
+    };
+    let mut magnitude = number.abs();
+    let mut suffix = 0;
+    // Rounding may reach the next suffix, e.g. 999,950 is `1.0M` rather than `1000.0K`
+    while rounded(magnitude) >= 1000. && suffix < SUFFIXES.len() {
+        magnitude /= 1000.;
+        suffix += 1;
+    }
+    if suffix == 0 {
+        return None;
+    }
+    let sign = if number < 0. { "-" } else { "" };
+    Some(format!(
+        "{}{:.*}{}",
+        sign,
+        places,
+        magnitude,
+        SUFFIXES[suffix - 1]
+    ))
+}
+
+/// The grapheme clusters of `text` with their byte offsets, except that Pango entities such as
+/// `&amp;` are kept together, since they are displayed as one character
+fn graphemes(text: &str) -> Vec<(usize, &str)> {
+    let mut graphemes = Vec::new();
+    let mut entity_end = 0;
+    for (i, grapheme) in text.grapheme_indices(true) {
+        if i < entity_end {
+            continue;
+        }
+        let entity_len = match grapheme {
+            "&" => text[i + 1..]
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '#')
+                .filter(|&len| (1..=10).contains(&len) && text[i + 1 + len..].starts_with(';'))
+                .map(|len| len + 2),
+            _ => None,
+        };
+        match entity_len {
+            Some(len) => {
+                entity_end = i + len;
+                graphemes.push((i, &text[i..entity_end]));
+            }
+            None => graphemes.push((i, grapheme)),
+        }
+    }
+    graphemes
+}
+
+/// The number of columns a grapheme cluster takes, i.e. what is displayed as one character,
+/// such as a letter with combining accents, an emoji sequence or a Pango entity. Each takes one
+/// column, or with `east_asian` as many as the East Asian Width property says, e.g. two for CJK
+/// characters.
+fn grapheme_width(grapheme: &str, east_asian: bool) -> usize {
+    if east_asian && !grapheme.starts_with('&') {
+        // The widths of the parts of emoji sequences add up, but they are displayed as one
+        grapheme.width().min(2)
+    } else {
+        1
+    }
+}
+
+/// The number of columns of `text`, see `grapheme_width`
+fn text_width(text: &str, east_asian: bool) -> usize {
+    graphemes(text)
+        .into_iter()
+        .map(|(_, grapheme)| grapheme_width(grapheme, east_asian))
+        .sum()
+}
+
+/// The longest start of `text` that fits into `max_width` columns
+fn take_width(text: &str, max_width: usize, east_asian: bool) -> &str {
+    let mut width = 0;
+    for (i, grapheme) in graphemes(text) {
+        width += grapheme_width(grapheme, east_asian);
+        if width > max_width {
+            return &text[..i];
+        }
+    }
+    text
+}
+
+/// Cut `text` off after `max_width` columns, replacing its end with `ellipsis` so that the result
+/// still fits into `max_width` columns. Text which fits is left as it is. Grapheme clusters are
+/// never split.
+fn truncate(text: &str, max_width: usize, ellipsis: &str, east_asian: bool) -> String {
+    if text_width(text, east_asian) <= max_width {
+        return text.to_string();
+    }
+    let ellipsis = take_width(ellipsis, max_width, east_asian);
+    let kept = max_width - text_width(ellipsis, east_asian);
+    format!("{}{}", take_width(text, kept, east_asian), ellipsis)
+}
+
+/// Shorten `text` to `max_width` columns by replacing its middle with `ellipsis`, keeping its
+/// start and end, e.g. `/home/…/file.txt`. The start gets the extra column if there is one.
+fn elide_middle(text: &str, max_width: usize, ellipsis: &str) -> String {
+    if text_width(text, false) <= max_width {
+        return text.to_string();
+    }
+    let ellipsis = take_width(ellipsis, max_width, false);
+    let kept = max_width - text_width(ellipsis, false);
+    let head = take_width(text, kept - kept / 2, false);
+    let mut tail_width = 0;
+    let mut tail_start = text.len();
+    for (i, grapheme) in graphemes(text).into_iter().rev() {
+        tail_width += grapheme_width(grapheme, false);
+        if tail_width > kept / 2 {
+            break;
+        }
+        tail_start = i;
+    }
+    format!("{}{}{}", head, ellipsis, &text[tail_start..])
+}
+
+/// The `width` characters of `text` starting at `offset`, as if `text` followed by `sep` was
+/// repeated endlessly. Text which fits into `width` is left as it is.
+fn marquee(text: &str, width: usize, offset: usize, sep: &str) -> String {
+    if text_width(text, false) <= width {
+        return text.to_string();
+    }
+    let cycle: Vec<&str> = graphemes(text)
+        .into_iter()
+        .chain(graphemes(sep))
+        .map(|(_, grapheme)| grapheme)
+        .collect();
+    cycle
+        .iter()
+        .cycle()
+        .skip(offset % cycle.len())
+        .take(width)
+        .copied()
+        .collect()
+}
+
+/// Format a number with as many decimal places as fit into `width` characters. Numbers too
+/// large for `width` are written without decimals.
+fn fit(number: f64, width: usize) -> String {
+    (1..width)
+        .rev()
+        .map(|places| format!("{:.*}", places, number))
+        .find(|text| text.len() <= width)
+        .unwrap_or_else(|| format!("{:.0}", number))
+}
+
+/// The first number in `text`, with its sign and decimals, e.g. `-45.2` in `temp: -45.2 C`
+fn first_number(text: &str) -> Option<&str> {
+    let bytes = text.as_bytes();
+    let digits = |from: usize| {
+        bytes[from..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count()
+    };
+    let mut start = bytes.iter().position(u8::is_ascii_digit)?;
+    let mut end = start + digits(start);
+    if bytes.get(end) == Some(&b'.') && digits(end + 1) > 0 {
+        end += 1 + digits(end + 1);
+    } else if start > 0 && bytes[start - 1] == b'.' {
+        // E.g. `.5`
+        start -= 1;
+    }
+    if start > 0 && matches!(bytes[start - 1], b'-' | b'+') {
+        start -= 1;
+    }
+    Some(&text[start..end])
+}
+
+/// Split a string into lowercase words on whitespace, underscores and hyphens
+fn words(s: &str) -> Vec<String> {
+    s.split(|c: char| c.is_whitespace() || c == '_' || c == '-')
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn camel_case(s: &str) -> String {
+    let mut words = words(s).into_iter();
+    let mut camel = words.next().unwrap_or_default();
+    for word in words {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            camel.extend(first.to_uppercase());
+            camel.push_str(chars.as_str());
+        }
+    }
+    camel
+}
+
+/// Split a CSV record into its fields. Fields may be quoted with `"`, in which case they can
+/// contain the separator, and `""` stands for a literal quote.
+fn csv_fields(record: &str, sep: char) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = record
+        .trim_end_matches(&['\r', '\n'][..])
+        .chars()
+        .peekable();
+    while let Some(c) = chars.next() {
+        let field = fields.last_mut().unwrap();
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            c if c == sep && !quoted => fields.push(String::new()),
+            c => field.push(c),
+        }
+    }
+    fields
+}
+
+/// Remove ANSI escape sequences, such as colors and cursor movements
+fn strip_ansi(s: &str) -> String {
+    const ESC: char = '\u{1b}';
+    const BEL: char = '\u{7}';
+    let mut stripped = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != ESC {
+            stripped.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI: parameters and intermediates, terminated by a byte in '@'..='~'
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: terminated by BEL or ESC '\'
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == BEL {
+                        break;
+                    }
+                    if c == ESC && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // Two character sequences, e.g. ESC '7' (save cursor)
+            _ => {}
+        }
+    }
+    stripped
+}
+
+/// Split a string on `sep`, unless it is escaped with a backslash.
+fn split_escaped(s: &str, sep: char) -> Vec<String> {
+    let mut items = vec![String::new()];
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ESCAPE => match chars.next() {
+                Some(next) if next == sep || next == ESCAPE => items.last_mut().unwrap().push(next),
+                Some(next) => {
+                    items.last_mut().unwrap().push(ESCAPE);
+                    items.last_mut().unwrap().push(next);
+                }
+                None => items.last_mut().unwrap().push(ESCAPE),
+            },
+            c if c == sep => items.push(String::new()),
+            c => items.last_mut().unwrap().push(c),
+        }
+    }
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SharedConfig;
+    use crate::formatting::{with_shared_config, FormatTemplate};
+
+    fn formatter(s: &str) -> Formatter {
+        let (formatter, rest) = Formatter::parse(s).unwrap();
+        assert!(rest.is_empty());
+        formatter
+    }
+
+    fn render(formatter: &Formatter, value: Value) -> String {
+        render_with(formatter, value, &HashMap::new())
+    }
+
+    fn render_with(formatter: &Formatter, value: Value, vars: &HashMap<&str, Value>) -> String {
+        let value = formatter.apply(&value, vars).unwrap();
+        value.format(&"x".parse().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        let (f, rest) = Formatter::parse("index(list:'a,b', default: x ):3").unwrap();
+        assert_eq!(f.name, "index");
+        assert_eq!(f.args[0].val, ArgValue::Literal("a,b".to_string()));
+        assert_eq!(f.args[1].val, ArgValue::Literal("x".to_string()));
+        assert_eq!(rest, ":3");
+
+        assert!(Formatter::parse("index").is_err());
+        assert!(Formatter::parse("index(list:'a,b)").is_err());
+
+        let (f, rest) = Formatter::parse(r#"index(list:"it's,b", default: "x" ):3"#).unwrap();
+        assert_eq!(f.args[0].val, ArgValue::Literal("it's,b".to_string()));
+        assert_eq!(f.args[1].val, ArgValue::Literal("x".to_string()));
+        assert_eq!(rest, ":3");
+        let f = formatter(r#"cap(9,suffix:'"more"')"#);
+        assert_eq!(f.args[1].val, ArgValue::Literal(r#""more""#.to_string()));
+        let f = formatter(r#"index(list:'',default:"")"#);
+        assert_eq!(f.args[0].val, ArgValue::Literal(String::new()));
+        assert_eq!(f.args[1].val, ArgValue::Literal(String::new()));
+        assert!(Formatter::parse(r#"index(list:"a,b)"#).is_err());
+        assert!(Formatter::parse(r#"index(list:"a,b')"#).is_err());
+        assert!(Formatter::parse("index(foo:1)").is_err());
+        assert!(Formatter::parse("no_such_formatter()").is_err());
+    }
+
+    #[test]
+    fn parse_unicode_whitespace() {
+        // No-break, em and ideographic spaces separate arguments like ASCII spaces
+        let (f, rest) =
+            Formatter::parse("index(\u{a0}list\u{a0}:\u{2003}'a,b',\u{3000}default:\u{a0}x\u{a0})")
+                .unwrap();
+        assert_eq!(f.args[0].key, "list");
+        assert_eq!(f.args[0].val, ArgValue::Literal("a,b".to_string()));
+        assert_eq!(f.args[1].key, "default");
+        assert_eq!(f.args[1].val, ArgValue::Literal("x".to_string()));
+        assert!(rest.is_empty());
+
+        let f = formatter("cap(\u{a0}9,\u{a0}suffix:'\u{a0}more\u{a0}')");
+        assert_eq!(
+            f.args[1].val,
+            ArgValue::Literal("\u{a0}more\u{a0}".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_escapes() {
+        let val = |s: &str| formatter(s).args[0].val.clone();
+        let literal = |s: &str| ArgValue::Literal(s.to_string());
+        assert_eq!(val(r"icon_or('it\'s here')"), literal("it's here"));
+        assert_eq!(val(r#"icon_or("say \"hi\"")"#), literal(r#"say "hi""#));
+        assert_eq!(val(r"icon_or('C:\\dir\\')"), literal(r"C:\dir\"));
+        assert_eq!(val(r"icon_or('a\nb')"), literal("a\nb"));
+        // Other escapes are kept for the formatters, e.g. the escaped commas of lists
+        assert_eq!(val(r"index(list:'a\,b,c')"), literal(r"a\,b,c"));
+        assert_eq!(val(r"icon_or(a\'b)"), literal(r"a\'b"));
+
+        assert!(Formatter::parse(r"icon_or('abc\')").is_err());
+        assert!(Formatter::parse(r"icon_or('abc\").is_err());
+    }
+
+    #[test]
+    fn index_in_range() {
+        let f = formatter("index(list:'low,med,high')");
+        assert_eq!(render(&f, Value::from_integer(0)), "low");
+        assert_eq!(render(&f, Value::from_integer(2)), "high");
+        assert_eq!(render(&f, Value::from_float(1.0)), "med");
+    }
+
+    #[test]
+    fn index_out_of_range() {
+        let f = formatter("index(list:'low,med,high',default:'n/a')");
+        assert_eq!(render(&f, Value::from_integer(3)), "n/a");
+        assert_eq!(render(&f, Value::from_integer(-1)), "n/a");
+        let f = formatter("index(list:'low,med,high')");
+        assert_eq!(render(&f, Value::from_integer(5)), "");
+    }
+
+    #[test]
+    fn index_escaped_comma() {
+        let f = formatter(r"index(list:'a\,b,c')");
+        assert_eq!(render(&f, Value::from_integer(0)), "a,b");
+        assert_eq!(render(&f, Value::from_integer(1)), "c");
+    }
+
+    #[test]
+    fn json_get() {
+        let data = || {
+            Value::from_string(
+                r#"{"a": {"b": [{"name": "first"}, 42]}, "flag": true, "none": null}"#.to_string(),
+            )
+        };
+        assert_eq!(
+            render(&formatter("json_get(path:'a.b.0.name')"), data()),
+            "first"
+        );
+        assert_eq!(render(&formatter("json_get(path:'a.b.1')"), data()), "42");
+        assert_eq!(render(&formatter("json_get(flag)"), data()), "true");
+        assert_eq!(render(&formatter("json_get(path:'a.b.1.c')"), data()), "");
+        assert_eq!(render(&formatter("json_get(path:'a.b.5')"), data()), "");
+        assert_eq!(render(&formatter("json_get(path:'a.x')"), data()), "");
+        assert_eq!(render(&formatter("json_get(path:none)"), data()), "");
+        assert!(formatter("json_get(path:a)")
+            .apply(&Value::from_string("not json".to_string()), &HashMap::new())
+            .is_err());
+    }
+
+    #[test]
+    fn max_floors() {
+        let f = formatter("max(0)");
+        assert_eq!(render(&f, Value::from_integer(-5)), " 0");
+        assert_eq!(render(&f, Value::from_integer(7)), " 7");
+        assert_eq!(render(&f, Value::from_float(-0.5)), "0.0");
+    }
+
+    #[test]
+    fn min_caps() {
+        let f = formatter("min(value:100)");
+        assert_eq!(render(&f, Value::from_integer(150)), "100");
+        assert_eq!(render(&f, Value::from_integer(42)), "42");
+
+        let f = formatter("min($cap)");
+        assert_eq!(f.args[0].val, ArgValue::Var("cap".to_string()));
+        let vars = map!("cap" => Value::from_integer(10));
+        assert_eq!(render_with(&f, Value::from_integer(15), &vars), "10");
+        assert_eq!(render_with(&f, Value::from_integer(5), &vars), " 5");
+        assert!(f.apply(&Value::from_integer(5), &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn cap() {
+        let f = formatter("cap(max:99,suffix:'+')");
+        assert_eq!(render(&f, Value::from_integer(98)), "98");
+        assert_eq!(render(&f, Value::from_integer(99)), "99");
+        assert_eq!(render(&f, Value::from_integer(100)), "99+");
+        assert_eq!(render(&f, Value::from_integer(12345)), "99+");
+        assert_eq!(render(&f, Value::from_string("250".to_string())), "99+");
+
+        let f = formatter("cap(9, suffix:' more')");
+        assert_eq!(render(&f, Value::from_integer(3)), " 3");
+        assert_eq!(render(&f, Value::from_integer(10)), "9 more");
+
+        assert!(formatter("cap()")
+            .apply(&Value::from_integer(1), &HashMap::new())
+            .is_err());
+    }
+
+    #[test]
+    fn clock() {
+        let f = formatter("clock()");
+        assert_eq!(render(&f, Value::from_integer(0)), "00:00:00");
+        assert_eq!(render(&f, Value::from_integer(754)), "00:12:34");
+        assert_eq!(render(&f, Value::from_integer(5025)), "01:23:45");
+        assert_eq!(render(&f, Value::from_float(180000.5)), "50:00:00");
+
+        let f = formatter("clock(drop_hours:true)");
+        assert_eq!(render(&f, Value::from_integer(754)), "12:34");
+        assert_eq!(render(&f, Value::from_integer(5025)), "01:23:45");
+        assert!(Formatter::parse("clock(drop_hours:maybe)")
+            .unwrap()
+            .0
+            .apply(&Value::from_integer(1), &HashMap::new())
+            .is_err());
+    }
+
+    #[test]
+    fn truthy_icon() {
+        let f = formatter("truthy_icon(true:bell,false:bell-slash)");
+        assert_eq!(render(&f, Value::from_integer(0)), " OFF ");
+        assert_eq!(render(&f, Value::from_float(0.0)), " OFF ");
+        assert_eq!(render(&f, Value::from_integer(3)), " ON ");
+        assert_eq!(render(&f, Value::from_integer(-2)), " ON ");
+
+        let f = formatter("truthy_icon(true:bell)");
+        assert_eq!(render(&f, Value::from_integer(0)), "");
+
+        let f = formatter("truthy_icon(true:no_such_icon)");
+        assert!(f.apply(&Value::from_integer(1), &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn grade() {
+        let f = formatter("grade(a:90,b:80,c:70,f:0)");
+        assert_eq!(render(&f, Value::from_integer(100)), "a");
+        assert_eq!(render(&f, Value::from_integer(90)), "a");
+        assert_eq!(render(&f, Value::from_float(89.9)), "b");
+        assert_eq!(render(&f, Value::from_integer(80)), "b");
+        assert_eq!(render(&f, Value::from_integer(70)), "c");
+        assert_eq!(render(&f, Value::from_integer(0)), "f");
+        assert_eq!(render(&f, Value::from_integer(-1)), "");
+
+        // The order of the arguments doesn't matter
+        let f = formatter("grade(low:0,high:$limit)");
+        let vars = map!("limit" => Value::from_integer(50));
+        assert_eq!(render_with(&f, Value::from_integer(60), &vars), "high");
+        assert_eq!(render_with(&f, Value::from_integer(40), &vars), "low");
+
+        // Labels are format strings that can show icons, colors and other placeholders
+        let f = formatter(
+            "grade(a:90,f:0,a_label:\"<span foreground='#00ff00'>{icon} A</span>\",f_label:'{x} F')",
+        );
+        let vars = map!(
+            "icon" => Value::from_string("\u{f00c}".to_string()),
+            "x" => Value::from_integer(12),
+        );
+        assert_eq!(
+            render_with(&f, Value::from_integer(95), &vars),
+            "<span foreground='#00ff00'>\u{f00c} A</span>"
+        );
+        assert_eq!(render_with(&f, Value::from_integer(12), &vars), "12 F");
+        assert!(formatter("grade(a:90,a_label:'{missing}')")
+            .apply(&Value::from_integer(95), &HashMap::new())
+            .is_err());
+
+        assert!(Formatter::parse("grade(90)").is_err());
+        assert!(formatter("grade(a:x)")
+            .apply(&Value::from_integer(1), &HashMap::new())
+            .is_err());
+    }
+
+    #[test]
+    fn abbr_unit() {
+        let text = |s: &str| Value::from_string(s.to_string());
+        let f = formatter("abbr_unit()");
+        assert_eq!(
+            render(&f, text("3 Gigabytes free, 1 minute left")),
+            "3 GB free, 1 min left"
+        );
+        assert_eq!(render(&f, text("90 seconds (12 percent)")), "90 s (12 %)");
+        // Unknown words and words merely containing a unit are left alone
+        assert_eq!(
+            render(&f, text("4 parsecs of bytesize")),
+            "4 parsecs of bytesize"
+        );
+
+        let f = formatter("abbr_unit(minutes:'m',fortnight:'ftn',parsec:$pc)");
+        let vars = map!("pc" => text("pc"));
+        assert_eq!(
+            render_with(
+                &f,
+                text("5 minutes, 2 fortnights, 3 parsecs, 1 hour"),
+                &vars
+            ),
+            "5 m, 2 ftn, 3 pc, 1 h"
+        );
+    }
+
+    #[test]
+    fn str() {
+        let text = |s: &str| Value::from_string(s.to_string());
+        let f = formatter("str(max_w:5,ellipsis:true)");
+        // Exact fit
+        assert_eq!(render(&f, text("hello")), "hello");
+        assert_eq!(render(&f, text("hello world")), "hell…");
+        // Characters are never split, however many bytes they take
+        assert_eq!(render(&f, text("ÄÖÜäöü")), "ÄÖÜä…");
+        assert_eq!(render(&f, text("日本語のテキスト")), "日本語の…");
+        assert_eq!(render(&f, text("🙂🙂🙂🙂🙂🙂")), "🙂🙂🙂🙂…");
+
+        let f = formatter("str(max_w:6,ellipsis:'...')");
+        assert_eq!(render(&f, text("hello world")), "hel...");
+        // The ellipsis itself is cut off if there isn't room for it
+        let f = formatter("str(max_w:2,ellipsis:'...')");
+        assert_eq!(render(&f, text("hello")), "..");
+        let f = formatter("str(max_w:0,ellipsis:true)");
+        assert_eq!(render(&f, text("hello")), "");
+
+        // Without an ellipsis the text is just cut off
+        assert_eq!(render(&formatter("str(3)"), text("hello")), "hel");
+        assert_eq!(
+            render(&formatter("str(3,ellipsis:false)"), text("hello")),
+            "hel"
+        );
+        assert_eq!(render(&formatter("str()"), text("hello")), "hello");
+
+        // Escaped characters are one column and are never split
+        let f = formatter("str(max_w:5,ellipsis:true)");
+        assert_eq!(render(&f, text("&lt;b&gt;&amp;")), "&lt;b&gt;&amp;");
+        assert_eq!(render(&f, text("Tom &amp; Jerry")), "Tom …");
+        assert_eq!(render(&f, text("a&amp;&#39;bcd")), "a&amp;&#39;b…");
+        assert_eq!(render(&f, text("a & b; c")), "a & …");
+    }
+
+    #[test]
+    fn elide_mid() {
+        let text = |s: &str| Value::from_string(s.to_string());
+        let f = formatter("elide_mid(max:20)");
+        assert_eq!(
+            render(&f, text("/home/user/documents/file.txt")),
+            "/home/user…/file.txt"
+        );
+        // Text which fits is left as it is
+        assert_eq!(render(&f, text("/etc/hosts")), "/etc/hosts");
+        assert_eq!(
+            render(&f, text("/home/user/file.txt")),
+            "/home/user/file.txt"
+        );
+
+        // Graphemes are never split
+        let f = formatter("elide_mid(max:5)");
+        assert_eq!(
+            render(&f, text("e\u{301}e\u{301}e\u{301}xyz")),
+            "e\u{301}e\u{301}…yz"
+        );
+        assert_eq!(render(&f, text("🙂🙂🙂🙂🙂🙂")), "🙂🙂…🙂🙂");
+
+        let f = formatter("elide_mid(max:9,ellipsis:'...')");
+        assert_eq!(render(&f, text("abcdefghijkl")), "abc...jkl");
+        let f = formatter("elide_mid(max:2,ellipsis:'...')");
+        assert_eq!(render(&f, text("abcdefghijkl")), "..");
+        assert!(formatter("elide_mid()")
+            .apply(&text("abc"), &HashMap::new())
+            .is_err());
+    }
+
+    #[test]
+    fn str_width() {
+        // A family (four emoji joined with ZWJ), a flag (two regional indicators), and a letter
+        // with a combining accent are one column each
+        let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}\u{200d}\u{1f466}";
+        let flag = "\u{1f1e9}\u{1f1ea}";
+        let accent = "e\u{301}";
+        let text = format!("{}{}{}ab", family, flag, accent);
+        assert_eq!(text_width(&text, false), 5);
+        assert_eq!(text_width(&text, true), 7);
+        assert_eq!(text_width("日本語", false), 3);
+        assert_eq!(text_width("日本語", true), 6);
+        assert_eq!(text_width("plain ASCII", true), 11);
+
+        let value = |s: &str| Value::from_string(s.to_string());
+        let f = formatter("str(max_w:4,ellipsis:true)");
+        assert_eq!(
+            render(&f, value(&text)),
+            format!("{}{}{}…", family, flag, accent)
+        );
+        let f = formatter("str(max_w:3,ellipsis:true)");
+        assert_eq!(render(&f, value(&text)), format!("{}{}…", family, flag));
+
+        // In East Asian mode wide characters aren't split in half
+        let f = formatter("str(max_w:5,ellipsis:true,east_asian:true)");
+        assert_eq!(render(&f, value("日本語のテキスト")), "日本…");
+        let f = formatter("str(max_w:6,ellipsis:true,east_asian:true)");
+        assert_eq!(render(&f, value("日本語")), "日本語");
+        let f = formatter("str(max_w:4,east_asian:true)");
+        assert_eq!(render(&f, value(&text)), format!("{}{}", family, flag));
+
+        let f = formatter("str(min_w:4)");
+        assert_eq!(render(&f, value("日本")), "日本  ");
+        assert_eq!(render(&f, value(flag)), format!("{}   ", flag));
+        let f = formatter("str(min_w:4,east_asian:true)");
+        assert_eq!(render(&f, value("日本")), "日本");
+        assert_eq!(render(&f, value("ab")), "ab  ");
+    }
+
+    #[test]
+    fn rot() {
+        let f = formatter("rot(width:5,step:2,interval:0.5,sep:' ~ ')");
+        let text = Value::from_string("abcdefgh".to_string());
+        let start = Instant::now();
+        let at = |ms| {
+            f.rot(&text, &HashMap::new(), start + Duration::from_millis(ms))
+                .unwrap()
+                .format(&"x".parse().unwrap())
+                .unwrap()
+        };
+        let tick = |ms| {
+            f.next_tick(&text, &HashMap::new(), start + Duration::from_millis(ms))
+                .unwrap()
+        };
+        assert_eq!(at(0), "abcde");
+        assert_eq!(tick(0), Some(Duration::from_millis(500)));
+        assert_eq!(tick(400), Some(Duration::from_millis(100)));
+        // Between steps nothing moves, e.g. if the block updates more often than that
+        assert_eq!(at(400), "abcde");
+        assert_eq!(at(500), "cdefg");
+        // If the block updates less often, several steps are taken at once
+        assert_eq!(at(1500), "gh ~ ");
+        assert_eq!(at(2000), " ~ ab");
+        // The cycle is the text and the separator, 11 characters
+        assert_eq!(at(2500), " abcd");
+        assert_eq!(at(5500), "abcde");
+
+        // A new text starts from the beginning
+        let other = Value::from_string("0123456789".to_string());
+        let at_other = |ms| {
+            f.rot(&other, &HashMap::new(), start + Duration::from_millis(ms))
+                .unwrap()
+                .format(&"x".parse().unwrap())
+                .unwrap()
+        };
+        assert_eq!(at_other(6000), "01234");
+        assert_eq!(at_other(6500), "23456");
+
+        // Text which fits doesn't scroll
+        let short = Value::from_string("abc".to_string());
+        assert_eq!(render(&formatter("rot(5)"), short.clone()), "abc");
+        assert_eq!(render(&formatter("rot(3)"), short), "abc");
+        assert_eq!(marquee("abcd", 3, 6, "|"), "bcd");
+        assert_eq!(marquee("日本語テキ", 3, 4, "・"), "キ・日");
+        assert!(Formatter::parse("rot(5,interval:0)")
+            .unwrap()
+            .0
+            .apply(&Value::from_string("abcdefgh".to_string()), &HashMap::new())
+            .is_err());
+    }
+
+    #[test]
+    fn pad() {
+        let text = |s: &str| Value::from_string(s.to_string());
+        assert_eq!(render(&formatter("pad(6)"), text("abc")), "abc   ");
+        assert_eq!(
+            render(&formatter("pad(6,align:left)"), text("abc")),
+            "abc   "
+        );
+        assert_eq!(
+            render(&formatter("pad(6,align:right)"), text("abc")),
+            "   abc"
+        );
+        assert_eq!(
+            render(&formatter("pad(7,align:center)"), text("abc")),
+            "  abc  "
+        );
+        // The extra column goes to the right
+        assert_eq!(
+            render(&formatter("pad(6,align:center)"), text("abc")),
+            " abc  "
+        );
+        assert_eq!(
+            render(
+                &formatter("pad(width:6,align:right,fill:'0')"),
+                Value::from_integer(42)
+            ),
+            "000042"
+        );
+        assert_eq!(
+            render(&formatter("pad(5,align:center,fill:'·')"), text("日本")),
+            "·日本··"
+        );
+        // Text which is already wide enough is left as it is
+        assert_eq!(render(&formatter("pad(3,align:right)"), text("abc")), "abc");
+        assert_eq!(
+            render(&formatter("pad(2,align:center)"), text("hello")),
+            "hello"
+        );
+
+        let apply = |s: &str| {
+            Formatter::parse(s)
+                .unwrap()
+                .0
+                .apply(&text("abc"), &HashMap::new())
+        };
+        assert!(apply("pad()").is_err());
+        assert!(apply("pad(6,fill:'ab')").is_err());
+        assert!(apply("pad(6,fill:'')").is_err());
+        assert!(apply("pad(6,align:middle)").is_err());
+    }
+
+    #[test]
+    fn number() {
+        let f = formatter("number()");
+        let number = |s: &str| {
+            f.apply(&Value::from_string(s.to_string()), &HashMap::new())
+                .unwrap()
+                .as_string()
+        };
+        assert_eq!(number("temp: 45.2 C"), "45.2");
+        assert_eq!(number("42"), "42");
+        assert_eq!(number("fan2 at -12rpm"), "2");
+        assert_eq!(number("offset -0.75dB"), "-0.75");
+        assert_eq!(number("load .5, 1.0"), "0.5");
+        assert_eq!(number("v1.2.3"), "1.2");
+        // The sign only belongs to the number if it directly precedes it
+        assert_eq!(number("3-5 days"), "3");
+        assert_eq!(number("- 5"), "5");
+        assert_eq!(number("+5"), "5");
+        // A dot without decimals ends the number
+        assert_eq!(number("5. Place"), "5");
+        // Without a number the value is empty, so that e.g. `coalesce` can fall back
+        assert_eq!(render(&f, Value::from_string("n/a".to_string())), "");
+        assert_eq!(render(&f, Value::from_string(String::new())), "");
+
+        // Numeric formatters can be applied to the result
+        let temp = f
+            .apply(
+                &Value::from_string("temp: 45.25 C".to_string()),
+                &HashMap::new(),
+            )
+            .unwrap();
+        assert_eq!(render(&formatter("fit(4)"), temp), "45.2");
+    }
+
+    #[test]
+    fn fit() {
+        let f = formatter("fit(width:6)");
+        let fit = |number: f64| render(&f, Value::from_float(number));
+        assert_eq!(fit(0.123456789), "0.1235");
+        assert_eq!(fit(5.55555), "5.5556");
+        assert_eq!(fit(42.5), "42.500");
+        assert_eq!(fit(-42.5), "-42.50");
+        assert_eq!(fit(1234.5678), "1234.6");
+        assert_eq!(fit(12345.678), "12346");
+        assert_eq!(fit(123456.7), "123457");
+        // Too large for the width
+        assert_eq!(fit(12345678.9), "12345679");
+        // Rounding may add a digit
+        assert_eq!(render(&formatter("fit(3)"), Value::from_float(9.96)), "10");
+        assert_eq!(render(&formatter("fit(3)"), Value::from_integer(7)), "7.0");
+        assert!(Formatter::parse("fit()")
+            .unwrap()
+            .0
+            .apply(&Value::from_float(1.), &HashMap::new())
+            .is_err());
+    }
+
+    #[test]
+    fn coalesce() {
+        let f = formatter("coalesce(a:$x,b:$y,c:'fallback')");
+        let empty = || Value::from_string(String::new());
+        let vars = map!(
+            "x" => empty(),
+            "y" => Value::from_string("second".to_string()),
+        );
+        assert_eq!(render_with(&f, empty(), &vars), "second");
+        assert_eq!(
+            render_with(&f, Value::from_string("own".to_string()), &vars),
+            "own"
+        );
+
+        let vars = map!("x" => empty(), "y" => empty());
+        assert_eq!(render_with(&f, empty(), &vars), "fallback");
+
+        let f = formatter("coalesce(a:$x)");
+        assert_eq!(render_with(&f, empty(), &vars), "");
+        assert!(f.apply(&empty(), &HashMap::new()).is_err());
+        assert!(Formatter::parse("coalesce($x)").is_err());
+    }
+
+    #[test]
+    fn csv() {
+        let line = || Value::from_string(r#"a,"b, with comma",3,"say ""hi""""#.to_string());
+        assert_eq!(render(&formatter("csv(0)"), line()), "a");
+        assert_eq!(render(&formatter("csv(field:1)"), line()), "b, with comma");
+        assert_eq!(render(&formatter("csv(field:2,sep:',')"), line()), "3");
+        assert_eq!(render(&formatter("csv(3)"), line()), r#"say "hi""#);
+        assert_eq!(render(&formatter("csv(4)"), line()), "");
+        assert_eq!(render(&formatter("csv(4,default:'n/a')"), line()), "n/a");
+        assert_eq!(render(&formatter("csv(-1,default:'n/a')"), line()), "n/a");
+
+        let line = || Value::from_string("x;\"1;2\";y\n".to_string());
+        assert_eq!(render(&formatter("csv(1,sep:';')"), line()), "1;2");
+        assert_eq!(render(&formatter("csv(2,sep:';')"), line()), "y");
+
+        assert!(formatter("csv(0,sep:'ab')")
+            .apply(&line(), &HashMap::new())
+            .is_err());
+    }
+
+    #[test]
+    fn ramp() {
+        let f = formatter("ramp(good:0,warn:60,crit:85)");
+        let state = |load| {
+            f.ramp_state(&Value::from_integer(load), &HashMap::new())
+                .unwrap()
+        };
+        assert_eq!(state(-1), None);
+        assert_eq!(state(0), Some("good"));
+        assert_eq!(state(59), Some("good"));
+        assert_eq!(state(60), Some("warning"));
+        assert_eq!(state(84), Some("warning"));
+        assert_eq!(state(85), Some("critical"));
+        assert_eq!(state(100), Some("critical"));
+
+        let mut config = SharedConfig::default();
+        let colors = map!(
+            "good_fg".to_string() => "#00ff00".to_string(),
+            "warning_fg".to_string() => "#ffff00".to_string(),
+        );
+        config.theme_override(&colors).unwrap();
+        let f = formatter("ramp(good:0,warning:$limit)");
+        let vars = map!("limit" => Value::from_integer(60));
+        with_shared_config(&config, || {
+            assert_eq!(
+                render_with(&f, Value::from_integer(12), &vars),
+                "<span foreground='#00ff00'>12</span>"
+            );
+            assert_eq!(
+                render_with(&f, Value::from_integer(75).percents(), &vars),
+                "<span foreground='#ffff00'>75%</span>"
+            );
+            assert_eq!(render_with(&f, Value::from_integer(-5), &vars), "-5");
+        });
+
+        assert!(Formatter::parse("ramp(hot:90)").is_err());
+    }
+
+    #[test]
+    fn state() {
+        let f = formatter("state(warn:60,crit:85)");
+        let state = |v| {
+            assert_eq!(render(&f, Value::from_integer(v)), v.to_string());
+            f.requested_state(&Value::from_integer(v), &HashMap::new())
+                .unwrap()
+        };
+        assert_eq!(state(12), None);
+        assert_eq!(state(60), Some(State::Warning));
+        assert_eq!(state(84), Some(State::Warning));
+        assert_eq!(state(85), Some(State::Critical));
+
+        // The most severe state wins
+        let format =
+            FormatTemplate::new("{a.state(good:0,crit:90)} {b.state(good:0,warn:50)}", None)
+                .unwrap();
+        let values = map!(
+            "a" => Value::from_integer(10),
+            "b" => Value::from_integer(70),
+        );
+        assert_eq!(format.render(&values).unwrap().state, Some(State::Warning));
+        // Each rendering has its own state
+        let format = FormatTemplate::new("{a}", None).unwrap();
+        assert_eq!(format.render(&values).unwrap().state, None);
+    }
+
+    #[test]
+    fn as_state() {
+        let f = formatter("as_state(ok:good,warn:warning,err:crit,default:info)");
+        let state = |status: &str| {
+            let value = Value::from_string(status.to_string());
+            assert_eq!(render(&f, value.clone()), status);
+            f.requested_state(&value, &HashMap::new()).unwrap()
+        };
+        assert_eq!(state("ok"), Some(State::Good));
+        assert_eq!(state("warn"), Some(State::Warning));
+        assert_eq!(state("err"), Some(State::Critical));
+        assert_eq!(state("err\n"), Some(State::Critical));
+        // Unknown values, including ones that differ in case, get the default state
+        assert_eq!(state("unknown"), Some(State::Info));
+        assert_eq!(state("OK"), Some(State::Info));
+        assert_eq!(state(""), Some(State::Info));
+
+        // Without a default the state is left alone
+        let f = formatter("as_state(ok:$good_state)");
+        let vars = map!("good_state" => Value::from_string("good".to_string()));
+        let state = |status: &str| {
+            f.requested_state(&Value::from_string(status.to_string()), &vars)
+                .unwrap()
+        };
+        assert_eq!(state("ok"), Some(State::Good));
+        assert_eq!(state("failed"), None);
+
+        // The state is returned by the template that uses the formatter, along with the most
+        // severe state of the other formatters
+        let format = FormatTemplate::new(
+            "{status.as_state(ok:good,err:crit)} {load.state(warn:1)}",
+            None,
+        )
+        .unwrap();
+        let rendered = |status: &str, load| {
+            let values = map!(
+                "status" => Value::from_string(status.to_string()),
+                "load" => Value::from_integer(load),
+            );
+            format.render(&values).unwrap().state
+        };
+        assert_eq!(rendered("ok", 0), Some(State::Good));
+        assert_eq!(rendered("ok", 2), Some(State::Warning));
+        assert_eq!(rendered("err", 2), Some(State::Critical));
+        assert_eq!(rendered("unknown", 0), None);
+
+        let apply = |f: &str, status: &str| {
+            formatter(f).requested_state(&Value::from_string(status.to_string()), &HashMap::new())
+        };
+        assert!(apply("as_state(ok:great)", "ok").is_err());
+        assert!(apply("as_state(default:great)", "failed").is_err());
+        // Only the state that is used has to be valid
+        assert!(apply("as_state(ok:good,err:great)", "ok").is_ok());
+    }
+
+    #[test]
+    fn ago() {
+        assert_eq!(super::ago(-30), "now");
+        assert_eq!(super::ago(0), "now");
+        assert_eq!(super::ago(59), "now");
+        assert_eq!(super::ago(60), "1m");
+        assert_eq!(super::ago(5 * 60 + 59), "5m");
+        assert_eq!(super::ago(3599), "59m");
+        assert_eq!(super::ago(3600), "1h");
+        assert_eq!(super::ago(2 * 3600 + 3599), "2h");
+        assert_eq!(super::ago(86399), "23h");
+        assert_eq!(super::ago(86400), "1d");
+        assert_eq!(super::ago(3 * 86400 + 7200), "3d");
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let f = formatter("ago()");
+        assert_eq!(render(&f, Value::from_integer(now - 10)), "now");
+        assert_eq!(render(&f, Value::from_integer(now - 7300)), "2h");
+        assert!(f
+            .apply(
+                &Value::from_string("yesterday".to_string()),
+                &HashMap::new()
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn delta_bytes() {
+        assert_eq!(super::delta_bytes(0), "0 B");
+        assert_eq!(super::delta_bytes(1), "+1 B");
+        assert_eq!(super::delta_bytes(-1023), "-1023 B");
+        assert_eq!(super::delta_bytes(1024), "+1.0 KiB");
+        assert_eq!(super::delta_bytes(-300 * 1024), "-300 KiB");
+        assert_eq!(super::delta_bytes(1258291), "+1.2 MiB");
+        assert_eq!(super::delta_bytes(-5 * 1024 * 1024 * 1024), "-5.0 GiB");
+        assert_eq!(super::delta_bytes(42 << 40), "+42 TiB");
+        assert_eq!(super::delta_bytes(i64::MIN), "-8.0 EiB");
+
+        let f = formatter("delta_bytes()");
+        assert_eq!(render(&f, Value::from_integer(-2048)), "-2.0 KiB");
+        assert_eq!(render(&f, Value::from_float(1536.7)), "+1.5 KiB");
+        assert!(f
+            .apply(&Value::from_string("lots".to_string()), &HashMap::new())
+            .is_err());
+    }
+
+    #[test]
+    fn colorlerp() {
+        let (green, red) = ((0, 255, 0, 255), (255, 0, 0, 255));
+        assert_eq!(color_lerp(green, red, 0.), green);
+        assert_eq!(color_lerp(green, red, 1.), red);
+        assert_eq!(color_lerp(green, red, 0.5), (188, 188, 0, 255));
+        assert_eq!(
+            color_lerp((0, 0, 0, 0), (255, 255, 255, 255), 0.5),
+            (188, 188, 188, 128)
+        );
+
+        let f = formatter("colorlerp(from:'00ff00',to:'#ff0000')");
+        assert_eq!(
+            render(&f, Value::from_float(0.)),
+            "<span foreground='#00FF00FF'>0.0</span>"
+        );
+        assert_eq!(
+            render(&f, Value::from_float(0.5)),
+            "<span foreground='#BCBC00FF'>0.5</span>"
+        );
+        assert_eq!(
+            render(&f, Value::from_integer(1)),
+            "<span foreground='#FF0000FF'> 1</span>"
+        );
+        // Fractions are clamped
+        assert_eq!(
+            render(&f, Value::from_integer(7)),
+            "<span foreground='#FF0000FF'> 7</span>"
+        );
+
+        assert!(formatter("colorlerp(from:green,to:'ff0000')")
+            .apply(&Value::from_float(0.5), &HashMap::new())
+            .is_err());
+        assert!(formatter("colorlerp(from:'00ff00')")
+            .apply(&Value::from_float(0.5), &HashMap::new())
+            .is_err());
+    }
+
+    #[test]
+    fn compact() {
+        assert_eq!(super::compact(999., 1), None);
+        assert_eq!(super::compact(-999., 1), None);
+        assert_eq!(super::compact(1000., 1), Some("1.0K".to_string()));
+        assert_eq!(super::compact(12_345., 1), Some("12.3K".to_string()));
+        assert_eq!(super::compact(999_949., 1), Some("999.9K".to_string()));
+        assert_eq!(super::compact(999_950., 1), Some("1.0M".to_string()));
+        assert_eq!(super::compact(4_500_000., 1), Some("4.5M".to_string()));
+        assert_eq!(super::compact(1_234_567_890., 1), Some("1.2B".to_string()));
+        assert_eq!(super::compact(-12_345., 0), Some("-12K".to_string()));
+        assert_eq!(super::compact(3e15, 0), Some("3000T".to_string()));
+
+        let f = formatter("compact()");
+        assert_eq!(render(&f, Value::from_integer(42)), "42");
+        assert_eq!(render(&f, Value::from_integer(12_345)), "12.3K");
+        assert_eq!(
+            render(&formatter("compact(2)"), Value::from_integer(1_234_567_890)),
+            "1.23B"
+        );
+        assert_eq!(
+            render(&f, Value::from_string("4500000".to_string())),
+            "4.5M"
+        );
+        assert!(f
+            .apply(&Value::from_string("many".to_string()), &HashMap::new())
+            .is_err());
+    }
+
+    #[test]
+    fn plural() {
+        let category = |n| plural_category("ru", n).unwrap();
+        assert_eq!(category(1.), "one");
+        assert_eq!(category(2.), "few");
+        assert_eq!(category(4.), "few");
+        assert_eq!(category(5.), "many");
+        assert_eq!(category(11.), "many");
+        assert_eq!(category(12.), "many");
+        assert_eq!(category(21.), "one");
+        assert_eq!(category(22.), "few");
+        assert_eq!(category(111.), "many");
+        assert_eq!(category(0.), "many");
+        assert_eq!(category(1.5), "other");
+        assert_eq!(plural_category("pl", 1.), Some("one"));
+        assert_eq!(plural_category("pl", 21.), Some("many"));
+        assert_eq!(plural_category("pl", 22.), Some("few"));
+        assert_eq!(plural_category("fr", 0.), Some("one"));
+        assert_eq!(plural_category("fr", 1.5), Some("one"));
+        assert_eq!(plural_category("en", 0.), Some("other"));
+        assert_eq!(plural_category("tlh", 1.), None);
+
+        let f = formatter("plural(ru,one:'файл',few:'файла',many:'файлов',other:'файла')");
+        let files = |n| render(&f, Value::from_integer(n));
+        assert_eq!(files(1), "файл");
+        assert_eq!(files(3), "файла");
+        assert_eq!(files(5), "файлов");
+        assert_eq!(files(21), "файл");
+
+        let f = formatter("plural(one:update,other:updates)");
+        assert_eq!(render(&f, Value::from_integer(1)), "update");
+        assert_eq!(render(&f, Value::from_integer(2)), "updates");
+        // Missing categories fall back to `other`
+        let f = formatter("plural(ru,one:x,other:y)");
+        assert_eq!(render(&f, Value::from_integer(5)), "y");
+        assert!(formatter("plural(ru,one:x)")
+            .apply(&Value::from_integer(5), &HashMap::new())
+            .is_err());
+        assert!(formatter("plural(tlh,other:x)")
+            .apply(&Value::from_integer(5), &HashMap::new())
+            .is_err());
+    }
+
+    #[test]
+    fn tally() {
+        let f = formatter("tally(one:message,many:messages)");
+        let messages = |n| render(&f, Value::from_integer(n));
+        assert_eq!(messages(0), "0 messages");
+        assert_eq!(messages(1), "1 message");
+        assert_eq!(messages(2), "2 messages");
+        assert_eq!(messages(-1), "-1 messages");
+
+        let f = formatter("tally(one:update,many:updates,zero:'no updates')");
+        assert_eq!(render(&f, Value::from_integer(0)), "no updates");
+        assert_eq!(render(&f, Value::from_integer(1)), "1 update");
+
+        let f = formatter("tally(one:'файл',many:'файлов',few:'файла',rules:ru)");
+        let files = |n| render(&f, Value::from_integer(n));
+        assert_eq!(files(0), "0 файлов");
+        assert_eq!(files(2), "2 файла");
+        assert_eq!(files(21), "21 файл");
+        assert_eq!(files(-2), "-2 файлов");
+
+        assert!(formatter("tally(one:x)")
+            .apply(&Value::from_integer(1), &HashMap::new())
+            .is_err());
+        assert!(formatter("tally(one:x,many:y,rules:tlh)")
+            .apply(&Value::from_integer(1), &HashMap::new())
+            .is_err());
+    }
+
+    #[test]
+    fn schedule() {
+        let f = formatter("schedule(h22_6:'sleep',sat_sun:$weekend,mon_fri:'work',h12:'lunch')");
+        let vars = map!("weekend" => Value::from_string("relax".to_string()));
+        let at = |date: &str| {
+            let now = NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap();
+            f.schedule(&Value::from_string("value".to_string()), &vars, now)
+                .unwrap()
+                .as_string()
+        };
+        // 2024-01-01 is a Monday
+        assert_eq!(at("2024-01-01 09:00"), "work");
+        assert_eq!(at("2024-01-05 21:59"), "work");
+        assert_eq!(at("2024-01-05 22:00"), "sleep");
+        assert_eq!(at("2024-01-02 05:59"), "sleep");
+        assert_eq!(at("2024-01-06 09:00"), "relax");
+        assert_eq!(at("2024-01-07 23:00"), "sleep");
+        // The first matching schedule wins
+        assert_eq!(at("2024-01-03 12:30"), "work");
+
+        let f = formatter("schedule(sun_mon:'start',h12:$missing,default:'other')");
+        let at = |date: &str| {
+            let now = NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap();
+            f.schedule(
+                &Value::from_string("value".to_string()),
+                &HashMap::new(),
+                now,
+            )
+        };
+        assert_eq!(at("2024-01-07 10:00").unwrap().as_string(), "start");
+        assert_eq!(at("2024-01-01 10:00").unwrap().as_string(), "start");
+        assert_eq!(at("2024-01-02 10:00").unwrap().as_string(), "other");
+        assert!(at("2024-01-02 12:00").is_err());
+
+        // Without a default the value is kept
+        let f = formatter("schedule(h9_17:'office')");
+        let now = NaiveDateTime::parse_from_str("2024-01-01 08:00", "%Y-%m-%d %H:%M").unwrap();
+        assert_eq!(
+            f.schedule(&Value::from_integer(5), &HashMap::new(), now)
+                .unwrap()
+                .as_string(),
+            "5"
+        );
+
+        for invalid in &["h24", "h9_25", "moon", "mon_x", "x_mon", "h"] {
+            assert_eq!(schedule_matches(invalid, now), None);
+        }
+        assert!(formatter("schedule(noon:'x')")
+            .schedule(&Value::from_integer(5), &HashMap::new(), now)
+            .is_err());
+    }
+
+    #[test]
+    fn color() {
+        let f = formatter("color(#ff0000)");
+        assert_eq!(
+            render(&f, Value::from_string("error".to_string())),
+            "<span foreground='#FF0000FF'>error</span>"
+        );
+        let f = formatter("color(fg:'00ff0080')");
+        assert_eq!(
+            render(&f, Value::from_string("ok".to_string())),
+            "<span foreground='#00FF0080'>ok</span>"
+        );
+        let f = formatter("color(Orange)");
+        assert_eq!(
+            render(&f, Value::from_string("hot".to_string())),
+            "<span foreground='#FFA500FF'>hot</span>"
+        );
+
+        let f = formatter("color(gradient:green-red)");
+        let gradient = |number: i64| render(&f, Value::from_integer(number));
+        assert_eq!(gradient(0), "<span foreground='#00FF00FF'> 0</span>");
+        assert_eq!(gradient(50), "<span foreground='#BCBC00FF'>50</span>");
+        assert_eq!(gradient(100), "<span foreground='#FF0000FF'>100</span>");
+        // Values outside of the range get the colors of its ends
+        assert_eq!(gradient(-5), "<span foreground='#00FF00FF'>-5</span>");
+        assert_eq!(gradient(120), "<span foreground='#FF0000FF'>120</span>");
+
+        let f = formatter("color(gradient:'#0000ff-#ffffff',min:$low,max:40)");
+        let vars = map!("low" => Value::from_integer(20));
+        assert_eq!(
+            render_with(&f, Value::from_integer(20), &vars),
+            "<span foreground='#0000FFFF'>20</span>"
+        );
+        assert_eq!(
+            render_with(&f, Value::from_integer(40), &vars),
+            "<span foreground='#FFFFFFFF'>40</span>"
+        );
+
+        let apply = |s: &str| formatter(s).apply(&Value::from_integer(1), &HashMap::new());
+        assert!(apply("color()").is_err());
+        assert!(apply("color(#ff0000,gradient:green-red)").is_err());
+        assert!(apply("color(purplish)").is_err());
+        assert!(apply("color(gradient:green)").is_err());
+        assert!(apply("color(gradient:green-nothing)").is_err());
+        assert!(apply("color(gradient:green-red,min:10,max:10)").is_err());
+        // Colors must not break out of the markup
+        assert!(apply("color('#ff0000\\'>')").is_err());
+    }
+
+    #[test]
+    fn style() {
+        let f = formatter("style(weight:bold,style:italic)");
+        assert_eq!(
+            render(&f, Value::from_string("mail".to_string())),
+            "<span weight='bold' style='italic'>mail</span>"
+        );
+        // The attributes are in the same order regardless of the order of the arguments
+        let f = formatter("style(size:large,underline:single,weight:800)");
+        assert_eq!(
+            render(&f, Value::from_integer(3)),
+            "<span weight='800' underline='single' size='large'>3</span>"
+        );
+        let f = formatter("style(strikethrough:$done,size:12288)");
+        let vars = map!("done" => Value::from_string("true".to_string()));
+        assert_eq!(
+            render_with(&f, Value::from_string("task".to_string()), &vars),
+            "<span strikethrough='true' size='12288'>task</span>"
+        );
+        assert_eq!(
+            render(&formatter("style()"), Value::from_string("3".to_string())),
+            "3"
+        );
+
+        // Only known attributes and values are accepted
+        assert!(Formatter::parse("style(color:red)").is_err());
+        assert!(Formatter::parse("style(weight:bold,onclick:x)").is_err());
+        for invalid in &[
+            "style(weight:heavier)",
+            "style(weight:50)",
+            r"style(style:'italic\' foreground=\'red')",
+            "style(size:0)",
+            "style(strikethrough:yes)",
+        ] {
+            assert!(formatter(invalid)
+                .apply(&Value::from_integer(3), &HashMap::new())
+                .is_err());
+        }
+        let names: Vec<_> = STYLE_ATTRIBUTES.iter().map(|(name, _)| *name).collect();
+        assert!(FORMATTERS.contains(&("style", names.as_slice())));
+    }
+
+    #[test]
+    fn highlight() {
+        let text = |s: &str| Value::from_string(s.to_string());
+        let red = |s: &str| format!("<span foreground='#FF0000FF'>{}</span>", s);
+        let f = formatter("highlight(pattern:'ERROR',color:'ff0000')");
+        assert_eq!(
+            render(&f, text("12:00 ERROR disk full")),
+            format!("12:00 {} disk full", red("ERROR"))
+        );
+        assert_eq!(
+            render(&f, text("ERROR: retry failed with ERROR 5")),
+            format!("{}: retry failed with {} 5", red("ERROR"), red("ERROR"))
+        );
+        // Text without matches is left as it is
+        assert_eq!(render(&f, text("12:00 all good")), "12:00 all good");
+        assert_eq!(render(&f, text("error")), "error");
+
+        // Literal patterns match characters which have a meaning in regular expressions
+        let f = formatter("highlight(pattern:'[warn]',color:red)");
+        assert_eq!(
+            render(&f, text("[warn] low battery, w")),
+            format!("{} low battery, w", red("[warn]"))
+        );
+
+        let f = formatter("highlight(pattern:'[0-9]+%',color:red,regex:true)");
+        assert_eq!(
+            render(&f, text("cpu 95% mem 40%")),
+            format!("cpu {} mem {}", red("95%"), red("40%"))
+        );
+        let f = formatter("highlight(pattern:'x*',color:red,regex:true)");
+        assert_eq!(render(&f, text("axxb")), format!("a{}b", red("xx")));
+
+        // Entities are matched as the characters they stand for, and the output is escaped
+        let f = formatter("highlight(pattern:'a & <b>',color:red)");
+        assert_eq!(
+            render(&f, text("x a & <b> y")),
+            format!("x {} y", red("a &amp; &lt;b&gt;"))
+        );
+        assert_eq!(
+            render(&f, text("Tom &amp; Jerry &lt;3 a &amp; &lt;b&gt;")),
+            format!("Tom &amp; Jerry &lt;3 {}", red("a &amp; &lt;b&gt;"))
+        );
+        let f = formatter("highlight(pattern:'amp',color:red)");
+        assert_eq!(render(&f, text("&amp;")), "&amp;");
+
+        let apply = |s: &str| formatter(s).apply(&text("abc"), &HashMap::new());
+        assert!(apply("highlight(pattern:a)").is_err());
+        assert!(apply("highlight(color:red)").is_err());
+        assert!(apply("highlight(pattern:'(',color:red,regex:true)").is_err());
+        assert!(apply("highlight(pattern:'(',color:red)").is_ok());
+    }
+
+    #[test]
+    fn datetime() {
+        // 2021-06-01 12:30:00 UTC, in summer time in both New York and Berlin
+        let summer = Value::from_integer(1_622_550_600);
+        // 2021-01-01 12:30:00 UTC, in standard time
+        let winter = Value::from_integer(1_609_504_200);
+
+        let f = formatter("datetime(tz:'America/New_York',format:'%H:%M %Z')");
+        assert_eq!(render(&f, summer.clone()), "08:30 EDT");
+        assert_eq!(render(&f, winter.clone()), "07:30 EST");
+        let f = formatter("datetime(tz:'Europe/Berlin',format:'%H:%M %Z')");
+        assert_eq!(render(&f, summer.clone()), "14:30 CEST");
+        assert_eq!(render(&f, winter), "13:30 CET");
+
+        let f = formatter("datetime(tz:UTC,locale:C)");
+        assert_eq!(render(&f, summer.clone()), "Tue 01/06 12:30");
+        let f = formatter("datetime(tz:'Asia/Kolkata',format:'%H:%M:%S')");
+        assert_eq!(render(&f, Value::from_float(1_622_550_600.5)), "18:00:00");
+
+        let error = |f: &str| match formatter(f).apply(&summer, &HashMap::new()) {
+            Err(InternalError(_, message, _)) => message,
+            _ => panic!("expected an error"),
+        };
+        assert_eq!(
+            error("datetime(tz:'Mars/Olympus_Mons')"),
+            "unknown timezone 'Mars/Olympus_Mons' in formatter 'datetime', expected an IANA name like 'America/New_York'"
+        );
+        assert_eq!(
+            error("datetime(locale:'xx_YY')"),
+            "unknown locale 'xx_YY' in formatter 'datetime', expected a name like 'de_DE'"
+        );
+        assert_eq!(
+            error("datetime(format:'%Q')"),
+            "argument 'format' of formatter 'datetime' must be a valid strftime format"
+        );
+        assert!(formatter("datetime()")
+            .apply(&Value::from_string("noon".to_string()), &HashMap::new())
+            .is_err());
+    }
+
+    #[test]
+    fn datetime_locale() {
+        // 2021-06-01 12:30:00 UTC
+        let time = Value::from_integer(1_622_550_600);
+        let f = formatter("datetime(tz:'Europe/Berlin',format:'%A %d %B',locale:'de_DE')");
+        assert_eq!(render(&f, time.clone()), "Dienstag 01 Juni");
+        let f = formatter("datetime(tz:'Europe/Paris',format:'%A %d %B',locale:fr_FR)");
+        assert_eq!(render(&f, time.clone()), "mardi 01 juin");
+        let f = formatter("datetime(tz:UTC,format:'%A %d %B',locale:POSIX)");
+        assert_eq!(render(&f, time), "Tuesday 01 June");
+
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            env_locale(move |name| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            })
+        };
+        let de_de: Locale = "de_DE".try_into().unwrap();
+        let fr_fr: Locale = "fr_FR".try_into().unwrap();
+        assert_eq!(env(&[("LANG", "de_DE.UTF-8")]), Some(de_de));
+        assert_eq!(
+            env(&[("LANG", "de_DE.UTF-8"), ("LC_TIME", "fr_FR@euro")]),
+            Some(fr_fr)
+        );
+        assert_eq!(env(&[("LC_ALL", "C"), ("LC_TIME", "fr_FR.UTF-8")]), None);
+        // Empty variables count as unset
+        assert_eq!(env(&[("LC_ALL", ""), ("LANG", "fr_FR")]), Some(fr_fr));
+        assert_eq!(env(&[("LANG", "C.UTF-8")]), None);
+        assert_eq!(env(&[("LANG", "nonsense")]), None);
+        assert_eq!(env(&[]), None);
+    }
+
+    #[test]
+    fn dur() {
+        let dur = |f: &str, seconds: f64| render(&formatter(f), Value::from_float(seconds));
+        let time = |h: f64, m: f64, s: f64| h * 3600. + m * 60. + s;
+
+        assert_eq!(dur("dur()", time(3., 7., 5.)), "3h 07m 05s");
+        assert_eq!(dur("dur()", time(50., 0., 1.)), "2d 02h 00m 01s");
+        assert_eq!(
+            dur(
+                "dur(max_unit:hours,min_unit:minutes,units:2)",
+                time(3., 7., 59.)
+            ),
+            "3h 07m"
+        );
+        // The first unit holds everything above it
+        assert_eq!(dur("dur(max_unit:h,units:2)", time(50., 0., 1.)), "50h 00m");
+        // Only units that are reached are shown
+        assert_eq!(dur("dur(units:2)", time(0., 7., 5.)), "7m 05s");
+        assert_eq!(dur("dur(units:2)", 42.), "42s");
+        // The rest is cut off rather than rounded, so that units never overflow
+        assert_eq!(dur("dur(units:2)", time(0., 59., 59.9)), "59m 59s");
+        assert_eq!(dur("dur(units:1)", time(0., 59., 59.)), "59m");
+        assert_eq!(dur("dur(units:1)", time(1., 0., 0.)), "1h");
+
+        // Zero durations are shown in the smallest unit
+        assert_eq!(dur("dur()", 0.), "0s");
+        assert_eq!(dur("dur(min_unit:minutes)", 59.), "0m");
+        assert_eq!(dur("dur(max_unit:m)", -5.), "0s");
+
+        // Sub-second durations
+        assert_eq!(dur("dur()", 0.25), "0s");
+        assert_eq!(dur("dur(min_unit:ms)", 0.25), "250ms");
+        assert_eq!(dur("dur(min_unit:ms)", 1.05), "1s 050ms");
+
+        assert_eq!(dur("dur(hms:true)", time(1., 2., 3.)), "1:02:03");
+        assert_eq!(dur("dur(hms:true)", time(0., 2., 3.)), "2:03");
+        assert_eq!(dur("dur(hms:true)", time(30., 0., 0.5)), "30:00:00");
+        assert_eq!(dur("dur(hms:true)", 0.), "0:00");
+
+        let apply = |f: &str| formatter(f).apply(&Value::from_integer(1), &HashMap::new());
+        assert!(apply("dur(max_unit:weeks)").is_err());
+        assert!(apply("dur(max_unit:s,min_unit:h)").is_err());
+        assert!(apply("dur(units:0)").is_err());
+    }
+
+    #[test]
+    fn lerp() {
+        let f = formatter("lerp(from:0,to:100)");
+        assert_eq!(render(&f, Value::from_integer(0)), "0");
+        assert_eq!(render(&f, Value::from_float(0.5)), "50");
+        assert_eq!(render(&f, Value::from_float(1.0)), "100");
+        assert_eq!(render(&f, Value::from_float(0.123)), "12");
+
+        // Out of range fractions are clamped
+        assert_eq!(render(&f, Value::from_float(-0.5)), "0");
+        assert_eq!(render(&f, Value::from_integer(3)), "100");
+
+        let f = formatter("lerp(from:20,to:$max,places:1)");
+        let vars = map!("max" => Value::from_integer(-10));
+        assert_eq!(render_with(&f, Value::from_float(0.), &vars), "20.0");
+        assert_eq!(render_with(&f, Value::from_float(0.25), &vars), "12.5");
+        assert_eq!(render_with(&f, Value::from_float(1.), &vars), "-10.0");
+
+        assert!(formatter("lerp(from:0)")
+            .apply(&Value::from_float(0.5), &HashMap::new())
+            .is_err());
+        assert!(f
+            .apply(&Value::from_string("half".to_string()), &vars)
+            .is_err());
+    }
+
+    #[test]
+    fn bar() {
+        let f = formatter("bar(width:4)");
+        assert_eq!(render(&f, Value::from_integer(0)), "    ");
+        assert_eq!(render(&f, Value::from_integer(50)), "\u{2588}\u{2588}  ");
+        assert_eq!(
+            render(&f, Value::from_float(62.5)),
+            "\u{2588}\u{2588}\u{258c} "
+        );
+        assert_eq!(
+            render(&f, Value::from_integer(100)),
+            "\u{2588}\u{2588}\u{2588}\u{2588}"
+        );
+        // Values are clamped to the range from 0 to `max`
+        assert_eq!(render(&f, Value::from_integer(-20)), "    ");
+        assert_eq!(
+            render(&f, Value::from_integer(250)),
+            "\u{2588}\u{2588}\u{2588}\u{2588}"
+        );
+
+        // A custom ramp, with as many steps per cell as it has glyphs after the empty one
+        let f = formatter("bar(width:3,max:6,glyphs:'.:#')");
+        let bar = |number| render(&f, Value::from_integer(number));
+        assert_eq!(bar(0), "...");
+        assert_eq!(bar(1), ":..");
+        assert_eq!(bar(3), "#:.");
+        assert_eq!(bar(6), "###");
+        assert_eq!(bar(7), "###");
+        assert_eq!(bar(-1), "...");
+
+        let f = formatter("bar(max:8,vertical:true)");
+        let bar = |number| render(&f, Value::from_integer(number));
+        assert_eq!(bar(0), " ");
+        assert_eq!(bar(4), "\u{2584}");
+        assert_eq!(bar(8), "\u{2588}");
+        assert_eq!(bar(-3), " ");
+        assert_eq!(bar(12), "\u{2588}");
+        let f = formatter("bar(max:1,vertical:true,glyphs:'_-=')");
+        assert_eq!(render(&f, Value::from_float(0.5)), "-");
+        assert_eq!(render(&f, Value::from_float(2.)), "=");
+
+        let apply = |s: &str| formatter(s).apply(&Value::from_integer(1), &HashMap::new());
+        assert!(apply("bar(glyphs:'')").is_err());
+        assert!(apply("bar(max:0)").is_err());
+        assert!(formatter("bar()")
+            .apply(&Value::from_string("full".to_string()), &HashMap::new())
+            .is_err());
+    }
+
+    #[test]
+    fn progress() {
+        let f = formatter("progress(pos:$position,len:$length,width:10)");
+        let render_at = |position, length| {
+            let vars = map!(
+                "position" => Value::from_integer(position),
+                "length" => Value::from_integer(length),
+            );
+            render_with(&f, Value::from_integer(0), &vars)
+        };
+        assert_eq!(render_at(0, 200), "░░░░░░░░░░");
+        assert_eq!(render_at(100, 200), "█████░░░░░");
+        assert_eq!(render_at(200, 200), "██████████");
+        assert_eq!(render_at(250, 200), "██████████");
+        assert_eq!(render_at(10, 0), "░░░░░░░░░░");
+
+        let f = formatter("progress(len:4,width:4)");
+        assert_eq!(render(&f, Value::from_float(1.)), "█░░░");
+    }
+
+    #[test]
+    fn arg_references() {
+        let vars = map!(
+            "cols" => Value::from_integer(4),
+            "half" => Value::from_float(2.5),
+            "names" => Value::from_string("zero,one".to_string()),
+            "more" => Value::from_string("…".to_string()),
+        );
+        assert_eq!(
+            render_with(
+                &formatter("progress(len:4,width:$cols)"),
+                Value::from_integer(2),
+                &vars
+            ),
+            "██░░"
+        );
+        assert_eq!(
+            render_with(
+                &formatter("index(list:$names)"),
+                Value::from_integer(1),
+                &vars
+            ),
+            "one"
+        );
+        assert_eq!(
+            render_with(
+                &formatter("cap(max:9,suffix:$more)"),
+                Value::from_integer(12),
+                &vars
+            ),
+            "9…"
+        );
+
+        let error = |f: &str| match formatter(f).apply(&Value::from_integer(1), &vars) {
+            Err(InternalError(_, message, _)) => message,
+            _ => panic!("expected an error"),
+        };
+        assert_eq!(
+            error("progress(len:4,width:$rows)"),
+            "argument 'width' of formatter 'progress' references unknown placeholder '$rows'"
+        );
+        assert_eq!(
+            error("progress(len:4,width:$half)"),
+            "argument 'width' of formatter 'progress' must be a non-negative integer, but '$half' is '2.5'"
+        );
+        assert_eq!(
+            error("progress(len:$names)"),
+            "argument 'len' of formatter 'progress' must be a number, but '$names' is 'zero,one'"
+        );
+        assert_eq!(
+            error("progress(len:4,width:-1)"),
+            "argument 'width' of formatter 'progress' must be a non-negative integer"
+        );
+    }
+
+    #[test]
+    fn localize_number() {
+        let year = || Value::from_string("2024".to_string());
+        assert_eq!(
+            render(&formatter("localize_number(arabic)"), year()),
+            "٢٠٢٤"
+        );
+        assert_eq!(
+            render(&formatter("localize_number(digits:devanagari)"), year()),
+            "२०२४"
+        );
+        assert_eq!(
+            render(
+                &formatter("localize_number('ABCDEFGHIJ')"),
+                Value::from_string("v1.05 - 30%".to_string())
+            ),
+            "vB.AF - DA%"
+        );
+        assert!(formatter("localize_number(klingon)")
+            .apply(&year(), &HashMap::new())
+            .is_err());
+    }
+
+    #[test]
+    fn ljust_icons() {
+        let f = formatter("ljust_icons(width:4)");
+        let with_icon = Value::from_integer(42).icon(" ON ".to_string());
+        let narrow_icon = Value::from_integer(42).icon("X".to_string());
+        let without_icon = Value::from_integer(42);
+        assert_eq!(render(&f, with_icon), " ON 42");
+        assert_eq!(render(&f, narrow_icon), "X   42");
+        assert_eq!(render(&f, without_icon.clone()), "    42");
+
+        // Without the formatter the columns don't line up
+        assert_eq!(without_icon.format(&"x".parse().unwrap()).unwrap(), "42");
+
+        // Wider icons are left alone
+        let f = formatter("ljust_icons(2)");
+        let wide_icon = Value::from_integer(7).icon("&lt;!&gt;".to_string());
+        assert_eq!(render(&f, wide_icon), "&lt;!&gt; 7");
+    }
+
+    #[test]
+    fn icon_or() {
+        let f = formatter("icon_or('no icon')");
+        assert_eq!(render(&f, Value::from_string("bell".to_string())), " ON ");
+        assert_eq!(
+            render(&f, Value::from_string("no_such_icon".to_string())),
+            "no icon"
+        );
+        assert_eq!(render(&formatter("icon_or()"), Value::from_integer(3)), "");
+    }
+
+    #[test]
+    fn eng() {
+        let rate = || Value::from_float(999_500.).hertz();
+        let render_eng = |f: &Formatter, value: Value| {
+            let value = f.apply(&value, &HashMap::new()).unwrap();
+            value.format(&"x".parse().unwrap()).unwrap()
+        };
+        assert_eq!(
+            render_eng(&formatter("eng(prefix:auto)"), rate()),
+            "1000KHz"
+        );
+        assert_eq!(render_eng(&formatter("eng(K)"), rate()), "1000KHz");
+        assert_eq!(render_eng(&formatter("eng(prefix:M)"), rate()), "1.0MHz");
+        assert_eq!(render_eng(&formatter("eng(prefix:1)"), rate()), "999500Hz");
+        // Integers get a prefix too
+        let f = formatter("eng(prefix:K)");
+        assert_eq!(render_eng(&f, Value::from_integer(1500).hertz()), "1.5KHz");
+        // Bytes have no prefixes below one
+        let f = formatter("eng(prefix:m)");
+        assert_eq!(render_eng(&f, Value::from_float(512.).bytes()), "512B");
+        let f = formatter("eng(prefix:K)");
+        assert_eq!(render_eng(&f, Value::from_float(2048.).bytes()), "2.0KB");
+
+        // Keeps the prefix of the largest value
+        let f = formatter("eng(prefix:fixed)");
+        assert_eq!(render_eng(&f, Value::from_float(900.).hertz()), "900Hz");
+        assert_eq!(
+            render_eng(&f, Value::from_float(1_200_000.).hertz()),
+            "1.2MHz"
+        );
+        assert_eq!(render_eng(&f, rate()), "1.0MHz");
+        assert_eq!(render_eng(&f, Value::from_float(2_000.).hertz()), "0.0MHz");
+        // Also after converting the unit
+        let f = formatter("eng(prefix:fixed)");
+        let apply = |value: Value| f.apply(&value, &HashMap::new()).unwrap();
+        apply(Value::from_float(150_000.).bytes());
+        let value = apply(Value::from_float(100_000.).bytes());
+        assert_eq!(value.format(&"x*b".parse().unwrap()).unwrap(), "0.8Mb");
+
+        assert!(formatter("eng(prefix:X)")
+            .apply(&rate(), &HashMap::new())
+            .is_err());
+        assert!(formatter("eng(prefix:k)")
+            .apply(&rate(), &HashMap::new())
+            .is_err());
+        assert!(formatter("eng(base:1000)")
+            .apply(&rate(), &HashMap::new())
+            .is_ok());
+        assert!(formatter("eng(base:2)")
+            .apply(&rate(), &HashMap::new())
+            .is_err());
+        assert!(formatter("eng(unit:bytes)")
+            .apply(&rate(), &HashMap::new())
+            .is_err());
+    }
+
+    #[test]
+    fn eng_base() {
+        let render_eng = |f: &str, value: Value| {
+            let value = formatter(f).apply(&value, &HashMap::new()).unwrap();
+            value.format(&"x".parse().unwrap()).unwrap()
+        };
+        let gib = || Value::from_integer(1_073_741_824);
+        assert_eq!(render_eng("eng(unit:B,base:1024)", gib()), "1.0GiB");
+        assert_eq!(render_eng("eng(unit:B,base:1000)", gib()), "1.1GB");
+        // Just below a gibibyte, which is above a gigabyte
+        let below = || Value::from_integer(1_073_741_823).bytes();
+        assert_eq!(render_eng("eng(base:1024)", below()), "1024MiB");
+        assert_eq!(render_eng("eng(base:1000)", below()), "1.1GB");
+        // Bytes are binary without a base, written with SI prefixes as before
+        assert_eq!(render_eng("eng()", gib().bytes()), "1.0GB");
+        assert_eq!(
+            render_eng("eng(base:1024)", Value::from_float(1000.).bytes()),
+            "1000B"
+        );
+
+        // Other units work too, but have no binary prefixes below one
+        let hz = || Value::from_float(2048.).hertz();
+        assert_eq!(render_eng("eng(base:1024)", hz()), "2.0KiHz");
+        assert_eq!(render_eng("eng(base:1000)", hz()), "2.0KHz");
+        let f = "eng(base:1024,prefix:m)";
+        assert_eq!(render_eng(f, Value::from_float(0.5).hertz()), "0.5Hz");
+        // Both can be combined with a fixed prefix
+        let f = "eng(unit:B,base:1000,prefix:M)";
+        assert_eq!(render_eng(f, gib()), "1074MB");
+        let f = "eng(unit:B,base:1024,prefix:M)";
+        assert_eq!(render_eng(f, gib()), "1024MiB");
+    }
+
+    #[test]
+    fn since_change() {
+        let tracker = ChangeTracker::default();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        assert_eq!(tracker.track("a".to_string(), at(0)).as_secs(), 0);
+        assert_eq!(tracker.track("b".to_string(), at(5)).as_secs(), 0);
+        assert_eq!(tracker.track("b".to_string(), at(8)).as_secs(), 3);
+        assert_eq!(tracker.track("b".to_string(), at(65)).as_secs(), 60);
+        assert_eq!(tracker.track("a".to_string(), at(70)).as_secs(), 0);
+        assert_eq!(tracker.track("a".to_string(), at(71)).as_secs(), 1);
+
+        let f = formatter("since_change()");
+        assert_eq!(render(&f, Value::from_integer(1)), " 0s");
+        assert!(Formatter::parse("since_change(1)").is_err());
+    }
+
+    #[test]
+    fn redact() {
+        let f = formatter("redact()");
+        let custom = formatter("redact(text:'hidden')");
+        let balance = || Value::from_string("1234.56 EUR".to_string());
+        // The privacy mode is global, so it is passed in rather than toggled while other tests
+        // run in parallel
+        let redact = |f: &Formatter, private| {
+            f.redact(&balance(), &HashMap::new(), private)
+                .unwrap()
+                .format(&"x".parse().unwrap())
+                .unwrap()
+        };
+        assert_eq!(render(&f, balance()), "1234.56 EUR");
+        assert_eq!(redact(&f, false), "1234.56 EUR");
+        assert_eq!(redact(&f, true), "\u{2022}\u{2022}\u{2022}\u{2022}");
+        assert_eq!(redact(&custom, true), "hidden");
+        assert_eq!(redact(&custom, false), "1234.56 EUR");
+    }
+
+    #[test]
+    fn strip_ansi() {
+        let f = formatter("strip_ansi()");
+        let text = |s: &str| Value::from_string(s.to_string());
+        assert_eq!(
+            render(
+                &f,
+                text("\u{1b}[1;31mERROR\u{1b}[0m: disk \u{1b}[38;5;208mfull\u{1b}[m")
+            ),
+            "ERROR: disk full"
+        );
+        assert_eq!(
+            render(
+                &f,
+                text("\u{1b}[2K\u{1b}[1Gprogress \u{1b}[3A50%\u{1b}[?25h")
+            ),
+            "progress 50%"
+        );
+        assert_eq!(
+            render(
+                &f,
+                text("\u{1b}]0;title\u{7}a\u{1b}]8;;http://x\u{1b}\\b\u{1b}7c")
+            ),
+            "abc"
+        );
+        assert_eq!(render(&f, text("plain [text]")), "plain [text]");
+        assert_eq!(render(&f, text("cut off \u{1b}[1;3")), "cut off ");
+    }
+
+    #[test]
+    fn escape() {
+        let f = formatter("escape()");
+        let text = |s: &str| Value::from_string(s.to_string());
+        assert_eq!(
+            render(&f, text("<b>Tom & Jerry</b>")),
+            "&lt;b&gt;Tom &amp; Jerry&lt;/b&gt;"
+        );
+        assert_eq!(
+            render(&f, text("it's \"fine\" ;) ~100%")),
+            "it&#39;s \"fine\" ;) ~100%"
+        );
+        assert_eq!(render(&f, text("&amp;")), "&amp;amp;");
+
+        // Literal markup around the placeholder is left alone
+        let format = FormatTemplate::new("<b>{title.escape()}</b>", None).unwrap();
+        let values = map!("title" => text("a<b"));
+        assert_eq!(format.render(&values).unwrap().full, "<b>a&lt;b</b>");
+    }
+
+    #[test]
+    fn case_converters() {
+        let phrase = || Value::from_string("  Hello wide_World-of  RUST ".to_string());
+        assert_eq!(
+            render(&formatter("camel()"), phrase()),
+            "helloWideWorldOfRust"
+        );
+        assert_eq!(
+            render(&formatter("snake()"), phrase()),
+            "hello_wide_world_of_rust"
+        );
+        assert_eq!(
+            render(&formatter("kebab()"), phrase()),
+            "hello-wide-world-of-rust"
+        );
+        assert_eq!(
+            render(&formatter("camel()"), Value::from_string(String::new())),
+            ""
+        );
+        assert!(Formatter::parse("snake(x:1)").is_err());
+    }
+}
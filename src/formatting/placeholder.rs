@@ -1,5 +1,7 @@
+use std::fmt::{self, Write};
 use std::str::FromStr;
 
+use super::formatter::{check_name, Formatter, FORMATTER_TOKEN};
 use super::prefix::Prefix;
 use super::unit::Unit;
 use crate::errors::*;
@@ -14,6 +16,8 @@ const BAR_MAX_VAL_TOKEN: char = DELIMETERS[4];
 #[derive(Debug, Clone, PartialEq)]
 pub struct Placeholder {
     pub name: String,
+    /// Applied to the value from left to right
+    pub formatters: Vec<Formatter>,
     pub min_width: MinWidthConfig,
     pub unit: UnitConfig,
     pub min_prefix: MinPrefixConfig,
@@ -33,6 +37,23 @@ impl FromStr for Placeholder {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self> {
+        // Split `"key.formatter(args).other():1"` into the name, the formatters and `":1"`
+        let name_len = s
+            .find(|c| DELIMETERS.contains(&c) || c == FORMATTER_TOKEN)
+            .unwrap_or(s.len());
+        let (name, mut s) = s.split_at(name_len);
+        check_name("placeholder", name)?;
+        let mut formatters = Vec::new();
+        while let Some(rest) = s.strip_prefix(FORMATTER_TOKEN) {
+            let (formatter, rest) = Formatter::parse(rest)?;
+            formatters.push(formatter);
+            s = rest;
+        }
+        match s.chars().next() {
+            Some(c) if !DELIMETERS.contains(&c) => return unexpected_token(c),
+            _ => (),
+        }
+
         // A handy macro for parsing placeholders configuration
         macro_rules! parse {
             ($delim:expr) => {
@@ -46,7 +67,6 @@ impl FromStr for Placeholder {
             };
         }
 
-        let name = s.split_once(DELIMETERS).unwrap_or((s, "")).0;
         let min_width = parse!(MIN_WIDTH_TOKEN);
         let max_width = parse!(MAX_WIDTH_TOKEN);
         let min_prefix = parse!(MIN_PREFIX_TOKEN);
@@ -74,6 +94,7 @@ impl FromStr for Placeholder {
 
         Ok(Self {
             name: name.to_string(),
+            formatters,
             min_width: min_width.parse()?,
             unit: unit.parse()?,
             min_prefix: min_prefix.parse()?,
@@ -83,6 +104,25 @@ impl FromStr for Placeholder {
     }
 }
 
+/// Writes the placeholder in a form that parses back to an equal one, e.g. `speed.max(10):3*B`
+impl fmt::Display for Placeholder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.name)?;
+        for formatter in &self.formatters {
+            write!(f, "{}{}", FORMATTER_TOKEN, formatter)?;
+        }
+        write!(f, "{}", self.min_width)?;
+        if let Some(max_width) = self.max_width {
+            write!(f, "{}{}", MAX_WIDTH_TOKEN, max_width)?;
+        }
+        write!(f, "{}{}", self.min_prefix, self.unit)?;
+        if let Some(bar_max_value) = self.bar_max_value {
+            write!(f, "{}{}", BAR_MAX_VAL_TOKEN, bar_max_value)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct MinWidthConfig {
     pub min_width: Option<usize>,
@@ -112,6 +152,22 @@ impl FromStr for MinWidthConfig {
     }
 }
 
+impl fmt::Display for MinWidthConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.min_width.is_none() && self.pad_with != '0' {
+            return Ok(());
+        }
+        f.write_char(MIN_WIDTH_TOKEN)?;
+        if self.pad_with == '0' {
+            f.write_char('0')?;
+        }
+        if let Some(min_width) = self.min_width {
+            write!(f, "{}", min_width)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct UnitConfig {
     pub unit: Option<Unit>,
@@ -133,6 +189,24 @@ impl FromStr for UnitConfig {
     }
 }
 
+impl fmt::Display for UnitConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.unit.is_none() && !self.hidden {
+            return Ok(());
+        }
+        f.write_char(UNIT_TOKEN)?;
+        if self.hidden {
+            f.write_char('_')?;
+        }
+        match self.unit {
+            // Displayed as `°`, but spelled `deg` in format strings
+            Some(Unit::Degrees) => f.write_str("deg"),
+            Some(unit) => write!(f, "{}", unit),
+            None => Ok(()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct MinPrefixConfig {
     pub value: Option<Prefix>,
@@ -160,3 +234,66 @@ impl FromStr for MinPrefixConfig {
         })
     }
 }
+
+impl fmt::Display for MinPrefixConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.value.is_none() && !self.space && !self.hidden {
+            return Ok(());
+        }
+        f.write_char(MIN_PREFIX_TOKEN)?;
+        if self.space {
+            f.write_char(' ')?;
+        }
+        if self.hidden {
+            f.write_char('_')?;
+        }
+        match self.value {
+            // Displayed as nothing, but spelled `1` in format strings
+            Some(Prefix::One) => f.write_char('1'),
+            Some(prefix) => write!(f, "{}", prefix),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(placeholder: &Placeholder) -> Vec<&str> {
+        placeholder
+            .formatters
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn formatters() {
+        let p: Placeholder = "x".parse().unwrap();
+        assert!(p.formatters.is_empty());
+
+        let p: Placeholder = "x.snake():5".parse().unwrap();
+        assert_eq!(names(&p), ["snake"]);
+        assert_eq!(p.min_width.min_width, Some(5));
+
+        let p: Placeholder = "bytes.max(10).cap(max:5)^8".parse().unwrap();
+        assert_eq!(p.name, "bytes");
+        assert_eq!(names(&p), ["max", "cap"]);
+        assert_eq!(p.max_width, Some(8));
+
+        let p: Placeholder = "x.csv(field:1,sep:';').strip_ansi().kebab()"
+            .parse()
+            .unwrap();
+        assert_eq!(names(&p), ["csv", "strip_ansi", "kebab"]);
+        assert_eq!(p.formatters[0].args.len(), 2);
+    }
+
+    #[test]
+    fn trailing_dot() {
+        assert!("x.".parse::<Placeholder>().is_err());
+        assert!("x.snake().".parse::<Placeholder>().is_err());
+        assert!("x.snake()..kebab()".parse::<Placeholder>().is_err());
+        assert!("x.snake().:3".parse::<Placeholder>().is_err());
+    }
+}
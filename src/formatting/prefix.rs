@@ -16,6 +16,35 @@ pub enum Prefix {
     Tera,
 }
 
+impl Prefix {
+    /// The power of 1000 (or of 1024 for bytes) of the prefix
+    pub fn exp_level(self) -> i32 {
+        match self {
+            Self::Tera => 4,
+            Self::Giga => 3,
+            Self::Mega => 2,
+            Self::Kilo => 1,
+            Self::One => 0,
+            Self::Milli => -1,
+            Self::Micro => -2,
+            Self::Nano => -3,
+        }
+    }
+
+    pub fn from_exp_level(exp_level: i32) -> Self {
+        match exp_level {
+            4 => Self::Tera,
+            3 => Self::Giga,
+            2 => Self::Mega,
+            1 => Self::Kilo,
+            0 => Self::One,
+            -1 => Self::Milli,
+            -2 => Self::Micro,
+            _ => Self::Nano,
+        }
+    }
+}
+
 impl fmt::Display for Prefix {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
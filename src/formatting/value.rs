@@ -1,4 +1,5 @@
 use crate::errors::*;
+use crate::util::pango_text_width;
 
 use super::placeholder::{MinPrefixConfig, Placeholder};
 use super::prefix::Prefix;
@@ -9,9 +10,30 @@ pub struct Value {
     unit: Unit,
     min_width: usize,
     icon: Option<String>,
+    /// Foreground color of the formatted value
+    color: Option<String>,
+    /// Overrides the prefix picked for the formatted number
+    prefix: Option<ForcedPrefix>,
+    /// Overrides whether prefixes are powers of 1000 or of 1024
+    base: Option<PrefixBase>,
     value: InternalValue,
 }
 
+#[derive(Debug, Clone, Copy)]
+pub(super) enum ForcedPrefix {
+    Exactly(Prefix),
+    /// The prefix a number this large would get
+    Of(f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum PrefixBase {
+    /// Powers of 1000, i.e. SI prefixes
+    Decimal,
+    /// Powers of 1024, i.e. IEC prefixes such as `Ki`
+    Binary,
+}
+
 #[derive(Debug, Clone)]
 enum InternalValue {
     Text(String),
@@ -23,54 +45,51 @@ fn format_number(
     raw_value: f64,
     min_width: usize,
     min_prefix_config: MinPrefixConfig,
+    forced_prefix: Option<ForcedPrefix>,
+    base: Option<PrefixBase>,
     unit: Unit,
     pad_with: char,
 ) -> String {
     let min_prefix = min_prefix_config.value.unwrap_or(Prefix::Nano);
     let is_byte = unit.is_byte();
-
-    let mut min_exp_level = match min_prefix {
-        Prefix::Tera => 4,
-        Prefix::Giga => 3,
-        Prefix::Mega => 2,
-        Prefix::Kilo => 1,
-        Prefix::One => 0,
-        Prefix::Milli => -1,
-        Prefix::Micro => -2,
-        Prefix::Nano => -3,
+    // Bytes and bits are counted in powers of 1024 unless told otherwise
+    let is_binary = match base {
+        Some(base) => base == PrefixBase::Binary,
+        None => is_byte,
     };
+    // There are no fractions of bytes, and no binary prefixes below one
+    let whole = is_byte || is_binary;
+
+    let mut min_exp_level = min_prefix.exp_level();
 
-    if is_byte {
+    if whole {
         min_exp_level = min_exp_level.max(0);
     }
 
-    let (mut value, mut prefix) = if !is_byte {
-        let exp_level = (raw_value.log10().div_euclid(3.) as i32).clamp(min_exp_level, 4);
-        let value = raw_value / (10f64).powi(exp_level * 3);
-
-        let prefix = match exp_level {
-            4 => Prefix::Tera,
-            3 => Prefix::Giga,
-            2 => Prefix::Mega,
-            1 => Prefix::Kilo,
-            0 => Prefix::One,
-            -1 => Prefix::Milli,
-            -2 => Prefix::Micro,
-            _ => Prefix::Nano,
+    let auto_exp_level = |value: f64| {
+        let exp_level = if is_binary {
+            value.log2().div_euclid(10.)
+        } else {
+            value.log10().div_euclid(3.)
         };
-        (value, prefix)
+        (exp_level as i32).clamp(min_exp_level, 4)
+    };
+    let exp_level = match forced_prefix {
+        Some(ForcedPrefix::Exactly(prefix)) if whole => prefix.exp_level().max(0),
+        Some(ForcedPrefix::Exactly(prefix)) => prefix.exp_level(),
+        Some(ForcedPrefix::Of(value)) => auto_exp_level(value),
+        None => auto_exp_level(raw_value),
+    };
+    let (mut value, mut prefix) = if !is_binary {
+        (
+            raw_value / (10f64).powi(exp_level * 3),
+            Prefix::from_exp_level(exp_level),
+        )
     } else {
-        let exp_level = (raw_value.log2().div_euclid(10.) as i32).clamp(min_exp_level, 4);
-        let value = raw_value / (2f64).powi(exp_level * 10);
-
-        let prefix = match exp_level {
-            4 => Prefix::Tera,
-            3 => Prefix::Giga,
-            2 => Prefix::Mega,
-            1 => Prefix::Kilo,
-            _ => Prefix::One,
-        };
-        (value, prefix)
+        (
+            raw_value / (2f64).powi(exp_level * 10),
+            Prefix::from_exp_level(exp_level.max(0)),
+        )
     };
 
     if unit == Unit::Percents || unit == Unit::None {
@@ -86,6 +105,10 @@ fn format_number(
     };
     if !min_prefix_config.hidden {
         prefix_str.push_str(&prefix.to_string());
+        // Only binary prefixes asked for explicitly are written as such, e.g. `Ki`
+        if base == Some(PrefixBase::Binary) && prefix != Prefix::One {
+            prefix_str.push('i');
+        }
     }
 
     // The length of the integer part of a number
@@ -101,25 +124,23 @@ fn format_number(
     }
 }
 
-fn format_bar(value: f64, length: usize) -> String {
+/// Glyphs filling a cell of a horizontal bar by eighths
+pub(super) const BAR_GLYPHS: &[char] = &[
+    ' ', '\u{258f}', '\u{258e}', '\u{258d}', '\u{258c}', '\u{258b}', '\u{258a}', '\u{2589}',
+    '\u{2588}',
+];
+
+/// Draw `value` (between 0 and 1) as a bar of `length` cells, each of which is filled with one of
+/// `glyphs`, from the emptiest to the fullest
+pub(super) fn format_bar(value: f64, length: usize, glyphs: &[char]) -> String {
     let value = value.clamp(0., 1.);
     let chars_to_fill = value * length as f64;
+    let steps = (glyphs.len() - 1) as f64;
     (0..length)
         .map(|i| {
             let printed_chars = i as f64;
-            let val = (chars_to_fill - printed_chars).clamp(0., 1.) * 8.;
-            match val as usize {
-                //TODO make those characters configurable?
-                0 => ' ',
-                1 => '\u{258f}',
-                2 => '\u{258e}',
-                3 => '\u{258d}',
-                4 => '\u{258c}',
-                5 => '\u{258b}',
-                6 => '\u{258a}',
-                7 => '\u{2589}',
-                _ => '\u{2588}',
-            }
+            let val = (chars_to_fill - printed_chars).clamp(0., 1.) * steps;
+            glyphs[val as usize]
         })
         .collect()
 }
@@ -129,6 +150,9 @@ impl Value {
     pub fn from_string(text: String) -> Self {
         Self {
             icon: None,
+            color: None,
+            prefix: None,
+            base: None,
             min_width: 0,
             unit: Unit::None,
             value: InternalValue::Text(text),
@@ -137,6 +161,9 @@ impl Value {
     pub fn from_integer(value: i64) -> Self {
         Self {
             icon: None,
+            color: None,
+            prefix: None,
+            base: None,
             min_width: 2,
             unit: Unit::None,
             value: InternalValue::Integer(value),
@@ -145,6 +172,9 @@ impl Value {
     pub fn from_float(value: f64) -> Self {
         Self {
             icon: None,
+            color: None,
+            prefix: None,
+            base: None,
             min_width: 3,
             unit: Unit::None,
             value: InternalValue::Float(value),
@@ -190,6 +220,116 @@ impl Value {
         self.unit = Unit::Hertz;
         self
     }
+    pub fn with_unit(mut self, unit: Unit) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    // Accessors used by formatters
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.value {
+            InternalValue::Text(ref text) => text.trim().parse().ok(),
+            InternalValue::Integer(value) => Some(value as f64),
+            InternalValue::Float(value) => Some(value),
+        }
+    }
+
+    pub(super) fn as_string(&self) -> String {
+        match self.value {
+            InternalValue::Text(ref text) => text.clone(),
+            InternalValue::Integer(value) => value.to_string(),
+            InternalValue::Float(value) => value.to_string(),
+        }
+    }
+
+    /// Create a numeric value that keeps the icon, the unit and the type of this value
+    pub(super) fn with_f64(&self, value: f64) -> Self {
+        let value = match self.value {
+            InternalValue::Integer(_) => InternalValue::Integer(value.round() as i64),
+            _ => InternalValue::Float(value),
+        };
+        Self {
+            value,
+            ..self.clone()
+        }
+    }
+
+    /// Create a text value that keeps the icon of this value
+    pub(super) fn with_text(&self, text: String) -> Self {
+        Self {
+            icon: self.icon.clone(),
+            ..Self::from_string(text)
+        }
+    }
+
+    /// Format a number with the placeholder's width, prefix and unit into a text value that keeps
+    /// the icon and the color of this value, for formatters which work on text. `None` for text.
+    pub(super) fn formatted_text(&self, var: &Placeholder) -> Result<Option<Self>> {
+        if let InternalValue::Text(_) = self.value {
+            return Ok(None);
+        }
+        let plain = Self {
+            icon: None,
+            color: None,
+            ..self.clone()
+        };
+        Ok(Some(Self {
+            icon: self.icon.clone(),
+            color: self.color.clone(),
+            ..Self::from_string(plain.format(var)?)
+        }))
+    }
+
+    /// Color the formatted value, including its icon and unit
+    pub(super) fn with_color(&self, color: String) -> Self {
+        Self {
+            color: Some(color),
+            ..self.clone()
+        }
+    }
+
+    /// Integers as floats, as only those get prefixes
+    pub(super) fn prefixable(&self) -> Self {
+        match self.value {
+            InternalValue::Integer(value) => Self {
+                icon: self.icon.clone(),
+                color: self.color.clone(),
+                unit: self.unit,
+                prefix: self.prefix,
+                base: self.base,
+                ..Self::from_float(value as f64)
+            },
+            _ => self.clone(),
+        }
+    }
+
+    /// Format the number with the given prefix rather than the one fitting it best
+    pub(super) fn with_prefix(&self, prefix: ForcedPrefix) -> Self {
+        Self {
+            prefix: Some(prefix),
+            ..self.prefixable()
+        }
+    }
+
+    /// Format the number with prefixes which are powers of 1000 or of 1024
+    pub(super) fn with_base(&self, base: PrefixBase) -> Self {
+        Self {
+            base: Some(base),
+            ..self.prefixable()
+        }
+    }
+
+    /// Pad the icon with spaces to `width` columns, or reserve them if there is no icon
+    pub(super) fn with_icon_width(&self, width: usize) -> Self {
+        let mut icon = self.icon.clone().unwrap_or_default();
+        for _ in pango_text_width(&icon)..width {
+            icon.push(' ');
+        }
+        Self {
+            icon: Some(icon),
+            ..self.clone()
+        }
+    }
 
     pub fn format(&self, var: &Placeholder) -> Result<String> {
         // Get user-specified min_width and pad_with values. Use defaults instead
@@ -203,9 +343,11 @@ impl Value {
         if let Some(bar_max_value) = var.bar_max_value {
             match self.value {
                 InternalValue::Integer(i) => {
-                    return Ok(format_bar(i as f64 / bar_max_value, min_width))
+                    return Ok(format_bar(i as f64 / bar_max_value, min_width, BAR_GLYPHS))
+                }
+                InternalValue::Float(f) => {
+                    return Ok(format_bar(f / bar_max_value, min_width, BAR_GLYPHS))
                 }
-                InternalValue::Float(f) => return Ok(format_bar(f / bar_max_value, min_width)),
                 _ => (),
             }
         }
@@ -243,10 +385,23 @@ impl Value {
             InternalValue::Float(value) => {
                 // Convert the value
                 // TODO better convertion mechanism
-                let value = value * self.unit.convert(unit)?;
+                let factor = self.unit.convert(unit)?;
+                let value = value * factor;
+                let prefix = match self.prefix {
+                    Some(ForcedPrefix::Of(of)) => Some(ForcedPrefix::Of(of * factor)),
+                    prefix => prefix,
+                };
 
                 // Apply engineering notation (Float-only)
-                format_number(value, min_width, var.min_prefix, unit, pad_with)
+                format_number(
+                    value,
+                    min_width,
+                    var.min_prefix,
+                    prefix,
+                    self.base,
+                    unit,
+                    pad_with,
+                )
             }
         };
 
@@ -260,6 +415,10 @@ impl Value {
             unit.to_string()
         };
 
-        Ok(format!("{}{}{}", icon_str, value, unit))
+        let text = format!("{}{}{}", icon_str, value, unit);
+        Ok(match &self.color {
+            Some(color) => format!("<span foreground='{}'>{}</span>", color, text),
+            None => text,
+        })
     }
 }
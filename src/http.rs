@@ -58,6 +58,29 @@ pub fn http_get_socket_json(path: std::path::PathBuf, url: &str) -> Result<HttpR
     })
 }
 
+pub fn http_get_text(url: &str, timeout: Option<Duration>) -> Result<HttpResponse<String>> {
+    let mut easy = curl::easy::Easy::new();
+
+    easy.url(url)?;
+
+    if let Some(t) = timeout {
+        easy.timeout(t)?;
+    }
+
+    easy.useragent("i3status")?;
+
+    let response = http_easy(easy)?;
+
+    let content = String::from_utf8(response.content)
+        .internal_error("curl", "response from server is not valid UTF-8")?;
+
+    Ok(HttpResponse {
+        code: response.code,
+        content,
+        headers: response.headers,
+    })
+}
+
 pub fn http_get_json(
     url: &str,
     timeout: Option<Duration>,
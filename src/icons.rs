@@ -75,6 +75,7 @@ impl Default for Icons {
             "pomodoro_started" => "STARTED",
             "pomodoro_stopped" => "STOPPED",
             "resolution" => "RES",
+            "scratchpad" => "SCRATCH",
             "tasks" => "TSK",
             "thermometer" => "TEMP",
             "time" => "TIME",
@@ -82,6 +83,7 @@ impl Default for Icons {
             "toggle_on" => "ON",
             "update" => "UPD",
             "uptime" => "UP",
+            "urgent" => "URGENT",
             "volume_empty" => "VOL",
             "volume_full" => "VOL",
             "volume_half" => "VOL",
@@ -36,8 +36,7 @@ use crate::config::SharedConfig;
 use crate::errors::*;
 use crate::protocol::i3bar_event::{process_events, I3BarEvent};
 use crate::scheduler::{Task, UpdateScheduler};
-use crate::signals::process_signals;
-use crate::util::deserialize_file;
+use crate::signals::{convert_to_valid_signal, process_signals};
 use crate::widgets::text::TextWidget;
 use crate::widgets::{I3BarWidget, State};
 
@@ -150,7 +149,7 @@ fn run(matches: &ArgMatches) -> Result<()> {
         Some(config_path) => std::path::PathBuf::from(config_path),
         None => util::xdg_config_home().join("i3status-rust/config.toml"),
     };
-    let config: Config = deserialize_file(&config_path)?;
+    let config = Config::from_file(&config_path)?;
 
     // Update request channel
     let (tx_update_requests, rx_update_requests): (Sender<Task>, Receiver<Task>) =
@@ -170,6 +169,10 @@ fn run(matches: &ArgMatches) -> Result<()> {
     }
 
     let shared_config = SharedConfig::new(&config);
+    let privacy_signal = config
+        .privacy_signal
+        .map(convert_to_valid_signal)
+        .transpose()?;
 
     // Initialize the blocks
     let mut blocks: Vec<Box<dyn Block>> = Vec::new();
@@ -215,9 +218,10 @@ fn run(matches: &ArgMatches) -> Result<()> {
             // Receive async update requests
             recv(rx_update_requests) -> request => if let Ok(req) = request {
                 // Process immediately and forget
-                blocks.get_mut(req.id)
-                    .internal_error("scheduler", "could not get required block")?
-                    .update()?;
+                let block = blocks.get_mut(req.id)
+                    .internal_error("scheduler", "could not get required block")?;
+                block.update()?;
+                scheduler.schedule_tick(block.as_ref());
                 protocol::print_blocks(&blocks, &shared_config)?;
             },
             // Receive update timer events
@@ -239,6 +243,13 @@ fn run(matches: &ArgMatches) -> Result<()> {
                         //USR2 signal that should reload the config
                         restart();
                     },
+                    sig if Some(sig) == privacy_signal => {
+                        //Real time signal that toggles privacy mode and redraws every block
+                        formatting::toggle_privacy_mode();
+                        for block in blocks.iter_mut() {
+                            block.update()?;
+                        }
+                    },
                     _ => {
                         //Real time signal that updates only the blocks listening
                         //for that signal
@@ -4,7 +4,7 @@ pub mod i3bar_event;
 use crate::blocks::Block;
 use crate::config::SharedConfig;
 use crate::errors::*;
-use crate::util::add_colors;
+use crate::util::{add_colors, pango_text_width};
 
 use i3bar_block::I3BarBlock;
 
@@ -21,28 +21,80 @@ pub fn print_blocks(blocks: &[Box<dyn Block>], config: &SharedConfig) -> Result<
 
     let mut rendered_blocks = vec![];
 
+    let mut visible_blocks: Vec<(Vec<I3BarBlock>, i64)> = blocks
+        .iter()
+        .map(|block| {
+            let widgets: Vec<I3BarBlock> = block.view().iter().map(|w| w.get_data()).collect();
+            (widgets, block.priority())
+        })
+        .filter(|(widgets, _)| !widgets.is_empty())
+        .collect();
+
+    // Hide low priority blocks if the bar is too narrow
+    if let Some(bar_width) = config.bar_width {
+        let separator_width = config
+            .theme
+            .separator
+            .as_deref()
+            .map(pango_text_width)
+            .unwrap_or(0);
+        let block_width = |widgets: &[I3BarBlock], short: bool| {
+            let width = widgets
+                .iter()
+                .map(|w| match &w.short_text {
+                    Some(short_text) if short => pango_text_width(short_text),
+                    _ => pango_text_width(&w.full_text),
+                })
+                .sum::<usize>();
+            width + separator_width
+        };
+
+        // Switch to the short format first
+        let sizes: Vec<(usize, usize, i64)> = visible_blocks
+            .iter()
+            .map(|(widgets, priority)| {
+                (
+                    block_width(widgets, false),
+                    block_width(widgets, true),
+                    *priority,
+                )
+            })
+            .collect();
+        for (shorten, (widgets, _)) in shorten_blocks(&sizes, bar_width)
+            .into_iter()
+            .zip(visible_blocks.iter_mut())
+        {
+            if shorten {
+                for widget in widgets {
+                    if let Some(short_text) = widget.short_text.take() {
+                        widget.full_text = short_text;
+                    }
+                }
+            }
+        }
+
+        // Then hide blocks if it's still not enough
+        let sizes: Vec<(usize, i64)> = visible_blocks
+            .iter()
+            .map(|(widgets, priority)| (block_width(widgets, false), *priority))
+            .collect();
+        let mut visible = fit_blocks(&sizes, bar_width).into_iter();
+        visible_blocks.retain(|_| visible.next().unwrap_or(true));
+    }
+
     /* To always start with the same alternating tint on the right side of the
      * bar it is easiest to calculate the number of visible blocks here and
      * flip the starting tint if an even number of blocks is visible. This way,
      * the last block should always be untinted.
      */
-    let visible_count = blocks
-        .iter()
-        .filter(|block| !block.view().is_empty())
-        .count();
+    let visible_count = visible_blocks.len();
 
     let mut alternator = visible_count % 2 == 0;
 
-    for block in blocks.iter() {
-        let widgets = block.view();
-        if widgets.is_empty() {
-            continue;
-        }
-
+    for (widgets, _) in visible_blocks {
         let mut rendered_widgets: Vec<I3BarBlock> = widgets
-            .iter()
-            .map(|widget| {
-                let mut data = widget.get_data();
+            .into_iter()
+            .map(|mut data| {
                 if alternator {
                     // Apply tint for all widgets of every second block
                     data.background = add_colors(
@@ -115,3 +167,76 @@ pub fn print_blocks(blocks: &[Box<dyn Block>], config: &SharedConfig) -> Result<
 
     Ok(())
 }
+
+/// Decide which blocks should use their short format, given their `(full width, short width,
+/// priority)` and the total available width. Blocks are shortened in the same order as they
+/// would be hidden by `fit_blocks`, until the bar fits.
+fn shorten_blocks(blocks: &[(usize, usize, i64)], max_width: usize) -> Vec<bool> {
+    let mut shortened = vec![false; blocks.len()];
+    let mut total_width: usize = blocks.iter().map(|(width, _, _)| width).sum();
+
+    let mut by_priority: Vec<usize> = (0..blocks.len()).collect();
+    by_priority.sort_by_key(|&i| blocks[i].2);
+
+    for i in by_priority {
+        if total_width <= max_width {
+            break;
+        }
+        let (full_width, short_width, _) = blocks[i];
+        if short_width < full_width {
+            shortened[i] = true;
+            total_width -= full_width - short_width;
+        }
+    }
+
+    shortened
+}
+
+/// Decide which blocks to show, given their `(width, priority)` and the total available width.
+/// Blocks with the lowest priority are hidden first; among blocks with the same priority, the
+/// leftmost one is hidden first.
+fn fit_blocks(blocks: &[(usize, i64)], max_width: usize) -> Vec<bool> {
+    let mut visible = vec![true; blocks.len()];
+    let mut total_width: usize = blocks.iter().map(|(width, _)| width).sum();
+
+    let mut by_priority: Vec<usize> = (0..blocks.len()).collect();
+    by_priority.sort_by_key(|&i| blocks[i].1);
+
+    for i in by_priority {
+        if total_width <= max_width {
+            break;
+        }
+        visible[i] = false;
+        total_width -= blocks[i].0;
+    }
+
+    visible
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_blocks_by_priority() {
+        let blocks = [(10, 0), (5, 2), (10, 1), (5, 0)];
+        assert_eq!(fit_blocks(&blocks, 30), [true, true, true, true]);
+        assert_eq!(fit_blocks(&blocks, 25), [false, true, true, true]);
+        assert_eq!(fit_blocks(&blocks, 20), [false, true, true, true]);
+        assert_eq!(fit_blocks(&blocks, 15), [false, true, true, false]);
+        assert_eq!(fit_blocks(&blocks, 12), [false, true, false, false]);
+        assert_eq!(fit_blocks(&blocks, 0), [false, false, false, false]);
+    }
+
+    #[test]
+    fn shorten_blocks_under_tight_budget() {
+        // The second block has no short format
+        let blocks = [(10, 4, 0), (5, 5, 0), (10, 6, 1)];
+        assert_eq!(shorten_blocks(&blocks, 25), [false, false, false]);
+        assert_eq!(shorten_blocks(&blocks, 24), [true, false, false]);
+        assert_eq!(shorten_blocks(&blocks, 19), [true, false, false]);
+        assert_eq!(shorten_blocks(&blocks, 18), [true, false, true]);
+        // Shortening all blocks is not enough, hiding is left to `fit_blocks`
+        assert_eq!(shorten_blocks(&blocks, 5), [true, false, true]);
+    }
+}
@@ -40,6 +40,15 @@ impl cmp::Ord for Task {
     }
 }
 
+/// The earliest time a widget of the block changes on its own
+fn next_tick(block: &dyn Block) -> Option<Duration> {
+    block
+        .view()
+        .iter()
+        .filter_map(|widget| widget.next_tick())
+        .min()
+}
+
 pub struct UpdateScheduler {
     schedule: BinaryHeap<Task>,
 }
@@ -104,21 +113,41 @@ impl UpdateScheduler {
         let now = Instant::now();
 
         for task in tasks_next {
-            if let Some(dur) = blocks
+            let block = blocks
                 .get_mut(task.id as usize)
-                .internal_error("scheduler", "could not get required block")?
-                .update()?
-            {
-                match dur {
-                    Update::Every(d) => self.schedule.push(Task {
-                        id: task.id,
-                        update_time: now + d,
-                    }),
-                    Update::Once => {} // do not schedule this task again
-                }
+                .internal_error("scheduler", "could not get required block")?;
+            let update = block.update()?;
+            let tick = next_tick(block.as_ref());
+            let next = match (update, tick) {
+                (Some(Update::Every(d)), Some(tick)) => Some(d.min(tick)),
+                (Some(Update::Every(d)), None) => Some(d),
+                // Blocks which aren't updated regularly are updated for their widgets only
+                (_, tick) => tick,
+            };
+            if let Some(d) = next {
+                self.schedule.push(Task {
+                    id: task.id,
+                    update_time: now + d,
+                });
             }
         }
 
         Ok(())
     }
+
+    /// Schedule an update of a block which was updated outside of the schedule, e.g. by an
+    /// update request of its own, for when one of its widgets changes. Blocks which are scheduled
+    /// already are updated then anyway.
+    pub fn schedule_tick(&mut self, block: &dyn Block) {
+        let id = block.id();
+        if self.schedule.iter().any(|task| task.id == id) {
+            return;
+        }
+        if let Some(tick) = next_tick(block) {
+            self.schedule.push(Task {
+                id,
+                update_time: Instant::now() + tick,
+            });
+        }
+    }
 }
@@ -83,6 +83,69 @@ pub fn escape_pango_text(text: String) -> String {
         .collect()
 }
 
+/// Undo `escape_pango_text`, also decoding `&quot;`, `&apos;` and numeric character references.
+/// Anything else that looks like an entity is kept as it is.
+pub fn unescape_pango_text(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                entity => match entity.strip_prefix('#') {
+                    Some(hex) if hex.starts_with(&['x', 'X'][..]) => {
+                        u32::from_str_radix(&hex[1..], 16).ok()
+                    }
+                    Some(dec) => dec.parse().ok(),
+                    None => None,
+                }
+                .and_then(std::char::from_u32),
+            };
+            c.map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                unescaped.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// The number of characters displayed for a string that may contain Pango markup
+pub fn pango_text_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut in_tag = false;
+    let mut in_entity = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if in_tag => (),
+            '&' => {
+                in_entity = true;
+                width += 1;
+            }
+            ';' if in_entity => in_entity = false,
+            _ if in_entity => (),
+            _ => width += 1,
+        }
+    }
+    width
+}
+
 pub fn battery_level_to_icon(charge_level: Result<u64>) -> &'static str {
     match charge_level {
         Ok(0..=5) => "bat_empty",
@@ -116,6 +179,23 @@ where
     toml::from_str(&contents).configuration_error("failed to parse TOML from file contents")
 }
 
+/// Hyprland's socket for requests, e.g. `j/workspaces`
+pub const HYPRLAND_REQUESTS: &str = ".socket.sock";
+/// Hyprland's socket for events, one per line, e.g. `workspacev2>>2,web`
+pub const HYPRLAND_EVENTS: &str = ".socket2.sock";
+
+/// The path of one of the sockets of the running Hyprland instance
+pub fn hyprland_socket(blockname: &str, socket: &str) -> Result<PathBuf> {
+    let signature = std::env::var("HYPRLAND_INSTANCE_SIGNATURE")
+        .block_error(blockname, "HYPRLAND_INSTANCE_SIGNATURE is not set")?;
+    let runtime_dir = std::env::var("XDG_RUNTIME_DIR")
+        .map(|dir| PathBuf::from(dir).join("hypr"))
+        .ok()
+        .filter(|dir| dir.join(&signature).exists())
+        .unwrap_or_else(|| PathBuf::from("/tmp/hypr"));
+    Ok(runtime_dir.join(signature).join(socket))
+}
+
 pub fn read_file(blockname: &str, path: &Path) -> Result<String> {
     let mut f = OpenOptions::new().read(true).open(path).block_error(
         blockname,
@@ -238,9 +318,35 @@ pub fn format_vec_to_bar_graph(content: &[f64], min: Option<f64>, max: Option<f6
     }
 }
 
+/// Fixtures for the tests of blocks that read the tree of sway or i3
+#[cfg(test)]
+pub mod sway_fixtures {
+    use serde_json::{json, Value};
+
+    /// A container as `get_tree` returns it, without a name and with a horizontal split layout.
+    /// Its children have the focus in order, floating ones last.
+    pub fn node(id: i64, node_type: &str, nodes: Vec<Value>, floating_nodes: Vec<Value>) -> Value {
+        let rect = json!({ "x": 0, "y": 0, "width": 0, "height": 0 });
+        let focus: Vec<i64> = nodes
+            .iter()
+            .chain(&floating_nodes)
+            .filter_map(|node| node["id"].as_i64())
+            .collect();
+        json!({
+            "id": id, "name": "", "type": node_type, "border": "none",
+            "current_border_width": 0, "layout": "splith", "rect": rect,
+            "window_rect": rect, "deco_rect": rect, "geometry": rect, "urgent": false,
+            "focused": false, "focus": focus, "nodes": nodes,
+            "floating_nodes": floating_nodes, "sticky": false, "fullscreen_mode": 0,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::util::{color_from_rgba, has_command};
+    use crate::util::{
+        color_from_rgba, escape_pango_text, has_command, pango_text_width, unescape_pango_text,
+    };
 
     #[test]
     // we assume sh is always available
@@ -283,4 +389,26 @@ mod tests {
         let rgba = color_from_rgba(invalid);
         assert!(rgba.is_err());
     }
+
+    #[test]
+    fn test_pango_text_width() {
+        assert_eq!(pango_text_width("abc"), 3);
+        assert_eq!(pango_text_width("<span foreground='red'>abc</span> d"), 5);
+        assert_eq!(pango_text_width("a &amp; b"), 5);
+        assert_eq!(pango_text_width("\u{f017} 12:00"), 7);
+    }
+
+    #[test]
+    fn test_unescape_pango_text() {
+        let text = "<b>Tom & Jerry's</b>";
+        assert_eq!(
+            unescape_pango_text(&escape_pango_text(text.to_string())),
+            text
+        );
+        assert_eq!(unescape_pango_text("&quot;a&#x26;b&#60;&apos;"), "\"a&b<'");
+        assert_eq!(
+            unescape_pango_text("a & b; &nbsp; &#xzz; &"),
+            "a & b; &nbsp; &#xzz; &"
+        );
+    }
 }
@@ -2,6 +2,7 @@ pub mod rotatingtext;
 pub mod text;
 
 use std::str::FromStr;
+use std::time::Duration;
 
 use serde::de::value::{Error, StrDeserializer};
 use serde::de::{Deserialize, IntoDeserializer};
@@ -44,7 +45,8 @@ impl Spacing {
     }
 }
 
-#[derive(Debug, Copy, Clone, Deserialize)]
+/// Ordered by severity
+#[derive(Debug, Copy, Clone, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub enum State {
     Idle,
     Info,
@@ -64,6 +66,14 @@ impl State {
             Critical => (&theme.critical_bg, &theme.critical_fg),
         }
     }
+
+    /// The value of the i3bar `urgent` flag for this state
+    pub fn urgent(self, urgent_on_critical: bool) -> Option<bool> {
+        match self {
+            State::Critical if urgent_on_critical => Some(true),
+            _ => None,
+        }
+    }
 }
 
 impl FromStr for State {
@@ -77,4 +87,10 @@ impl FromStr for State {
 
 pub trait I3BarWidget {
     fn get_data(&self) -> I3BarBlock;
+
+    /// How long until the widget changes on its own, e.g. because its format scrolls text, so
+    /// that its block must be updated then
+    fn next_tick(&self) -> Option<Duration> {
+        None
+    }
 }
@@ -174,6 +174,7 @@ impl RotatingTextWidget {
         };
         self.inner.background = key_bg.clone();
         self.inner.color = key_fg.clone();
+        self.inner.urgent = self.state.urgent(self.shared_config.urgent_on_critical);
     }
 
     pub fn next(&mut self) -> Result<(bool, Option<Duration>)> {
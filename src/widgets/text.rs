@@ -1,6 +1,9 @@
+use std::time::Duration;
+
 use super::{I3BarWidget, Spacing, State};
 use crate::config::SharedConfig;
 use crate::errors::*;
+use crate::formatting::Rendered;
 use crate::protocol::i3bar_block::I3BarBlock;
 
 #[derive(Clone, Debug)]
@@ -11,6 +14,10 @@ pub struct TextWidget {
     content_short: Option<String>,
     icon: Option<String>,
     state: State,
+    /// The state requested by the format string, shown instead of `state` if more severe
+    format_state: Option<State>,
+    /// When the rendered format changes on its own, see `Rendered::tick`
+    tick: Option<Duration>,
     spacing: Spacing,
     spacing_short: Spacing,
     shared_config: SharedConfig,
@@ -35,6 +42,8 @@ impl TextWidget {
             content_short: None,
             icon: None,
             state: State::Idle,
+            format_state: None,
+            tick: None,
             spacing: Spacing::Normal,
             spacing_short: Spacing::Normal,
             shared_config,
@@ -82,15 +91,18 @@ impl TextWidget {
         self.set_texts((content, None));
     }
 
-    pub fn set_texts(&mut self, contents: (String, Option<String>)) {
-        self.spacing = Spacing::from_content(&contents.0);
-        self.spacing_short = if let Some(ref short) = contents.1 {
+    pub fn set_texts(&mut self, contents: impl Into<Rendered>) {
+        let contents = contents.into();
+        self.spacing = Spacing::from_content(&contents.full);
+        self.spacing_short = if let Some(ref short) = contents.short {
             Spacing::from_content(&short)
         } else {
             self.spacing
         };
-        self.content = Some(contents.0);
-        self.content_short = contents.1;
+        self.content = Some(contents.full);
+        self.content_short = contents.short;
+        self.format_state = contents.state;
+        self.tick = contents.tick;
         self.update();
     }
 
@@ -116,7 +128,10 @@ impl TextWidget {
     }
 
     fn update(&mut self) {
-        let (key_bg, key_fg) = self.state.theme_keys(&self.shared_config.theme);
+        let state = self
+            .format_state
+            .map_or(self.state, |format_state| format_state.max(self.state));
+        let (key_bg, key_fg) = state.theme_keys(&self.shared_config.theme);
 
         self.inner.full_text =
             self.format_text(self.content.clone().unwrap_or_default(), self.spacing);
@@ -126,6 +141,7 @@ impl TextWidget {
         };
         self.inner.background = key_bg.clone();
         self.inner.color = key_fg.clone();
+        self.inner.urgent = state.urgent(self.shared_config.urgent_on_critical);
     }
 }
 
@@ -133,4 +149,65 @@ impl I3BarWidget for TextWidget {
     fn get_data(&self) -> I3BarBlock {
         self.inner.clone()
     }
+
+    fn next_tick(&self) -> Option<Duration> {
+        self.tick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urgent_on_critical() {
+        let mut shared_config = SharedConfig::default();
+        shared_config.urgent_on_critical = true;
+        let mut widget = TextWidget::new(0, 0, shared_config).with_text("text");
+        assert!(!widget.get_data().render().contains("\"urgent\""));
+        widget.set_state(State::Critical);
+        assert!(widget.get_data().render().contains("\"urgent\":true"));
+        widget.set_state(State::Warning);
+        assert!(!widget.get_data().render().contains("\"urgent\""));
+
+        let mut widget = TextWidget::new(0, 0, SharedConfig::default()).with_text("text");
+        widget.set_state(State::Critical);
+        assert!(!widget.get_data().render().contains("\"urgent\""));
+    }
+
+    #[test]
+    fn state_from_format() {
+        use crate::formatting::value::Value;
+        use crate::formatting::FormatTemplate;
+
+        let mut shared_config = SharedConfig::default();
+        shared_config.urgent_on_critical = true;
+        let format = FormatTemplate::new("{v.state(warn:60,crit:85)}", None).unwrap();
+        let mut widget = TextWidget::new(0, 0, shared_config);
+        let mut render = |v| {
+            widget.set_texts(format.render(&map!("v" => Value::from_integer(v))).unwrap());
+            widget.get_data().render()
+        };
+        assert!(render(90).contains("\"urgent\":true"));
+        assert!(!render(70).contains("\"urgent\""));
+
+        // Plain text doesn't carry a state over
+        widget.set_texts(
+            format
+                .render(&map!("v" => Value::from_integer(90)))
+                .unwrap(),
+        );
+        widget.set_text("plain".to_string());
+        widget.set_state(State::Idle);
+        assert!(!widget.get_data().render().contains("\"urgent\""));
+
+        // A more severe state of the block itself is kept
+        widget.set_texts(
+            format
+                .render(&map!("v" => Value::from_integer(70)))
+                .unwrap(),
+        );
+        widget.set_state(State::Critical);
+        assert!(widget.get_data().render().contains("\"urgent\":true"));
+    }
 }